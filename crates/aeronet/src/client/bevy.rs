@@ -11,6 +11,9 @@ use derivative::Derivative;
 
 use super::{ClientTransport, DisconnectReason};
 
+#[cfg(feature = "reconnect")]
+use super::{ReconnectEvent, ReconnectingClient};
+
 /// System set for client-side networking systems.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
 pub enum ClientTransportSet {
@@ -137,3 +140,60 @@ pub struct NackFromServer<T: ClientTransport> {
     /// Key of the sent message, obtained by [`ClientTransport::send`].
     pub msg_key: T::MessageKey,
 }
+
+/// A [`ReconnectingClient`] started, finished, or gave up on reconnecting after
+/// losing its connection.
+///
+/// This is only sent if the [`send_reconnect_events`] system is added.
+///
+/// See [`ReconnectEvent`].
+#[cfg(feature = "reconnect")]
+#[derive(Derivative, Event)]
+#[derivative(Debug(bound = ""), Clone(bound = ""))]
+pub struct LocalClientReconnect<T: ClientTransport> {
+    /// What happened to the reconnection attempt.
+    pub event: ReconnectEvent,
+    #[derivative(Debug = "ignore")]
+    #[doc(hidden)]
+    pub _phantom: PhantomData<T>,
+}
+
+/// System which takes the [`ReconnectEvent`]s from the
+/// [`ReconnectingClient<T>`] resource, and sends them as
+/// [`LocalClientReconnect`] events.
+///
+/// Run this after the system which polls the client, and register the event
+/// with `App::add_event::<LocalClientReconnect<ReconnectingClient<T>>>`.
+///
+/// # Example
+///
+/// ```
+/// # use bevy_app::prelude::*;
+/// # use bevy_ecs::prelude::*;
+/// # use aeronet::client::{
+/// #     send_reconnect_events, ClientTransport, ClientTransportSet, LocalClientReconnect,
+/// #     ReconnectingClient,
+/// # };
+/// # fn run<T: ClientTransport + Send + Sync + 'static>() {
+/// let mut app = App::new();
+/// app.add_event::<LocalClientReconnect<ReconnectingClient<T>>>()
+///     .add_systems(
+///         PreUpdate,
+///         send_reconnect_events::<T>.after(ClientTransportSet::Recv),
+///     );
+/// # }
+/// ```
+#[cfg(feature = "reconnect")]
+pub fn send_reconnect_events<T: ClientTransport + Send + Sync + 'static>(
+    mut client: ResMut<ReconnectingClient<T>>,
+    mut events: EventWriter<LocalClientReconnect<ReconnectingClient<T>>>,
+) {
+    events.send_batch(
+        client
+            .drain_reconnect_events()
+            .map(|event| LocalClientReconnect {
+                event,
+                _phantom: PhantomData,
+            }),
+    );
+}
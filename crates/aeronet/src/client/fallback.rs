@@ -0,0 +1,368 @@
+use std::fmt::Debug;
+
+use bytes::Bytes;
+use derivative::Derivative;
+use web_time::Duration;
+
+use crate::{
+    client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
+    lane::LaneIndex,
+    shared::Multi,
+};
+
+/// Disconnect reason used when a connection attempt takes too long and the
+/// [`FallbackClient`] moves on to the next transport.
+pub const FALLBACK_TIMEOUT_REASON: &str = "connection attempt timed out";
+
+/// Client transport which tries connecting using one transport, then falls
+/// back to a second transport if the first one fails to connect.
+///
+/// Use [`FallbackClient::connect`] to start connecting. The first transport
+/// `A` is tried first, and if it disconnects before connecting, or does not
+/// connect within the configured timeout, the connection is attempted using
+/// the second transport `B`.
+///
+/// Once connected, this behaves as a single [`ClientTransport`].
+/// [`ClientEvent::Disconnected`] is only emitted after every transport has
+/// failed to connect, or after the connected transport disconnects - there is
+/// no fallback once a connection has been established. If both transports
+/// fail, the disconnect reason is a [`FallbackError::AllFailed`] holding why
+/// each one failed.
+///
+/// # Nesting
+///
+/// To try more than two transports in order, nest this type, i.e.
+/// `FallbackClient<A, FallbackClient<B, C>>`. The inner client's `connect`
+/// function is then passed as `connect_b`:
+///
+/// ```ignore
+/// client.connect(
+///     |a| a.connect(/* .. */),
+///     move |bc| bc.connect(|b| b.connect(/* .. */), move |c| c.connect(/* .. */)),
+/// )
+/// ```
+///
+/// If all three fail, the reason for `B` and `C` failing is nested inside the
+/// [`FallbackError::AllFailed::b`] of the outer client. Each level has its
+/// own timeout.
+///
+/// Use [`FallbackClient::state`] to find out which transport succeeded, by
+/// checking whether the [`ClientState::Connected`] holds a [`Multi::A`] or a
+/// [`Multi::B`].
+#[derive(Derivative)]
+#[derivative(Debug(bound = "A: Debug, B: Debug"))]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct FallbackClient<A: ClientTransport, B: ClientTransport> {
+    a: A,
+    b: B,
+    timeout: Duration,
+    active: Active,
+    #[derivative(Debug = "ignore")]
+    connect_b: Option<ConnectFn<B>>,
+    a_failure: Option<DisconnectReason<A::Error>>,
+}
+
+type ConnectFn<T> =
+    Box<dyn FnOnce(&mut T) -> Result<(), <T as ClientTransport>::Error> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Active {
+    None,
+    A { elapsed: Duration, connected: bool },
+    B,
+}
+
+/// Error type for operations on a [`FallbackClient`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum FallbackError<A, B> {
+    /// Client is already connecting or connected.
+    #[error("already connecting or connected")]
+    AlreadyConnected,
+    /// Error from the first transport.
+    #[error(transparent)]
+    A(A),
+    /// Error from the second transport.
+    #[error(transparent)]
+    B(B),
+    /// Both transports failed to connect.
+    #[error("all transports failed to connect (first: {a}; second: {b})")]
+    AllFailed {
+        /// Why the first transport failed to connect, or
+        /// [`FALLBACK_TIMEOUT_REASON`] if it timed out.
+        a: DisconnectReason<A>,
+        /// Why the second transport failed to connect.
+        b: DisconnectReason<B>,
+    },
+}
+
+impl<A: ClientTransport, B: ClientTransport> FallbackClient<A, B> {
+    /// Wraps two disconnected transports.
+    ///
+    /// `timeout` is how long the first transport is given to connect before
+    /// falling back to the second one.
+    #[must_use]
+    pub const fn new(a: A, b: B, timeout: Duration) -> Self {
+        Self {
+            a,
+            b,
+            timeout,
+            active: Active::None,
+            connect_b: None,
+            a_failure: None,
+        }
+    }
+
+    /// Gets a reference to the first transport.
+    pub const fn a(&self) -> &A {
+        &self.a
+    }
+
+    /// Gets a mutable reference to the first transport.
+    pub fn a_mut(&mut self) -> &mut A {
+        &mut self.a
+    }
+
+    /// Gets a reference to the second transport.
+    pub const fn b(&self) -> &B {
+        &self.b
+    }
+
+    /// Gets a mutable reference to the second transport.
+    pub fn b_mut(&mut self) -> &mut B {
+        &mut self.b
+    }
+
+    /// Gets how long the first transport is given to connect.
+    pub const fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets how long the first transport is given to connect.
+    ///
+    /// This does not affect a connection attempt which is already in progress.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Starts connecting using the first transport, and stores how to connect
+    /// using the second transport in case the first one fails.
+    ///
+    /// `connect_a` and `connect_b` should call the transport-specific connect
+    /// function on the transport they are given, e.g.
+    /// `|client| client.connect(&runtime, config, target)`. `connect_b` is only
+    /// called if the first transport fails.
+    ///
+    /// If `connect_a` returns an error, `connect_b` is called immediately.
+    ///
+    /// # Errors
+    ///
+    /// Errors if this client is already connecting or connected, or with
+    /// [`FallbackError::AllFailed`] if both transports failed to start
+    /// connecting.
+    pub fn connect(
+        &mut self,
+        connect_a: impl FnOnce(&mut A) -> Result<(), A::Error>,
+        connect_b: impl FnOnce(&mut B) -> Result<(), B::Error> + Send + Sync + 'static,
+    ) -> Result<(), FallbackError<A::Error, B::Error>> {
+        if self.active != Active::None {
+            return Err(FallbackError::AlreadyConnected);
+        }
+
+        self.a_failure = None;
+        match connect_a(&mut self.a) {
+            Ok(()) => {
+                self.active = Active::A {
+                    elapsed: Duration::ZERO,
+                    connected: false,
+                };
+                self.connect_b = Some(Box::new(connect_b));
+                Ok(())
+            }
+            Err(err) => {
+                self.connect_b = Some(Box::new(connect_b));
+                self.fall_back(DisconnectReason::Error(err))
+            }
+        }
+    }
+
+    /// Starts connecting using the second transport after the first one failed
+    /// for `a_failure`, which is kept until the second transport connects.
+    fn fall_back(
+        &mut self,
+        a_failure: DisconnectReason<A::Error>,
+    ) -> Result<(), FallbackError<A::Error, B::Error>> {
+        self.active = Active::None;
+        let connect_b = self
+            .connect_b
+            .take()
+            .expect("should only fall back when `connect_b` is present");
+        match connect_b(&mut self.b) {
+            Ok(()) => {
+                self.active = Active::B;
+                self.a_failure = Some(a_failure);
+                Ok(())
+            }
+            Err(err) => Err(FallbackError::AllFailed {
+                a: a_failure,
+                b: DisconnectReason::Error(err),
+            }),
+        }
+    }
+}
+
+impl<A: ClientTransport, B: ClientTransport> ClientTransport for FallbackClient<A, B> {
+    type Error = FallbackError<A::Error, B::Error>;
+
+    type Connecting<'this>
+        = Multi<A::Connecting<'this>, B::Connecting<'this>>
+    where
+        Self: 'this;
+
+    type Connected<'this>
+        = Multi<A::Connected<'this>, B::Connected<'this>>
+    where
+        Self: 'this;
+
+    type MessageKey = Multi<A::MessageKey, B::MessageKey>;
+
+    fn state(&self) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        match self.active {
+            Active::None => ClientState::Disconnected,
+            Active::A { .. } => self.a.state().map(Multi::A, Multi::A),
+            Active::B => self.b.state().map(Multi::B, Multi::B),
+        }
+    }
+
+    fn poll(&mut self, delta_time: Duration) -> impl Iterator<Item = ClientEvent<Self>> {
+        let mut events = Vec::new();
+
+        if let Active::A { .. } = self.active {
+            let a_events = self.a.poll(delta_time).collect::<Vec<_>>();
+            for event in a_events {
+                let Active::A { connected, .. } = &mut self.active else {
+                    break;
+                };
+                match event {
+                    ClientEvent::Connected => {
+                        *connected = true;
+                        // we won't need to fall back anymore
+                        self.connect_b = None;
+                        events.push(ClientEvent::Connected);
+                    }
+                    ClientEvent::Disconnected { reason }
+                        if !*connected && self.connect_b.is_some() =>
+                    {
+                        if let Err(err) = self.fall_back(reason) {
+                            events.push(ClientEvent::Disconnected {
+                                reason: DisconnectReason::Error(err),
+                            });
+                        }
+                    }
+                    ClientEvent::Disconnected { reason } => {
+                        self.active = Active::None;
+                        events.push(ClientEvent::Disconnected {
+                            reason: reason.map_err(FallbackError::A),
+                        });
+                    }
+                    ClientEvent::Recv { msg, lane } => events.push(ClientEvent::Recv { msg, lane }),
+                    ClientEvent::Ack { msg_key } => events.push(ClientEvent::Ack {
+                        msg_key: Multi::A(msg_key),
+                    }),
+                    ClientEvent::Nack { msg_key } => events.push(ClientEvent::Nack {
+                        msg_key: Multi::A(msg_key),
+                    }),
+                }
+            }
+
+            if let Active::A { elapsed, connected } = &mut self.active {
+                *elapsed += delta_time;
+                if !*connected && *elapsed >= self.timeout && self.connect_b.is_some() {
+                    let _ = self.a.disconnect(FALLBACK_TIMEOUT_REASON);
+                    // the transport may have failed by itself since we last
+                    // polled it, in which case that is the more useful reason
+                    let a_failure = self
+                        .a
+                        .poll(Duration::ZERO)
+                        .find_map(|event| match event {
+                            ClientEvent::Disconnected { reason } => Some(reason),
+                            _ => None,
+                        })
+                        .unwrap_or_else(|| {
+                            DisconnectReason::Local(FALLBACK_TIMEOUT_REASON.to_owned())
+                        });
+                    if let Err(err) = self.fall_back(a_failure) {
+                        events.push(ClientEvent::Disconnected {
+                            reason: DisconnectReason::Error(err),
+                        });
+                    }
+                }
+            }
+        }
+
+        if self.active == Active::B {
+            for event in self.b.poll(delta_time) {
+                events.push(match event {
+                    ClientEvent::Connected => {
+                        self.a_failure = None;
+                        ClientEvent::Connected
+                    }
+                    ClientEvent::Disconnected { reason } => {
+                        self.active = Active::None;
+                        let reason = match self.a_failure.take() {
+                            Some(a) => {
+                                DisconnectReason::Error(FallbackError::AllFailed { a, b: reason })
+                            }
+                            None => reason.map_err(FallbackError::B),
+                        };
+                        ClientEvent::Disconnected { reason }
+                    }
+                    ClientEvent::Recv { msg, lane } => ClientEvent::Recv { msg, lane },
+                    ClientEvent::Ack { msg_key } => ClientEvent::Ack {
+                        msg_key: Multi::B(msg_key),
+                    },
+                    ClientEvent::Nack { msg_key } => ClientEvent::Nack {
+                        msg_key: Multi::B(msg_key),
+                    },
+                });
+            }
+        }
+
+        events.into_iter()
+    }
+
+    fn send(
+        &mut self,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        match self.active {
+            Active::A { .. } => self
+                .a
+                .send(msg, lane)
+                .map(Multi::A)
+                .map_err(FallbackError::A),
+            Active::B | Active::None => self
+                .b
+                .send(msg, lane)
+                .map(Multi::B)
+                .map_err(FallbackError::B),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        match self.active {
+            Active::A { .. } => self.a.flush().map_err(FallbackError::A),
+            Active::B | Active::None => self.b.flush().map_err(FallbackError::B),
+        }
+    }
+
+    fn disconnect(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
+        // a local disconnect must not trigger a fallback
+        self.connect_b = None;
+        self.a_failure = None;
+        match self.active {
+            Active::A { .. } => self.a.disconnect(reason).map_err(FallbackError::A),
+            Active::B | Active::None => self.b.disconnect(reason).map_err(FallbackError::B),
+        }
+    }
+}
@@ -5,6 +5,14 @@ mod bevy;
 
 #[cfg(feature = "bevy")]
 pub use bevy::*;
+
+mod fallback;
+pub use fallback::*;
+
+#[cfg(feature = "reconnect")]
+mod reconnect;
+#[cfg(feature = "reconnect")]
+pub use reconnect::*;
 use web_time::Duration;
 
 use std::{error::Error, fmt::Debug, hash::Hash};
@@ -121,7 +129,7 @@ pub trait ClientTransport {
 /// of bytes sent or [round-trip time], if the transport exposes it.
 ///
 /// [round-trip time]: crate::stats::Rtt
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum ClientState<A, B> {
     /// Not connected to a server, and making no attempts to connect to one.
     #[default]
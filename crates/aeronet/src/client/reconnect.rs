@@ -0,0 +1,358 @@
+use std::fmt::Debug;
+
+use bytes::Bytes;
+use derivative::Derivative;
+use rand::Rng;
+use web_time::Duration;
+
+use crate::{
+    client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
+    lane::LaneIndex,
+};
+
+/// Configuration for a [`ReconnectingClient`].
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// How long to wait before the first reconnection attempt.
+    pub initial_delay: Duration,
+    /// Maximum time to wait between reconnection attempts.
+    pub max_delay: Duration,
+    /// How much the delay is multiplied by after each failed attempt.
+    ///
+    /// Must be at least 1, otherwise [`ReconnectConfig::validate`] fails.
+    pub multiplier: f64,
+    /// Fraction of each delay which is randomly removed, so that many clients
+    /// disconnected at the same time don't all reconnect at the same time.
+    ///
+    /// Must be in the range `[0, 1]`, otherwise [`ReconnectConfig::validate`]
+    /// fails.
+    pub jitter: f64,
+    /// Maximum number of reconnection attempts in a row before giving up, or
+    /// [`None`] to never give up.
+    pub max_attempts: Option<u32>,
+    /// Whether to reconnect after the server deliberately disconnected us, i.e.
+    /// [`DisconnectReason::Remote`].
+    ///
+    /// Clients always reconnect after a [`DisconnectReason::Error`], and never
+    /// after a [`DisconnectReason::Local`].
+    pub reconnect_on_remote: bool,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+            max_attempts: Some(10),
+            reconnect_on_remote: false,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// Gets the delay before attempt number `attempt` (starting from 0),
+    /// before jitter is applied.
+    #[must_use]
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = i32::try_from(attempt).unwrap_or(i32::MAX);
+        let secs = self.initial_delay.as_secs_f64() * self.multiplier.powi(exp);
+        Duration::try_from_secs_f64(secs)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// Checks that the values of this configuration are in their valid
+    /// ranges.
+    ///
+    /// # Errors
+    ///
+    /// Errors if any value is out of range.
+    pub fn validate(&self) -> Result<(), ReconnectConfigError> {
+        if self.multiplier.is_nan() || self.multiplier < 1.0 {
+            return Err(ReconnectConfigError::InvalidMultiplier);
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(ReconnectConfigError::InvalidJitter);
+        }
+        Ok(())
+    }
+}
+
+/// A [`ReconnectConfig`] has a value out of its valid range.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReconnectConfigError {
+    /// [`ReconnectConfig::multiplier`] was less than 1.
+    #[error("multiplier must be at least 1")]
+    InvalidMultiplier,
+    /// [`ReconnectConfig::jitter`] was not in the range `[0, 1]`.
+    #[error("jitter must be in the range [0, 1]")]
+    InvalidJitter,
+}
+
+/// Event emitted by a [`ReconnectingClient`] about its reconnection attempts.
+///
+/// See [`ReconnectingClient::drain_reconnect_events`].
+///
+/// In Bevy, these are sent wrapped in a `LocalClientReconnect` event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconnectEvent {
+    /// The client started another attempt at reconnecting.
+    Attempt {
+        /// Number of this attempt, starting from 1.
+        attempt: u32,
+    },
+    /// The client connected again after losing its connection.
+    Reconnected {
+        /// How many attempts it took to reconnect.
+        attempts: u32,
+    },
+    /// The client reached the maximum number of attempts, and will not try to
+    /// reconnect anymore.
+    GaveUp {
+        /// How many attempts were made.
+        attempts: u32,
+    },
+}
+
+/// Wrapper around a [`ClientTransport`] which automatically reconnects when the
+/// connection is lost, using exponential backoff with jitter.
+///
+/// Use [`ReconnectingClient::connect`] to start connecting - the connection
+/// function passed in is stored, and called again each time this client tries
+/// to reconnect.
+///
+/// The inner transport's events are passed through [`ClientTransport::poll`]
+/// unchanged, including [`ClientEvent::Disconnected`] when the connection is
+/// lost. Events about reconnection attempts are buffered separately, and can
+/// be read using [`ReconnectingClient::drain_reconnect_events`]. In Bevy, add
+/// the `send_reconnect_events` system after polling this client to receive
+/// them as `LocalClientReconnect` events instead.
+///
+/// See [`ReconnectConfig`] for which disconnect reasons cause a reconnection.
+#[derive(Derivative)]
+#[derivative(Debug(bound = "T: Debug"))]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct ReconnectingClient<T: ClientTransport> {
+    inner: T,
+    config: ReconnectConfig,
+    #[derivative(Debug = "ignore")]
+    connect: Option<ConnectFn<T>>,
+    backoff: Option<Backoff>,
+    attempts: u32,
+    events: Vec<ReconnectEvent>,
+}
+
+type ConnectFn<T> =
+    Box<dyn FnMut(&mut T) -> Result<(), <T as ClientTransport>::Error> + Send + Sync>;
+
+#[derive(Debug, Clone)]
+struct Backoff {
+    delay: Duration,
+    elapsed: Duration,
+}
+
+impl<T: ClientTransport> ReconnectingClient<T> {
+    /// Wraps an existing client transport.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the configuration provided is invalid.
+    pub fn new(inner: T, config: ReconnectConfig) -> Result<Self, ReconnectConfigError> {
+        config.validate()?;
+        Ok(Self {
+            inner,
+            config,
+            connect: None,
+            backoff: None,
+            attempts: 0,
+            events: Vec::new(),
+        })
+    }
+
+    /// Gets a reference to the inner transport.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the inner transport.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Gets the configuration of this client.
+    pub const fn config(&self) -> &ReconnectConfig {
+        &self.config
+    }
+
+    /// Sets the configuration of this client.
+    ///
+    /// This does not affect a reconnection attempt which is already scheduled.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the configuration provided is invalid, in which case the
+    /// current configuration is kept.
+    pub fn set_config(&mut self, config: ReconnectConfig) -> Result<(), ReconnectConfigError> {
+        config.validate()?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Gets if the inner transport is disconnected, and this client is waiting
+    /// to attempt reconnecting.
+    pub const fn is_reconnecting(&self) -> bool {
+        self.backoff.is_some()
+    }
+
+    /// Starts connecting the inner transport using `connect`, and stores
+    /// `connect` so that it can be used to reconnect later.
+    ///
+    /// `connect` should call the transport-specific connect function on the
+    /// transport it is given, e.g.
+    /// `|client| client.connect(&runtime, config.clone(), target)`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `connect` errors.
+    pub fn connect(
+        &mut self,
+        mut connect: impl FnMut(&mut T) -> Result<(), T::Error> + Send + Sync + 'static,
+    ) -> Result<(), T::Error> {
+        connect(&mut self.inner)?;
+        self.connect = Some(Box::new(connect));
+        self.backoff = None;
+        self.attempts = 0;
+        Ok(())
+    }
+
+    /// Takes all [`ReconnectEvent`]s emitted since the last call to this
+    /// function.
+    pub fn drain_reconnect_events(&mut self) -> impl Iterator<Item = ReconnectEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    const fn should_reconnect(&self, reason: &DisconnectReason<T::Error>) -> bool {
+        match reason {
+            DisconnectReason::Local(_) => false,
+            DisconnectReason::Remote(_) => self.config.reconnect_on_remote,
+            DisconnectReason::Error(_) => true,
+        }
+    }
+
+    fn schedule(&mut self) {
+        if self
+            .config
+            .max_attempts
+            .is_some_and(|max| self.attempts >= max)
+        {
+            self.connect = None;
+            self.events.push(ReconnectEvent::GaveUp {
+                attempts: self.attempts,
+            });
+            return;
+        }
+
+        let delay = self.config.delay(self.attempts);
+        let jitter = rand::thread_rng().gen_range(0.0..=self.config.jitter);
+        self.backoff = Some(Backoff {
+            delay: delay.mul_f64(1.0 - jitter),
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    fn attempt(&mut self) {
+        let Some(connect) = &mut self.connect else {
+            return;
+        };
+        self.attempts += 1;
+        self.events.push(ReconnectEvent::Attempt {
+            attempt: self.attempts,
+        });
+        if connect(&mut self.inner).is_err() {
+            self.schedule();
+        }
+    }
+}
+
+impl<T: ClientTransport> ClientTransport for ReconnectingClient<T> {
+    type Error = T::Error;
+
+    type Connecting<'this>
+        = T::Connecting<'this>
+    where
+        Self: 'this;
+
+    type Connected<'this>
+        = T::Connected<'this>
+    where
+        Self: 'this;
+
+    type MessageKey = T::MessageKey;
+
+    fn state(&self) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        self.inner.state()
+    }
+
+    fn poll(&mut self, delta_time: Duration) -> impl Iterator<Item = ClientEvent<Self>> {
+        let events = self
+            .inner
+            .poll(delta_time)
+            .map(ClientEvent::remap)
+            .collect::<Vec<_>>();
+
+        for event in &events {
+            match event {
+                ClientEvent::Connected => {
+                    if self.attempts > 0 {
+                        self.events.push(ReconnectEvent::Reconnected {
+                            attempts: self.attempts,
+                        });
+                    }
+                    self.attempts = 0;
+                }
+                ClientEvent::Disconnected { reason } => {
+                    if self.connect.is_some() && self.should_reconnect(reason) {
+                        self.schedule();
+                    } else {
+                        self.connect = None;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(backoff) = &mut self.backoff {
+            backoff.elapsed += delta_time;
+            if backoff.elapsed >= backoff.delay {
+                self.backoff = None;
+                self.attempt();
+            }
+        }
+
+        events.into_iter()
+    }
+
+    fn send(
+        &mut self,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        self.inner.send(msg, lane)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn disconnect(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
+        self.connect = None;
+        if self.backoff.take().is_some() {
+            // the inner transport is already disconnected, and has already
+            // emitted its disconnect event
+            return Ok(());
+        }
+        self.inner.disconnect(reason)
+    }
+}
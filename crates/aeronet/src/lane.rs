@@ -47,11 +47,43 @@
 //! messages *may* be dropped if they are older than the last received message.
 //! You should probably use [`UnreliableSequenced`] instead.
 //!
+//! # Declaring lanes
+//!
+//! The lanes that an app uses are usually declared as a fieldless enum, where
+//! each variant is converted into a [`LaneIndex`] to send on, and into a
+//! [`LaneKind`] to configure the transport with. With the `derive` feature
+//! enabled, [`Lanes`](macro@Lanes) can be derived to generate these
+//! conversions, so that the index and kind of each lane are defined in one
+//! place:
+//!
+//! ```
+//! # #[cfg(feature = "derive")] {
+//! use aeronet::lane::{LaneIndex, LaneKind, Lanes};
+//!
+//! #[derive(Debug, Clone, Copy, Lanes)]
+//! enum AppLane {
+//!     #[lane(UnreliableUnordered)]
+//!     Movement,
+//!     #[lane(ReliableOrdered)]
+//!     Chat,
+//! }
+//!
+//! assert_eq!(LaneIndex::from_raw(1), LaneIndex::from(AppLane::Chat));
+//! assert_eq!(
+//!     &[LaneKind::UnreliableUnordered, LaneKind::ReliableOrdered],
+//!     AppLane::KINDS,
+//! );
+//! # }
+//! ```
+//!
 //! [`UnreliableUnordered`]: LaneKind::UnreliableUnordered
 //! [`UnreliableSequenced`]: LaneKind::UnreliableSequenced
 //! [`ReliableUnordered`]: LaneKind::ReliableUnordered
 //! [`ReliableOrdered`]: LaneKind::ReliableOrdered
 
+#[cfg(feature = "derive")]
+pub use aeronet_derive::Lanes;
+
 /// Kind of lane which can provide guarantees about the manner of message
 /// delivery.
 ///
@@ -212,3 +244,16 @@ impl LaneIndex {
         self.0
     }
 }
+
+/// Set of lanes used by an app, where each lane can be converted into its
+/// [`LaneIndex`] and [`LaneKind`].
+///
+/// This is usually derived on a fieldless enum using
+/// [`Lanes`](macro@Lanes) - see [`lane`](crate::lane).
+pub trait Lanes: Into<LaneIndex> + Into<LaneKind> {
+    /// Kinds of all lanes in this set, ordered by their [`LaneIndex`].
+    ///
+    /// Pass this into the lane configuration of your transport, e.g.
+    /// `SessionConfig::with_lanes(AppLane::KINDS.iter().copied())`.
+    const KINDS: &'static [LaneKind];
+}
@@ -17,3 +17,18 @@ pub mod server;
 
 #[cfg(feature = "condition")]
 pub mod condition;
+
+#[cfg(feature = "message")]
+pub mod message;
+
+#[cfg(feature = "replay")]
+pub mod replay;
+
+#[cfg(feature = "rpc")]
+pub mod rpc;
+
+#[cfg(all(feature = "transfer", any(feature = "client", feature = "server")))]
+pub mod transfer;
+
+#[cfg(feature = "testing")]
+pub mod testing;
@@ -0,0 +1,154 @@
+use std::{
+    any::{Any, TypeId},
+    fmt::Debug,
+    marker::PhantomData,
+};
+
+use bevy_ecs::prelude::*;
+use derivative::Derivative;
+
+use super::{DecodedMessage, MessageRegistry};
+
+#[cfg(feature = "client")]
+use crate::client::ClientTransport;
+
+#[cfg(feature = "server")]
+use crate::server::ServerTransport;
+
+/// Our client received a message of type `M` from the server.
+///
+/// Sent by [`MessageRegistry::send_client_event`], if the event was added
+/// using [`MessageRegistry::add_client_event`].
+#[cfg(feature = "client")]
+#[derive(Derivative, Event)]
+#[derivative(Debug(bound = "M: Debug"), Clone(bound = "M: Clone"))]
+pub struct MessageFromServer<T: ClientTransport, M> {
+    /// The received message.
+    pub msg: M,
+    #[derivative(Debug = "ignore")]
+    #[doc(hidden)]
+    pub _phantom: PhantomData<T>,
+}
+
+/// Our server received a message of type `M` from a client.
+///
+/// Sent by [`MessageRegistry::send_server_event`], if the event was added
+/// using [`MessageRegistry::add_server_event`].
+#[cfg(feature = "server")]
+#[derive(Derivative, Event)]
+#[derivative(Debug(bound = "M: Debug"), Clone(bound = "M: Clone"))]
+pub struct MessageFromClient<T: ServerTransport, M> {
+    /// Key of the client which sent the message.
+    pub client_key: T::ClientKey,
+    /// The received message.
+    pub msg: M,
+}
+
+#[cfg(feature = "client")]
+type ClientEventFn = fn(&mut Commands, Box<dyn Any + Send + Sync>);
+
+#[cfg(feature = "server")]
+type ServerEventFn<T> =
+    fn(&mut Commands, <T as ServerTransport>::ClientKey, Box<dyn Any + Send + Sync>);
+
+#[cfg(feature = "client")]
+impl MessageRegistry {
+    /// Makes [`MessageRegistry::send_client_event`] send messages of type `M`
+    /// received by the client `T` as [`MessageFromServer<T, M>`] events.
+    ///
+    /// The event must also be added to the app, e.g. using `App::add_event`.
+    #[allow(clippy::missing_panics_doc)] // shouldn't panic
+    pub fn add_client_event<T, M>(&mut self) -> &mut Self
+    where
+        T: ClientTransport + Send + Sync + 'static,
+        M: Send + Sync + 'static,
+    {
+        let send: ClientEventFn = |commands, msg| {
+            let msg = *msg
+                .downcast::<M>()
+                .expect("message should be of the registered type");
+            commands.add(move |world: &mut World| {
+                world.send_event(MessageFromServer::<T, M> {
+                    msg,
+                    _phantom: PhantomData,
+                });
+            });
+        };
+        self.events
+            .insert((TypeId::of::<T>(), TypeId::of::<M>()), Box::new(send));
+        self
+    }
+
+    /// Sends `msg`, which was received by the client `T`, as a
+    /// [`MessageFromServer`] event for its type.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the original message if no event was added for its type
+    /// using [`MessageRegistry::add_client_event`].
+    pub fn send_client_event<T: ClientTransport + 'static>(
+        &self,
+        commands: &mut Commands,
+        msg: DecodedMessage,
+    ) -> Result<(), DecodedMessage> {
+        let Some(send) = self
+            .events
+            .get(&(TypeId::of::<T>(), msg.type_id))
+            .and_then(|send| send.downcast_ref::<ClientEventFn>())
+        else {
+            return Err(msg);
+        };
+        send(commands, msg.msg);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "server")]
+impl MessageRegistry {
+    /// Makes [`MessageRegistry::send_server_event`] send messages of type `M`
+    /// received by the server `T` as [`MessageFromClient<T, M>`] events.
+    ///
+    /// The event must also be added to the app, e.g. using `App::add_event`.
+    #[allow(clippy::missing_panics_doc)] // shouldn't panic
+    pub fn add_server_event<T, M>(&mut self) -> &mut Self
+    where
+        T: ServerTransport + Send + Sync + 'static,
+        M: Send + Sync + 'static,
+    {
+        let send: ServerEventFn<T> = |commands, client_key, msg| {
+            let msg = *msg
+                .downcast::<M>()
+                .expect("message should be of the registered type");
+            commands.add(move |world: &mut World| {
+                world.send_event(MessageFromClient::<T, M> { client_key, msg });
+            });
+        };
+        self.events
+            .insert((TypeId::of::<T>(), TypeId::of::<M>()), Box::new(send));
+        self
+    }
+
+    /// Sends `msg`, which was received by the server `T` from `client_key`, as
+    /// a [`MessageFromClient`] event for its type.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the original message if no event was added for its type
+    /// using [`MessageRegistry::add_server_event`].
+    pub fn send_server_event<T: ServerTransport + 'static>(
+        &self,
+        commands: &mut Commands,
+        client_key: T::ClientKey,
+        msg: DecodedMessage,
+    ) -> Result<(), DecodedMessage> {
+        let Some(send) = self
+            .events
+            .get(&(TypeId::of::<T>(), msg.type_id))
+            .and_then(|send| send.downcast_ref::<ServerEventFn<T>>())
+        else {
+            return Err(msg);
+        };
+        send(commands, client_key, msg.msg);
+        Ok(())
+    }
+}
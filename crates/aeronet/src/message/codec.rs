@@ -0,0 +1,70 @@
+use std::error::Error;
+
+use bytes::Bytes;
+
+/// Error produced by a [`Codec`] when it fails to encode or decode a message.
+pub type CodecError = Box<dyn Error + Send + Sync>;
+
+/// Converts values of type `M` to and from their wire representation.
+///
+/// A codec is given to [`MessageRegistry::register`] along with the message
+/// type it handles. The registry takes care of identifying which type a
+/// message is, so a codec only needs to deal with the value itself.
+///
+/// # Example
+///
+/// ```
+/// use aeronet::{bytes::Bytes, message::{Codec, CodecError}};
+///
+/// struct Ping(u32);
+///
+/// struct PingCodec;
+///
+/// impl Codec<Ping> for PingCodec {
+///     fn encode(&self, msg: &Ping) -> Result<Bytes, CodecError> {
+///         Ok(Bytes::copy_from_slice(&msg.0.to_le_bytes()))
+///     }
+///
+///     fn decode(&self, buf: &[u8]) -> Result<Ping, CodecError> {
+///         let buf = <[u8; 4]>::try_from(buf)?;
+///         Ok(Ping(u32::from_le_bytes(buf)))
+///     }
+/// }
+/// ```
+///
+/// [`MessageRegistry::register`]: crate::message::MessageRegistry::register
+pub trait Codec<M>: Send + Sync + 'static {
+    /// Encodes `msg` into bytes.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `msg` cannot be represented by this codec.
+    fn encode(&self, msg: &M) -> Result<Bytes, CodecError>;
+
+    /// Decodes a message from the bytes produced by [`Codec::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if `buf` is not a valid encoding of `M`.
+    fn decode(&self, buf: &[u8]) -> Result<M, CodecError>;
+}
+
+/// [`Codec`] which encodes any [`serde`] type using
+/// [`bincode`](https://docs.rs/bincode).
+#[cfg(feature = "bincode")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl<M> Codec<M> for BincodeCodec
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn encode(&self, msg: &M) -> Result<Bytes, CodecError> {
+        Ok(Bytes::from(bincode::serialize(msg)?))
+    }
+
+    fn decode(&self, buf: &[u8]) -> Result<M, CodecError> {
+        Ok(bincode::deserialize(buf)?)
+    }
+}
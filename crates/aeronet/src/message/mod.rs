@@ -0,0 +1,425 @@
+//! Typed messages on top of the raw [`Bytes`] sent and received by transports.
+//!
+//! Transports only deal in bytes and [`LaneIndex`]es, which leaves it up to
+//! the user to decide how to turn their message types into bytes, which lane
+//! each type is sent on, and how to tell which type a received message is. A
+//! [`MessageRegistry`] answers all of these: each message type is registered
+//! once, along with the lane it is sent on and the [`Codec`] used to encode it.
+//!
+//! # Sending
+//!
+//! Use [`TypedClientTransport::send_typed`] or
+//! [`TypedServerTransport::send_typed`], which are implemented for every
+//! transport. The message is encoded, and sent on the lane that its type was
+//! registered with.
+//!
+//! # Receiving
+//!
+//! Pass the bytes of a received message to [`MessageRegistry::decode`] to get
+//! back a [`DecodedMessage`], then [`downcast`](DecodedMessage::downcast) it
+//! into one of your message types.
+//!
+//! With the `bevy` feature enabled, a decoded message can also be sent as an
+//! event specific to its type - see [`MessageFromServer`] and
+//! [`MessageFromClient`].
+//!
+//! # Wire format
+//!
+//! Each message type is given an ID based on the order that it was registered
+//! in, starting from 0. A message is encoded as its ID as a little-endian
+//! [`u16`], followed by the bytes produced by its codec. This means that both
+//! sides of a connection must register the same types, in the same order.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(feature = "bincode")] {
+//! use aeronet::{
+//!     lane::LaneIndex,
+//!     message::{BincodeCodec, MessageRegistry},
+//! };
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Chat(String);
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Move { x: f32, y: f32 }
+//!
+//! let registry = MessageRegistry::new()
+//!     .with::<Chat>(LaneIndex::from_raw(0), BincodeCodec)
+//!     .with::<Move>(LaneIndex::from_raw(1), BincodeCodec);
+//!
+//! let (bytes, lane) = registry.encode(&Move { x: 1.0, y: 2.0 }).unwrap();
+//! assert_eq!(1, lane.into_raw());
+//!
+//! let msg = registry.decode(&bytes).unwrap();
+//! assert_eq!(Move { x: 1.0, y: 2.0 }, msg.downcast::<Move>().unwrap());
+//! # }
+//! ```
+
+mod codec;
+pub use codec::*;
+
+#[cfg(all(feature = "bevy", any(feature = "client", feature = "server")))]
+mod bevy;
+#[cfg(all(feature = "bevy", any(feature = "client", feature = "server")))]
+pub use bevy::*;
+
+use std::{
+    any::{self, Any, TypeId},
+    collections::HashMap,
+    sync::Arc,
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use derivative::Derivative;
+
+use crate::lane::LaneIndex;
+
+/// Type-erased function which encodes a message.
+type EncodeFn = Box<dyn Fn(&dyn Any) -> Result<Bytes, CodecError> + Send + Sync>;
+
+/// Type-erased function which decodes a message.
+type DecodeFn = Box<dyn Fn(&[u8]) -> Result<Box<dyn Any + Send + Sync>, CodecError> + Send + Sync>;
+
+/// Set of message types which can be sent and received, along with the lane
+/// and [`Codec`] of each type.
+///
+/// See [`message`](crate::message).
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct MessageRegistry {
+    types: Vec<MessageType>,
+    ids: HashMap<TypeId, u16>,
+    #[cfg(all(feature = "bevy", any(feature = "client", feature = "server")))]
+    #[derivative(Debug = "ignore")]
+    events: HashMap<(TypeId, TypeId), Box<dyn Any + Send + Sync>>,
+}
+
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct MessageType {
+    type_id: TypeId,
+    type_name: &'static str,
+    lane: LaneIndex,
+    #[derivative(Debug = "ignore")]
+    encode: EncodeFn,
+    #[derivative(Debug = "ignore")]
+    decode: DecodeFn,
+}
+
+/// Failed to encode a typed message using a [`MessageRegistry`].
+#[derive(Debug, thiserror::Error)]
+pub enum EncodeError {
+    /// The message type was not registered.
+    #[error("message type `{type_name}` is not registered")]
+    NotRegistered {
+        /// Name of the message type.
+        type_name: &'static str,
+    },
+    /// The codec of the message type failed to encode the message.
+    #[error("failed to encode `{type_name}`")]
+    Codec {
+        /// Name of the message type.
+        type_name: &'static str,
+        /// Error produced by the codec.
+        #[source]
+        source: CodecError,
+    },
+}
+
+/// Failed to decode a typed message using a [`MessageRegistry`].
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    /// The message was too short to contain a message type ID.
+    #[error("message too short to contain a type ID")]
+    TooShort,
+    /// The message type ID does not refer to any registered message type.
+    #[error("unknown message type ID {id}")]
+    UnknownId {
+        /// ID of the message type.
+        id: u16,
+    },
+    /// The codec of the message type failed to decode the message.
+    #[error("failed to decode `{type_name}`")]
+    Codec {
+        /// Name of the message type.
+        type_name: &'static str,
+        /// Error produced by the codec.
+        #[source]
+        source: CodecError,
+    },
+}
+
+/// Failed to send a typed message using
+/// [`TypedClientTransport::send_typed`] or
+/// [`TypedServerTransport::send_typed`].
+#[derive(Debug, thiserror::Error)]
+pub enum SendTypedError<E> {
+    /// Failed to encode the message.
+    #[error(transparent)]
+    Encode(EncodeError),
+    /// The transport failed to send the encoded message.
+    #[error("failed to send message")]
+    Send(#[source] E),
+}
+
+impl MessageRegistry {
+    /// Creates a new registry with no message types.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the message type `M`, to be sent on `lane` and encoded using
+    /// `codec`.
+    ///
+    /// Message types are given IDs in the order that they are registered, so
+    /// both sides of a connection must register the same types in the same
+    /// order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `M` is already registered, or if more than [`u16::MAX`]
+    /// types are registered.
+    pub fn register<M: Send + Sync + 'static>(
+        &mut self,
+        lane: impl Into<LaneIndex>,
+        codec: impl Codec<M>,
+    ) -> &mut Self {
+        let type_id = TypeId::of::<M>();
+        let type_name = any::type_name::<M>();
+        assert!(
+            !self.ids.contains_key(&type_id),
+            "message type `{type_name}` is already registered"
+        );
+        let id = u16::try_from(self.types.len()).expect("too many message types registered");
+
+        let codec = Arc::new(codec);
+        let encode: EncodeFn = {
+            let codec = codec.clone();
+            Box::new(move |msg| {
+                let msg = msg
+                    .downcast_ref::<M>()
+                    .expect("message should be of the registered type");
+                codec.encode(msg)
+            })
+        };
+        let decode: DecodeFn = Box::new(move |buf| {
+            codec
+                .decode(buf)
+                .map(|msg| Box::new(msg) as Box<dyn Any + Send + Sync>)
+        });
+
+        self.types.push(MessageType {
+            type_id,
+            type_name,
+            lane: lane.into(),
+            encode,
+            decode,
+        });
+        self.ids.insert(type_id, id);
+        self
+    }
+
+    /// Registers the message type `M`, to be sent on `lane` and encoded using
+    /// `codec`.
+    ///
+    /// See [`MessageRegistry::register`].
+    ///
+    /// # Panics
+    ///
+    /// See [`MessageRegistry::register`].
+    #[must_use]
+    pub fn with<M: Send + Sync + 'static>(
+        mut self,
+        lane: impl Into<LaneIndex>,
+        codec: impl Codec<M>,
+    ) -> Self {
+        self.register::<M>(lane, codec);
+        self
+    }
+
+    /// Gets if the message type `M` is registered.
+    #[must_use]
+    pub fn contains<M: 'static>(&self) -> bool {
+        self.ids.contains_key(&TypeId::of::<M>())
+    }
+
+    /// Gets the lane which the message type `M` is sent on, or [`None`] if it
+    /// is not registered.
+    #[must_use]
+    pub fn lane<M: 'static>(&self) -> Option<LaneIndex> {
+        let id = self.ids.get(&TypeId::of::<M>())?;
+        Some(self.types[usize::from(*id)].lane)
+    }
+
+    /// Encodes `msg`, and gets the lane that it should be sent on.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `M` is not registered, or if its codec fails to encode `msg`.
+    pub fn encode<M: 'static>(&self, msg: &M) -> Result<(Bytes, LaneIndex), EncodeError> {
+        let type_name = any::type_name::<M>();
+        let id = *self
+            .ids
+            .get(&TypeId::of::<M>())
+            .ok_or(EncodeError::NotRegistered { type_name })?;
+        let ty = &self.types[usize::from(id)];
+
+        let body = (ty.encode)(msg).map_err(|source| EncodeError::Codec { type_name, source })?;
+        let mut buf = BytesMut::with_capacity(2 + body.len());
+        buf.put_u16_le(id);
+        buf.put_slice(&body);
+        Ok((buf.freeze(), ty.lane))
+    }
+
+    /// Decodes a message produced by [`MessageRegistry::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the message does not start with the ID of a registered
+    /// message type, or if the codec of that type fails to decode it.
+    pub fn decode(&self, msg: &[u8]) -> Result<DecodedMessage, DecodeError> {
+        let (id, body) = msg.split_first_chunk::<2>().ok_or(DecodeError::TooShort)?;
+        let id = u16::from_le_bytes(*id);
+        let ty = self
+            .types
+            .get(usize::from(id))
+            .ok_or(DecodeError::UnknownId { id })?;
+
+        let msg = (ty.decode)(body).map_err(|source| DecodeError::Codec {
+            type_name: ty.type_name,
+            source,
+        })?;
+        Ok(DecodedMessage {
+            type_id: ty.type_id,
+            type_name: ty.type_name,
+            msg,
+        })
+    }
+}
+
+/// Message decoded by [`MessageRegistry::decode`], which may be any of the
+/// registered message types.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct DecodedMessage {
+    type_id: TypeId,
+    type_name: &'static str,
+    #[derivative(Debug = "ignore")]
+    msg: Box<dyn Any + Send + Sync>,
+}
+
+impl DecodedMessage {
+    /// Gets the name of the type of this message.
+    #[must_use]
+    pub const fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Gets if this message is of type `M`.
+    #[must_use]
+    pub fn is<M: 'static>(&self) -> bool {
+        self.type_id == TypeId::of::<M>()
+    }
+
+    /// Gets a reference to this message as type `M`, or [`None`] if it is of a
+    /// different type.
+    #[must_use]
+    pub fn downcast_ref<M: 'static>(&self) -> Option<&M> {
+        self.msg.downcast_ref()
+    }
+
+    /// Converts this message into type `M`.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the original message if it is of a different type.
+    #[allow(clippy::missing_panics_doc)] // shouldn't panic
+    pub fn downcast<M: 'static>(self) -> Result<M, Self> {
+        if self.is::<M>() {
+            Ok(*self
+                .msg
+                .downcast()
+                .expect("message should be of the checked type"))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// Extension trait for sending typed messages on any [`ClientTransport`].
+///
+/// See [`message`](crate::message).
+///
+/// [`ClientTransport`]: crate::client::ClientTransport
+#[cfg(feature = "client")]
+pub trait TypedClientTransport: crate::client::ClientTransport {
+    /// Encodes `msg` using `registry`, and sends it to the server on the lane
+    /// that `M` was registered with.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the message could not be encoded, or if
+    /// [`ClientTransport::send`] fails.
+    ///
+    /// [`ClientTransport::send`]: crate::client::ClientTransport::send
+    fn send_typed<M: 'static>(
+        &mut self,
+        registry: &MessageRegistry,
+        msg: &M,
+    ) -> Result<Self::MessageKey, SendTypedError<Self::Error>>;
+}
+
+#[cfg(feature = "client")]
+impl<T: crate::client::ClientTransport> TypedClientTransport for T {
+    fn send_typed<M: 'static>(
+        &mut self,
+        registry: &MessageRegistry,
+        msg: &M,
+    ) -> Result<Self::MessageKey, SendTypedError<Self::Error>> {
+        let (msg, lane) = registry.encode(msg).map_err(SendTypedError::Encode)?;
+        self.send(msg, lane).map_err(SendTypedError::Send)
+    }
+}
+
+/// Extension trait for sending typed messages on any [`ServerTransport`].
+///
+/// See [`message`](crate::message).
+///
+/// [`ServerTransport`]: crate::server::ServerTransport
+#[cfg(feature = "server")]
+pub trait TypedServerTransport: crate::server::ServerTransport {
+    /// Encodes `msg` using `registry`, and sends it to the client
+    /// `client_key` on the lane that `M` was registered with.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the message could not be encoded, or if
+    /// [`ServerTransport::send`] fails.
+    ///
+    /// [`ServerTransport::send`]: crate::server::ServerTransport::send
+    fn send_typed<M: 'static>(
+        &mut self,
+        registry: &MessageRegistry,
+        client_key: Self::ClientKey,
+        msg: &M,
+    ) -> Result<Self::MessageKey, SendTypedError<Self::Error>>;
+}
+
+#[cfg(feature = "server")]
+impl<T: crate::server::ServerTransport> TypedServerTransport for T {
+    fn send_typed<M: 'static>(
+        &mut self,
+        registry: &MessageRegistry,
+        client_key: Self::ClientKey,
+        msg: &M,
+    ) -> Result<Self::MessageKey, SendTypedError<Self::Error>> {
+        let (msg, lane) = registry.encode(msg).map_err(SendTypedError::Encode)?;
+        self.send(client_key, msg, lane)
+            .map_err(SendTypedError::Send)
+    }
+}
@@ -0,0 +1,467 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+};
+
+use bytes::Bytes;
+use derivative::Derivative;
+use web_time::Duration;
+
+use crate::{
+    client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
+    lane::LaneIndex,
+};
+
+use super::{
+    disconnect_reason_string, put_bytes, put_disconnect_reason, put_duration, put_key, put_lane,
+    put_varint, Decoder, Encoder, ReadError, ReplayError, ReplayKey, Side, UNKNOWN_KEY,
+};
+
+/// Single entry in a recording of a [`ClientTransport`].
+///
+/// See [`replay`](crate::replay).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientRecord {
+    /// The client was polled.
+    ///
+    /// All event records up to the next `Poll` were emitted by this poll.
+    Poll {
+        /// State of the client before it was polled.
+        state: ClientState<(), ()>,
+        /// Time passed into the poll.
+        delta_time: Duration,
+    },
+    /// [`ClientEvent::Connected`] was emitted.
+    Connected,
+    /// [`ClientEvent::Disconnected`] was emitted.
+    Disconnected {
+        /// Why the client was disconnected.
+        reason: DisconnectReason<String>,
+    },
+    /// [`ClientEvent::Recv`] was emitted.
+    Recv {
+        /// The message received.
+        msg: Bytes,
+        /// Lane on which the message was received.
+        lane: LaneIndex,
+    },
+    /// [`ClientEvent::Ack`] was emitted.
+    Ack {
+        /// Recorded key of the message.
+        msg_key: u64,
+    },
+    /// [`ClientEvent::Nack`] was emitted.
+    Nack {
+        /// Recorded key of the message.
+        msg_key: u64,
+    },
+    /// A message was sent.
+    Send {
+        /// The message sent.
+        msg: Bytes,
+        /// Lane on which the message was sent.
+        lane: LaneIndex,
+        /// Recorded key of the message, or [`None`] if sending failed.
+        msg_key: Option<u64>,
+    },
+    /// The client was flushed.
+    Flush,
+    /// The client was disconnected by user code.
+    Disconnect {
+        /// Why the client was disconnected.
+        reason: String,
+    },
+}
+
+mod tag {
+    pub const POLL: u8 = 0;
+    pub const CONNECTED: u8 = 1;
+    pub const DISCONNECTED: u8 = 2;
+    pub const RECV: u8 = 3;
+    pub const ACK: u8 = 4;
+    pub const NACK: u8 = 5;
+    pub const SEND: u8 = 6;
+    pub const FLUSH: u8 = 7;
+    pub const DISCONNECT: u8 = 8;
+}
+
+impl ClientRecord {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::Poll { state, delta_time } => {
+                buf.push(tag::POLL);
+                buf.push(match state {
+                    ClientState::Disconnected => 0,
+                    ClientState::Connecting(()) => 1,
+                    ClientState::Connected(()) => 2,
+                });
+                put_duration(buf, *delta_time);
+            }
+            Self::Connected => buf.push(tag::CONNECTED),
+            Self::Disconnected { reason } => {
+                buf.push(tag::DISCONNECTED);
+                put_disconnect_reason(buf, reason);
+            }
+            Self::Recv { msg, lane } => {
+                buf.push(tag::RECV);
+                put_lane(buf, *lane);
+                put_bytes(buf, msg);
+            }
+            Self::Ack { msg_key } => {
+                buf.push(tag::ACK);
+                put_varint(buf, *msg_key);
+            }
+            Self::Nack { msg_key } => {
+                buf.push(tag::NACK);
+                put_varint(buf, *msg_key);
+            }
+            Self::Send { msg, lane, msg_key } => {
+                buf.push(tag::SEND);
+                put_lane(buf, *lane);
+                put_bytes(buf, msg);
+                put_key(buf, *msg_key);
+            }
+            Self::Flush => buf.push(tag::FLUSH),
+            Self::Disconnect { reason } => {
+                buf.push(tag::DISCONNECT);
+                put_bytes(buf, reason.as_bytes());
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, ReadError> {
+        Ok(match decoder.u8()? {
+            tag::POLL => Self::Poll {
+                state: match decoder.u8()? {
+                    0 => ClientState::Disconnected,
+                    1 => ClientState::Connecting(()),
+                    2 => ClientState::Connected(()),
+                    _ => return Err(ReadError::InvalidRecord),
+                },
+                delta_time: decoder.duration()?,
+            },
+            tag::CONNECTED => Self::Connected,
+            tag::DISCONNECTED => Self::Disconnected {
+                reason: decoder.disconnect_reason()?,
+            },
+            tag::RECV => {
+                let lane = decoder.lane()?;
+                let msg = decoder.bytes()?;
+                Self::Recv { msg, lane }
+            }
+            tag::ACK => Self::Ack {
+                msg_key: decoder.varint()?,
+            },
+            tag::NACK => Self::Nack {
+                msg_key: decoder.varint()?,
+            },
+            tag::SEND => {
+                let lane = decoder.lane()?;
+                let msg = decoder.bytes()?;
+                let msg_key = decoder.key()?;
+                Self::Send { msg, lane, msg_key }
+            }
+            tag::FLUSH => Self::Flush,
+            tag::DISCONNECT => Self::Disconnect {
+                reason: decoder.string()?,
+            },
+            _ => return Err(ReadError::InvalidRecord),
+        })
+    }
+}
+
+/// Reads all records from a recording made by a [`RecordingClient`].
+///
+/// # Errors
+///
+/// Errors if the recording could not be read, or is not a valid client
+/// recording.
+pub fn read_client_records(reader: impl io::Read) -> Result<Vec<ClientRecord>, ReadError> {
+    let mut decoder = Decoder::new(reader, Side::Client)?;
+    let mut records = Vec::new();
+    while !decoder.is_empty() {
+        records.push(ClientRecord::decode(&mut decoder)?);
+    }
+    Ok(records)
+}
+
+/// Wrapper around a [`ClientTransport`] which records everything that happens
+/// on it to a writer.
+///
+/// Use [`ClientTransport::poll`], [`ClientTransport::send`] and the other
+/// transport functions on this wrapper rather than on the inner transport, so
+/// that they are recorded.
+///
+/// Writing the recording never causes transport operations to fail. If
+/// writing fails, recording stops, and the error can be read using
+/// [`RecordingClient::io_error`].
+///
+/// See [`replay`](crate::replay).
+#[derive(Derivative)]
+#[derivative(Debug(bound = "T: std::fmt::Debug, W: std::fmt::Debug"))]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct RecordingClient<T: ClientTransport, W> {
+    inner: T,
+    encoder: Encoder<W>,
+    msg_keys: HashMap<T::MessageKey, u64>,
+    next_msg_key: u64,
+}
+
+impl<T: ClientTransport, W: io::Write> RecordingClient<T, W> {
+    /// Wraps an existing client transport, writing the recording into
+    /// `writer`.
+    pub fn new(inner: T, writer: W) -> Self {
+        Self {
+            inner,
+            encoder: Encoder::new(writer, Side::Client),
+            msg_keys: HashMap::new(),
+            next_msg_key: 0,
+        }
+    }
+
+    /// Gets a reference to the inner transport.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the inner transport.
+    ///
+    /// Operations performed on the inner transport directly are not recorded.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Gets the error which stopped this recording, if writing to the writer
+    /// has failed.
+    pub const fn io_error(&self) -> Option<&io::Error> {
+        self.encoder.error.as_ref()
+    }
+
+    /// Takes the inner transport and the writer out of this wrapper.
+    pub fn into_parts(self) -> (T, W) {
+        (self.inner, self.encoder.writer)
+    }
+
+    fn record(&mut self, record: &ClientRecord) {
+        self.encoder.record(|buf| record.encode(buf));
+    }
+
+    fn record_event(&mut self, event: &ClientEvent<T>) {
+        let record = match event {
+            ClientEvent::Connected => ClientRecord::Connected,
+            ClientEvent::Disconnected { reason } => ClientRecord::Disconnected {
+                reason: disconnect_reason_string(reason),
+            },
+            ClientEvent::Recv { msg, lane } => ClientRecord::Recv {
+                msg: msg.clone(),
+                lane: *lane,
+            },
+            ClientEvent::Ack { msg_key } => ClientRecord::Ack {
+                msg_key: self.msg_keys.remove(msg_key).unwrap_or(UNKNOWN_KEY),
+            },
+            ClientEvent::Nack { msg_key } => ClientRecord::Nack {
+                msg_key: self.msg_keys.remove(msg_key).unwrap_or(UNKNOWN_KEY),
+            },
+        };
+        self.record(&record);
+    }
+}
+
+impl<T: ClientTransport, W: io::Write> ClientTransport for RecordingClient<T, W> {
+    type Error = T::Error;
+
+    type Connecting<'this> = T::Connecting<'this> where Self: 'this;
+
+    type Connected<'this> = T::Connected<'this> where Self: 'this;
+
+    type MessageKey = T::MessageKey;
+
+    fn state(&self) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        self.inner.state()
+    }
+
+    fn poll(&mut self, delta_time: Duration) -> impl Iterator<Item = ClientEvent<Self>> {
+        let state = self.inner.state().map(drop, drop);
+        self.record(&ClientRecord::Poll { state, delta_time });
+
+        let events = self.inner.poll(delta_time).collect::<Vec<_>>();
+        for event in &events {
+            self.record_event(event);
+        }
+        events.into_iter().map(ClientEvent::remap)
+    }
+
+    fn send(
+        &mut self,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        let msg = msg.into();
+        let lane = lane.into();
+        let res = self.inner.send(msg.clone(), lane);
+
+        let msg_key = res.as_ref().ok().map(|msg_key| {
+            let id = self.next_msg_key;
+            self.next_msg_key += 1;
+            self.msg_keys.insert(msg_key.clone(), id);
+            id
+        });
+        self.record(&ClientRecord::Send { msg, lane, msg_key });
+        res
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.record(&ClientRecord::Flush);
+        self.encoder.flush();
+        self.inner.flush()
+    }
+
+    fn disconnect(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
+        let reason = reason.into();
+        self.record(&ClientRecord::Disconnect {
+            reason: reason.clone(),
+        });
+        self.inner.disconnect(reason)
+    }
+}
+
+/// Client transport which plays back a recording made by a
+/// [`RecordingClient`], without any networking.
+///
+/// See [`replay`](crate::replay).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct ReplayClient {
+    frames: VecDeque<ClientFrame>,
+    state: ClientState<(), ()>,
+    next_msg_key: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ClientFrame {
+    state: ClientState<(), ()>,
+    delta_time: Duration,
+    events: Vec<ClientEvent<ReplayClient>>,
+}
+
+impl ReplayClient {
+    /// Creates a replay from the records of a recording.
+    ///
+    /// Records before the first [`ClientRecord::Poll`] are ignored.
+    #[must_use]
+    pub fn from_records(records: impl IntoIterator<Item = ClientRecord>) -> Self {
+        let mut frames = VecDeque::<ClientFrame>::new();
+        for record in records {
+            if let ClientRecord::Poll { state, delta_time } = record {
+                frames.push_back(ClientFrame {
+                    state,
+                    delta_time,
+                    events: Vec::new(),
+                });
+                continue;
+            }
+
+            let Some(frame) = frames.back_mut() else {
+                continue;
+            };
+            let event = match record {
+                ClientRecord::Connected => ClientEvent::Connected,
+                ClientRecord::Disconnected { reason } => ClientEvent::Disconnected {
+                    reason: reason.map_err(ReplayError::Recorded),
+                },
+                ClientRecord::Recv { msg, lane } => ClientEvent::Recv { msg, lane },
+                ClientRecord::Ack { msg_key } => ClientEvent::Ack {
+                    msg_key: ReplayKey(msg_key),
+                },
+                ClientRecord::Nack { msg_key } => ClientEvent::Nack {
+                    msg_key: ReplayKey(msg_key),
+                },
+                ClientRecord::Poll { .. }
+                | ClientRecord::Send { .. }
+                | ClientRecord::Flush
+                | ClientRecord::Disconnect { .. } => continue,
+            };
+            frame.events.push(event);
+        }
+
+        Self {
+            frames,
+            state: ClientState::Disconnected,
+            next_msg_key: 0,
+        }
+    }
+
+    /// Reads a recording made by a [`RecordingClient`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the recording could not be read, or is not a valid client
+    /// recording.
+    pub fn read(reader: impl io::Read) -> Result<Self, ReadError> {
+        read_client_records(reader).map(Self::from_records)
+    }
+
+    /// Gets the `delta_time` that was passed into the next recorded poll, or
+    /// [`None`] if the replay is finished.
+    #[must_use]
+    pub fn next_delta_time(&self) -> Option<Duration> {
+        self.frames.front().map(|frame| frame.delta_time)
+    }
+
+    /// Gets if every recorded poll has been played back.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl ClientTransport for ReplayClient {
+    type Error = ReplayError;
+
+    type Connecting<'this> = ();
+
+    type Connected<'this> = ();
+
+    type MessageKey = ReplayKey;
+
+    fn state(&self) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        self.state.clone()
+    }
+
+    fn poll(&mut self, _: Duration) -> impl Iterator<Item = ClientEvent<Self>> {
+        let Some(frame) = self.frames.pop_front() else {
+            return Vec::new().into_iter();
+        };
+
+        self.state = frame.state;
+        for event in &frame.events {
+            match event {
+                ClientEvent::Connected => self.state = ClientState::Connected(()),
+                ClientEvent::Disconnected { .. } => self.state = ClientState::Disconnected,
+                _ => {}
+            }
+        }
+        frame.events.into_iter()
+    }
+
+    fn send(
+        &mut self,
+        _: impl Into<Bytes>,
+        _: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        if !self.state.is_connected() {
+            return Err(ReplayError::NotConnected);
+        }
+        let msg_key = ReplayKey(self.next_msg_key);
+        self.next_msg_key += 1;
+        Ok(msg_key)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn disconnect(&mut self, _: impl Into<String>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+//! Provides transports which record everything that happens on a transport to
+//! a file, and transports which replay that file back without any networking.
+//!
+//! **This is for debugging purposes only!** Recordings hold the full contents
+//! of every message sent and received, so be careful about where you store
+//! them.
+//!
+//! # Recording
+//!
+//! Wrap a client or server transport in a [`RecordingClient`] or
+//! [`RecordingServer`], and give it a writer such as a [`std::fs::File`].
+//! Every call to `poll`, along with the `delta_time` passed in, the state of
+//! the transport before polling, and all events emitted, is written to the
+//! recording. Calls to `send`, `flush`, `disconnect` and `close` are written
+//! as well.
+//!
+//! Keys for clients and messages are not written as-is, since the key types of
+//! each transport are different. Instead, each key is assigned a number: the
+//! first message which is sent successfully gets message key 0, the next one
+//! gets 1, and so on. Clients on a server are numbered in the order that the
+//! recording first sees them.
+//!
+//! Errors are written as their pretty-printed string (see
+//! [`pretty_error`]).
+//!
+//! # Replaying
+//!
+//! A [`ReplayClient`] or [`ReplayServer`] reads a recording, and acts as a
+//! transport which emits exactly the events that were recorded. Each call to
+//! `poll` emits the events of the next recorded `poll` call, regardless of the
+//! `delta_time` passed in. To run your app at the same pace that it was
+//! recorded at, use [`ReplayClient::next_delta_time`] or
+//! [`ReplayServer::next_delta_time`] to advance your app's clock.
+//!
+//! Message keys given out by `send` are numbered in the same way as in the
+//! recording, so if your app sends the same messages in the same order as it
+//! did while recording, acknowledgements will refer to the same messages.
+//! Calls to `disconnect` and `close` have no effect, since the recording
+//! already holds the events caused by them.
+//!
+//! To inspect a recording without replaying it, use [`read_client_records`]
+//! or [`read_server_records`].
+//!
+//! # Usage
+//!
+//! ```
+//! # use aeronet::client::ClientTransport;
+//! # use aeronet::replay::{RecordingClient, ReplayClient};
+//! # use web_time::Duration;
+//! # fn run<T: ClientTransport>(backing_transport: T, dt: Duration) {
+//! let mut recording = Vec::new();
+//! let mut client = RecordingClient::new(backing_transport, &mut recording);
+//! for event in client.poll(dt) { /* .. */ }
+//! drop(client);
+//!
+//! let mut client = ReplayClient::read(recording.as_slice()).unwrap();
+//! while let Some(dt) = client.next_delta_time() {
+//!     for event in client.poll(dt) { /* .. */ }
+//! }
+//! # }
+//! ```
+//!
+//! [`pretty_error`]: crate::error::pretty_error
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "server")]
+pub use server::*;
+
+use std::{error::Error, io};
+
+use bytes::{Buf, Bytes};
+use web_time::Duration;
+
+use crate::{client::DisconnectReason, error::pretty_error, lane::LaneIndex};
+
+const MAGIC: [u8; 4] = *b"AERR";
+
+const VERSION: u8 = 1;
+
+const UNKNOWN_KEY: u64 = u64::MAX;
+
+/// Key of a client or message in a replayed transport.
+///
+/// See [`replay`](crate::replay) for how keys are numbered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReplayKey(u64);
+
+impl ReplayKey {
+    /// Creates a new key from its raw number.
+    #[must_use]
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    /// Gets the raw number of this key.
+    #[must_use]
+    pub const fn into_raw(self) -> u64 {
+        self.0
+    }
+}
+
+/// Error type for operations on a [`ReplayClient`] or [`ReplayServer`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ReplayError {
+    /// Error which was emitted by the recorded transport, stored as its
+    /// pretty-printed string.
+    #[error("{0}")]
+    Recorded(String),
+    /// Client is not connected.
+    #[error("not connected")]
+    NotConnected,
+    /// Server is not open.
+    #[error("not open")]
+    NotOpen,
+    /// Given client is not connected.
+    #[error("client not connected")]
+    ClientNotConnected,
+}
+
+/// Failed to read a recording.
+#[derive(Debug, thiserror::Error)]
+pub enum ReadError {
+    /// Failed to read from the underlying reader.
+    #[error("failed to read recording")]
+    Io(#[source] io::Error),
+    /// Recording does not start with a valid header.
+    #[error("invalid header")]
+    InvalidHeader,
+    /// Recording was made of a client, but is being read as a server, or the
+    /// other way around.
+    #[error("recording is of the wrong side")]
+    WrongSide,
+    /// Recording ended in the middle of a record.
+    #[error("recording is truncated")]
+    Truncated,
+    /// Recording contains a record which could not be decoded.
+    #[error("invalid record")]
+    InvalidRecord,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum Side {
+    Client = 0,
+    Server = 1,
+}
+
+/// Writes encoded records, storing the first I/O error that occurs and
+/// discarding all records after that.
+#[derive(Debug)]
+struct Encoder<W> {
+    writer: W,
+    buf: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Encoder<W> {
+    fn new(writer: W, side: Side) -> Self {
+        let mut this = Self {
+            writer,
+            buf: Vec::new(),
+            error: None,
+        };
+        this.record(|buf| {
+            buf.extend_from_slice(&MAGIC);
+            buf.push(VERSION);
+            buf.push(side as u8);
+        });
+        this
+    }
+
+    fn record(&mut self, f: impl FnOnce(&mut Vec<u8>)) {
+        if self.error.is_some() {
+            return;
+        }
+        self.buf.clear();
+        f(&mut self.buf);
+        if let Err(err) = self.writer.write_all(&self.buf) {
+            self.error = Some(err);
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Err(err) = self.writer.flush() {
+            self.error = Some(err);
+        }
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        #[allow(clippy::cast_possible_truncation)] // truncation is intended
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_duration(buf: &mut Vec<u8>, duration: Duration) {
+    put_varint(buf, u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX));
+}
+
+fn put_lane(buf: &mut Vec<u8>, lane: LaneIndex) {
+    put_varint(buf, lane.into_raw());
+}
+
+fn put_key(buf: &mut Vec<u8>, key: Option<u64>) {
+    // shift the keys up by 1 so that 0 means no key
+    put_varint(buf, key.map_or(0, |key| key + 1));
+}
+
+fn put_disconnect_reason(buf: &mut Vec<u8>, reason: &DisconnectReason<String>) {
+    let (tag, reason) = match reason {
+        DisconnectReason::Local(reason) => (0, reason),
+        DisconnectReason::Remote(reason) => (1, reason),
+        DisconnectReason::Error(reason) => (2, reason),
+    };
+    buf.push(tag);
+    put_bytes(buf, reason.as_bytes());
+}
+
+fn error_string(err: &impl Error) -> String {
+    format!("{:#}", pretty_error(err))
+}
+
+fn disconnect_reason_string<E: Error>(reason: &DisconnectReason<E>) -> DisconnectReason<String> {
+    match reason {
+        DisconnectReason::Local(reason) => DisconnectReason::Local(reason.clone()),
+        DisconnectReason::Remote(reason) => DisconnectReason::Remote(reason.clone()),
+        DisconnectReason::Error(err) => DisconnectReason::Error(error_string(err)),
+    }
+}
+
+/// Reads records from an in-memory recording.
+#[derive(Debug)]
+struct Decoder {
+    buf: Bytes,
+}
+
+impl Decoder {
+    fn new(mut reader: impl io::Read, side: Side) -> Result<Self, ReadError> {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).map_err(ReadError::Io)?;
+        let mut this = Self { buf: buf.into() };
+
+        let header = this.take(MAGIC.len() + 2).ok_or(ReadError::InvalidHeader)?;
+        if header[..MAGIC.len()] != MAGIC || header[MAGIC.len()] != VERSION {
+            return Err(ReadError::InvalidHeader);
+        }
+        if header[MAGIC.len() + 1] != side as u8 {
+            return Err(ReadError::WrongSide);
+        }
+        Ok(this)
+    }
+
+    const fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Option<Bytes> {
+        if self.buf.len() < len {
+            return None;
+        }
+        Some(self.buf.split_to(len))
+    }
+
+    fn u8(&mut self) -> Result<u8, ReadError> {
+        if self.buf.is_empty() {
+            return Err(ReadError::Truncated);
+        }
+        Ok(self.buf.get_u8())
+    }
+
+    fn varint(&mut self) -> Result<u64, ReadError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ReadError::InvalidRecord)
+    }
+
+    fn bytes(&mut self) -> Result<Bytes, ReadError> {
+        let len = usize::try_from(self.varint()?).map_err(|_| ReadError::InvalidRecord)?;
+        self.take(len).ok_or(ReadError::Truncated)
+    }
+
+    fn string(&mut self) -> Result<String, ReadError> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| ReadError::InvalidRecord)
+    }
+
+    fn duration(&mut self) -> Result<Duration, ReadError> {
+        self.varint().map(Duration::from_nanos)
+    }
+
+    fn lane(&mut self) -> Result<LaneIndex, ReadError> {
+        self.varint().map(LaneIndex::from_raw)
+    }
+
+    fn key(&mut self) -> Result<Option<u64>, ReadError> {
+        self.varint().map(|key| key.checked_sub(1))
+    }
+
+    fn disconnect_reason(&mut self) -> Result<DisconnectReason<String>, ReadError> {
+        let tag = self.u8()?;
+        let reason = self.string()?;
+        match tag {
+            0 => Ok(DisconnectReason::Local(reason)),
+            1 => Ok(DisconnectReason::Remote(reason)),
+            2 => Ok(DisconnectReason::Error(reason)),
+            _ => Err(ReadError::InvalidRecord),
+        }
+    }
+}
@@ -0,0 +1,786 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io,
+};
+
+use bytes::Bytes;
+use derivative::Derivative;
+use web_time::Duration;
+
+use crate::{
+    client::{ClientState, DisconnectReason},
+    lane::LaneIndex,
+    server::{CloseReason, ServerEvent, ServerState, ServerTransport},
+};
+
+use super::{
+    disconnect_reason_string, error_string, put_bytes, put_disconnect_reason, put_duration,
+    put_key, put_lane, put_varint, Decoder, Encoder, ReadError, ReplayError, ReplayKey, Side,
+    UNKNOWN_KEY,
+};
+
+/// Single entry in a recording of a [`ServerTransport`].
+///
+/// See [`replay`](crate::replay).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerRecord {
+    /// The server was polled.
+    ///
+    /// All event records up to the next `Poll` were emitted by this poll.
+    Poll {
+        /// State of the server before it was polled.
+        state: ServerState<(), ()>,
+        /// Time passed into the poll.
+        delta_time: Duration,
+    },
+    /// [`ServerEvent::Opened`] was emitted.
+    Opened,
+    /// [`ServerEvent::Closed`] was emitted.
+    Closed {
+        /// Why the server was closed.
+        reason: CloseReason<String>,
+    },
+    /// [`ServerEvent::Reloaded`] was emitted.
+    Reloaded {
+        /// Whether the reload succeeded, or why it failed.
+        result: Result<(), String>,
+    },
+    /// [`ServerEvent::Connecting`] was emitted.
+    Connecting {
+        /// Recorded key of the client.
+        client_key: u64,
+    },
+    /// [`ServerEvent::Connected`] was emitted.
+    Connected {
+        /// Recorded key of the client.
+        client_key: u64,
+    },
+    /// [`ServerEvent::Disconnected`] was emitted.
+    Disconnected {
+        /// Recorded key of the client.
+        client_key: u64,
+        /// Why the client was disconnected.
+        reason: DisconnectReason<String>,
+    },
+    /// [`ServerEvent::Recv`] was emitted.
+    Recv {
+        /// Recorded key of the client.
+        client_key: u64,
+        /// The message received.
+        msg: Bytes,
+        /// Lane on which the message was received.
+        lane: LaneIndex,
+    },
+    /// [`ServerEvent::Ack`] was emitted.
+    Ack {
+        /// Recorded key of the client.
+        client_key: u64,
+        /// Recorded key of the message.
+        msg_key: u64,
+    },
+    /// [`ServerEvent::Nack`] was emitted.
+    Nack {
+        /// Recorded key of the client.
+        client_key: u64,
+        /// Recorded key of the message.
+        msg_key: u64,
+    },
+    /// A message was sent to a client.
+    Send {
+        /// Recorded key of the client.
+        client_key: u64,
+        /// The message sent.
+        msg: Bytes,
+        /// Lane on which the message was sent.
+        lane: LaneIndex,
+        /// Recorded key of the message, or [`None`] if sending failed.
+        msg_key: Option<u64>,
+    },
+    /// The server was flushed.
+    Flush,
+    /// A client was disconnected by user code.
+    Disconnect {
+        /// Recorded key of the client.
+        client_key: u64,
+        /// Why the client was disconnected.
+        reason: String,
+    },
+    /// The server was closed by user code.
+    Close {
+        /// Why the server was closed.
+        reason: String,
+    },
+}
+
+mod tag {
+    pub const POLL: u8 = 0;
+    pub const OPENED: u8 = 1;
+    pub const CLOSED: u8 = 2;
+    pub const CONNECTING: u8 = 3;
+    pub const CONNECTED: u8 = 4;
+    pub const DISCONNECTED: u8 = 5;
+    pub const RECV: u8 = 6;
+    pub const ACK: u8 = 7;
+    pub const NACK: u8 = 8;
+    pub const SEND: u8 = 9;
+    pub const FLUSH: u8 = 10;
+    pub const DISCONNECT: u8 = 11;
+    pub const CLOSE: u8 = 12;
+    pub const RELOADED: u8 = 13;
+}
+
+impl ServerRecord {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::Poll { state, delta_time } => {
+                buf.push(tag::POLL);
+                buf.push(match state {
+                    ServerState::Closed => 0,
+                    ServerState::Opening(()) => 1,
+                    ServerState::Open(()) => 2,
+                });
+                put_duration(buf, *delta_time);
+            }
+            Self::Opened => buf.push(tag::OPENED),
+            Self::Closed { reason } => {
+                buf.push(tag::CLOSED);
+                let (tag, reason) = match reason {
+                    CloseReason::Local(reason) => (0, reason),
+                    CloseReason::Error(reason) => (1, reason),
+                };
+                buf.push(tag);
+                put_bytes(buf, reason.as_bytes());
+            }
+            Self::Reloaded { result } => {
+                buf.push(tag::RELOADED);
+                match result {
+                    Ok(()) => buf.push(0),
+                    Err(err) => {
+                        buf.push(1);
+                        put_bytes(buf, err.as_bytes());
+                    }
+                }
+            }
+            Self::Connecting { client_key } => {
+                buf.push(tag::CONNECTING);
+                put_varint(buf, *client_key);
+            }
+            Self::Connected { client_key } => {
+                buf.push(tag::CONNECTED);
+                put_varint(buf, *client_key);
+            }
+            Self::Disconnected { client_key, reason } => {
+                buf.push(tag::DISCONNECTED);
+                put_varint(buf, *client_key);
+                put_disconnect_reason(buf, reason);
+            }
+            Self::Recv {
+                client_key,
+                msg,
+                lane,
+            } => {
+                buf.push(tag::RECV);
+                put_varint(buf, *client_key);
+                put_lane(buf, *lane);
+                put_bytes(buf, msg);
+            }
+            Self::Ack {
+                client_key,
+                msg_key,
+            } => {
+                buf.push(tag::ACK);
+                put_varint(buf, *client_key);
+                put_varint(buf, *msg_key);
+            }
+            Self::Nack {
+                client_key,
+                msg_key,
+            } => {
+                buf.push(tag::NACK);
+                put_varint(buf, *client_key);
+                put_varint(buf, *msg_key);
+            }
+            Self::Send {
+                client_key,
+                msg,
+                lane,
+                msg_key,
+            } => {
+                buf.push(tag::SEND);
+                put_varint(buf, *client_key);
+                put_lane(buf, *lane);
+                put_bytes(buf, msg);
+                put_key(buf, *msg_key);
+            }
+            Self::Flush => buf.push(tag::FLUSH),
+            Self::Disconnect { client_key, reason } => {
+                buf.push(tag::DISCONNECT);
+                put_varint(buf, *client_key);
+                put_bytes(buf, reason.as_bytes());
+            }
+            Self::Close { reason } => {
+                buf.push(tag::CLOSE);
+                put_bytes(buf, reason.as_bytes());
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Self, ReadError> {
+        Ok(match decoder.u8()? {
+            tag::POLL => Self::Poll {
+                state: match decoder.u8()? {
+                    0 => ServerState::Closed,
+                    1 => ServerState::Opening(()),
+                    2 => ServerState::Open(()),
+                    _ => return Err(ReadError::InvalidRecord),
+                },
+                delta_time: decoder.duration()?,
+            },
+            tag::OPENED => Self::Opened,
+            tag::CLOSED => {
+                let tag = decoder.u8()?;
+                let reason = decoder.string()?;
+                Self::Closed {
+                    reason: match tag {
+                        0 => CloseReason::Local(reason),
+                        1 => CloseReason::Error(reason),
+                        _ => return Err(ReadError::InvalidRecord),
+                    },
+                }
+            }
+            tag::RELOADED => Self::Reloaded {
+                result: match decoder.u8()? {
+                    0 => Ok(()),
+                    1 => Err(decoder.string()?),
+                    _ => return Err(ReadError::InvalidRecord),
+                },
+            },
+            tag::CONNECTING => Self::Connecting {
+                client_key: decoder.varint()?,
+            },
+            tag::CONNECTED => Self::Connected {
+                client_key: decoder.varint()?,
+            },
+            tag::DISCONNECTED => Self::Disconnected {
+                client_key: decoder.varint()?,
+                reason: decoder.disconnect_reason()?,
+            },
+            tag::RECV => {
+                let client_key = decoder.varint()?;
+                let lane = decoder.lane()?;
+                let msg = decoder.bytes()?;
+                Self::Recv {
+                    client_key,
+                    msg,
+                    lane,
+                }
+            }
+            tag::ACK => Self::Ack {
+                client_key: decoder.varint()?,
+                msg_key: decoder.varint()?,
+            },
+            tag::NACK => Self::Nack {
+                client_key: decoder.varint()?,
+                msg_key: decoder.varint()?,
+            },
+            tag::SEND => {
+                let client_key = decoder.varint()?;
+                let lane = decoder.lane()?;
+                let msg = decoder.bytes()?;
+                let msg_key = decoder.key()?;
+                Self::Send {
+                    client_key,
+                    msg,
+                    lane,
+                    msg_key,
+                }
+            }
+            tag::FLUSH => Self::Flush,
+            tag::DISCONNECT => Self::Disconnect {
+                client_key: decoder.varint()?,
+                reason: decoder.string()?,
+            },
+            tag::CLOSE => Self::Close {
+                reason: decoder.string()?,
+            },
+            _ => return Err(ReadError::InvalidRecord),
+        })
+    }
+}
+
+/// Reads all records from a recording made by a [`RecordingServer`].
+///
+/// # Errors
+///
+/// Errors if the recording could not be read, or is not a valid server
+/// recording.
+pub fn read_server_records(reader: impl io::Read) -> Result<Vec<ServerRecord>, ReadError> {
+    let mut decoder = Decoder::new(reader, Side::Server)?;
+    let mut records = Vec::new();
+    while !decoder.is_empty() {
+        records.push(ServerRecord::decode(&mut decoder)?);
+    }
+    Ok(records)
+}
+
+/// Wrapper around a [`ServerTransport`] which records everything that happens
+/// on it to a writer.
+///
+/// Use [`ServerTransport::poll`], [`ServerTransport::send`] and the other
+/// transport functions on this wrapper rather than on the inner transport, so
+/// that they are recorded.
+///
+/// Writing the recording never causes transport operations to fail. If
+/// writing fails, recording stops, and the error can be read using
+/// [`RecordingServer::io_error`].
+///
+/// See [`replay`](crate::replay).
+#[derive(Derivative)]
+#[derivative(Debug(bound = "T: std::fmt::Debug, W: std::fmt::Debug"))]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct RecordingServer<T: ServerTransport, W> {
+    inner: T,
+    encoder: Encoder<W>,
+    client_keys: HashMap<T::ClientKey, u64>,
+    next_client_key: u64,
+    msg_keys: HashMap<(T::ClientKey, T::MessageKey), u64>,
+    next_msg_key: u64,
+}
+
+impl<T: ServerTransport, W: io::Write> RecordingServer<T, W> {
+    /// Wraps an existing server transport, writing the recording into
+    /// `writer`.
+    pub fn new(inner: T, writer: W) -> Self {
+        Self {
+            inner,
+            encoder: Encoder::new(writer, Side::Server),
+            client_keys: HashMap::new(),
+            next_client_key: 0,
+            msg_keys: HashMap::new(),
+            next_msg_key: 0,
+        }
+    }
+
+    /// Gets a reference to the inner transport.
+    pub const fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the inner transport.
+    ///
+    /// Operations performed on the inner transport directly are not recorded.
+    pub fn inner_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Gets the error which stopped this recording, if writing to the writer
+    /// has failed.
+    pub const fn io_error(&self) -> Option<&io::Error> {
+        self.encoder.error.as_ref()
+    }
+
+    /// Takes the inner transport and the writer out of this wrapper.
+    pub fn into_parts(self) -> (T, W) {
+        (self.inner, self.encoder.writer)
+    }
+
+    fn record(&mut self, record: &ServerRecord) {
+        self.encoder.record(|buf| record.encode(buf));
+    }
+
+    fn client_key(&mut self, client_key: &T::ClientKey) -> u64 {
+        if let Some(id) = self.client_keys.get(client_key) {
+            return *id;
+        }
+        let id = self.next_client_key;
+        self.next_client_key += 1;
+        self.client_keys.insert(client_key.clone(), id);
+        id
+    }
+
+    fn msg_key(&mut self, client_key: &T::ClientKey, msg_key: &T::MessageKey) -> u64 {
+        self.msg_keys
+            .remove(&(client_key.clone(), msg_key.clone()))
+            .unwrap_or(UNKNOWN_KEY)
+    }
+
+    fn record_event(&mut self, event: &ServerEvent<T>) {
+        let record = match event {
+            ServerEvent::Opened => ServerRecord::Opened,
+            ServerEvent::Closed { reason } => {
+                self.client_keys.clear();
+                self.msg_keys.clear();
+                ServerRecord::Closed {
+                    reason: match reason {
+                        CloseReason::Local(reason) => CloseReason::Local(reason.clone()),
+                        CloseReason::Error(err) => CloseReason::Error(error_string(err)),
+                    },
+                }
+            }
+            ServerEvent::Reloaded { result } => ServerRecord::Reloaded {
+                result: result.as_ref().copied().map_err(error_string),
+            },
+            ServerEvent::Connecting { client_key } => ServerRecord::Connecting {
+                client_key: self.client_key(client_key),
+            },
+            ServerEvent::Connected { client_key } => ServerRecord::Connected {
+                client_key: self.client_key(client_key),
+            },
+            ServerEvent::Disconnected { client_key, reason } => {
+                let id = self.client_key(client_key);
+                self.client_keys.remove(client_key);
+                self.msg_keys.retain(|(key, _), _| key != client_key);
+                ServerRecord::Disconnected {
+                    client_key: id,
+                    reason: disconnect_reason_string(reason),
+                }
+            }
+            ServerEvent::Recv {
+                client_key,
+                msg,
+                lane,
+            } => ServerRecord::Recv {
+                client_key: self.client_key(client_key),
+                msg: msg.clone(),
+                lane: *lane,
+            },
+            ServerEvent::Ack {
+                client_key,
+                msg_key,
+            } => ServerRecord::Ack {
+                client_key: self.client_key(client_key),
+                msg_key: self.msg_key(client_key, msg_key),
+            },
+            ServerEvent::Nack {
+                client_key,
+                msg_key,
+            } => ServerRecord::Nack {
+                client_key: self.client_key(client_key),
+                msg_key: self.msg_key(client_key, msg_key),
+            },
+        };
+        self.record(&record);
+    }
+}
+
+impl<T: ServerTransport, W: io::Write> ServerTransport for RecordingServer<T, W> {
+    type Error = T::Error;
+
+    type Opening<'this>
+        = T::Opening<'this>
+    where
+        Self: 'this;
+
+    type Open<'this>
+        = T::Open<'this>
+    where
+        Self: 'this;
+
+    type Connecting<'this>
+        = T::Connecting<'this>
+    where
+        Self: 'this;
+
+    type Connected<'this>
+        = T::Connected<'this>
+    where
+        Self: 'this;
+
+    type ClientKey = T::ClientKey;
+
+    type MessageKey = T::MessageKey;
+
+    fn state(&self) -> ServerState<Self::Opening<'_>, Self::Open<'_>> {
+        self.inner.state()
+    }
+
+    fn client_state(
+        &self,
+        client_key: Self::ClientKey,
+    ) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        self.inner.client_state(client_key)
+    }
+
+    fn client_keys(&self) -> impl Iterator<Item = Self::ClientKey> + '_ {
+        self.inner.client_keys()
+    }
+
+    fn poll(&mut self, delta_time: Duration) -> impl Iterator<Item = ServerEvent<Self>> {
+        let state = self.inner.state().map(drop, drop);
+        self.record(&ServerRecord::Poll { state, delta_time });
+
+        let events = self.inner.poll(delta_time).collect::<Vec<_>>();
+        for event in &events {
+            self.record_event(event);
+        }
+        events.into_iter().map(ServerEvent::remap)
+    }
+
+    fn send(
+        &mut self,
+        client_key: Self::ClientKey,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        let msg = msg.into();
+        let lane = lane.into();
+        let res = self.inner.send(client_key.clone(), msg.clone(), lane);
+
+        let id = self.client_key(&client_key);
+        let msg_key = res.as_ref().ok().map(|msg_key| {
+            let msg_id = self.next_msg_key;
+            self.next_msg_key += 1;
+            self.msg_keys.insert((client_key, msg_key.clone()), msg_id);
+            msg_id
+        });
+        self.record(&ServerRecord::Send {
+            client_key: id,
+            msg,
+            lane,
+            msg_key,
+        });
+        res
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.record(&ServerRecord::Flush);
+        self.encoder.flush();
+        self.inner.flush()
+    }
+
+    fn disconnect(
+        &mut self,
+        client_key: Self::ClientKey,
+        reason: impl Into<String>,
+    ) -> Result<(), Self::Error> {
+        let reason = reason.into();
+        let id = self.client_key(&client_key);
+        self.record(&ServerRecord::Disconnect {
+            client_key: id,
+            reason: reason.clone(),
+        });
+        self.inner.disconnect(client_key, reason)
+    }
+
+    fn close(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
+        let reason = reason.into();
+        self.record(&ServerRecord::Close {
+            reason: reason.clone(),
+        });
+        self.inner.close(reason)
+    }
+}
+
+/// Server transport which plays back a recording made by a
+/// [`RecordingServer`], without any networking.
+///
+/// See [`replay`](crate::replay).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct ReplayServer {
+    frames: VecDeque<ServerFrame>,
+    state: ServerState<(), ()>,
+    clients: BTreeMap<ReplayKey, ClientState<(), ()>>,
+    next_msg_key: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ServerFrame {
+    state: ServerState<(), ()>,
+    delta_time: Duration,
+    events: Vec<ServerEvent<ReplayServer>>,
+}
+
+impl ReplayServer {
+    /// Creates a replay from the records of a recording.
+    ///
+    /// Records before the first [`ServerRecord::Poll`] are ignored.
+    #[must_use]
+    pub fn from_records(records: impl IntoIterator<Item = ServerRecord>) -> Self {
+        let mut frames = VecDeque::<ServerFrame>::new();
+        for record in records {
+            if let ServerRecord::Poll { state, delta_time } = record {
+                frames.push_back(ServerFrame {
+                    state,
+                    delta_time,
+                    events: Vec::new(),
+                });
+                continue;
+            }
+
+            let Some(frame) = frames.back_mut() else {
+                continue;
+            };
+            let event = match record {
+                ServerRecord::Opened => ServerEvent::Opened,
+                ServerRecord::Closed { reason } => ServerEvent::Closed {
+                    reason: reason.map_err(ReplayError::Recorded),
+                },
+                ServerRecord::Reloaded { result } => ServerEvent::Reloaded {
+                    result: result.map_err(ReplayError::Recorded),
+                },
+                ServerRecord::Connecting { client_key } => ServerEvent::Connecting {
+                    client_key: ReplayKey(client_key),
+                },
+                ServerRecord::Connected { client_key } => ServerEvent::Connected {
+                    client_key: ReplayKey(client_key),
+                },
+                ServerRecord::Disconnected { client_key, reason } => ServerEvent::Disconnected {
+                    client_key: ReplayKey(client_key),
+                    reason: reason.map_err(ReplayError::Recorded),
+                },
+                ServerRecord::Recv {
+                    client_key,
+                    msg,
+                    lane,
+                } => ServerEvent::Recv {
+                    client_key: ReplayKey(client_key),
+                    msg,
+                    lane,
+                },
+                ServerRecord::Ack {
+                    client_key,
+                    msg_key,
+                } => ServerEvent::Ack {
+                    client_key: ReplayKey(client_key),
+                    msg_key: ReplayKey(msg_key),
+                },
+                ServerRecord::Nack {
+                    client_key,
+                    msg_key,
+                } => ServerEvent::Nack {
+                    client_key: ReplayKey(client_key),
+                    msg_key: ReplayKey(msg_key),
+                },
+                ServerRecord::Poll { .. }
+                | ServerRecord::Send { .. }
+                | ServerRecord::Flush
+                | ServerRecord::Disconnect { .. }
+                | ServerRecord::Close { .. } => continue,
+            };
+            frame.events.push(event);
+        }
+
+        Self {
+            frames,
+            state: ServerState::Closed,
+            clients: BTreeMap::new(),
+            next_msg_key: 0,
+        }
+    }
+
+    /// Reads a recording made by a [`RecordingServer`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the recording could not be read, or is not a valid server
+    /// recording.
+    pub fn read(reader: impl io::Read) -> Result<Self, ReadError> {
+        read_server_records(reader).map(Self::from_records)
+    }
+
+    /// Gets the `delta_time` that was passed into the next recorded poll, or
+    /// [`None`] if the replay is finished.
+    #[must_use]
+    pub fn next_delta_time(&self) -> Option<Duration> {
+        self.frames.front().map(|frame| frame.delta_time)
+    }
+
+    /// Gets if every recorded poll has been played back.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl ServerTransport for ReplayServer {
+    type Error = ReplayError;
+
+    type Opening<'this> = ();
+
+    type Open<'this> = ();
+
+    type Connecting<'this> = ();
+
+    type Connected<'this> = ();
+
+    type ClientKey = ReplayKey;
+
+    type MessageKey = ReplayKey;
+
+    fn state(&self) -> ServerState<Self::Opening<'_>, Self::Open<'_>> {
+        self.state.clone()
+    }
+
+    fn client_state(
+        &self,
+        client_key: Self::ClientKey,
+    ) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        self.clients
+            .get(&client_key)
+            .cloned()
+            .unwrap_or(ClientState::Disconnected)
+    }
+
+    fn client_keys(&self) -> impl Iterator<Item = Self::ClientKey> + '_ {
+        self.clients.keys().copied()
+    }
+
+    fn poll(&mut self, _: Duration) -> impl Iterator<Item = ServerEvent<Self>> {
+        let Some(frame) = self.frames.pop_front() else {
+            return Vec::new().into_iter();
+        };
+
+        self.state = frame.state;
+        for event in &frame.events {
+            match event {
+                ServerEvent::Opened => self.state = ServerState::Open(()),
+                ServerEvent::Closed { .. } => {
+                    self.state = ServerState::Closed;
+                    self.clients.clear();
+                }
+                ServerEvent::Connecting { client_key } => {
+                    self.clients
+                        .insert(*client_key, ClientState::Connecting(()));
+                }
+                ServerEvent::Connected { client_key } => {
+                    self.clients.insert(*client_key, ClientState::Connected(()));
+                }
+                ServerEvent::Disconnected { client_key, .. } => {
+                    self.clients.remove(client_key);
+                }
+                _ => {}
+            }
+        }
+        frame.events.into_iter()
+    }
+
+    fn send(
+        &mut self,
+        client_key: Self::ClientKey,
+        _: impl Into<Bytes>,
+        _: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        if !self.state.is_open() {
+            return Err(ReplayError::NotOpen);
+        }
+        if !self.client_state(client_key).is_connected() {
+            return Err(ReplayError::ClientNotConnected);
+        }
+        let msg_key = ReplayKey(self.next_msg_key);
+        self.next_msg_key += 1;
+        Ok(msg_key)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn disconnect(&mut self, _: Self::ClientKey, _: impl Into<String>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn close(&mut self, _: impl Into<String>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
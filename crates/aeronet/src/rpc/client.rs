@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use web_time::Duration;
+
+use crate::{
+    client::{ClientEvent, ClientTransport},
+    lane::LaneIndex,
+};
+
+use super::{
+    frame::{read_frame, write_frame, REQUEST, RESPONSE},
+    RpcId,
+};
+
+/// Client-side half of request/response calls.
+///
+/// See [`rpc`](crate::rpc).
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct RpcClient {
+    lane: LaneIndex,
+    timeout: Duration,
+    next_id: u64,
+    pending: BTreeMap<RpcId, Duration>,
+    completed: Vec<RpcComplete>,
+}
+
+/// A call started by [`RpcClient::call`] has completed, either with a
+/// response from the server, or with an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
+pub struct RpcComplete {
+    /// ID of the call.
+    pub id: RpcId,
+    /// Response sent by the server, or why the call failed.
+    pub result: Result<Bytes, RpcError>,
+}
+
+/// Why a call started by [`RpcClient::call`] failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RpcError {
+    /// The server did not respond before the call's timeout elapsed.
+    #[error("timed out")]
+    TimedOut,
+    /// The client disconnected before the server responded.
+    #[error("disconnected")]
+    Disconnected,
+    /// The call was cancelled using [`RpcClient::cancel`].
+    #[error("cancelled")]
+    Cancelled,
+}
+
+impl RpcClient {
+    /// Creates a new client-side RPC helper which sends calls on `lane`, and
+    /// gives up on calls which take longer than `timeout` by default.
+    #[must_use]
+    pub fn new(lane: impl Into<LaneIndex>, timeout: Duration) -> Self {
+        Self {
+            lane: lane.into(),
+            timeout,
+            next_id: 0,
+            pending: BTreeMap::new(),
+            completed: Vec::new(),
+        }
+    }
+
+    /// Gets the lane which calls are sent on.
+    #[must_use]
+    pub const fn lane(&self) -> LaneIndex {
+        self.lane
+    }
+
+    /// Gets the default timeout of calls.
+    #[must_use]
+    pub const fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Gets the number of calls which are still waiting for a response.
+    #[must_use]
+    pub fn num_pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Gets if the call `id` is still waiting for a response.
+    #[must_use]
+    pub fn is_pending(&self, id: RpcId) -> bool {
+        self.pending.contains_key(&id)
+    }
+
+    /// Sends `request` to the server using `client`, using the default
+    /// timeout.
+    ///
+    /// See [`RpcClient::call_with_timeout`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the transport fails to send the request, in which case no
+    /// call is started.
+    pub fn call<T: ClientTransport>(
+        &mut self,
+        client: &mut T,
+        request: impl AsRef<[u8]>,
+    ) -> Result<RpcId, T::Error> {
+        self.call_with_timeout(client, request, self.timeout)
+    }
+
+    /// Sends `request` to the server using `client`, and starts a call which
+    /// fails if no response is received within `timeout`.
+    ///
+    /// The returned [`RpcId`] identifies this call in the [`RpcComplete`]
+    /// taken from [`RpcClient::drain`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the transport fails to send the request, in which case no
+    /// call is started.
+    pub fn call_with_timeout<T: ClientTransport>(
+        &mut self,
+        client: &mut T,
+        request: impl AsRef<[u8]>,
+        timeout: Duration,
+    ) -> Result<RpcId, T::Error> {
+        let id = RpcId(self.next_id);
+        client.send(write_frame(REQUEST, id, request.as_ref()), self.lane)?;
+        self.next_id += 1;
+        self.pending.insert(id, timeout);
+        Ok(id)
+    }
+
+    /// Stops waiting for a response to the call `id`, completing it with
+    /// [`RpcError::Cancelled`].
+    ///
+    /// Returns `false` if the call was not pending.
+    pub fn cancel(&mut self, id: RpcId) -> bool {
+        if self.pending.remove(&id).is_none() {
+            return false;
+        }
+        self.completed.push(RpcComplete {
+            id,
+            result: Err(RpcError::Cancelled),
+        });
+        true
+    }
+
+    /// Handles an event emitted by the client transport.
+    ///
+    /// Responses to calls are consumed and complete their call, and
+    /// [`ClientEvent::Disconnected`] completes all pending calls with
+    /// [`RpcError::Disconnected`]. Responses to calls which are no longer
+    /// pending, e.g. because they timed out, are dropped.
+    ///
+    /// Returns the event back if it was not consumed, so that you can handle
+    /// it yourself.
+    pub fn handle<T: ClientTransport>(&mut self, event: ClientEvent<T>) -> Option<ClientEvent<T>> {
+        match event {
+            ClientEvent::Recv { msg, lane } if lane == self.lane => {
+                match read_frame(RESPONSE, msg) {
+                    Ok((id, msg)) => {
+                        if self.pending.remove(&id).is_some() {
+                            self.completed.push(RpcComplete {
+                                id,
+                                result: Ok(msg),
+                            });
+                        }
+                        None
+                    }
+                    Err(msg) => Some(ClientEvent::Recv { msg, lane }),
+                }
+            }
+            ClientEvent::Disconnected { reason } => {
+                self.fail_all(RpcError::Disconnected);
+                Some(ClientEvent::Disconnected { reason })
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Advances the timeouts of all pending calls by `delta_time`, completing
+    /// any calls which time out with [`RpcError::TimedOut`].
+    pub fn update(&mut self, delta_time: Duration) {
+        let completed = &mut self.completed;
+        self.pending.retain(|id, remaining| {
+            *remaining = remaining.saturating_sub(delta_time);
+            if remaining.is_zero() {
+                completed.push(RpcComplete {
+                    id: *id,
+                    result: Err(RpcError::TimedOut),
+                });
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Takes all calls which have completed since the last call to this
+    /// function.
+    ///
+    /// When using Bevy, these can be sent directly as events.
+    pub fn drain(&mut self) -> impl Iterator<Item = RpcComplete> + '_ {
+        self.completed.drain(..)
+    }
+
+    fn fail_all(&mut self, error: RpcError) {
+        let pending = std::mem::take(&mut self.pending);
+        self.completed
+            .extend(pending.into_keys().map(|id| RpcComplete {
+                id,
+                result: Err(error),
+            }));
+    }
+}
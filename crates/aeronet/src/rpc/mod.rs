@@ -0,0 +1,117 @@
+//! Request/response calls from a client to a server, on top of any transport.
+//!
+//! Transports only send one-way messages, so to ask the server something and
+//! get back a matching answer, the client has to tag each request with an ID,
+//! and the server has to send that ID back along with its response. This
+//! module does that bookkeeping for you, and also gives up on calls which
+//! take too long, or which can never be answered because the client
+//! disconnected.
+//!
+//! # Usage
+//!
+//! Pick a lane which is only used for calls - every message on this lane is
+//! treated as part of a call. Usually this should be a reliable lane, since a
+//! lost request or response can only be detected by the call timing out.
+//!
+//! On the client, create an [`RpcClient`] for that lane, and start a call with
+//! [`RpcClient::call`], which gives you an [`RpcId`] to identify it. Pass
+//! every event that the client transport emits into [`RpcClient::handle`],
+//! and advance time with [`RpcClient::update`]. Completed calls, whether they
+//! got a response or failed, can then be taken with [`RpcClient::drain`].
+//!
+//! On the server, create an [`RpcServer`] for the same lane, and pass every
+//! event that the server transport emits into [`RpcServer::handle`]. Requests
+//! are returned as [`RpcRequest`]s, and can be answered with
+//! [`RpcServer::respond`].
+//!
+//! ```
+//! # #[cfg(feature = "client")] {
+//! # use aeronet::{client::ClientTransport, lane::LaneIndex, rpc::RpcClient};
+//! # use web_time::Duration;
+//! # fn run<T: ClientTransport>(mut client: T, lane: LaneIndex, dt: Duration) {
+//! let mut rpc = RpcClient::new(lane, Duration::from_secs(5));
+//! let id = rpc.call(&mut client, b"can I buy this item?".as_slice()).unwrap();
+//!
+//! // every update:
+//! for event in client.poll(dt) {
+//!     let Some(event) = rpc.handle(event) else {
+//!         continue;
+//!     };
+//!     // handle other events
+//! }
+//! rpc.update(dt);
+//! for complete in rpc.drain() {
+//!     if complete.id == id {
+//!         // use `complete.result`
+//!     }
+//! }
+//! # }
+//! # }
+//! ```
+//!
+//! # Wire format
+//!
+//! Each message on the call lane starts with a single byte marking it as a
+//! request (`0`) or a response (`1`), followed by the [`RpcId`] as a
+//! little-endian [`u64`], followed by the payload.
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "server")]
+pub use server::*;
+
+/// ID assigned to a call by [`RpcClient::call`], used to match the response to
+/// its request.
+///
+/// IDs are unique for the lifetime of an [`RpcClient`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RpcId(u64);
+
+impl RpcId {
+    /// Creates a new ID from its raw number.
+    #[must_use]
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    /// Gets the raw number of this ID.
+    #[must_use]
+    pub const fn into_raw(self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(any(feature = "client", feature = "server"))]
+mod frame {
+    use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+    use super::RpcId;
+
+    pub const REQUEST: u8 = 0;
+
+    pub const RESPONSE: u8 = 1;
+
+    const HEADER_LEN: usize = 1 + 8;
+
+    pub fn write_frame(kind: u8, id: RpcId, payload: &[u8]) -> Bytes {
+        let mut buf = BytesMut::with_capacity(HEADER_LEN + payload.len());
+        buf.put_u8(kind);
+        buf.put_u64_le(id.0);
+        buf.put_slice(payload);
+        buf.freeze()
+    }
+
+    pub fn read_frame(kind: u8, mut msg: Bytes) -> Result<(RpcId, Bytes), Bytes> {
+        if msg.len() < HEADER_LEN || msg[0] != kind {
+            return Err(msg);
+        }
+        msg.advance(1);
+        let id = RpcId(msg.get_u64_le());
+        Ok((id, msg))
+    }
+}
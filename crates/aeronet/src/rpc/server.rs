@@ -0,0 +1,100 @@
+use bytes::Bytes;
+
+use crate::{
+    lane::LaneIndex,
+    server::{ServerEvent, ServerTransport},
+};
+
+use super::{
+    frame::{read_frame, write_frame, REQUEST, RESPONSE},
+    RpcId,
+};
+
+/// Server-side half of request/response calls.
+///
+/// See [`rpc`](crate::rpc).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct RpcServer {
+    lane: LaneIndex,
+}
+
+/// A client started a call, which should be answered using
+/// [`RpcServer::respond`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
+pub struct RpcRequest<K> {
+    /// Key of the client which started the call.
+    pub client_key: K,
+    /// ID of the call.
+    pub id: RpcId,
+    /// Payload of the request.
+    pub msg: Bytes,
+}
+
+impl RpcServer {
+    /// Creates a new server-side RPC helper which receives calls and sends
+    /// responses on `lane`.
+    #[must_use]
+    pub fn new(lane: impl Into<LaneIndex>) -> Self {
+        Self { lane: lane.into() }
+    }
+
+    /// Gets the lane which calls are received on.
+    #[must_use]
+    pub const fn lane(&self) -> LaneIndex {
+        self.lane
+    }
+
+    /// Handles an event emitted by the server transport.
+    ///
+    /// # Errors
+    ///
+    /// If the event is a request for a call, it is consumed and returned as an
+    /// [`RpcRequest`] in [`Ok`]. Otherwise, the event is returned back in
+    /// [`Err`], so that you can handle it yourself.
+    pub fn handle<T: ServerTransport>(
+        &self,
+        event: ServerEvent<T>,
+    ) -> Result<RpcRequest<T::ClientKey>, ServerEvent<T>> {
+        match event {
+            ServerEvent::Recv {
+                client_key,
+                msg,
+                lane,
+            } if lane == self.lane => match read_frame(REQUEST, msg) {
+                Ok((id, msg)) => Ok(RpcRequest {
+                    client_key,
+                    id,
+                    msg,
+                }),
+                Err(msg) => Err(ServerEvent::Recv {
+                    client_key,
+                    msg,
+                    lane,
+                }),
+            },
+            event => Err(event),
+        }
+    }
+
+    /// Sends `response` to the client `client_key` as the answer to the call
+    /// `id`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the transport fails to send the response.
+    pub fn respond<T: ServerTransport>(
+        &self,
+        server: &mut T,
+        client_key: T::ClientKey,
+        id: RpcId,
+        response: impl AsRef<[u8]>,
+    ) -> Result<T::MessageKey, T::Error> {
+        server.send(
+            client_key,
+            write_frame(RESPONSE, id, response.as_ref()),
+            self.lane,
+        )
+    }
+}
@@ -105,6 +105,18 @@ pub struct ServerClosed<T: ServerTransport> {
     pub error: CloseReason<T::Error>,
 }
 
+/// The server finished reloading its configuration while open.
+///
+/// See [`ServerEvent::Reloaded`].
+///
+/// [`ServerEvent::Reloaded`]: super::ServerEvent::Reloaded
+#[derive(Derivative, Event)]
+#[derivative(Debug(bound = "T::Error: Debug"), Clone(bound = "T::Error: Clone"))]
+pub struct ServerReloaded<T: ServerTransport> {
+    /// Whether the reload succeeded, or why it failed.
+    pub result: Result<(), T::Error>,
+}
+
 /// A remote client has requested to connect to this server.
 ///
 /// The client has been given a key, and the server is trying to establish
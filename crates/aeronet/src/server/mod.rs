@@ -5,6 +5,9 @@ mod bevy;
 
 #[cfg(feature = "bevy")]
 pub use bevy::*;
+
+mod multi;
+pub use multi::*;
 use web_time::Duration;
 
 use std::{error::Error, fmt::Debug, hash::Hash};
@@ -177,7 +180,7 @@ pub trait ServerTransport {
 /// transport exposes it.
 ///
 /// [local address]: crate::stats::LocalAddr
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum ServerState<A, B> {
     /// Not listening to client connections, and making no attempts to start
     /// listening.
@@ -258,6 +261,16 @@ pub enum ServerEvent<T: ServerTransport + ?Sized> {
         /// Why the server closed.
         reason: CloseReason<T::Error>,
     },
+    /// The server finished reloading its configuration while open, e.g. to
+    /// swap its TLS identity for a new certificate.
+    ///
+    /// Clients which were already connected are not affected by a reload,
+    /// whether it succeeded or not. Only transports which support reloading
+    /// emit this event.
+    Reloaded {
+        /// Whether the reload succeeded, or why it failed.
+        result: Result<(), T::Error>,
+    },
 
     // client state
     /// A remote client has requested to connect to this server.
@@ -337,6 +350,7 @@ where
         match self {
             Self::Opened => ServerEvent::Opened,
             Self::Closed { reason } => ServerEvent::Closed { reason },
+            Self::Reloaded { result } => ServerEvent::Reloaded { result },
             Self::Connecting { client_key } => ServerEvent::Connecting { client_key },
             Self::Connected { client_key } => ServerEvent::Connected { client_key },
             Self::Disconnected { client_key, reason } => {
@@ -0,0 +1,319 @@
+use bytes::Bytes;
+use web_time::Duration;
+
+use crate::{
+    client::ClientState,
+    lane::LaneIndex,
+    server::{ServerEvent, ServerState, ServerStateFor, ServerTransport},
+    shared::Multi,
+};
+
+/// Combines two [`ServerTransport`]s into a single transport, allowing a
+/// single server app to accept clients from both at once.
+///
+/// Clients from each transport are identified by a [`Multi`] of the two
+/// transports' client keys, and [`ServerTransport::send`] and
+/// [`ServerTransport::disconnect`] are routed to the transport that the client
+/// belongs to. To combine more than two transports, nest this type, i.e.
+/// `MultiServer<A, MultiServer<B, C>>`.
+///
+/// Each transport is still opened individually, using [`MultiServer::a_mut`]
+/// and [`MultiServer::b_mut`] to access the transport-specific functions.
+///
+/// # State
+///
+/// The combined [`ServerState`] is:
+/// - [`ServerState::Open`] if at least one transport is open, or if one was
+///   open since this server last closed and the other is still opening
+/// - [`ServerState::Opening`] if no transport is open, but at least one is
+///   opening
+/// - [`ServerState::Closed`] if both transports are closed
+///
+/// Both the opening and open states give access to the states of the two
+/// inner transports.
+///
+/// Following from this, [`ServerEvent::Opened`] is only emitted when the first
+/// transport opens, and [`ServerEvent::Closed`] is only emitted once the last
+/// transport closes - the state never goes from open back to opening. If one
+/// transport closes while the other stays open or opening, the reason for it
+/// closing is discarded, so you should check the state of each transport if
+/// you need to know this.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct MultiServer<A, B> {
+    a: A,
+    b: B,
+    /// Whether either transport has been open since the last
+    /// [`ServerEvent::Closed`].
+    opened: bool,
+}
+
+impl<A, B> MultiServer<A, B> {
+    /// Combines two existing server transports.
+    #[must_use]
+    pub const fn new(a: A, b: B) -> Self {
+        Self {
+            a,
+            b,
+            opened: false,
+        }
+    }
+
+    /// Gets a reference to the first transport.
+    pub const fn a(&self) -> &A {
+        &self.a
+    }
+
+    /// Gets a mutable reference to the first transport.
+    pub fn a_mut(&mut self) -> &mut A {
+        &mut self.a
+    }
+
+    /// Gets a reference to the second transport.
+    pub const fn b(&self) -> &B {
+        &self.b
+    }
+
+    /// Gets a mutable reference to the second transport.
+    pub fn b_mut(&mut self) -> &mut B {
+        &mut self.b
+    }
+
+    /// Takes the two transports out of this combinator.
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+}
+
+/// Server state of a [`MultiServer`] when it is opening or open.
+///
+/// Holds the states of the two inner transports.
+pub type MultiServerState<'t, A, B> = (ServerStateFor<'t, A>, ServerStateFor<'t, B>);
+
+impl<A: ServerTransport, B: ServerTransport> ServerTransport for MultiServer<A, B> {
+    type Error = Multi<A::Error, B::Error>;
+
+    type Opening<'this>
+        = MultiServerState<'this, A, B>
+    where
+        Self: 'this;
+
+    type Open<'this>
+        = MultiServerState<'this, A, B>
+    where
+        Self: 'this;
+
+    type Connecting<'this>
+        = Multi<A::Connecting<'this>, B::Connecting<'this>>
+    where
+        Self: 'this;
+
+    type Connected<'this>
+        = Multi<A::Connected<'this>, B::Connected<'this>>
+    where
+        Self: 'this;
+
+    type ClientKey = Multi<A::ClientKey, B::ClientKey>;
+
+    type MessageKey = Multi<A::MessageKey, B::MessageKey>;
+
+    fn state(&self) -> ServerState<Self::Opening<'_>, Self::Open<'_>> {
+        let (a, b) = (self.a.state(), self.b.state());
+        if a.is_open() || b.is_open() {
+            ServerState::Open((a, b))
+        } else if a.is_opening() || b.is_opening() {
+            if self.opened {
+                ServerState::Open((a, b))
+            } else {
+                ServerState::Opening((a, b))
+            }
+        } else {
+            ServerState::Closed
+        }
+    }
+
+    fn client_state(
+        &self,
+        client_key: Self::ClientKey,
+    ) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        match client_key {
+            Multi::A(client_key) => self.a.client_state(client_key).map(Multi::A, Multi::A),
+            Multi::B(client_key) => self.b.client_state(client_key).map(Multi::B, Multi::B),
+        }
+    }
+
+    fn client_keys(&self) -> impl Iterator<Item = Self::ClientKey> + '_ {
+        self.a
+            .client_keys()
+            .map(Multi::A)
+            .chain(self.b.client_keys().map(Multi::B))
+    }
+
+    fn poll(&mut self, delta_time: Duration) -> impl Iterator<Item = ServerEvent<Self>> {
+        let mut events = Vec::new();
+        // if both transports close in the same update, only report it once
+        let mut closed = false;
+        // not every transport emits `Opened`, i.e. if it opens immediately
+        self.opened |= self.a.state().is_open() || self.b.state().is_open();
+
+        let a_events = self.a.poll(delta_time).collect::<Vec<_>>();
+        for event in a_events {
+            // `b` hasn't been polled yet, so this uses its state from before
+            // this update
+            let b_closed = self.b.state().is_closed();
+            if let Some(event) = map_event(
+                event,
+                Multi::A,
+                Multi::A,
+                Multi::A,
+                b_closed,
+                &mut self.opened,
+                &mut closed,
+            ) {
+                events.push(event);
+            }
+        }
+
+        let b_events = self.b.poll(delta_time).collect::<Vec<_>>();
+        for event in b_events {
+            let a_closed = self.a.state().is_closed();
+            if let Some(event) = map_event(
+                event,
+                Multi::B,
+                Multi::B,
+                Multi::B,
+                a_closed,
+                &mut self.opened,
+                &mut closed,
+            ) {
+                events.push(event);
+            }
+        }
+
+        events.into_iter()
+    }
+
+    fn send(
+        &mut self,
+        client_key: Self::ClientKey,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        match client_key {
+            Multi::A(client_key) => self
+                .a
+                .send(client_key, msg, lane)
+                .map(Multi::A)
+                .map_err(Multi::A),
+            Multi::B(client_key) => self
+                .b
+                .send(client_key, msg, lane)
+                .map(Multi::B)
+                .map_err(Multi::B),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        // only flush open transports, since one of them being closed
+        // shouldn't stop the other from sending
+        if self.a.state().is_open() {
+            self.a.flush().map_err(Multi::A)?;
+        }
+        if self.b.state().is_open() {
+            self.b.flush().map_err(Multi::B)?;
+        }
+        Ok(())
+    }
+
+    fn disconnect(
+        &mut self,
+        client_key: Self::ClientKey,
+        reason: impl Into<String>,
+    ) -> Result<(), Self::Error> {
+        match client_key {
+            Multi::A(client_key) => self.a.disconnect(client_key, reason).map_err(Multi::A),
+            Multi::B(client_key) => self.b.disconnect(client_key, reason).map_err(Multi::B),
+        }
+    }
+
+    fn close(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
+        let reason = reason.into();
+        // only fail if neither transport could be closed,
+        // since it's fine for one of them to already be closed
+        match (self.a.close(reason.clone()), self.b.close(reason)) {
+            (Err(err), Err(_)) => Err(Multi::A(err)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn map_event<T, R>(
+    event: ServerEvent<T>,
+    map_client_key: impl FnOnce(T::ClientKey) -> R::ClientKey,
+    map_msg_key: impl FnOnce(T::MessageKey) -> R::MessageKey,
+    map_err: impl FnOnce(T::Error) -> R::Error,
+    other_closed: bool,
+    opened: &mut bool,
+    closed: &mut bool,
+) -> Option<ServerEvent<R>>
+where
+    T: ServerTransport,
+    R: ServerTransport,
+{
+    Some(match event {
+        ServerEvent::Opened => {
+            if *opened {
+                return None;
+            }
+            *opened = true;
+            *closed = false;
+            ServerEvent::Opened
+        }
+        ServerEvent::Closed { reason } => {
+            if !other_closed || *closed {
+                return None;
+            }
+            *opened = false;
+            *closed = true;
+            ServerEvent::Closed {
+                reason: reason.map_err(map_err),
+            }
+        }
+        ServerEvent::Reloaded { result } => ServerEvent::Reloaded {
+            result: result.map_err(map_err),
+        },
+        ServerEvent::Connecting { client_key } => ServerEvent::Connecting {
+            client_key: map_client_key(client_key),
+        },
+        ServerEvent::Connected { client_key } => ServerEvent::Connected {
+            client_key: map_client_key(client_key),
+        },
+        ServerEvent::Disconnected { client_key, reason } => ServerEvent::Disconnected {
+            client_key: map_client_key(client_key),
+            reason: reason.map_err(map_err),
+        },
+        ServerEvent::Recv {
+            client_key,
+            msg,
+            lane,
+        } => ServerEvent::Recv {
+            client_key: map_client_key(client_key),
+            msg,
+            lane,
+        },
+        ServerEvent::Ack {
+            client_key,
+            msg_key,
+        } => ServerEvent::Ack {
+            client_key: map_client_key(client_key),
+            msg_key: map_msg_key(msg_key),
+        },
+        ServerEvent::Nack {
+            client_key,
+            msg_key,
+        } => ServerEvent::Nack {
+            client_key: map_client_key(client_key),
+            msg_key: map_msg_key(msg_key),
+        },
+    })
+}
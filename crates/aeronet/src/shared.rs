@@ -1,5 +1,7 @@
 //! Client/server-independent items.
 
+use std::{error::Error, fmt};
+
 /// Disconnect reason that may be used when a client or server is dropped.
 ///
 /// When a client is dropped, it must disconnect itself from its server.
@@ -8,3 +10,34 @@
 /// required. Implementations may use this string as a default disconnect
 /// reason.
 pub const DROP_DISCONNECT_REASON: &str = "dropped";
+
+/// Value belonging to one of two transports which have been combined into a
+/// single transport.
+///
+/// This is used for the keys, states, and errors of transport combinators such
+/// as `MultiServer` and `FallbackClient`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Multi<A, B> {
+    /// Value belongs to the first transport.
+    A(A),
+    /// Value belongs to the second transport.
+    B(B),
+}
+
+impl<A: fmt::Display, B: fmt::Display> fmt::Display for Multi<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A(a) => a.fmt(f),
+            Self::B(b) => b.fmt(f),
+        }
+    }
+}
+
+impl<A: Error, B: Error> Error for Multi<A, B> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::A(a) => a.source(),
+            Self::B(b) => b.source(),
+        }
+    }
+}
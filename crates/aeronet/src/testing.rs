@@ -0,0 +1,980 @@
+//! Conformance test suite for [`ClientTransport`] and [`ServerTransport`]
+//! implementations.
+//!
+//! The transport traits make promises which can't be enforced by the type
+//! system, such as [`ServerEvent::Connecting`] being emitted before
+//! [`ServerEvent::Connected`], or lanes upholding the guarantees of their
+//! [`LaneKind`]. This module provides a set of generic checks which a
+//! transport implementation can run against itself, to make sure that it keeps
+//! these promises.
+//!
+//! # Usage
+//!
+//! Implement [`TransportPair`] for a type which can open a server and connect
+//! clients to it, then create a [`Conformance`] suite from it in your
+//! transport's tests. Each check is a separate function, so that you can run
+//! each one as its own test:
+//!
+//! ```ignore
+//! use aeronet::testing::Conformance;
+//!
+//! #[test]
+//! fn lifecycle() {
+//!     Conformance::new(MyTransportPair).lifecycle();
+//! }
+//!
+//! #[test]
+//! fn lanes() {
+//!     Conformance::new(MyTransportPair).lanes();
+//! }
+//! ```
+//!
+//! If the transport does not behave as expected, the check panics with a
+//! description of what went wrong.
+//!
+//! While a check is running, every event emitted by the client and the server
+//! is also checked against the general rules of the transport traits, e.g. a
+//! client must not receive messages before it has emitted
+//! [`ClientEvent::Connected`], and after an event which changes the state of
+//! the transport, the transport must be in that new state.
+//!
+//! # Polling
+//!
+//! Checks poll both sides in a loop until the expected events arrive, sleeping
+//! for the [poll interval](Conformance::with_poll_interval) between polls, and
+//! fail if the events do not arrive before the
+//! [timeout](Conformance::with_timeout). The checks block the current thread,
+//! so transports which do their work on an async runtime must run that runtime
+//! in the background.
+
+use std::{collections::HashMap, fmt::Debug, thread};
+
+use bytes::Bytes;
+use web_time::{Duration, Instant};
+
+use crate::{
+    client::{ClientEvent, ClientTransport, DisconnectReason},
+    lane::{LaneIndex, LaneKind, LaneOrdering, LaneReliability},
+    server::{CloseReason, ServerEvent, ServerTransport},
+    shared::DROP_DISCONNECT_REASON,
+};
+
+const REASON: &str = "conformance test";
+
+const MSGS_PER_LANE: u32 = 8;
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+/// Creates connected client and server transports for a [`Conformance`]
+/// suite.
+///
+/// See the [module-level documentation](self).
+pub trait TransportPair {
+    /// Client transport under test.
+    type Client: ClientTransport;
+
+    /// Server transport under test.
+    type Server: ServerTransport;
+
+    /// Gets the kinds of lanes that the clients and servers created by this
+    /// pair are configured with.
+    ///
+    /// The lane kind at index `i` is the kind of the lane with [`LaneIndex`]
+    /// `i`. If the transport does not support lanes, this should return a
+    /// single [`LaneKind::ReliableOrdered`].
+    fn lanes(&self) -> &[LaneKind];
+
+    /// Creates a server which has started opening.
+    ///
+    /// The server must not be [`ServerState::Closed`], and will be polled until
+    /// it is open.
+    ///
+    /// [`ServerState::Closed`]: crate::server::ServerState::Closed
+    fn open_server(&mut self) -> Self::Server;
+
+    /// Creates a client which has started connecting to `server`.
+    ///
+    /// The client must not be [`ClientState::Disconnected`], and will be
+    /// polled alongside the server until it is connected.
+    ///
+    /// [`ClientState::Disconnected`]: crate::client::ClientState::Disconnected
+    fn connect_client(&mut self, server: &mut Self::Server) -> Self::Client;
+
+    /// Gets if the transport communicates the reason passed to
+    /// [`ClientTransport::disconnect`] and [`ServerTransport::disconnect`] to
+    /// the peer.
+    ///
+    /// Transports only make a best-effort attempt at communicating the reason.
+    /// If this returns `false`, the peer is allowed to observe any
+    /// [`DisconnectReason::Remote`] or [`DisconnectReason::Error`] instead of
+    /// the exact reason.
+    ///
+    /// By default, this returns `true`.
+    fn sends_disconnect_reason(&self) -> bool {
+        true
+    }
+}
+
+/// Generic conformance test suite for a [`TransportPair`].
+///
+/// See the [module-level documentation](self).
+#[derive(Debug)]
+pub struct Conformance<P> {
+    pair: P,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+type ClientKey<P> = <<P as TransportPair>::Server as ServerTransport>::ClientKey;
+
+type ClientEvents<P> = Vec<ClientEvent<<P as TransportPair>::Client>>;
+
+type ServerEvents<P> = Vec<ServerEvent<<P as TransportPair>::Server>>;
+
+impl<P: TransportPair> Conformance<P> {
+    /// Creates a suite which runs checks against transports created by `pair`.
+    ///
+    /// By default, checks poll every millisecond and time out after 5 seconds.
+    pub const fn new(pair: P) -> Self {
+        Self {
+            pair,
+            poll_interval: Duration::from_millis(1),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Sets how long to sleep for between polls.
+    #[must_use]
+    pub const fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets how long a check waits for expected events before failing.
+    #[must_use]
+    pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Gets a reference to the underlying pair.
+    pub const fn pair(&self) -> &P {
+        &self.pair
+    }
+
+    /// Gets a mutable reference to the underlying pair.
+    pub fn pair_mut(&mut self) -> &mut P {
+        &mut self.pair
+    }
+
+    /// Runs every check in this suite.
+    pub fn run_all(&mut self) {
+        self.lifecycle();
+        self.new_client_key();
+        self.lanes();
+        self.errors_when_disconnected();
+        self.client_disconnect();
+        self.server_disconnect();
+        self.server_close();
+        self.client_drop();
+        self.server_drop();
+    }
+
+    /// Checks that a server opens, and that a client connects to it.
+    ///
+    /// The server must emit [`ServerEvent::Connecting`] before
+    /// [`ServerEvent::Connected`] for the client, and both sides must then
+    /// consider the client connected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn lifecycle(&mut self) {
+        let mut run = self.open();
+        let client_key = run.connect(&mut self.pair);
+        assert!(
+            run.server().client_keys().any(|key| key == client_key),
+            "server did not list connected client {client_key:?} in its client keys"
+        );
+    }
+
+    /// Checks that a client which connects again after disconnecting is given
+    /// a new client key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn new_client_key(&mut self) {
+        let mut run = self.open();
+        let old_key = run.connect(&mut self.pair);
+        run.client_mut()
+            .disconnect(REASON)
+            .expect("client should be able to disconnect while connected");
+        run.wait("server to observe the disconnect", |_, _, server| {
+            server
+                .into_iter()
+                .any(|event| {
+                    matches!(event, ServerEvent::Disconnected { client_key, .. } if client_key == old_key)
+                })
+                .then_some(())
+        });
+
+        // connecting a client with a key that the server has already used
+        // fails in `Run::poll`
+        let new_key = run.connect(&mut self.pair);
+        assert_ne!(old_key, new_key, "reconnected client reused a client key");
+    }
+
+    /// Checks that messages sent on each lane are received on the same lane,
+    /// with the guarantees of that lane's [`LaneKind`].
+    ///
+    /// A batch of messages is sent on every lane in both directions. Messages
+    /// on unreliable lanes are allowed to be lost - however, if they are, this
+    /// check waits until it times out before checking what was received.
+    ///
+    /// If the transport emits [`ClientEvent::Ack`] or [`ServerEvent::Ack`], the
+    /// acknowledged message key must be one returned from a `send` call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn lanes(&mut self) {
+        let lanes = self.pair.lanes().to_vec();
+        let mut run = self.open();
+        let client_key = run.connect(&mut self.pair);
+
+        let mut c2s_keys = Vec::new();
+        let mut s2c_keys = Vec::new();
+        for seq in 0..MSGS_PER_LANE {
+            for lane_index in 0..lanes.len() {
+                let lane = LaneIndex::from_raw(lane_index as u64);
+                c2s_keys.push(
+                    run.client_mut()
+                        .send(payload(lane, seq), lane)
+                        .unwrap_or_else(|err| {
+                            panic!("client failed to send on lane {lane_index}: {err:?}")
+                        }),
+                );
+                s2c_keys.push(
+                    run.server_mut()
+                        .send(client_key.clone(), payload(lane, seq), lane)
+                        .unwrap_or_else(|err| {
+                            panic!("server failed to send on lane {lane_index}: {err:?}")
+                        }),
+                );
+            }
+        }
+        run.client_mut()
+            .flush()
+            .expect("client should be able to flush");
+        run.server_mut()
+            .flush()
+            .expect("server should be able to flush");
+
+        let mut client_recv = LaneRecv::new("client", &lanes);
+        let mut server_recv = LaneRecv::new("server", &lanes);
+        run.try_wait(|_, client, server| {
+            for event in client {
+                match event {
+                    ClientEvent::Recv { msg, lane } => client_recv.recv(&msg, lane),
+                    ClientEvent::Ack { msg_key } | ClientEvent::Nack { msg_key } => assert!(
+                        c2s_keys.contains(&msg_key),
+                        "client (n)acknowledged unknown message {msg_key:?}"
+                    ),
+                    event => panic!("client emitted {event:?} while receiving messages"),
+                }
+            }
+            for event in server {
+                match event {
+                    ServerEvent::Recv {
+                        client_key: key,
+                        msg,
+                        lane,
+                    } => {
+                        assert_eq!(client_key, key, "server received message from wrong client");
+                        server_recv.recv(&msg, lane);
+                    }
+                    ServerEvent::Ack { msg_key, .. } | ServerEvent::Nack { msg_key, .. } => {
+                        assert!(
+                            s2c_keys.contains(&msg_key),
+                            "server (n)acknowledged unknown message {msg_key:?}"
+                        );
+                    }
+                    event => panic!("server emitted {event:?} while receiving messages"),
+                }
+            }
+            (client_recv.is_complete() && server_recv.is_complete()).then_some(())
+        });
+
+        client_recv.check_reliable();
+        server_recv.check_reliable();
+    }
+
+    /// Checks that operations on a disconnected client, or on a disconnected
+    /// client of a server, return errors, and that disconnecting twice does not
+    /// emit a second [`ClientEvent::Disconnected`] or
+    /// [`ServerEvent::Disconnected`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn errors_when_disconnected(&mut self) {
+        let mut run = self.open();
+        let client_key = run.connect(&mut self.pair);
+
+        run.client_mut()
+            .disconnect(REASON)
+            .expect("client should be able to disconnect while connected");
+        // must be a no-op; it may return an error
+        let _ = run.client_mut().disconnect(REASON);
+        run.wait("client to disconnect", |_, client, _| {
+            client
+                .into_iter()
+                .any(|event| matches!(event, ClientEvent::Disconnected { .. }))
+                .then_some(())
+        });
+        run.poll();
+        let client = run.client_mut();
+        assert!(
+            client.state().is_disconnected(),
+            "client is not disconnected after emitting Disconnected"
+        );
+        assert!(
+            client.send(payload(LANE, 0), LANE).is_err(),
+            "client sent a message while disconnected"
+        );
+
+        let client_key2 = run.connect(&mut self.pair);
+        run.server_mut()
+            .disconnect(client_key2.clone(), REASON)
+            .expect("server should be able to disconnect a connected client");
+        let _ = run.server_mut().disconnect(client_key2.clone(), REASON);
+        run.wait("server to disconnect the client", |_, _, server| {
+            server
+                .into_iter()
+                .any(|event| {
+                    matches!(event, ServerEvent::Disconnected { client_key, .. } if client_key == client_key2)
+                })
+                .then_some(())
+        });
+        run.poll();
+        for key in [client_key, client_key2] {
+            let server = run.server_mut();
+            assert!(
+                server.client_state(key.clone()).is_disconnected(),
+                "server does not consider client {key:?} disconnected"
+            );
+            assert!(
+                server.send(key.clone(), payload(LANE, 0), LANE).is_err(),
+                "server sent a message to disconnected client {key:?}"
+            );
+        }
+
+        run.server_mut()
+            .close(REASON)
+            .expect("server should be able to close while open");
+        let _ = run.server_mut().close(REASON);
+        run.wait("server to close", |_, _, server| {
+            server
+                .into_iter()
+                .any(|event| matches!(event, ServerEvent::Closed { .. }))
+                .then_some(())
+        });
+        run.poll();
+        assert!(
+            run.server().state().is_closed(),
+            "server is not closed after emitting Closed"
+        );
+        assert_eq!(
+            0,
+            run.server().client_keys().count(),
+            "closed server still has clients"
+        );
+    }
+
+    /// Checks that when a client disconnects, the client observes
+    /// [`DisconnectReason::Local`], and the server observes
+    /// [`DisconnectReason::Remote`], with the given reason.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn client_disconnect(&mut self) {
+        let sends_reason = self.pair.sends_disconnect_reason();
+        let mut run = self.open();
+        let client_key = run.connect(&mut self.pair);
+
+        run.client_mut()
+            .disconnect(REASON)
+            .expect("client should be able to disconnect while connected");
+        let mut client_done = false;
+        let mut server_done = false;
+        run.wait(
+            "both sides to observe the disconnect",
+            |_, client, server| {
+                for event in client {
+                    if let ClientEvent::Disconnected { reason } = event {
+                        check_local("client", &reason, REASON);
+                        client_done = true;
+                    }
+                }
+                for event in server {
+                    if let ServerEvent::Disconnected {
+                        client_key: key,
+                        reason,
+                    } = event
+                    {
+                        assert_eq!(client_key, key, "server disconnected the wrong client");
+                        check_remote("server", &reason, REASON, sends_reason);
+                        server_done = true;
+                    }
+                }
+                (client_done && server_done).then_some(())
+            },
+        );
+    }
+
+    /// Checks that when a server disconnects a client, the server observes
+    /// [`DisconnectReason::Local`], and the client observes
+    /// [`DisconnectReason::Remote`], with the given reason.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn server_disconnect(&mut self) {
+        let sends_reason = self.pair.sends_disconnect_reason();
+        let mut run = self.open();
+        let client_key = run.connect(&mut self.pair);
+
+        run.server_mut()
+            .disconnect(client_key.clone(), REASON)
+            .expect("server should be able to disconnect a connected client");
+        let mut client_done = false;
+        let mut server_done = false;
+        run.wait(
+            "both sides to observe the disconnect",
+            |_, client, server| {
+                for event in client {
+                    if let ClientEvent::Disconnected { reason } = event {
+                        check_remote("client", &reason, REASON, sends_reason);
+                        client_done = true;
+                    }
+                }
+                for event in server {
+                    if let ServerEvent::Disconnected {
+                        client_key: key,
+                        reason,
+                    } = event
+                    {
+                        assert_eq!(client_key, key, "server disconnected the wrong client");
+                        check_local("server", &reason, REASON);
+                        server_done = true;
+                    }
+                }
+                (client_done && server_done).then_some(())
+            },
+        );
+    }
+
+    /// Checks that when a server closes, the server observes
+    /// [`CloseReason::Local`], and its clients observe
+    /// [`DisconnectReason::Remote`], with the given reason.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn server_close(&mut self) {
+        let sends_reason = self.pair.sends_disconnect_reason();
+        let mut run = self.open();
+        run.connect(&mut self.pair);
+
+        run.server_mut()
+            .close(REASON)
+            .expect("server should be able to close while open");
+        let mut client_done = false;
+        let mut server_done = false;
+        run.wait("both sides to observe the close", |_, client, server| {
+            for event in client {
+                if let ClientEvent::Disconnected { reason } = event {
+                    check_remote("client", &reason, REASON, sends_reason);
+                    client_done = true;
+                }
+            }
+            for event in server {
+                if let ServerEvent::Closed { reason } = event {
+                    match reason {
+                        CloseReason::Local(reason) => {
+                            assert_eq!(REASON, reason, "server was closed with the wrong reason");
+                        }
+                        CloseReason::Error(err) => {
+                            panic!("expected server to be closed locally, got error {err:?}")
+                        }
+                    }
+                    server_done = true;
+                }
+            }
+            (client_done && server_done).then_some(())
+        });
+    }
+
+    /// Checks that dropping a client disconnects it from the server, using
+    /// [`DROP_DISCONNECT_REASON`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn client_drop(&mut self) {
+        let sends_reason = self.pair.sends_disconnect_reason();
+        let mut run = self.open();
+        let client_key = run.connect(&mut self.pair);
+
+        drop(run.client.take());
+        run.wait("server to observe the disconnect", |_, _, server| {
+            server.into_iter().find_map(|event| match event {
+                ServerEvent::Disconnected {
+                    client_key: key,
+                    reason,
+                } => {
+                    assert_eq!(client_key, key, "server disconnected the wrong client");
+                    check_remote("server", &reason, DROP_DISCONNECT_REASON, sends_reason);
+                    Some(())
+                }
+                _ => None,
+            })
+        });
+    }
+
+    /// Checks that dropping a server disconnects its clients, using
+    /// [`DROP_DISCONNECT_REASON`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transport does not behave as expected.
+    pub fn server_drop(&mut self) {
+        let sends_reason = self.pair.sends_disconnect_reason();
+        let mut run = self.open();
+        run.connect(&mut self.pair);
+
+        drop(run.server.take());
+        run.wait("client to observe the disconnect", |_, client, _| {
+            client.into_iter().find_map(|event| match event {
+                ClientEvent::Disconnected { reason } => {
+                    check_remote("client", &reason, DROP_DISCONNECT_REASON, sends_reason);
+                    Some(())
+                }
+                _ => None,
+            })
+        });
+    }
+
+    fn open(&mut self) -> Run<P> {
+        let server = self.pair.open_server();
+        assert!(
+            !server.state().is_closed(),
+            "server created by the pair is closed"
+        );
+
+        let mut run = Run {
+            poll_interval: self.poll_interval,
+            timeout: self.timeout,
+            last_poll: Instant::now(),
+            client: None,
+            client_phase: Phase::Connecting,
+            server: Some(server),
+            server_opened: false,
+            server_closed: false,
+            clients: HashMap::new(),
+        };
+        run.wait("server to open", |run: &Run<P>, _, _| {
+            run.server().state().is_open().then_some(())
+        });
+        run
+    }
+}
+
+/// State of a client or server's client, as observed through its events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+/// Transports being tested in a single check.
+struct Run<P: TransportPair> {
+    poll_interval: Duration,
+    timeout: Duration,
+    last_poll: Instant,
+    client: Option<P::Client>,
+    client_phase: Phase,
+    server: Option<P::Server>,
+    server_opened: bool,
+    server_closed: bool,
+    clients: HashMap<ClientKey<P>, Phase>,
+}
+
+impl<P: TransportPair> Run<P> {
+    fn client_mut(&mut self) -> &mut P::Client {
+        self.client.as_mut().expect("client should exist")
+    }
+
+    fn server(&self) -> &P::Server {
+        self.server.as_ref().expect("server should exist")
+    }
+
+    fn server_mut(&mut self) -> &mut P::Server {
+        self.server.as_mut().expect("server should exist")
+    }
+
+    /// Connects a new client, replacing the current one, and returns its key.
+    fn connect(&mut self, pair: &mut P) -> ClientKey<P> {
+        let client = pair.connect_client(self.server_mut());
+        assert!(
+            !client.state().is_disconnected(),
+            "client created by the pair is disconnected"
+        );
+        self.client = Some(client);
+        self.client_phase = Phase::Connecting;
+
+        let mut client_connected = false;
+        let mut connecting_key = None;
+        let mut client_key = None;
+        let client_key = self.wait("client to connect", |_, client, server| {
+            for event in client {
+                match event {
+                    ClientEvent::Connected => client_connected = true,
+                    event => panic!("client emitted {event:?} while connecting"),
+                }
+            }
+            for event in server {
+                match event {
+                    ServerEvent::Connecting { client_key: key } => {
+                        assert!(connecting_key.is_none(), "more than one client connecting");
+                        connecting_key = Some(key);
+                    }
+                    ServerEvent::Connected { client_key: key } => {
+                        assert_eq!(
+                            connecting_key.as_ref(),
+                            Some(&key),
+                            "server connected a client other than the connecting one"
+                        );
+                        client_key = Some(key);
+                    }
+                    // a previous client in this check may still be disconnecting
+                    ServerEvent::Disconnected {
+                        client_key: key, ..
+                    } if connecting_key.as_ref() != Some(&key) => {}
+                    event => panic!("server emitted {event:?} while client was connecting"),
+                }
+            }
+            if client_connected {
+                client_key.clone()
+            } else {
+                None
+            }
+        });
+
+        assert!(
+            self.client_mut().state().is_connected(),
+            "client is not connected after emitting Connected"
+        );
+        client_key
+    }
+
+    /// Polls the current client and server, and checks that the events they
+    /// emitted follow the rules of the transport traits.
+    fn poll(&mut self) -> (ClientEvents<P>, ServerEvents<P>) {
+        let now = Instant::now();
+        let delta_time = now.duration_since(self.last_poll);
+        self.last_poll = now;
+
+        let client_events = self.client.as_mut().map_or_else(Vec::new, |client| {
+            client.poll(delta_time).collect::<Vec<_>>()
+        });
+        self.check_client_events(&client_events);
+
+        let server_events = self.server.as_mut().map_or_else(Vec::new, |server| {
+            server.poll(delta_time).collect::<Vec<_>>()
+        });
+        self.check_server_events(&server_events);
+
+        (client_events, server_events)
+    }
+
+    fn check_client_events(&mut self, events: &ClientEvents<P>) {
+        for event in events {
+            match (self.client_phase, event) {
+                (Phase::Connecting, ClientEvent::Connected) => {
+                    self.client_phase = Phase::Connected;
+                }
+                (Phase::Connecting | Phase::Connected, ClientEvent::Disconnected { .. }) => {
+                    self.client_phase = Phase::Disconnected;
+                }
+                (
+                    Phase::Connected,
+                    ClientEvent::Recv { .. } | ClientEvent::Ack { .. } | ClientEvent::Nack { .. },
+                ) => {}
+                (phase, event) => panic!("client emitted {event:?} while {phase:?}"),
+            }
+        }
+
+        if events.is_empty() {
+            return;
+        }
+        let Some(client) = &self.client else {
+            return;
+        };
+        let state = client.state();
+        match self.client_phase {
+            Phase::Connecting => {}
+            Phase::Connected => assert!(
+                state.is_connected(),
+                "client is not connected after emitting Connected"
+            ),
+            Phase::Disconnected => assert!(
+                state.is_disconnected(),
+                "client is not disconnected after emitting Disconnected"
+            ),
+        }
+    }
+
+    fn check_server_events(&mut self, events: &ServerEvents<P>) {
+        assert!(
+            !self.server_closed || events.is_empty(),
+            "server emitted {events:?} after Closed"
+        );
+
+        let mut changed = Vec::new();
+        for event in events {
+            match event {
+                ServerEvent::Opened => {
+                    assert!(!self.server_opened, "server emitted Opened twice");
+                    self.server_opened = true;
+                }
+                ServerEvent::Closed { .. } => {
+                    self.server_closed = true;
+                }
+                ServerEvent::Reloaded { .. } => {
+                    assert!(self.server_opened, "server emitted Reloaded before Opened");
+                }
+                ServerEvent::Connecting { client_key } => {
+                    assert!(
+                        !self.clients.contains_key(client_key),
+                        "server emitted Connecting for already used client key {client_key:?}"
+                    );
+                    self.clients.insert(client_key.clone(), Phase::Connecting);
+                    changed.push(client_key.clone());
+                }
+                ServerEvent::Connected { client_key } => {
+                    let phase = self.clients.get(client_key).copied();
+                    assert_eq!(
+                        Some(Phase::Connecting),
+                        phase,
+                        "server emitted Connected for {client_key:?} without Connecting first"
+                    );
+                    self.clients.insert(client_key.clone(), Phase::Connected);
+                    changed.push(client_key.clone());
+                }
+                ServerEvent::Disconnected { client_key, .. } => {
+                    let phase = self.clients.get(client_key).copied();
+                    assert!(
+                        matches!(phase, Some(Phase::Connecting | Phase::Connected)),
+                        "server emitted Disconnected for {client_key:?} while {phase:?}"
+                    );
+                    self.clients.insert(client_key.clone(), Phase::Disconnected);
+                    changed.push(client_key.clone());
+                }
+                ServerEvent::Recv { client_key, .. }
+                | ServerEvent::Ack { client_key, .. }
+                | ServerEvent::Nack { client_key, .. } => {
+                    let phase = self.clients.get(client_key).copied();
+                    assert_eq!(
+                        Some(Phase::Connected),
+                        phase,
+                        "server emitted {event:?} for a client which is not connected"
+                    );
+                }
+            }
+        }
+
+        let Some(server) = &self.server else {
+            return;
+        };
+        if self.server_closed {
+            assert!(
+                server.state().is_closed(),
+                "server is not closed after emitting Closed"
+            );
+            return;
+        }
+        if self.server_opened {
+            assert!(
+                server.state().is_open(),
+                "server is not open after emitting Opened"
+            );
+        }
+        for client_key in changed {
+            let state = server.client_state(client_key.clone());
+            let consistent = match self.clients[&client_key] {
+                Phase::Connecting => state.is_connecting(),
+                Phase::Connected => state.is_connected(),
+                Phase::Disconnected => state.is_disconnected(),
+            };
+            assert!(
+                consistent,
+                "server's state for client {client_key:?} does not match the last event emitted for it"
+            );
+        }
+    }
+
+    /// Polls until `f` returns [`Some`], or the timeout elapses.
+    fn try_wait<R>(
+        &mut self,
+        mut f: impl FnMut(&Self, ClientEvents<P>, ServerEvents<P>) -> Option<R>,
+    ) -> Option<R> {
+        let start = Instant::now();
+        loop {
+            let (client_events, server_events) = self.poll();
+            if let Some(result) = f(self, client_events, server_events) {
+                return Some(result);
+            }
+            if start.elapsed() >= self.timeout {
+                return None;
+            }
+            thread::sleep(self.poll_interval);
+        }
+    }
+
+    /// Polls until `f` returns [`Some`], panicking if the timeout elapses.
+    fn wait<R>(
+        &mut self,
+        what: &str,
+        f: impl FnMut(&Self, ClientEvents<P>, ServerEvents<P>) -> Option<R>,
+    ) -> R {
+        self.try_wait(f)
+            .unwrap_or_else(|| panic!("timed out waiting for {what}"))
+    }
+}
+
+fn payload(lane: LaneIndex, seq: u32) -> Bytes {
+    let mut buf = Vec::with_capacity(12);
+    buf.extend_from_slice(&lane.into_raw().to_le_bytes());
+    buf.extend_from_slice(&seq.to_le_bytes());
+    Bytes::from(buf)
+}
+
+fn read_payload(msg: &[u8]) -> Option<(u64, u32)> {
+    let lane = msg.get(..8)?.try_into().ok()?;
+    let seq = msg.get(8..)?.try_into().ok()?;
+    Some((u64::from_le_bytes(lane), u32::from_le_bytes(seq)))
+}
+
+/// Messages received by one side in [`Conformance::lanes`].
+struct LaneRecv<'a> {
+    side: &'static str,
+    lanes: &'a [LaneKind],
+    recv: Vec<Vec<u32>>,
+}
+
+impl<'a> LaneRecv<'a> {
+    fn new(side: &'static str, lanes: &'a [LaneKind]) -> Self {
+        Self {
+            side,
+            lanes,
+            recv: vec![Vec::new(); lanes.len()],
+        }
+    }
+
+    fn recv(&mut self, msg: &[u8], lane: LaneIndex) {
+        let side = self.side;
+        let lane_index = usize::try_from(lane.into_raw())
+            .ok()
+            .filter(|index| *index < self.lanes.len())
+            .unwrap_or_else(|| panic!("{side} received a message on invalid lane {lane:?}"));
+        let (sent_lane, seq) = read_payload(msg)
+            .filter(|(_, seq)| *seq < MSGS_PER_LANE)
+            .unwrap_or_else(|| panic!("{side} received a corrupted message {msg:?}"));
+        assert_eq!(
+            sent_lane,
+            lane.into_raw(),
+            "{side} received a message sent on lane {sent_lane} on lane {lane_index}"
+        );
+
+        let kind = self.lanes[lane_index];
+        let recv = &mut self.recv[lane_index];
+        if kind.reliability() == LaneReliability::Reliable {
+            assert!(
+                !recv.contains(&seq),
+                "{side} received message {seq} twice on {kind:?} lane {lane_index}"
+            );
+        }
+        match kind.ordering() {
+            LaneOrdering::Unordered => {}
+            LaneOrdering::Sequenced => {
+                if let Some(last) = recv.last() {
+                    assert!(
+                        seq > *last,
+                        "{side} received message {seq} after {last} on {kind:?} lane {lane_index}"
+                    );
+                }
+            }
+            LaneOrdering::Ordered => {
+                assert_eq!(
+                    recv.len() as u32,
+                    seq,
+                    "{side} received message {seq} out of order on {kind:?} lane {lane_index}"
+                );
+            }
+        }
+        recv.push(seq);
+    }
+
+    fn missing(&self, lane_index: usize) -> Vec<u32> {
+        (0..MSGS_PER_LANE)
+            .filter(|seq| !self.recv[lane_index].contains(seq))
+            .collect()
+    }
+
+    fn is_complete(&self) -> bool {
+        (0..self.lanes.len()).all(|lane_index| self.missing(lane_index).is_empty())
+    }
+
+    fn check_reliable(&self) {
+        for (lane_index, kind) in self.lanes.iter().enumerate() {
+            if kind.reliability() == LaneReliability::Reliable {
+                let missing = self.missing(lane_index);
+                assert!(
+                    missing.is_empty(),
+                    "{} did not receive messages {missing:?} on {kind:?} lane {lane_index}",
+                    self.side
+                );
+            }
+        }
+    }
+}
+
+fn check_local<E: Debug>(side: &str, reason: &DisconnectReason<E>, expected: &str) {
+    match reason {
+        DisconnectReason::Local(reason) => assert_eq!(
+            expected, reason,
+            "{side} was disconnected locally with the wrong reason"
+        ),
+        reason => panic!("expected {side} to be disconnected locally, got {reason:?}"),
+    }
+}
+
+fn check_remote<E: Debug>(
+    side: &str,
+    reason: &DisconnectReason<E>,
+    expected: &str,
+    sends_reason: bool,
+) {
+    match reason {
+        DisconnectReason::Remote(reason) if sends_reason => assert_eq!(
+            expected, reason,
+            "{side} was disconnected remotely with the wrong reason"
+        ),
+        DisconnectReason::Remote(_) | DisconnectReason::Error(_) if !sends_reason => {}
+        reason => panic!("expected {side} to be disconnected remotely, got {reason:?}"),
+    }
+}
@@ -0,0 +1,679 @@
+//! Sending payloads of any size by splitting them into chunks, with progress
+//! reporting, bandwidth limiting, and cancellation.
+//!
+//! Transports may limit how large a single message can be - for example, a
+//! message sent through an `aeronet_proto` session can be split into at most
+//! 128 fragments. Bulk data such as maps, replays, or assets can easily exceed
+//! this. A [`Transfers`] splits a payload into chunks which are small enough to
+//! be sent as individual messages, sends them at a limited rate so that other
+//! messages still get through, and reassembles them on the receiving side.
+//!
+//! # Usage
+//!
+//! Pick a lane which is only used for transfers - every message on this lane
+//! is treated as part of a transfer. This lane must be
+//! [reliable-ordered](crate::lane::LaneKind::ReliableOrdered), since chunks
+//! are reassembled in the order they are received, and a lost chunk is never
+//! resent.
+//!
+//! Both the sending and receiving side create a [`Transfers`] for that lane:
+//! * start sending a payload with [`Transfers::send`]
+//! * pass every event that your transport emits into `handle_client` or
+//!   `handle_server`
+//! * advance time with [`Transfers::update`], which determines how many bytes
+//!   may be sent
+//! * send out chunks with `flush_client` or `flush_server`, before flushing
+//!   the transport itself
+//! * take the [`TransferEvent`]s produced with [`Transfers::drain`]
+//!
+//! A transfer can be cancelled by its sender using [`Transfers::cancel_send`],
+//! or by its receiver using [`Transfers::cancel_recv`]. Either way, both sides
+//! get a [`TransferEvent::Cancelled`].
+//!
+//! Each transfer is sent to or received from a peer. On a server, the peer is
+//! the client key of the transport. On a client, the peer is always the
+//! server, so it is represented as `()`.
+//!
+//! ```
+//! # #[cfg(feature = "client")] {
+//! # use aeronet::{client::ClientTransport, lane::LaneIndex};
+//! # use aeronet::transfer::{TransferConfig, TransferEvent, Transfers};
+//! # use web_time::Duration;
+//! # fn run<T: ClientTransport>(mut client: T, lane: LaneIndex, dt: Duration) {
+//! let mut transfers = Transfers::new(lane, TransferConfig::default());
+//! let map = vec![0u8; 8 * 1024 * 1024];
+//! let id = transfers.send((), map);
+//!
+//! // every update:
+//! for event in client.poll(dt) {
+//!     let Some(event) = transfers.handle_client(event) else {
+//!         continue;
+//!     };
+//!     // handle other events
+//! }
+//! transfers.update(dt);
+//! transfers.flush_client(&mut client).unwrap();
+//! client.flush().unwrap();
+//!
+//! for event in transfers.drain() {
+//!     if let TransferEvent::SendProgress { sent, total, .. } = event {
+//!         println!("{sent} / {total} bytes sent");
+//!     }
+//! }
+//! # }
+//! # }
+//! ```
+//!
+//! # Wire format
+//!
+//! Each message on the transfer lane starts with a single byte for the kind
+//! of message, followed by the [`TransferId`] given by the sender as a
+//! little-endian [`u64`]:
+//! * `0` - start of a transfer, followed by the total length as a
+//!   little-endian [`u64`]
+//! * `1` - chunk of a transfer, followed by the chunk's bytes
+//! * `2` - the sender cancelled the transfer
+//! * `3` - the receiver cancelled the transfer
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    hash::Hash,
+};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use web_time::Duration;
+
+use crate::lane::LaneIndex;
+
+/// ID of a transfer, assigned by [`Transfers::send`].
+///
+/// IDs are only unique for the side which sent the transfer, so a received
+/// transfer is identified by both its peer and its ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TransferId(u64);
+
+impl TransferId {
+    /// Creates a new ID from its raw number.
+    #[must_use]
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    /// Gets the raw number of this ID.
+    #[must_use]
+    pub const fn into_raw(self) -> u64 {
+        self.0
+    }
+}
+
+/// Configuration for a [`Transfers`].
+#[derive(Debug, Clone)]
+pub struct TransferConfig {
+    /// Maximum number of payload bytes sent in a single message.
+    ///
+    /// This must be small enough for the transport to send as a single
+    /// message.
+    ///
+    /// By default, this is 16 KiB.
+    pub chunk_len: usize,
+    /// Maximum number of payload bytes sent per second, across all outgoing
+    /// transfers.
+    ///
+    /// By default, this is 1 MiB.
+    pub bandwidth: usize,
+    /// Maximum total length of a transfer which will be received.
+    ///
+    /// If a peer starts a transfer longer than this, it is cancelled with
+    /// [`CancelReason::TooLarge`].
+    ///
+    /// By default, this is 64 MiB.
+    pub max_recv_len: usize,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            chunk_len: 16 * 1024,
+            bandwidth: 1024 * 1024,
+            max_recv_len: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Sends and receives transfers of large payloads to and from peers.
+///
+/// See [`transfer`](crate::transfer).
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct Transfers<P> {
+    lane: LaneIndex,
+    config: TransferConfig,
+    budget: usize,
+    next_id: u64,
+    outgoing: BTreeMap<TransferId, Outgoing<P>>,
+    incoming: HashMap<(P, TransferId), Incoming>,
+    control: VecDeque<(P, Bytes)>,
+    events: Vec<TransferEvent<P>>,
+}
+
+#[derive(Debug)]
+struct Outgoing<P> {
+    peer: P,
+    payload: Bytes,
+    sent: usize,
+    started: bool,
+}
+
+#[derive(Debug)]
+struct Incoming {
+    total: usize,
+    buf: BytesMut,
+}
+
+/// Something happened to a transfer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
+pub enum TransferEvent<P> {
+    /// More of an outgoing transfer was sent.
+    SendProgress {
+        /// Peer which the transfer is sent to.
+        peer: P,
+        /// ID of the transfer.
+        id: TransferId,
+        /// Number of bytes sent so far.
+        sent: usize,
+        /// Total length of the payload.
+        total: usize,
+    },
+    /// All of an outgoing transfer was sent.
+    ///
+    /// This means that all chunks were passed to the transport, not that the
+    /// peer has received them yet.
+    Sent {
+        /// Peer which the transfer is sent to.
+        peer: P,
+        /// ID of the transfer.
+        id: TransferId,
+    },
+    /// A peer started sending us a transfer.
+    RecvStarted {
+        /// Peer which the transfer is received from.
+        peer: P,
+        /// ID of the transfer.
+        id: TransferId,
+        /// Total length of the payload.
+        total: usize,
+    },
+    /// More of an incoming transfer was received.
+    RecvProgress {
+        /// Peer which the transfer is received from.
+        peer: P,
+        /// ID of the transfer.
+        id: TransferId,
+        /// Number of bytes received so far.
+        received: usize,
+        /// Total length of the payload.
+        total: usize,
+    },
+    /// All of an incoming transfer was received.
+    Received {
+        /// Peer which the transfer is received from.
+        peer: P,
+        /// ID of the transfer.
+        id: TransferId,
+        /// The full payload.
+        payload: Bytes,
+    },
+    /// A transfer was cancelled, and no more of it will be sent or received.
+    Cancelled {
+        /// Peer which the transfer is sent to or received from.
+        peer: P,
+        /// ID of the transfer.
+        id: TransferId,
+        /// Whether we were sending or receiving this transfer.
+        direction: TransferDirection,
+        /// Why the transfer was cancelled.
+        reason: CancelReason,
+    },
+}
+
+/// Whether a transfer is being sent or received by us.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferDirection {
+    /// We are sending this transfer.
+    Send,
+    /// We are receiving this transfer.
+    Recv,
+}
+
+/// Why a transfer was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CancelReason {
+    /// We cancelled the transfer using [`Transfers::cancel_send`] or
+    /// [`Transfers::cancel_recv`].
+    Local,
+    /// The peer cancelled the transfer.
+    Remote,
+    /// The peer disconnected.
+    Disconnected,
+    /// The transfer is longer than [`TransferConfig::max_recv_len`].
+    TooLarge,
+    /// The peer sent more bytes than the length it started the transfer with.
+    Invalid,
+}
+
+const START: u8 = 0;
+
+const CHUNK: u8 = 1;
+
+const CANCEL_SEND: u8 = 2;
+
+const CANCEL_RECV: u8 = 3;
+
+const HEADER_LEN: usize = 1 + 8;
+
+fn write_frame(kind: u8, id: TransferId, payload: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(HEADER_LEN + payload.len());
+    buf.put_u8(kind);
+    buf.put_u64_le(id.0);
+    buf.put_slice(payload);
+    buf.freeze()
+}
+
+impl<P: Clone + Eq + Hash> Transfers<P> {
+    /// Creates a new set of transfers which are sent and received on `lane`.
+    #[must_use]
+    pub fn new(lane: impl Into<LaneIndex>, config: TransferConfig) -> Self {
+        Self {
+            lane: lane.into(),
+            config,
+            budget: 0,
+            next_id: 0,
+            outgoing: BTreeMap::new(),
+            incoming: HashMap::new(),
+            control: VecDeque::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Gets the lane which transfers are sent and received on.
+    #[must_use]
+    pub const fn lane(&self) -> LaneIndex {
+        self.lane
+    }
+
+    /// Gets the configuration of this value.
+    #[must_use]
+    pub const fn config(&self) -> &TransferConfig {
+        &self.config
+    }
+
+    /// Gets the number of transfers which are still being sent.
+    #[must_use]
+    pub fn num_sending(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    /// Gets the number of transfers which are still being received.
+    #[must_use]
+    pub fn num_receiving(&self) -> usize {
+        self.incoming.len()
+    }
+
+    /// Starts sending `payload` to `peer`.
+    ///
+    /// Transfers are sent one after another, in the order that they were
+    /// started.
+    pub fn send(&mut self, peer: P, payload: impl Into<Bytes>) -> TransferId {
+        let id = TransferId(self.next_id);
+        self.next_id += 1;
+        self.outgoing.insert(
+            id,
+            Outgoing {
+                peer,
+                payload: payload.into(),
+                sent: 0,
+                started: false,
+            },
+        );
+        id
+    }
+
+    /// Stops sending the transfer `id`, and tells the peer that it was
+    /// cancelled.
+    ///
+    /// Returns `false` if the transfer is not being sent.
+    pub fn cancel_send(&mut self, id: TransferId) -> bool {
+        let Some(outgoing) = self.outgoing.remove(&id) else {
+            return false;
+        };
+        if outgoing.started {
+            self.control
+                .push_back((outgoing.peer.clone(), write_frame(CANCEL_SEND, id, &[])));
+        }
+        self.events.push(TransferEvent::Cancelled {
+            peer: outgoing.peer,
+            id,
+            direction: TransferDirection::Send,
+            reason: CancelReason::Local,
+        });
+        true
+    }
+
+    /// Stops receiving the transfer `id` from `peer`, and tells the peer that
+    /// it was cancelled.
+    ///
+    /// Returns `false` if the transfer is not being received.
+    pub fn cancel_recv(&mut self, peer: P, id: TransferId) -> bool {
+        if self.incoming.remove(&(peer.clone(), id)).is_none() {
+            return false;
+        }
+        self.reject(peer, id, CancelReason::Local);
+        true
+    }
+
+    /// Advances time by `delta_time`, allowing more bytes to be sent.
+    ///
+    /// Unused bandwidth is carried over, up to one second's worth of
+    /// [`TransferConfig::bandwidth`] (or one chunk, whichever is larger).
+    pub fn update(&mut self, delta_time: Duration) {
+        let allowance = (self.config.bandwidth as f64 * delta_time.as_secs_f64()) as usize;
+        let max_budget = self.config.bandwidth.max(self.config.chunk_len);
+        self.budget = self.budget.saturating_add(allowance).min(max_budget);
+    }
+
+    /// Takes all events which happened since the last call to this function.
+    ///
+    /// When using Bevy, these can be sent directly as events.
+    pub fn drain(&mut self) -> impl Iterator<Item = TransferEvent<P>> + '_ {
+        self.events.drain(..)
+    }
+
+    fn reject(&mut self, peer: P, id: TransferId, reason: CancelReason) {
+        self.control
+            .push_back((peer.clone(), write_frame(CANCEL_RECV, id, &[])));
+        self.events.push(TransferEvent::Cancelled {
+            peer,
+            id,
+            direction: TransferDirection::Recv,
+            reason,
+        });
+    }
+
+    /// Handles a message received on the transfer lane, returning it back if
+    /// it is not a valid transfer message.
+    fn recv(&mut self, peer: P, mut msg: Bytes) -> Result<(), Bytes> {
+        if msg.len() < HEADER_LEN {
+            return Err(msg);
+        }
+        let kind = msg[0];
+        let id = TransferId(u64::from_le_bytes(
+            msg[1..HEADER_LEN]
+                .try_into()
+                .expect("slice is 8 bytes long"),
+        ));
+        match kind {
+            START if msg.len() == HEADER_LEN + 8 => {
+                msg.advance(HEADER_LEN);
+                let total = usize::try_from(msg.get_u64_le()).unwrap_or(usize::MAX);
+                if total > self.config.max_recv_len {
+                    self.reject(peer, id, CancelReason::TooLarge);
+                    return Ok(());
+                }
+                self.events.push(TransferEvent::RecvStarted {
+                    peer: peer.clone(),
+                    id,
+                    total,
+                });
+                let incoming = Incoming {
+                    total,
+                    buf: BytesMut::new(),
+                };
+                self.recv_progress(peer, id, incoming);
+            }
+            CHUNK => {
+                let Some(mut incoming) = self.incoming.remove(&(peer.clone(), id)) else {
+                    // we already cancelled this transfer
+                    return Ok(());
+                };
+                msg.advance(HEADER_LEN);
+                if incoming.buf.len() + msg.len() > incoming.total {
+                    self.reject(peer, id, CancelReason::Invalid);
+                    return Ok(());
+                }
+                incoming.buf.put(msg);
+                self.events.push(TransferEvent::RecvProgress {
+                    peer: peer.clone(),
+                    id,
+                    received: incoming.buf.len(),
+                    total: incoming.total,
+                });
+                self.recv_progress(peer, id, incoming);
+            }
+            CANCEL_SEND => {
+                if self.incoming.remove(&(peer.clone(), id)).is_some() {
+                    self.events.push(TransferEvent::Cancelled {
+                        peer,
+                        id,
+                        direction: TransferDirection::Recv,
+                        reason: CancelReason::Remote,
+                    });
+                }
+            }
+            CANCEL_RECV => {
+                if self
+                    .outgoing
+                    .get(&id)
+                    .is_some_and(|outgoing| outgoing.peer == peer)
+                {
+                    self.outgoing.remove(&id);
+                    self.events.push(TransferEvent::Cancelled {
+                        peer,
+                        id,
+                        direction: TransferDirection::Send,
+                        reason: CancelReason::Remote,
+                    });
+                }
+            }
+            _ => return Err(msg),
+        }
+        Ok(())
+    }
+
+    fn recv_progress(&mut self, peer: P, id: TransferId, incoming: Incoming) {
+        if incoming.buf.len() == incoming.total {
+            self.events.push(TransferEvent::Received {
+                peer,
+                id,
+                payload: incoming.buf.freeze(),
+            });
+        } else {
+            self.incoming.insert((peer, id), incoming);
+        }
+    }
+
+    /// Cancels all transfers to and from `peer`, since it disconnected.
+    fn disconnected(&mut self, peer: &P) {
+        self.control.retain(|(p, _)| p != peer);
+
+        let outgoing = std::mem::take(&mut self.outgoing);
+        for (id, outgoing) in outgoing {
+            if outgoing.peer == *peer {
+                self.events.push(TransferEvent::Cancelled {
+                    peer: outgoing.peer,
+                    id,
+                    direction: TransferDirection::Send,
+                    reason: CancelReason::Disconnected,
+                });
+            } else {
+                self.outgoing.insert(id, outgoing);
+            }
+        }
+
+        let mut incoming = self
+            .incoming
+            .keys()
+            .filter(|(p, _)| p == peer)
+            .map(|(_, id)| *id)
+            .collect::<Vec<_>>();
+        incoming.sort_unstable();
+        for id in incoming {
+            self.incoming.remove(&(peer.clone(), id));
+            self.events.push(TransferEvent::Cancelled {
+                peer: peer.clone(),
+                id,
+                direction: TransferDirection::Recv,
+                reason: CancelReason::Disconnected,
+            });
+        }
+    }
+
+    /// Sends control messages, then as many chunks as the bandwidth budget
+    /// allows, using `send`.
+    fn flush<E>(&mut self, mut send: impl FnMut(P, Bytes) -> Result<(), E>) -> Result<(), E> {
+        while let Some((peer, msg)) = self.control.front().cloned() {
+            send(peer, msg)?;
+            self.control.pop_front();
+        }
+
+        while let Some(mut entry) = self.outgoing.first_entry() {
+            let id = *entry.key();
+            let outgoing = entry.get_mut();
+            let total = outgoing.payload.len();
+            if !outgoing.started {
+                let len = (total as u64).to_le_bytes();
+                send(outgoing.peer.clone(), write_frame(START, id, &len))?;
+                outgoing.started = true;
+            }
+
+            while outgoing.sent < total {
+                let chunk_len = (total - outgoing.sent).min(self.config.chunk_len);
+                if self.budget < chunk_len {
+                    return Ok(());
+                }
+                let chunk = &outgoing.payload[outgoing.sent..outgoing.sent + chunk_len];
+                send(outgoing.peer.clone(), write_frame(CHUNK, id, chunk))?;
+                outgoing.sent += chunk_len;
+                self.budget -= chunk_len;
+                self.events.push(TransferEvent::SendProgress {
+                    peer: outgoing.peer.clone(),
+                    id,
+                    sent: outgoing.sent,
+                    total,
+                });
+            }
+
+            let outgoing = entry.remove();
+            self.events.push(TransferEvent::Sent {
+                peer: outgoing.peer,
+                id,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "client")]
+impl Transfers<()> {
+    /// Handles an event emitted by a client transport.
+    ///
+    /// Messages on the transfer lane are consumed, and
+    /// [`ClientEvent::Disconnected`] cancels all transfers with
+    /// [`CancelReason::Disconnected`].
+    ///
+    /// Returns the event back if it was not consumed, so that you can handle
+    /// it yourself.
+    ///
+    /// [`ClientEvent::Disconnected`]: crate::client::ClientEvent::Disconnected
+    pub fn handle_client<T: crate::client::ClientTransport>(
+        &mut self,
+        event: crate::client::ClientEvent<T>,
+    ) -> Option<crate::client::ClientEvent<T>> {
+        use crate::client::ClientEvent;
+
+        match event {
+            ClientEvent::Recv { msg, lane } if lane == self.lane => self
+                .recv((), msg)
+                .err()
+                .map(|msg| ClientEvent::Recv { msg, lane }),
+            ClientEvent::Disconnected { reason } => {
+                self.disconnected(&());
+                Some(ClientEvent::Disconnected { reason })
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Sends pending transfer messages to the server using `client`.
+    ///
+    /// This does not flush the transport itself.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the transport fails to send a message. The message which
+    /// failed to send will be retried on the next call.
+    pub fn flush_client<T: crate::client::ClientTransport>(
+        &mut self,
+        client: &mut T,
+    ) -> Result<(), T::Error> {
+        let lane = self.lane;
+        self.flush(|(), msg| client.send(msg, lane).map(drop))
+    }
+}
+
+#[cfg(feature = "server")]
+impl<P: Clone + Eq + Hash> Transfers<P> {
+    /// Handles an event emitted by a server transport.
+    ///
+    /// Messages on the transfer lane are consumed, and
+    /// [`ServerEvent::Disconnected`] cancels all transfers to and from that
+    /// client with [`CancelReason::Disconnected`].
+    ///
+    /// Returns the event back if it was not consumed, so that you can handle
+    /// it yourself.
+    ///
+    /// [`ServerEvent::Disconnected`]: crate::server::ServerEvent::Disconnected
+    pub fn handle_server<T: crate::server::ServerTransport<ClientKey = P>>(
+        &mut self,
+        event: crate::server::ServerEvent<T>,
+    ) -> Option<crate::server::ServerEvent<T>> {
+        use crate::server::ServerEvent;
+
+        match event {
+            ServerEvent::Recv {
+                client_key,
+                msg,
+                lane,
+            } if lane == self.lane => {
+                self.recv(client_key.clone(), msg)
+                    .err()
+                    .map(|msg| ServerEvent::Recv {
+                        client_key,
+                        msg,
+                        lane,
+                    })
+            }
+            ServerEvent::Disconnected { client_key, reason } => {
+                self.disconnected(&client_key);
+                Some(ServerEvent::Disconnected { client_key, reason })
+            }
+            event => Some(event),
+        }
+    }
+
+    /// Sends pending transfer messages to clients using `server`.
+    ///
+    /// This does not flush the transport itself.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the transport fails to send a message. The message which
+    /// failed to send will be retried on the next call.
+    pub fn flush_server<T: crate::server::ServerTransport<ClientKey = P>>(
+        &mut self,
+        server: &mut T,
+    ) -> Result<(), T::Error> {
+        let lane = self.lane;
+        self.flush(|client_key, msg| server.send(client_key, msg, lane).map(drop))
+    }
+}
@@ -209,7 +209,7 @@ fn server_poll(
                     .push(format!("{:?} < {resp}", slotmap::Key::data(&client_key)));
                 to_send.push((client_key, resp));
             }
-            ServerEvent::Ack { .. } | ServerEvent::Nack { .. } => {}
+            ServerEvent::Reloaded { .. } | ServerEvent::Ack { .. } | ServerEvent::Nack { .. } => {}
         }
     }
 
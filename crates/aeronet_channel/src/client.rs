@@ -1,6 +1,6 @@
 //! Client-side items.
 
-use std::{convert::Infallible, num::Saturating};
+use std::num::Saturating;
 
 use aeronet::{
     client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
@@ -8,11 +8,17 @@ use aeronet::{
     shared::DROP_DISCONNECT_REASON,
     stats::{ConnectedAt, MessageStats},
 };
+use aeronet_proto::session::MessageKey;
 use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use web_time::{Duration, Instant};
 
-use crate::server::{ChannelServer, ClientKey};
+use crate::{
+    network::{ChannelNetwork, ConnectRequest},
+    server::{ChannelServer, ClientKey},
+    session::SessionChannelClient,
+    shared::{self, Accept, ClientEnds, Message, SendLanes},
+};
 
 /// Implementation of [`ClientTransport`] using in-memory MPSC channels.
 ///
@@ -26,10 +32,23 @@ pub struct ChannelClient {
 #[derive(Debug)]
 enum State {
     Disconnected,
+    Connecting(Connecting),
     Connected(Connected),
     Disconnecting { reason: String },
 }
 
+/// State of a [`ChannelClient`] when it is [`ClientState::Connecting`].
+///
+/// A client is only in this state while it is waiting for a server in a
+/// [`ChannelNetwork`] to accept its connection.
+#[derive(Debug)]
+pub struct Connecting {
+    /// Address of the server that this client is connecting to.
+    pub addr: String,
+    ends: ClientEnds,
+    recv_accept: Receiver<Accept>,
+}
+
 /// State of a [`ChannelClient`] when it is [`ClientState::Connected`].
 #[derive(Debug)]
 pub struct Connected {
@@ -43,10 +62,13 @@ pub struct Connected {
     pub bytes_sent: Saturating<usize>,
     /// See [`MessageStats::bytes_recv`]
     pub bytes_recv: Saturating<usize>,
-    send_c2s: Sender<(Bytes, LaneIndex)>,
-    recv_s2c: Receiver<(Bytes, LaneIndex)>,
+    send_c2s: Sender<Message>,
+    recv_s2c: Receiver<Message>,
     send_dc_c2s: Sender<String>,
     recv_dc_s2c: Receiver<String>,
+    send_ack_c2s: Option<Sender<MessageKey>>,
+    recv_ack_s2c: Receiver<MessageKey>,
+    send_lanes: SendLanes,
     send_initial: bool,
 }
 
@@ -81,10 +103,25 @@ pub enum ClientError {
     /// Attempted to connect to a server which is closed.
     #[error("server closed")]
     ServerClosed,
+    /// Attempted to connect to an address in a [`ChannelNetwork`] which no
+    /// server is listening on.
+    #[error("no server listening on address")]
+    AddrNotFound,
     /// Attempted to perform an operation, but the channel to the peer was
     /// unexpectedly closed.
     #[error("disconnected")]
     Disconnected,
+    /// Attempted to send a message on a lane which is not in the
+    /// [`SessionConfig::client_lanes`] of a server opened with a config.
+    ///
+    /// See [`session`](crate::session).
+    ///
+    /// [`SessionConfig::client_lanes`]: aeronet_proto::session::SessionConfig::client_lanes
+    #[error("invalid lane {lane:?}")]
+    InvalidLane {
+        /// Index of the invalid lane.
+        lane: LaneIndex,
+    },
 }
 
 impl Default for ChannelClient {
@@ -111,36 +148,76 @@ impl ChannelClient {
     /// Errors if this client is already connected to a server, or if the server
     /// is closed.
     pub fn connect(&mut self, server: &mut ChannelServer) -> Result<(), ClientError> {
-        if matches!(self.state, State::Connected(..)) {
+        if matches!(self.state, State::Connecting(..) | State::Connected(..)) {
             return Err(ClientError::AlreadyConnected);
         }
 
-        let (send_c2s, recv_c2s) = crossbeam_channel::unbounded();
-        let (send_s2c, recv_s2c) = crossbeam_channel::unbounded();
-        let (send_dc_c2s, recv_dc_c2s) = crossbeam_channel::bounded(1);
-        let (send_dc_s2c, recv_dc_s2c) = crossbeam_channel::bounded(1);
-        let key = server
-            .insert_client(recv_c2s, send_s2c, recv_dc_c2s, send_dc_s2c)
+        let (ends, server_ends) = shared::channels();
+        let accept = server
+            .insert_client(server_ends)
             .ok_or(ClientError::ServerClosed)?;
-        self.state = State::Connected(Connected {
-            key,
+        self.state = State::Connected(Connected::new(ends, accept));
+        Ok(())
+    }
+
+    /// Starts connecting this client to the server listening on `addr` in
+    /// `network`.
+    ///
+    /// The client stays [`ClientState::Connecting`] until the server accepts
+    /// the connection when it is next polled.
+    ///
+    /// # Errors
+    ///
+    /// Errors if this client is already connected to a server, or if no server
+    /// is listening on `addr`.
+    pub fn connect_to(&mut self, network: &ChannelNetwork, addr: &str) -> Result<(), ClientError> {
+        if matches!(self.state, State::Connecting(..) | State::Connected(..)) {
+            return Err(ClientError::AlreadyConnected);
+        }
+
+        let (ends, server_ends) = shared::channels();
+        let (send_accept, recv_accept) = crossbeam_channel::bounded(1);
+        network
+            .request(
+                addr,
+                ConnectRequest {
+                    ends: server_ends,
+                    send_accept,
+                },
+            )
+            .ok_or(ClientError::AddrNotFound)?;
+        self.state = State::Connecting(Connecting {
+            addr: addr.to_owned(),
+            ends,
+            recv_accept,
+        });
+        Ok(())
+    }
+}
+
+impl Connected {
+    fn new(ends: ClientEnds, accept: Accept) -> Self {
+        Self {
+            key: accept.client_key,
             connected_at: Instant::now(),
             bytes_sent: Saturating(0),
             bytes_recv: Saturating(0),
-            send_c2s,
-            recv_s2c,
-            send_dc_c2s,
-            recv_dc_s2c,
+            send_c2s: ends.send_c2s,
+            recv_s2c: ends.recv_s2c,
+            send_dc_c2s: ends.send_dc_c2s,
+            recv_dc_s2c: ends.recv_dc_s2c,
+            send_ack_c2s: accept.client_lanes.map(|_| ends.send_ack_c2s),
+            recv_ack_s2c: ends.recv_ack_s2c,
+            send_lanes: SendLanes::new(accept.client_lanes),
             send_initial: true,
-        });
-        Ok(())
+        }
     }
 }
 
 impl ClientTransport for ChannelClient {
     type Error = ClientError;
 
-    type Connecting<'this> = Infallible;
+    type Connecting<'this> = &'this Connecting;
 
     type Connected<'this> = &'this Connected;
 
@@ -150,23 +227,21 @@ impl ClientTransport for ChannelClient {
     fn state(&self) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
         match &self.state {
             State::Disconnected | State::Disconnecting { .. } => ClientState::Disconnected,
+            State::Connecting(client) => ClientState::Connecting(client),
             State::Connected(client) => ClientState::Connected(client),
         }
     }
 
     fn poll(&mut self, _: Duration) -> impl Iterator<Item = ClientEvent<Self>> {
-        let mut events = Vec::new();
-        replace_with::replace_with_or_abort(&mut self.state, |state| match state {
-            State::Disconnected => state,
-            State::Disconnecting { reason } => {
-                events.push(ClientEvent::Disconnected {
-                    reason: DisconnectReason::Local(reason),
-                });
-                State::Disconnected
-            }
-            State::Connected(client) => Self::poll_connected(client, &mut events),
-        });
-        events.into_iter()
+        self.poll_keyed()
+            .into_iter()
+            .filter_map(|event| match event {
+                ClientEvent::Connected => Some(ClientEvent::Connected),
+                ClientEvent::Disconnected { reason } => Some(ClientEvent::Disconnected { reason }),
+                ClientEvent::Recv { msg, lane } => Some(ClientEvent::Recv { msg, lane }),
+                // messages sent by this client have no keys to acknowledge
+                ClientEvent::Ack { .. } | ClientEvent::Nack { .. } => None,
+            })
     }
 
     fn send(
@@ -174,20 +249,7 @@ impl ClientTransport for ChannelClient {
         msg: impl Into<Bytes>,
         lane: impl Into<LaneIndex>,
     ) -> Result<Self::MessageKey, Self::Error> {
-        let State::Connected(client) = &mut self.state else {
-            return Err(ClientError::NotConnected);
-        };
-
-        let msg = msg.into();
-        let lane = lane.into();
-
-        let msg_len = msg.len();
-        client
-            .send_c2s
-            .send((msg, lane))
-            .map_err(|_| ClientError::Disconnected)?;
-        client.bytes_sent += msg_len;
-        Ok(())
+        self.send_keyed(msg.into(), lane.into()).map(drop)
     }
 
     fn flush(&mut self) -> Result<(), Self::Error> {
@@ -200,9 +262,13 @@ impl ClientTransport for ChannelClient {
 
     fn disconnect(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
         replace_with::replace_with_or_abort_and_return(&mut self.state, |state| match state {
-            State::Connected(client) => {
+            State::Connecting(Connecting {
+                ends: ClientEnds { send_dc_c2s, .. },
+                ..
+            })
+            | State::Connected(Connected { send_dc_c2s, .. }) => {
                 let reason = reason.into();
-                let _ = client.send_dc_c2s.try_send(reason.clone());
+                let _ = send_dc_c2s.try_send(reason.clone());
                 (Ok(()), State::Disconnecting { reason })
             }
             State::Disconnected | State::Disconnecting { .. } => {
@@ -213,7 +279,67 @@ impl ClientTransport for ChannelClient {
 }
 
 impl ChannelClient {
-    fn poll_connected(mut client: Connected, events: &mut Vec<ClientEvent<Self>>) -> State {
+    /// Polls this client, keeping the keys of acknowledged messages.
+    pub(crate) fn poll_keyed(&mut self) -> Vec<ClientEvent<SessionChannelClient>> {
+        let mut events = Vec::new();
+        replace_with::replace_with_or_abort(&mut self.state, |state| match state {
+            State::Disconnected => state,
+            State::Disconnecting { reason } => {
+                events.push(ClientEvent::Disconnected {
+                    reason: DisconnectReason::Local(reason),
+                });
+                State::Disconnected
+            }
+            State::Connecting(client) => Self::poll_connecting(client, &mut events),
+            State::Connected(client) => Self::poll_connected(client, &mut events),
+        });
+        events
+    }
+
+    /// Sends a message, returning the key which it was given.
+    pub(crate) fn send_keyed(
+        &mut self,
+        msg: Bytes,
+        lane: LaneIndex,
+    ) -> Result<MessageKey, ClientError> {
+        let State::Connected(client) = &mut self.state else {
+            return Err(ClientError::NotConnected);
+        };
+
+        let msg_key = client
+            .send_lanes
+            .next_key(lane)
+            .ok_or(ClientError::InvalidLane { lane })?;
+
+        let msg_len = msg.len();
+        client
+            .send_c2s
+            .send((msg, msg_key))
+            .map_err(|_| ClientError::Disconnected)?;
+        client.bytes_sent += msg_len;
+        Ok(msg_key)
+    }
+
+    fn poll_connecting(
+        client: Connecting,
+        events: &mut Vec<ClientEvent<SessionChannelClient>>,
+    ) -> State {
+        match client.recv_accept.try_recv() {
+            Ok(accept) => Self::poll_connected(Connected::new(client.ends, accept), events),
+            Err(TryRecvError::Empty) => State::Connecting(client),
+            Err(TryRecvError::Disconnected) => {
+                events.push(ClientEvent::Disconnected {
+                    reason: DisconnectReason::Error(ClientError::ServerClosed),
+                });
+                State::Disconnected
+            }
+        }
+    }
+
+    fn poll_connected(
+        mut client: Connected,
+        events: &mut Vec<ClientEvent<SessionChannelClient>>,
+    ) -> State {
         if client.send_initial {
             events.push(ClientEvent::Connected);
             client.send_initial = false;
@@ -228,8 +354,12 @@ impl ChannelClient {
 
         let res = (|| loop {
             match client.recv_s2c.try_recv() {
-                Ok((msg, lane)) => {
+                Ok((msg, msg_key)) => {
                     client.bytes_recv += msg.len();
+                    if let Some(send_ack_c2s) = &client.send_ack_c2s {
+                        let _ = send_ack_c2s.send(msg_key);
+                    }
+                    let (lane, _) = msg_key.into_raw();
                     events.push(ClientEvent::Recv { msg, lane });
                 }
                 Err(TryRecvError::Empty) => return Ok(()),
@@ -237,6 +367,13 @@ impl ChannelClient {
             }
         })();
 
+        events.extend(
+            client
+                .recv_ack_s2c
+                .try_iter()
+                .map(|msg_key| ClientEvent::Ack { msg_key }),
+        );
+
         match res {
             Ok(()) => State::Connected(client),
             Err(err) => {
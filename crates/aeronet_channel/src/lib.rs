@@ -2,4 +2,8 @@
 #![doc = include_str!("../README.md")]
 
 pub mod client;
+pub mod network;
 pub mod server;
+pub mod session;
+
+mod shared;
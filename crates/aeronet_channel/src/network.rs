@@ -0,0 +1,119 @@
+//! In-process registry of channel servers, addressed by name.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::shared::{Accept, ServerEnds};
+
+/// In-process registry which lets [`ChannelServer`]s listen on a string
+/// address, and [`ChannelClient`]s connect to them by address.
+///
+/// Connecting with [`ChannelClient::connect`] requires a mutable borrow of the
+/// server, which is awkward when the server and its clients live in different
+/// apps, threads, or resources. Instead, a server can
+/// [`listen`](ChannelServer::listen) on an address in a network, and clients
+/// can [`connect_to`](ChannelClient::connect_to) that address from anywhere
+/// in the process that has access to the same network.
+///
+/// A client connecting by address starts in [`ClientState::Connecting`], and
+/// its connection request is queued until the server is next polled. The
+/// server then accepts it, emitting [`ServerEvent::Connecting`] and
+/// [`ServerEvent::Connected`] as usual, and the client becomes connected on
+/// its next poll.
+///
+/// This is a cheaply cloneable handle, and all clones refer to the same
+/// network.
+///
+/// [`ChannelServer`]: crate::server::ChannelServer
+/// [`ChannelClient`]: crate::client::ChannelClient
+/// [`ChannelServer::listen`]: crate::server::ChannelServer::listen
+/// [`ChannelClient::connect`]: crate::client::ChannelClient::connect
+/// [`ChannelClient::connect_to`]: crate::client::ChannelClient::connect_to
+/// [`ClientState::Connecting`]: aeronet::client::ClientState::Connecting
+/// [`ServerEvent::Connecting`]: aeronet::server::ServerEvent::Connecting
+/// [`ServerEvent::Connected`]: aeronet::server::ServerEvent::Connected
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct ChannelNetwork {
+    listeners: Arc<Mutex<HashMap<String, Sender<ConnectRequest>>>>,
+}
+
+/// Request from a client to connect to a listening server.
+#[derive(Debug)]
+pub(crate) struct ConnectRequest {
+    pub ends: ServerEnds,
+    pub send_accept: Sender<Accept>,
+}
+
+/// Address that a server is listening on in a [`ChannelNetwork`].
+#[derive(Debug)]
+pub(crate) struct Listener {
+    network: ChannelNetwork,
+    addr: String,
+    send_requests: Sender<ConnectRequest>,
+    pub recv_requests: Receiver<ConnectRequest>,
+}
+
+impl ChannelNetwork {
+    /// Creates a new network with no servers listening on it.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the addresses which servers are currently listening on.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // shouldn't panic
+    pub fn addrs(&self) -> Vec<String> {
+        self.listeners
+            .lock()
+            .expect("lock should not be poisoned")
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Starts listening on `addr`, or returns [`None`] if the address is
+    /// already in use.
+    pub(crate) fn listen(&self, addr: String) -> Option<Listener> {
+        let mut listeners = self.listeners.lock().expect("lock should not be poisoned");
+        if listeners.contains_key(&addr) {
+            return None;
+        }
+
+        let (send_requests, recv_requests) = crossbeam_channel::unbounded();
+        listeners.insert(addr.clone(), send_requests.clone());
+        drop(listeners);
+        Some(Listener {
+            network: self.clone(),
+            addr,
+            send_requests,
+            recv_requests,
+        })
+    }
+
+    /// Queues a connection request to the server listening on `addr`, or
+    /// returns [`None`] if no server is listening on it.
+    pub(crate) fn request(&self, addr: &str, request: ConnectRequest) -> Option<()> {
+        let listeners = self.listeners.lock().expect("lock should not be poisoned");
+        listeners.get(addr)?.send(request).ok()
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let Ok(mut listeners) = self.network.listeners.lock() else {
+            return;
+        };
+        if listeners
+            .get(&self.addr)
+            .is_some_and(|send_requests| send_requests.same_channel(&self.send_requests))
+        {
+            listeners.remove(&self.addr);
+        }
+    }
+}
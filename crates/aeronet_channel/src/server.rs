@@ -9,11 +9,18 @@ use aeronet::{
     shared::DROP_DISCONNECT_REASON,
     stats::{ConnectedAt, MessageStats},
 };
+use aeronet_proto::session::{MessageKey, SessionConfig};
 use bytes::Bytes;
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use slotmap::SlotMap;
 use web_time::{Duration, Instant};
 
+use crate::{
+    network::{ChannelNetwork, Listener},
+    session::SessionChannelServer,
+    shared::{Accept, Message, SendLanes, ServerEnds},
+};
+
 slotmap::new_key_type! {
     /// Key identifying a unique client connected to a [`ChannelServer`].
     ///
@@ -42,6 +49,8 @@ enum State {
 #[derive(Debug)]
 pub struct Open {
     clients: SlotMap<ClientKey, Client>,
+    config: Option<SessionConfig>,
+    listener: Option<Listener>,
 }
 
 /// State of a [`ChannelServer`]'s client when it is [`ClientState::Connected`].
@@ -53,10 +62,13 @@ pub struct Connected {
     pub bytes_sent: Saturating<usize>,
     /// See [`MessageStats::bytes_recv`]
     pub bytes_recv: Saturating<usize>,
-    recv_c2s: Receiver<(Bytes, LaneIndex)>,
-    send_s2c: Sender<(Bytes, LaneIndex)>,
+    recv_c2s: Receiver<Message>,
+    send_s2c: Sender<Message>,
     recv_dc_c2s: Receiver<String>,
     send_dc_s2c: Sender<String>,
+    recv_ack_c2s: Receiver<MessageKey>,
+    send_ack_s2c: Option<Sender<MessageKey>>,
+    send_lanes: SendLanes,
     send_initial: bool,
 }
 
@@ -101,6 +113,19 @@ pub enum ServerError {
     /// Client was unexpectedly disconnected.
     #[error("client disconnected")]
     Disconnected,
+    /// Attempted to listen on an address in a [`ChannelNetwork`] which another
+    /// server is already listening on.
+    #[error("address already in use")]
+    AddrInUse,
+    /// Attempted to send a message on a lane which is not in the
+    /// [`SessionConfig::server_lanes`] of a server opened with a config.
+    ///
+    /// See [`session`](crate::session).
+    #[error("invalid lane {lane:?}")]
+    InvalidLane {
+        /// Index of the invalid lane.
+        lane: LaneIndex,
+    },
 }
 
 impl Default for ChannelServer {
@@ -126,37 +151,66 @@ impl ChannelServer {
     ///
     /// Errors if this server is already open.
     pub fn open(&mut self) -> Result<(), ServerError> {
+        self.open_inner(None, None)
+    }
+
+    /// Allows accepting connections on this server, and starts listening on
+    /// `addr` in `network`, so that clients can connect using
+    /// [`ChannelClient::connect_to`].
+    ///
+    /// The server stops listening once it is closed or dropped.
+    ///
+    /// # Errors
+    ///
+    /// Errors if this server is already open, or if another server is already
+    /// listening on `addr`.
+    ///
+    /// [`ChannelClient::connect_to`]: crate::client::ChannelClient::connect_to
+    pub fn listen(
+        &mut self,
+        network: &ChannelNetwork,
+        addr: impl Into<String>,
+    ) -> Result<(), ServerError> {
+        self.listen_inner(network, addr.into(), None)
+    }
+
+    pub(crate) fn listen_inner(
+        &mut self,
+        network: &ChannelNetwork,
+        addr: String,
+        config: Option<SessionConfig>,
+    ) -> Result<(), ServerError> {
+        if !matches!(self.state, State::Closed) {
+            return Err(ServerError::AlreadyOpen);
+        }
+
+        let listener = network.listen(addr).ok_or(ServerError::AddrInUse)?;
+        self.open_inner(config, Some(listener))
+    }
+
+    pub(crate) fn open_inner(
+        &mut self,
+        config: Option<SessionConfig>,
+        listener: Option<Listener>,
+    ) -> Result<(), ServerError> {
         if !matches!(self.state, State::Closed) {
             return Err(ServerError::AlreadyOpen);
         }
 
         self.state = State::Open(Open {
             clients: SlotMap::default(),
+            config,
+            listener,
         });
         Ok(())
     }
 
-    pub(super) fn insert_client(
-        &mut self,
-        recv_c2s: Receiver<(Bytes, LaneIndex)>,
-        send_s2c: Sender<(Bytes, LaneIndex)>,
-        recv_dc_c2s: Receiver<String>,
-        send_dc_s2c: Sender<String>,
-    ) -> Option<ClientKey> {
+    pub(super) fn insert_client(&mut self, ends: ServerEnds) -> Option<Accept> {
         let State::Open(server) = &mut self.state else {
             return None;
         };
 
-        Some(server.clients.insert(Client::Connected(Connected {
-            connected_at: Instant::now(),
-            bytes_sent: Saturating(0),
-            bytes_recv: Saturating(0),
-            recv_c2s,
-            send_s2c,
-            recv_dc_c2s,
-            send_dc_s2c,
-            send_initial: true,
-        })))
+        Some(server.insert_client(ends))
     }
 }
 
@@ -208,18 +262,33 @@ impl ServerTransport for ChannelServer {
     }
 
     fn poll(&mut self, _: Duration) -> impl Iterator<Item = ServerEvent<Self>> {
-        let mut events = Vec::new();
-        replace_with::replace_with_or_abort(&mut self.state, |state| match state {
-            State::Closed => state,
-            State::Open(server) => Self::poll_open(server, &mut events),
-            State::Closing { reason } => {
-                events.push(ServerEvent::Closed {
-                    reason: CloseReason::Local(reason),
-                });
-                State::Closed
-            }
-        });
-        events.into_iter()
+        self.poll_keyed()
+            .into_iter()
+            .filter_map(|event| match event {
+                ServerEvent::Opened => Some(ServerEvent::Opened),
+                ServerEvent::Closed { reason } => Some(ServerEvent::Closed { reason }),
+                ServerEvent::Reloaded { result } => Some(ServerEvent::Reloaded { result }),
+                ServerEvent::Connecting { client_key } => {
+                    Some(ServerEvent::Connecting { client_key })
+                }
+                ServerEvent::Connected { client_key } => {
+                    Some(ServerEvent::Connected { client_key })
+                }
+                ServerEvent::Disconnected { client_key, reason } => {
+                    Some(ServerEvent::Disconnected { client_key, reason })
+                }
+                ServerEvent::Recv {
+                    client_key,
+                    msg,
+                    lane,
+                } => Some(ServerEvent::Recv {
+                    client_key,
+                    msg,
+                    lane,
+                }),
+                // messages sent by this server have no keys to acknowledge
+                ServerEvent::Ack { .. } | ServerEvent::Nack { .. } => None,
+            })
     }
 
     fn send(
@@ -228,23 +297,8 @@ impl ServerTransport for ChannelServer {
         msg: impl Into<Bytes>,
         lane: impl Into<LaneIndex>,
     ) -> Result<Self::MessageKey, Self::Error> {
-        let State::Open(server) = &mut self.state else {
-            return Err(ServerError::NotOpen);
-        };
-        let Some(Client::Connected(client)) = server.clients.get_mut(client_key) else {
-            return Err(ServerError::NotConnected);
-        };
-
-        let msg = msg.into();
-        let lane = lane.into();
-
-        let msg_len = msg.len();
-        client
-            .send_s2c
-            .send((msg, lane))
-            .map_err(|_| ServerError::Disconnected)?;
-        client.bytes_sent += msg_len;
-        Ok(())
+        self.send_keyed(client_key, msg.into(), lane.into())
+            .map(drop)
     }
 
     fn flush(&mut self) -> Result<(), Self::Error> {
@@ -300,8 +354,87 @@ impl ServerTransport for ChannelServer {
     }
 }
 
+impl Open {
+    fn insert_client(&mut self, ends: ServerEnds) -> Accept {
+        let config = self.config.as_ref();
+        let client_key = self.clients.insert(Client::Connected(Connected {
+            connected_at: Instant::now(),
+            bytes_sent: Saturating(0),
+            bytes_recv: Saturating(0),
+            recv_c2s: ends.recv_c2s,
+            send_s2c: ends.send_s2c,
+            recv_dc_c2s: ends.recv_dc_c2s,
+            send_dc_s2c: ends.send_dc_s2c,
+            recv_ack_c2s: ends.recv_ack_c2s,
+            send_ack_s2c: config.map(|_| ends.send_ack_s2c),
+            send_lanes: SendLanes::new(config.map(|config| config.server_lanes.len())),
+            send_initial: true,
+        }));
+        Accept {
+            client_key,
+            client_lanes: config.map(|config| config.client_lanes.len()),
+        }
+    }
+}
+
 impl ChannelServer {
-    fn poll_open(mut server: Open, events: &mut Vec<ServerEvent<Self>>) -> State {
+    /// Polls this server, keeping the keys of acknowledged messages.
+    pub(crate) fn poll_keyed(&mut self) -> Vec<ServerEvent<SessionChannelServer>> {
+        let mut events = Vec::new();
+        replace_with::replace_with_or_abort(&mut self.state, |state| match state {
+            State::Closed => state,
+            State::Open(server) => Self::poll_open(server, &mut events),
+            State::Closing { reason } => {
+                events.push(ServerEvent::Closed {
+                    reason: CloseReason::Local(reason),
+                });
+                State::Closed
+            }
+        });
+        events
+    }
+
+    /// Sends a message to a client, returning the key which it was given.
+    pub(crate) fn send_keyed(
+        &mut self,
+        client_key: ClientKey,
+        msg: Bytes,
+        lane: LaneIndex,
+    ) -> Result<MessageKey, ServerError> {
+        let State::Open(server) = &mut self.state else {
+            return Err(ServerError::NotOpen);
+        };
+        let Some(Client::Connected(client)) = server.clients.get_mut(client_key) else {
+            return Err(ServerError::NotConnected);
+        };
+
+        let msg_key = client
+            .send_lanes
+            .next_key(lane)
+            .ok_or(ServerError::InvalidLane { lane })?;
+
+        let msg_len = msg.len();
+        client
+            .send_s2c
+            .send((msg, msg_key))
+            .map_err(|_| ServerError::Disconnected)?;
+        client.bytes_sent += msg_len;
+        Ok(msg_key)
+    }
+
+    fn poll_open(mut server: Open, events: &mut Vec<ServerEvent<SessionChannelServer>>) -> State {
+        let requests = server
+            .listener
+            .as_ref()
+            .map(|listener| listener.recv_requests.try_iter().collect::<Vec<_>>())
+            .unwrap_or_default();
+        for request in requests {
+            let accept = server.insert_client(request.ends);
+            // if the client has already been dropped, its channels are
+            // disconnected, and it will be disconnected below
+            let _ = request.send_accept.send(accept);
+        }
+
         for (client_key, client) in &mut server.clients {
             replace_with::replace_with_or_abort(client, |client| match client {
                 Client::Disconnected => client,
@@ -324,7 +457,7 @@ impl ChannelServer {
     }
 
     fn poll_connected(
-        events: &mut Vec<ServerEvent<Self>>,
+        events: &mut Vec<ServerEvent<SessionChannelServer>>,
         client_key: ClientKey,
         mut client: Connected,
     ) -> Client {
@@ -344,8 +477,12 @@ impl ChannelServer {
 
         let res = (|| loop {
             match client.recv_c2s.try_recv() {
-                Ok((msg, lane)) => {
+                Ok((msg, msg_key)) => {
                     client.bytes_recv += msg.len();
+                    if let Some(send_ack_s2c) = &client.send_ack_s2c {
+                        let _ = send_ack_s2c.send(msg_key);
+                    }
+                    let (lane, _) = msg_key.into_raw();
                     events.push(ServerEvent::Recv {
                         client_key,
                         msg,
@@ -357,6 +494,16 @@ impl ChannelServer {
             }
         })();
 
+        events.extend(
+            client
+                .recv_ack_c2s
+                .try_iter()
+                .map(|msg_key| ServerEvent::Ack {
+                    client_key,
+                    msg_key,
+                }),
+        );
+
         match res {
             Ok(()) => Client::Connected(client),
             Err(err) => {
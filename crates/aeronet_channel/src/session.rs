@@ -0,0 +1,224 @@
+//! Channel transports which behave like an [`aeronet_proto`] [`Session`].
+//!
+//! [`ChannelClient`] and [`ChannelServer`] accept messages on any lane, and
+//! do not report when their messages are received. When testing code which is
+//! later run on top of a [`Session`], it is useful for the channel transports
+//! to behave more like one - [`SessionChannelClient`] and
+//! [`SessionChannelServer`] wrap the plain transports to do this.
+//!
+//! A [`SessionChannelServer`] is opened from a [`SessionConfig`]. Clients
+//! connected to it may only send messages on [`SessionConfig::client_lanes`],
+//! and the server may only send messages on [`SessionConfig::server_lanes`] -
+//! sending on any other lane fails with an `InvalidLane` error. Sent messages
+//! are given a [`MessageKey`], and once the peer polls a message, the side
+//! which sent it emits an `Ack` event for it on its next poll.
+//!
+//! Only the lane indices are validated. Lane kinds are not simulated, since
+//! every message sent over a channel is already delivered reliably and in
+//! order, and no message is ever `Nack`ed. Other fields of the
+//! [`SessionConfig`] are ignored.
+//!
+//! [`Session`]: aeronet_proto::session::Session
+
+use std::convert::Infallible;
+
+use aeronet::{
+    client::{ClientEvent, ClientState, ClientTransport},
+    lane::LaneIndex,
+    server::{ServerEvent, ServerState, ServerTransport},
+};
+use aeronet_proto::session::{MessageKey, SessionConfig};
+use bytes::Bytes;
+use web_time::Duration;
+
+use crate::{
+    client::{self, ChannelClient, ClientError},
+    network::ChannelNetwork,
+    server::{self, ChannelServer, ClientKey, ServerError},
+};
+
+/// [`ChannelClient`] which returns [`MessageKey`]s for sent messages.
+///
+/// See the [module-level documentation](self).
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct SessionChannelClient {
+    inner: ChannelClient,
+}
+
+/// [`ChannelServer`] which validates lanes and returns [`MessageKey`]s for
+/// sent messages, according to a [`SessionConfig`].
+///
+/// See the [module-level documentation](self).
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct SessionChannelServer {
+    inner: ChannelServer,
+}
+
+impl SessionChannelClient {
+    /// Creates a new client which is not connected to a server.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            inner: ChannelClient::new(),
+        }
+    }
+
+    /// Connects this client to an existing server.
+    ///
+    /// See [`ChannelClient::connect`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if this client is already connected to a server, or if the server
+    /// is closed.
+    pub fn connect(&mut self, server: &mut SessionChannelServer) -> Result<(), ClientError> {
+        self.inner.connect(&mut server.inner)
+    }
+
+    /// Starts connecting this client to the server listening on `addr` in
+    /// `network`.
+    ///
+    /// See [`ChannelClient::connect_to`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if this client is already connected to a server, or if no server
+    /// is listening on `addr`.
+    pub fn connect_to(&mut self, network: &ChannelNetwork, addr: &str) -> Result<(), ClientError> {
+        self.inner.connect_to(network, addr)
+    }
+}
+
+impl SessionChannelServer {
+    /// Creates a server which is not open for connections.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            inner: ChannelServer::new(),
+        }
+    }
+
+    /// Allows accepting connections on this server, validating lanes and
+    /// acknowledging messages according to `config`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if this server is already open.
+    pub fn open(&mut self, config: SessionConfig) -> Result<(), ServerError> {
+        self.inner.open_inner(Some(config), None)
+    }
+
+    /// Allows accepting connections on this server according to `config`, and
+    /// starts listening on `addr` in `network`.
+    ///
+    /// See [`ChannelServer::listen`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if this server is already open, or if another server is already
+    /// listening on `addr`.
+    pub fn listen(
+        &mut self,
+        network: &ChannelNetwork,
+        addr: impl Into<String>,
+        config: SessionConfig,
+    ) -> Result<(), ServerError> {
+        self.inner.listen_inner(network, addr.into(), Some(config))
+    }
+}
+
+impl ClientTransport for SessionChannelClient {
+    type Error = ClientError;
+
+    type Connecting<'this> = &'this client::Connecting;
+
+    type Connected<'this> = &'this client::Connected;
+
+    type MessageKey = MessageKey;
+
+    fn state(&self) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        self.inner.state()
+    }
+
+    fn poll(&mut self, _: Duration) -> impl Iterator<Item = ClientEvent<Self>> {
+        self.inner.poll_keyed().into_iter()
+    }
+
+    fn send(
+        &mut self,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        self.inner.send_keyed(msg.into(), lane.into())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn disconnect(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
+        self.inner.disconnect(reason)
+    }
+}
+
+impl ServerTransport for SessionChannelServer {
+    type Error = ServerError;
+
+    type Opening<'this> = Infallible;
+
+    type Open<'this> = &'this server::Open;
+
+    type Connecting<'this> = Infallible;
+
+    type Connected<'this> = &'this server::Connected;
+
+    type ClientKey = ClientKey;
+
+    type MessageKey = MessageKey;
+
+    fn state(&self) -> ServerState<Self::Opening<'_>, Self::Open<'_>> {
+        self.inner.state()
+    }
+
+    fn client_state(
+        &self,
+        client_key: ClientKey,
+    ) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        self.inner.client_state(client_key)
+    }
+
+    fn client_keys(&self) -> impl Iterator<Item = Self::ClientKey> + '_ {
+        self.inner.client_keys()
+    }
+
+    fn poll(&mut self, _: Duration) -> impl Iterator<Item = ServerEvent<Self>> {
+        self.inner.poll_keyed().into_iter()
+    }
+
+    fn send(
+        &mut self,
+        client_key: Self::ClientKey,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        self.inner.send_keyed(client_key, msg.into(), lane.into())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        self.inner.flush()
+    }
+
+    fn disconnect(
+        &mut self,
+        client_key: Self::ClientKey,
+        reason: impl Into<String>,
+    ) -> Result<(), Self::Error> {
+        self.inner.disconnect(client_key, reason)
+    }
+
+    fn close(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
+        self.inner.close(reason)
+    }
+}
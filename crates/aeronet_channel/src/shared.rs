@@ -0,0 +1,114 @@
+//! Items shared between the client and server.
+
+use std::collections::HashMap;
+
+use aeronet::lane::LaneIndex;
+use aeronet_proto::{
+    session::MessageKey,
+    ty::{MessageSeq, Seq},
+};
+use bytes::Bytes;
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::server::ClientKey;
+
+/// Message sent over a channel, along with the key that the sender gave it.
+pub type Message = (Bytes, MessageKey);
+
+/// Assigns [`MessageKey`]s to messages sent by one side of a connection.
+#[derive(Debug)]
+pub struct SendLanes {
+    num_lanes: Option<usize>,
+    next_seqs: HashMap<LaneIndex, MessageSeq>,
+}
+
+impl SendLanes {
+    /// Creates a value which allows sending on `num_lanes` lanes, or on any
+    /// lane if [`None`].
+    pub fn new(num_lanes: Option<usize>) -> Self {
+        Self {
+            num_lanes,
+            next_seqs: HashMap::new(),
+        }
+    }
+
+    /// Gets the key of the next message sent on `lane`, or [`None`] if the lane
+    /// is invalid.
+    ///
+    /// Only the lane index is validated. Lane kinds are not simulated, since
+    /// every message sent over a channel is already delivered reliably and in
+    /// order.
+    pub fn next_key(&mut self, lane: LaneIndex) -> Option<MessageKey> {
+        if let Some(num_lanes) = self.num_lanes {
+            let lane_index = usize::try_from(lane.into_raw()).ok()?;
+            if lane_index >= num_lanes {
+                return None;
+            }
+        }
+
+        let seq = self.next_seqs.entry(lane).or_default();
+        let key = MessageKey::from_raw(lane, *seq);
+        *seq += MessageSeq(Seq(1));
+        Some(key)
+    }
+}
+
+/// Client's ends of the channels between a client and a server.
+#[derive(Debug)]
+pub struct ClientEnds {
+    pub send_c2s: Sender<Message>,
+    pub recv_s2c: Receiver<Message>,
+    pub send_dc_c2s: Sender<String>,
+    pub recv_dc_s2c: Receiver<String>,
+    pub send_ack_c2s: Sender<MessageKey>,
+    pub recv_ack_s2c: Receiver<MessageKey>,
+}
+
+/// Server's ends of the channels between a client and a server.
+#[derive(Debug)]
+pub struct ServerEnds {
+    pub recv_c2s: Receiver<Message>,
+    pub send_s2c: Sender<Message>,
+    pub recv_dc_c2s: Receiver<String>,
+    pub send_dc_s2c: Sender<String>,
+    pub recv_ack_c2s: Receiver<MessageKey>,
+    pub send_ack_s2c: Sender<MessageKey>,
+}
+
+/// Creates the channels for a new connection between a client and a server.
+pub fn channels() -> (ClientEnds, ServerEnds) {
+    let (send_c2s, recv_c2s) = crossbeam_channel::unbounded();
+    let (send_s2c, recv_s2c) = crossbeam_channel::unbounded();
+    let (send_dc_c2s, recv_dc_c2s) = crossbeam_channel::bounded(1);
+    let (send_dc_s2c, recv_dc_s2c) = crossbeam_channel::bounded(1);
+    let (send_ack_c2s, recv_ack_c2s) = crossbeam_channel::unbounded();
+    let (send_ack_s2c, recv_ack_s2c) = crossbeam_channel::unbounded();
+    (
+        ClientEnds {
+            send_c2s,
+            recv_s2c,
+            send_dc_c2s,
+            recv_dc_s2c,
+            send_ack_c2s,
+            recv_ack_s2c,
+        },
+        ServerEnds {
+            recv_c2s,
+            send_s2c,
+            recv_dc_c2s,
+            send_dc_s2c,
+            recv_ack_c2s,
+            send_ack_s2c,
+        },
+    )
+}
+
+/// Sent by a server to a client once it has accepted the client's connection.
+#[derive(Debug)]
+pub struct Accept {
+    /// Key that the server gave the client.
+    pub client_key: ClientKey,
+    /// Number of lanes that the client may send on, if the server was opened
+    /// with a config.
+    pub client_lanes: Option<usize>,
+}
@@ -0,0 +1,51 @@
+//! Helpers for opening channel servers and connecting clients to them.
+#![allow(dead_code)] // not every test uses every helper
+
+use std::time::Duration;
+
+use aeronet::{
+    client::ClientTransport,
+    server::{ServerEvent, ServerTransport},
+};
+use aeronet_channel::{
+    client::ChannelClient,
+    server::{ChannelServer, ClientKey},
+};
+
+/// Channel transports don't depend on time passing, so tests poll with no
+/// delta time.
+pub const DT: Duration = Duration::ZERO;
+
+/// Creates a [`ChannelServer`] which clients can connect to.
+pub fn open_server() -> ChannelServer {
+    let mut server = ChannelServer::new();
+    server.open().unwrap();
+    server
+}
+
+/// Polls a client which has just connected to `server` and the server itself,
+/// returning the key the server assigned to the client.
+pub fn connected<S: ServerTransport<ClientKey = ClientKey>>(
+    client: &mut impl ClientTransport,
+    server: &mut S,
+    delta_time: Duration,
+) -> ClientKey {
+    assert_eq!(1, client.poll(delta_time).count());
+    server
+        .poll(delta_time)
+        .find_map(|event| match event {
+            ServerEvent::Connected { client_key } => Some(client_key),
+            _ => None,
+        })
+        .unwrap()
+}
+
+/// Opens a [`ChannelServer`] and connects a single [`ChannelClient`] to it.
+pub fn open() -> (ChannelClient, ChannelServer, ClientKey) {
+    let mut server = open_server();
+    let mut client = ChannelClient::new();
+    client.connect(&mut server).unwrap();
+
+    let client_key = connected(&mut client, &mut server, DT);
+    (client, server, client_key)
+}
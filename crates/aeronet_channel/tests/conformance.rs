@@ -0,0 +1,90 @@
+//! Runs the transport conformance suite against the channel transport.
+
+use aeronet::{
+    lane::LaneKind,
+    testing::{Conformance, TransportPair},
+};
+use aeronet_channel::{client::ChannelClient, server::ChannelServer};
+use web_time::Duration;
+
+/// Channels pass lane indices through without interpreting them, and deliver
+/// every message reliably and in order, so they satisfy every lane kind.
+const LANES: &[LaneKind] = &[
+    LaneKind::UnreliableUnordered,
+    LaneKind::UnreliableSequenced,
+    LaneKind::ReliableUnordered,
+    LaneKind::ReliableOrdered,
+];
+
+struct ChannelPair;
+
+impl TransportPair for ChannelPair {
+    type Client = ChannelClient;
+
+    type Server = ChannelServer;
+
+    fn lanes(&self) -> &[LaneKind] {
+        LANES
+    }
+
+    fn open_server(&mut self) -> Self::Server {
+        let mut server = ChannelServer::new();
+        server.open().unwrap();
+        server
+    }
+
+    fn connect_client(&mut self, server: &mut Self::Server) -> Self::Client {
+        let mut client = ChannelClient::new();
+        client.connect(server).unwrap();
+        client
+    }
+}
+
+const fn suite() -> Conformance<ChannelPair> {
+    Conformance::new(ChannelPair).with_poll_interval(Duration::ZERO)
+}
+
+#[test]
+fn lifecycle() {
+    suite().lifecycle();
+}
+
+#[test]
+fn new_client_key() {
+    suite().new_client_key();
+}
+
+#[test]
+fn lanes() {
+    suite().lanes();
+}
+
+#[test]
+fn errors_when_disconnected() {
+    suite().errors_when_disconnected();
+}
+
+#[test]
+fn client_disconnect() {
+    suite().client_disconnect();
+}
+
+#[test]
+fn server_disconnect() {
+    suite().server_disconnect();
+}
+
+#[test]
+fn server_close() {
+    suite().server_close();
+}
+
+#[test]
+fn client_drop() {
+    suite().client_drop();
+}
+
+#[test]
+fn server_drop() {
+    suite().server_drop();
+}
@@ -0,0 +1,212 @@
+//! Tests for falling back between channel clients using a `FallbackClient`.
+
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aeronet::{
+    client::{
+        ClientEvent, ClientState, ClientTransport, DisconnectReason, FallbackClient, FallbackError,
+        FALLBACK_TIMEOUT_REASON,
+    },
+    lane::LaneIndex,
+    server::{ServerEvent, ServerTransport},
+    shared::{Multi, DROP_DISCONNECT_REASON},
+};
+use aeronet_channel::{
+    client::{ChannelClient, ClientError},
+    network::ChannelNetwork,
+    server::ChannelServer,
+};
+use assert_matches::assert_matches;
+use common::DT;
+
+const MSG: &[u8] = b"hello";
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+type Client = FallbackClient<ChannelClient, ChannelClient>;
+
+const fn client() -> Client {
+    FallbackClient::new(ChannelClient::new(), ChannelClient::new(), TIMEOUT)
+}
+
+#[test]
+fn uses_first() {
+    let mut server_a = common::open_server();
+    let server_b = Arc::new(Mutex::new(common::open_server()));
+
+    let mut client = client();
+    let server_b_clone = server_b.clone();
+    client
+        .connect(
+            |client| client.connect(&mut server_a),
+            move |client| client.connect(&mut server_b_clone.lock().unwrap()),
+        )
+        .unwrap();
+
+    assert_matches!(client.poll(DT).next().unwrap(), ClientEvent::Connected);
+    assert_matches!(client.state(), ClientState::Connected(Multi::A(_)));
+    assert!(client.b().state().is_disconnected());
+
+    client.send(MSG, LANE).unwrap();
+    assert_matches!(
+        server_a.poll(DT).last().unwrap(),
+        ServerEvent::Recv { msg, .. } if msg == MSG
+    );
+    assert!(server_b.lock().unwrap().poll(DT).next().is_none());
+}
+
+#[test]
+fn falls_back_to_second() {
+    // not open, so connecting to this fails
+    let mut server_a = ChannelServer::new();
+    let server_b = Arc::new(Mutex::new(common::open_server()));
+
+    let mut client = client();
+    let server_b_clone = server_b.clone();
+    client
+        .connect(
+            |client| client.connect(&mut server_a),
+            move |client| client.connect(&mut server_b_clone.lock().unwrap()),
+        )
+        .unwrap();
+
+    let mut events = client.poll(DT);
+    assert_matches!(events.next().unwrap(), ClientEvent::Connected);
+    assert!(events.next().is_none());
+    drop(events);
+    assert_matches!(client.state(), ClientState::Connected(Multi::B(_)));
+
+    client.send(MSG, LANE).unwrap();
+    assert_matches!(
+        server_b.lock().unwrap().poll(DT).last().unwrap(),
+        ServerEvent::Recv { msg, .. } if msg == MSG
+    );
+}
+
+#[test]
+fn all_failed() {
+    let mut server_a = ChannelServer::new();
+    let server_b = Arc::new(Mutex::new(ChannelServer::new()));
+
+    let mut client = client();
+    assert_matches!(
+        client.connect(
+            |client| client.connect(&mut server_a),
+            move |client| client.connect(&mut server_b.lock().unwrap()),
+        ),
+        Err(FallbackError::AllFailed {
+            a: DisconnectReason::Error(ClientError::ServerClosed),
+            b: DisconnectReason::Error(ClientError::ServerClosed),
+        })
+    );
+    assert!(client.state().is_disconnected());
+}
+
+#[test]
+fn all_failed_after_connecting() {
+    let network = ChannelNetwork::new();
+    let mut server_a = ChannelServer::new();
+    server_a.listen(&network, "a").unwrap();
+    let mut server_b = ChannelServer::new();
+    server_b.listen(&network, "b").unwrap();
+
+    let mut client = client();
+    let network_clone = network.clone();
+    client
+        .connect(
+            |client| client.connect_to(&network, "a"),
+            move |client| client.connect_to(&network_clone, "b"),
+        )
+        .unwrap();
+    assert!(client.poll(DT).next().is_none());
+
+    // each server closes before accepting its client
+    drop(server_a);
+    assert!(client.poll(DT).next().is_none());
+    assert_matches!(client.state(), ClientState::Connecting(Multi::B(_)));
+    drop(server_b);
+
+    // the final reason has why both transports failed
+    assert_matches!(
+        client.poll(DT).next().unwrap(),
+        ClientEvent::Disconnected {
+            reason: DisconnectReason::Error(FallbackError::AllFailed {
+                a: DisconnectReason::Error(ClientError::ServerClosed),
+                b: DisconnectReason::Error(ClientError::ServerClosed),
+            })
+        }
+    );
+    assert!(client.state().is_disconnected());
+}
+
+#[test]
+fn falls_back_after_timeout() {
+    let network = ChannelNetwork::new();
+    // never polled, so never accepts its client
+    let mut server_a = ChannelServer::new();
+    server_a.listen(&network, "a").unwrap();
+
+    let mut client = client();
+    client.set_timeout(Duration::from_secs(1));
+    let network_clone = network.clone();
+    client
+        .connect(
+            |client| client.connect_to(&network, "a"),
+            move |client| client.connect_to(&network_clone, "b"),
+        )
+        .unwrap();
+    assert!(client.poll(Duration::from_millis(500)).next().is_none());
+    assert_matches!(client.state(), ClientState::Connecting(Multi::A(_)));
+
+    // nothing listens on `b`, so the fallback fails straight away, and the
+    // first transport's failure is the timeout
+    assert_matches!(
+        client.poll(Duration::from_millis(500)).next().unwrap(),
+        ClientEvent::Disconnected {
+            reason: DisconnectReason::Error(FallbackError::AllFailed {
+                a: DisconnectReason::Local(a),
+                b: DisconnectReason::Error(ClientError::AddrNotFound),
+            })
+        } if a == FALLBACK_TIMEOUT_REASON
+    );
+    assert!(client.state().is_disconnected());
+}
+
+#[test]
+fn already_connected() {
+    let mut server_a = common::open_server();
+    let mut client = client();
+    client
+        .connect(|client| client.connect(&mut server_a), |_| unreachable!())
+        .unwrap();
+
+    let mut server_a = common::open_server();
+    assert_matches!(
+        client.connect(|client| client.connect(&mut server_a), |_| unreachable!()),
+        Err(FallbackError::AlreadyConnected)
+    );
+}
+
+#[test]
+fn no_fallback_after_connected() {
+    let mut server_a = common::open_server();
+    let mut client = client();
+    client
+        .connect(|client| client.connect(&mut server_a), |_| unreachable!())
+        .unwrap();
+    assert_matches!(client.poll(DT).next().unwrap(), ClientEvent::Connected);
+
+    drop(server_a);
+    assert_matches!(
+        client.poll(DT).next().unwrap(),
+        ClientEvent::Disconnected { reason: DisconnectReason::Remote(reason) }
+        if reason == DROP_DISCONNECT_REASON
+    );
+    assert!(client.state().is_disconnected());
+}
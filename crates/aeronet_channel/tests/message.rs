@@ -0,0 +1,211 @@
+//! Tests for sending typed messages over channel transports.
+
+mod common;
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport},
+    lane::LaneIndex,
+    message::{
+        BincodeCodec, DecodeError, EncodeError, MessageFromClient, MessageFromServer,
+        MessageRegistry, SendTypedError, TypedClientTransport, TypedServerTransport,
+    },
+    server::{ServerEvent, ServerTransport},
+};
+use aeronet_channel::{
+    client::ChannelClient,
+    server::{ChannelServer, ClientKey},
+};
+use assert_matches::assert_matches;
+use bevy::ecs::{prelude::*, world::CommandQueue};
+use bytes::Bytes;
+use common::DT;
+use serde::{Deserialize, Serialize};
+
+const CHAT_LANE: LaneIndex = LaneIndex::from_raw(0);
+
+const MOVE_LANE: LaneIndex = LaneIndex::from_raw(1);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Chat(String);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Move {
+    x: f32,
+    y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Unregistered;
+
+fn registry() -> MessageRegistry {
+    MessageRegistry::new()
+        .with::<Chat>(CHAT_LANE, BincodeCodec)
+        .with::<Move>(MOVE_LANE, BincodeCodec)
+}
+
+fn recv_from_client(server: &mut ChannelServer) -> Vec<(ClientKey, Bytes, LaneIndex)> {
+    server
+        .poll(DT)
+        .filter_map(|event| match event {
+            ServerEvent::Recv {
+                client_key,
+                msg,
+                lane,
+            } => Some((client_key, msg, lane)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn recv_from_server(client: &mut ChannelClient) -> Vec<(Bytes, LaneIndex)> {
+    client
+        .poll(DT)
+        .filter_map(|event| match event {
+            ClientEvent::Recv { msg, lane } => Some((msg, lane)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn client_to_server() {
+    let registry = registry();
+    let (mut client, mut server, client_key) = common::open();
+
+    client.send_typed(&registry, &Chat("hello".into())).unwrap();
+    client
+        .send_typed(&registry, &Move { x: 1.0, y: 2.0 })
+        .unwrap();
+
+    let recv = recv_from_client(&mut server);
+    assert_eq!(2, recv.len());
+
+    let (key, msg, lane) = &recv[0];
+    assert_eq!(client_key, *key);
+    assert_eq!(CHAT_LANE, *lane);
+    let msg = registry.decode(msg).unwrap();
+    assert!(msg.is::<Chat>());
+    assert_eq!(Chat("hello".into()), msg.downcast::<Chat>().unwrap());
+
+    let (key, msg, lane) = &recv[1];
+    assert_eq!(client_key, *key);
+    assert_eq!(MOVE_LANE, *lane);
+    let msg = registry.decode(msg).unwrap();
+    let msg = msg.downcast::<Chat>().unwrap_err();
+    assert_eq!(Move { x: 1.0, y: 2.0 }, msg.downcast::<Move>().unwrap());
+}
+
+#[test]
+fn server_to_client() {
+    let registry = registry();
+    let (mut client, mut server, client_key) = common::open();
+
+    server
+        .send_typed(&registry, client_key, &Move { x: 3.0, y: 4.0 })
+        .unwrap();
+
+    let recv = recv_from_server(&mut client);
+    assert_eq!(1, recv.len());
+
+    let (msg, lane) = &recv[0];
+    assert_eq!(MOVE_LANE, *lane);
+    let msg = registry.decode(msg).unwrap();
+    assert_eq!(Some(&Move { x: 3.0, y: 4.0 }), msg.downcast_ref::<Move>());
+}
+
+#[test]
+fn send_unregistered() {
+    let registry = registry();
+    let (mut client, _server, _) = common::open();
+
+    assert_matches!(
+        client.send_typed(&registry, &Unregistered),
+        Err(SendTypedError::Encode(EncodeError::NotRegistered { .. }))
+    );
+}
+
+#[test]
+fn decode_invalid() {
+    let registry = registry();
+
+    assert_matches!(registry.decode(&[0]), Err(DecodeError::TooShort));
+    assert_matches!(
+        registry.decode(&[2, 0]),
+        Err(DecodeError::UnknownId { id: 2 })
+    );
+    assert_matches!(
+        registry.decode(&[0, 0, 0xff]),
+        Err(DecodeError::Codec { .. })
+    );
+}
+
+#[test]
+fn lanes() {
+    let registry = registry();
+
+    assert!(registry.contains::<Chat>());
+    assert!(!registry.contains::<Unregistered>());
+    assert_eq!(Some(CHAT_LANE), registry.lane::<Chat>());
+    assert_eq!(Some(MOVE_LANE), registry.lane::<Move>());
+    assert_eq!(None, registry.lane::<Unregistered>());
+}
+
+#[test]
+#[should_panic = "already registered"]
+fn register_twice() {
+    let _ = registry().with::<Chat>(MOVE_LANE, BincodeCodec);
+}
+
+#[test]
+fn bevy_events() {
+    let mut registry = registry();
+    registry
+        .add_client_event::<ChannelClient, Move>()
+        .add_server_event::<ChannelServer, Chat>();
+
+    let mut world = World::new();
+    world.init_resource::<Events<MessageFromServer<ChannelClient, Move>>>();
+    world.init_resource::<Events<MessageFromClient<ChannelServer, Chat>>>();
+
+    let (mut client, mut server, client_key) = common::open();
+    client.send_typed(&registry, &Chat("hello".into())).unwrap();
+    server
+        .send_typed(&registry, client_key, &Move { x: 1.0, y: 2.0 })
+        .unwrap();
+    server
+        .send_typed(&registry, client_key, &Chat("not added".into()))
+        .unwrap();
+
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &world);
+    for (key, msg, _) in recv_from_client(&mut server) {
+        let msg = registry.decode(&msg).unwrap();
+        registry
+            .send_server_event::<ChannelServer>(&mut commands, key, msg)
+            .unwrap();
+    }
+    let mut recv = recv_from_server(&mut client).into_iter();
+    let (msg, _) = recv.next().unwrap();
+    let msg = registry.decode(&msg).unwrap();
+    registry
+        .send_client_event::<ChannelClient>(&mut commands, msg)
+        .unwrap();
+    let (msg, _) = recv.next().unwrap();
+    let msg = registry.decode(&msg).unwrap();
+    let msg = registry
+        .send_client_event::<ChannelClient>(&mut commands, msg)
+        .unwrap_err();
+    assert!(msg.is::<Chat>());
+    queue.apply(&mut world);
+
+    let events = world.resource::<Events<MessageFromClient<ChannelServer, Chat>>>();
+    let events = events.iter_current_update_events().collect::<Vec<_>>();
+    assert_eq!(1, events.len());
+    assert_eq!(client_key, events[0].client_key);
+    assert_eq!(Chat("hello".into()), events[0].msg);
+
+    let events = world.resource::<Events<MessageFromServer<ChannelClient, Move>>>();
+    let events = events.iter_current_update_events().collect::<Vec<_>>();
+    assert_eq!(1, events.len());
+    assert_eq!(Move { x: 1.0, y: 2.0 }, events[0].msg);
+}
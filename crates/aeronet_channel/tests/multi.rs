@@ -0,0 +1,182 @@
+//! Tests for combining multiple channel servers using a `MultiServer`.
+
+mod common;
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport, DisconnectReason},
+    lane::LaneIndex,
+    replay::{ReplayServer, ServerRecord},
+    server::{CloseReason, MultiServer, ServerEvent, ServerState, ServerTransport},
+    shared::Multi,
+};
+use aeronet_channel::{client::ChannelClient, server::ChannelServer};
+use assert_matches::assert_matches;
+use common::DT;
+
+const MSG: &[u8] = b"hello";
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+const REASON: &str = "disconnection reason here";
+
+type Server = MultiServer<ChannelServer, ChannelServer>;
+
+fn open() -> (Server, ChannelClient, ChannelClient) {
+    let mut server = MultiServer::new(common::open_server(), common::open_server());
+
+    let mut client_a = ChannelClient::new();
+    client_a.connect(server.a_mut()).unwrap();
+    let mut client_b = ChannelClient::new();
+    client_b.connect(server.b_mut()).unwrap();
+    assert_matches!(client_a.poll(DT).next().unwrap(), ClientEvent::Connected);
+    assert_matches!(client_b.poll(DT).next().unwrap(), ClientEvent::Connected);
+
+    (server, client_a, client_b)
+}
+
+#[test]
+fn merged_events() {
+    let (mut server, _client_a, _client_b) = open();
+    assert!(server.state().is_open());
+
+    let events = server.poll(DT).collect::<Vec<_>>();
+    assert_matches!(
+        events.as_slice(),
+        [
+            ServerEvent::Connecting {
+                client_key: Multi::A(_)
+            },
+            ServerEvent::Connected {
+                client_key: Multi::A(_)
+            },
+            ServerEvent::Connecting {
+                client_key: Multi::B(_)
+            },
+            ServerEvent::Connected {
+                client_key: Multi::B(_)
+            },
+        ]
+    );
+    assert_eq!(2, server.client_keys().count());
+}
+
+#[test]
+fn routed_send() {
+    let (mut server, mut client_a, mut client_b) = open();
+    let _ = server.poll(DT).count();
+
+    let key_b = server
+        .client_keys()
+        .find(|key| matches!(key, Multi::B(_)))
+        .unwrap();
+    assert!(server.client_state(key_b).is_connected());
+    server.send(key_b, MSG, LANE).unwrap();
+
+    assert!(client_a.poll(DT).next().is_none());
+    assert_matches!(
+        client_b.poll(DT).next().unwrap(),
+        ClientEvent::Recv { msg, lane } if msg == MSG && lane == LANE
+    );
+
+    client_a.send(MSG, LANE).unwrap();
+    let mut events = server.poll(DT);
+    assert_matches!(
+        events.next().unwrap(),
+        ServerEvent::Recv { client_key: Multi::A(_), msg, .. } if msg == MSG
+    );
+    assert!(events.next().is_none());
+}
+
+#[test]
+fn routed_disconnect() {
+    let (mut server, mut client_a, mut client_b) = open();
+    let _ = server.poll(DT).count();
+
+    let key_a = server
+        .client_keys()
+        .find(|key| matches!(key, Multi::A(_)))
+        .unwrap();
+    server.disconnect(key_a, REASON).unwrap();
+
+    assert_matches!(
+        client_a.poll(DT).next().unwrap(),
+        ClientEvent::Disconnected { reason: DisconnectReason::Remote(reason) } if reason == REASON
+    );
+    assert!(client_b.poll(DT).next().is_none());
+    assert_matches!(
+        server.poll(DT).next().unwrap(),
+        ServerEvent::Disconnected { client_key: Multi::A(_), reason: DisconnectReason::Local(reason) } if reason == REASON
+    );
+    assert_eq!(1, server.client_keys().count());
+}
+
+#[test]
+fn closed_when_all_closed() {
+    let (mut server, _client_a, _client_b) = open();
+    let _ = server.poll(DT).count();
+
+    server.a_mut().close(REASON).unwrap();
+    assert!(server.state().is_open());
+    assert!(!server
+        .poll(DT)
+        .any(|event| matches!(event, ServerEvent::Closed { .. })));
+
+    server.close(REASON).unwrap();
+    assert!(server.state().is_closed());
+    assert_matches!(
+        server.poll(DT).next().unwrap(),
+        ServerEvent::Closed { reason: CloseReason::Local(reason) } if reason == REASON
+    );
+}
+
+#[test]
+fn closed_once_when_both_close() {
+    let (mut server, _client_a, _client_b) = open();
+    let _ = server.poll(DT).count();
+
+    server.close(REASON).unwrap();
+    let events = server.poll(DT).collect::<Vec<_>>();
+    assert_matches!(
+        events.as_slice(),
+        [ServerEvent::Closed { reason: CloseReason::Local(reason) }] if reason == REASON
+    );
+}
+
+#[test]
+fn stays_open_while_other_opening() {
+    let poll = |state| ServerRecord::Poll {
+        state,
+        delta_time: DT,
+    };
+    // a server which takes a few updates to open, then closes
+    let slow = ReplayServer::from_records([
+        poll(ServerState::Opening(())),
+        poll(ServerState::Opening(())),
+        poll(ServerState::Opening(())),
+        ServerRecord::Opened,
+        poll(ServerState::Open(())),
+        ServerRecord::Closed {
+            reason: CloseReason::Local(REASON.to_owned()),
+        },
+    ]);
+    let mut server = MultiServer::new(ChannelServer::new(), slow);
+    server.a_mut().open().unwrap();
+    assert!(server.poll(DT).next().is_none());
+    assert!(server.state().is_open());
+
+    // the combined server doesn't go back to opening
+    server.a_mut().close(REASON).unwrap();
+    assert!(server.state().is_open());
+    assert!(server.poll(DT).next().is_none());
+    assert!(server.state().is_open());
+
+    // and doesn't open again
+    assert!(server.poll(DT).next().is_none());
+    assert!(server.state().is_open());
+
+    assert_matches!(
+        server.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ServerEvent::Closed { reason: CloseReason::Local(reason) }] if reason == REASON
+    );
+    assert!(server.state().is_closed());
+}
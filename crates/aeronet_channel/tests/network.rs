@@ -0,0 +1,193 @@
+//! Tests for connecting channel transports by address in a `ChannelNetwork`.
+
+mod common;
+
+use std::{thread, time::Duration};
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport, DisconnectReason},
+    lane::{LaneIndex, LaneKind},
+    server::{ServerEvent, ServerTransport},
+    shared::DROP_DISCONNECT_REASON,
+    testing::{Conformance, TransportPair},
+};
+use aeronet_channel::{
+    client::{ChannelClient, ClientError},
+    network::ChannelNetwork,
+    server::{ChannelServer, ServerError},
+};
+use assert_matches::assert_matches;
+use common::DT;
+
+const ADDR: &str = "server";
+
+const MSG: &[u8] = b"hello";
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+fn listen(network: &ChannelNetwork) -> ChannelServer {
+    let mut server = ChannelServer::new();
+    server.listen(network, ADDR).unwrap();
+    server
+}
+
+#[test]
+fn connect_by_addr() {
+    let network = ChannelNetwork::new();
+    let mut server = listen(&network);
+    assert_eq!(vec![ADDR.to_owned()], network.addrs());
+
+    let mut client = ChannelClient::new();
+    client.connect_to(&network, ADDR).unwrap();
+    assert!(client.state().is_connecting());
+    assert!(client.poll(DT).next().is_none());
+
+    let client_key = {
+        let events = server.poll(DT).collect::<Vec<_>>();
+        let [ServerEvent::Connecting { client_key }, ServerEvent::Connected { client_key: key }] =
+            events.as_slice()
+        else {
+            panic!("expected Connecting and Connected, got {events:?}");
+        };
+        assert_eq!(client_key, key);
+        *client_key
+    };
+
+    assert_matches!(
+        client.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ClientEvent::Connected]
+    );
+    client.send(MSG, LANE).unwrap();
+    assert_matches!(
+        server.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ServerEvent::Recv { client_key: key, msg, .. }] if *key == client_key && msg == MSG
+    );
+}
+
+#[test]
+fn connect_from_thread() {
+    let network = ChannelNetwork::new();
+    let mut server = listen(&network);
+
+    let mut client = thread::spawn(move || {
+        let mut client = ChannelClient::new();
+        client.connect_to(&network, ADDR).unwrap();
+        client
+    })
+    .join()
+    .unwrap();
+
+    assert!(client.state().is_connecting());
+    assert_eq!(2, server.poll(DT).count());
+    assert_matches!(
+        client.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ClientEvent::Connected]
+    );
+}
+
+#[test]
+fn addr_not_found() {
+    let network = ChannelNetwork::new();
+    let mut client = ChannelClient::new();
+    assert_matches!(
+        client.connect_to(&network, ADDR),
+        Err(ClientError::AddrNotFound)
+    );
+    assert!(client.state().is_disconnected());
+}
+
+#[test]
+fn addr_in_use() {
+    let network = ChannelNetwork::new();
+    let mut server = listen(&network);
+    assert_matches!(
+        ChannelServer::new().listen(&network, ADDR),
+        Err(ServerError::AddrInUse)
+    );
+
+    // the address is freed once the server closes
+    server.close("closing").unwrap();
+    assert_eq!(Vec::<String>::new(), network.addrs());
+    let server = listen(&network);
+
+    // or once it is dropped
+    drop(server);
+    assert_eq!(Vec::<String>::new(), network.addrs());
+}
+
+#[test]
+fn client_dropped_before_accept() {
+    let network = ChannelNetwork::new();
+    let mut server = listen(&network);
+
+    let mut client = ChannelClient::new();
+    client.connect_to(&network, ADDR).unwrap();
+    drop(client);
+
+    assert_matches!(
+        server.poll(DT).collect::<Vec<_>>().as_slice(),
+        [
+            ServerEvent::Connecting { .. },
+            ServerEvent::Connected { .. },
+            ServerEvent::Disconnected { reason: DisconnectReason::Remote(reason), .. },
+        ] if reason == DROP_DISCONNECT_REASON
+    );
+}
+
+#[test]
+fn server_closed_before_accept() {
+    let network = ChannelNetwork::new();
+    let mut server = listen(&network);
+
+    let mut client = ChannelClient::new();
+    client.connect_to(&network, ADDR).unwrap();
+    server.close("closing").unwrap();
+
+    assert_matches!(
+        client.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ClientEvent::Disconnected {
+            reason: DisconnectReason::Error(ClientError::ServerClosed)
+        }]
+    );
+    assert!(client.state().is_disconnected());
+}
+
+#[derive(Default)]
+struct NetworkPair {
+    network: ChannelNetwork,
+    next_addr: usize,
+    addr: String,
+}
+
+impl TransportPair for NetworkPair {
+    type Client = ChannelClient;
+
+    type Server = ChannelServer;
+
+    fn lanes(&self) -> &[LaneKind] {
+        &[LaneKind::ReliableOrdered]
+    }
+
+    fn open_server(&mut self) -> Self::Server {
+        // servers from previous checks may still be alive, so use a fresh
+        // address for each one
+        self.addr = format!("server-{}", self.next_addr);
+        self.next_addr += 1;
+        let mut server = ChannelServer::new();
+        server.listen(&self.network, self.addr.clone()).unwrap();
+        server
+    }
+
+    fn connect_client(&mut self, _: &mut Self::Server) -> Self::Client {
+        let mut client = ChannelClient::new();
+        client.connect_to(&self.network, &self.addr).unwrap();
+        client
+    }
+}
+
+#[test]
+fn conformance() {
+    Conformance::new(NetworkPair::default())
+        .with_poll_interval(Duration::ZERO)
+        .run_all();
+}
@@ -0,0 +1,188 @@
+//! Tests for automatically reconnecting a channel client using a
+//! `ReconnectingClient`.
+
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use aeronet::{
+    client::{
+        send_reconnect_events, ClientEvent, ClientTransport, DisconnectReason,
+        LocalClientReconnect, ReconnectConfig, ReconnectConfigError, ReconnectEvent,
+        ReconnectingClient,
+    },
+    shared::DROP_DISCONNECT_REASON,
+};
+use aeronet_channel::{client::ChannelClient, server::ChannelServer};
+use assert_matches::assert_matches;
+use bevy::{ecs::event::Events, prelude::*};
+use common::DT;
+
+const DELAY: Duration = Duration::from_secs(1);
+
+fn config() -> ReconnectConfig {
+    ReconnectConfig {
+        initial_delay: DELAY,
+        max_delay: DELAY * 4,
+        multiplier: 2.0,
+        jitter: 0.0,
+        max_attempts: Some(2),
+        reconnect_on_remote: true,
+    }
+}
+
+fn connect() -> (ReconnectingClient<ChannelClient>, Arc<Mutex<ChannelServer>>) {
+    let server = Arc::new(Mutex::new(common::open_server()));
+    let mut client = ReconnectingClient::new(ChannelClient::new(), config()).unwrap();
+    let server_clone = server.clone();
+    client
+        .connect(move |client| client.connect(&mut server_clone.lock().unwrap()))
+        .unwrap();
+    assert_matches!(client.poll(DT).next().unwrap(), ClientEvent::Connected);
+    assert!(client.drain_reconnect_events().next().is_none());
+    (client, server)
+}
+
+#[test]
+fn reconnects() {
+    let (mut client, server) = connect();
+
+    // drop the old server, so the client loses connection
+    *server.lock().unwrap() = common::open_server();
+    assert_matches!(
+        client.poll(DT).next().unwrap(),
+        ClientEvent::Disconnected { reason: DisconnectReason::Remote(reason) }
+        if reason == DROP_DISCONNECT_REASON
+    );
+    assert!(client.is_reconnecting());
+
+    assert!(client.poll(DELAY / 2).next().is_none());
+    assert!(client.drain_reconnect_events().next().is_none());
+
+    assert!(client.poll(DELAY / 2).next().is_none());
+    assert_eq!(
+        vec![ReconnectEvent::Attempt { attempt: 1 }],
+        client.drain_reconnect_events().collect::<Vec<_>>()
+    );
+
+    assert_matches!(client.poll(DT).next().unwrap(), ClientEvent::Connected);
+    assert_eq!(
+        vec![ReconnectEvent::Reconnected { attempts: 1 }],
+        client.drain_reconnect_events().collect::<Vec<_>>()
+    );
+    assert!(!client.is_reconnecting());
+}
+
+#[test]
+fn gives_up() {
+    let (mut client, server) = connect();
+
+    // replace with a server which isn't open, so reconnection attempts fail
+    *server.lock().unwrap() = ChannelServer::new();
+    let _ = client.poll(DT).count();
+
+    let _ = client.poll(DELAY).count();
+    let _ = client.poll(DELAY * 2).count();
+    assert_eq!(
+        vec![
+            ReconnectEvent::Attempt { attempt: 1 },
+            ReconnectEvent::Attempt { attempt: 2 },
+            ReconnectEvent::GaveUp { attempts: 2 },
+        ],
+        client.drain_reconnect_events().collect::<Vec<_>>()
+    );
+    assert!(!client.is_reconnecting());
+    assert!(client.state().is_disconnected());
+}
+
+#[test]
+fn no_reconnect_after_local_disconnect() {
+    let (mut client, _server) = connect();
+
+    client.disconnect("reason").unwrap();
+    assert_matches!(
+        client.poll(DT).next().unwrap(),
+        ClientEvent::Disconnected {
+            reason: DisconnectReason::Local(_)
+        }
+    );
+    assert!(!client.is_reconnecting());
+    let _ = client.poll(DELAY * 10).count();
+    assert!(client.drain_reconnect_events().next().is_none());
+}
+
+#[test]
+fn invalid_config() {
+    assert_matches!(
+        ReconnectingClient::new(
+            ChannelClient::new(),
+            ReconnectConfig {
+                multiplier: 0.5,
+                ..config()
+            }
+        ),
+        Err(ReconnectConfigError::InvalidMultiplier)
+    );
+    assert_matches!(
+        ReconnectingClient::new(
+            ChannelClient::new(),
+            ReconnectConfig {
+                multiplier: f64::NAN,
+                ..config()
+            }
+        ),
+        Err(ReconnectConfigError::InvalidMultiplier)
+    );
+
+    let (mut client, _server) = connect();
+    assert_eq!(
+        Err(ReconnectConfigError::InvalidJitter),
+        client.set_config(ReconnectConfig {
+            jitter: 1.5,
+            ..config()
+        })
+    );
+    // the old config is kept
+    assert_eq!(0.0, client.config().jitter);
+}
+
+#[test]
+fn bevy_events() {
+    type Client = ReconnectingClient<ChannelClient>;
+
+    fn poll(mut client: ResMut<Client>) {
+        let _ = client.poll(DELAY).count();
+    }
+
+    let (client, server) = connect();
+    let mut app = App::new();
+    app.add_event::<LocalClientReconnect<Client>>()
+        .insert_resource(client)
+        .add_systems(
+            Update,
+            (poll, send_reconnect_events::<ChannelClient>).chain(),
+        );
+
+    *server.lock().unwrap() = ChannelServer::new();
+    let mut events = Vec::new();
+    for _ in 0..3 {
+        app.update();
+        events.extend(
+            app.world_mut()
+                .resource_mut::<Events<LocalClientReconnect<Client>>>()
+                .drain()
+                .map(|event| event.event),
+        );
+    }
+    assert_eq!(
+        vec![
+            ReconnectEvent::Attempt { attempt: 1 },
+            ReconnectEvent::Attempt { attempt: 2 },
+            ReconnectEvent::GaveUp { attempts: 2 },
+        ],
+        events
+    );
+}
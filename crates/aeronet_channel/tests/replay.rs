@@ -0,0 +1,205 @@
+//! Tests for recording channel transports and replaying the recordings.
+
+mod common;
+
+use std::time::Duration;
+
+use aeronet::{
+    client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
+    lane::LaneIndex,
+    replay::{
+        read_client_records, read_server_records, ClientRecord, ReadError, RecordingClient,
+        RecordingServer, ReplayClient, ReplayError, ReplayKey, ReplayServer, ServerRecord,
+    },
+    server::{ServerEvent, ServerState, ServerTransport},
+};
+use aeronet_channel::{client::ChannelClient, server::ChannelServer};
+use assert_matches::assert_matches;
+
+const C2S: &[u8] = b"hello server";
+const S2C: &[u8] = b"hello client";
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+const DT: Duration = Duration::from_millis(16);
+
+const REASON: &str = "disconnection reason here";
+
+/// Records a client connecting, exchanging a message with the server, and
+/// disconnecting.
+fn record() -> (Vec<u8>, Vec<u8>) {
+    let mut client_rec = Vec::new();
+    let mut server_rec = Vec::new();
+    {
+        let mut server = RecordingServer::new(ChannelServer::new(), &mut server_rec);
+        server.inner_mut().open().unwrap();
+        let mut client = RecordingClient::new(ChannelClient::new(), &mut client_rec);
+        client.inner_mut().connect(server.inner_mut()).unwrap();
+
+        let client_key = common::connected(&mut client, &mut server, DT);
+
+        client.send(C2S, LANE).unwrap();
+        client.flush().unwrap();
+        assert_eq!(1, server.poll(DT).count());
+        server.send(client_key, S2C, LANE).unwrap();
+        server.flush().unwrap();
+        assert_eq!(1, client.poll(DT).count());
+
+        server.disconnect(client_key, REASON).unwrap();
+        assert_eq!(1, client.poll(DT).count());
+        assert_eq!(1, server.poll(DT).count());
+
+        assert!(client.io_error().is_none());
+        assert!(server.io_error().is_none());
+    }
+    (client_rec, server_rec)
+}
+
+#[test]
+fn client_records() {
+    let (client_rec, _) = record();
+    let records = read_client_records(client_rec.as_slice()).unwrap();
+    assert_eq!(
+        vec![
+            ClientRecord::Poll {
+                state: ClientState::Connected(()),
+                delta_time: DT,
+            },
+            ClientRecord::Connected,
+            ClientRecord::Send {
+                msg: C2S.into(),
+                lane: LANE,
+                msg_key: Some(0),
+            },
+            ClientRecord::Flush,
+            ClientRecord::Poll {
+                state: ClientState::Connected(()),
+                delta_time: DT,
+            },
+            ClientRecord::Recv {
+                msg: S2C.into(),
+                lane: LANE,
+            },
+            ClientRecord::Poll {
+                state: ClientState::Connected(()),
+                delta_time: DT,
+            },
+            ClientRecord::Disconnected {
+                reason: DisconnectReason::Remote(REASON.into()),
+            },
+        ],
+        records
+    );
+}
+
+#[test]
+fn server_records() {
+    let (_, server_rec) = record();
+    let records = read_server_records(server_rec.as_slice()).unwrap();
+    assert_matches!(
+        records.as_slice(),
+        [
+            ServerRecord::Poll { state: ServerState::Open(()), .. },
+            ServerRecord::Connecting { client_key: 0 },
+            ServerRecord::Connected { client_key: 0 },
+            ServerRecord::Poll { .. },
+            ServerRecord::Recv { client_key: 0, msg, lane: LANE },
+            ServerRecord::Send { client_key: 0, lane: LANE, msg_key: Some(0), .. },
+            ServerRecord::Flush,
+            ServerRecord::Disconnect { client_key: 0, reason },
+            ServerRecord::Poll { .. },
+            ServerRecord::Disconnected {
+                client_key: 0,
+                reason: DisconnectReason::Local(_),
+            },
+        ] if msg == C2S && reason == REASON
+    );
+}
+
+#[test]
+fn replay_client() {
+    let (client_rec, _) = record();
+    let mut client = ReplayClient::read(client_rec.as_slice()).unwrap();
+    assert!(client.state().is_disconnected());
+    assert_matches!(client.send(C2S, LANE), Err(ReplayError::NotConnected));
+
+    assert_eq!(Some(DT), client.next_delta_time());
+    assert_matches!(
+        client.poll(Duration::ZERO).collect::<Vec<_>>().as_slice(),
+        [ClientEvent::Connected]
+    );
+    assert!(client.state().is_connected());
+    assert_matches!(client.send(C2S, LANE), Ok(key) if key == ReplayKey::from_raw(0));
+
+    assert_matches!(
+        client.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ClientEvent::Recv { msg, lane: LANE }] if msg == S2C
+    );
+    assert_matches!(
+        client.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ClientEvent::Disconnected { reason: DisconnectReason::Remote(reason) }] if reason == REASON
+    );
+    assert!(client.state().is_disconnected());
+    assert!(client.is_finished());
+    assert!(client.poll(DT).next().is_none());
+}
+
+#[test]
+fn replay_server() {
+    let (_, server_rec) = record();
+    let mut server = ReplayServer::read(server_rec.as_slice()).unwrap();
+    let client_key = ReplayKey::from_raw(0);
+
+    assert_matches!(
+        server.poll(DT).collect::<Vec<_>>().as_slice(),
+        [
+            ServerEvent::Connecting { client_key: a },
+            ServerEvent::Connected { client_key: b },
+        ] if *a == client_key && *b == client_key
+    );
+    assert!(server.state().is_open());
+    assert!(server.client_state(client_key).is_connected());
+    assert_eq!(vec![client_key], server.client_keys().collect::<Vec<_>>());
+
+    assert_matches!(
+        server.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ServerEvent::Recv { msg, lane: LANE, .. }] if msg == C2S
+    );
+    assert_matches!(server.send(client_key, S2C, LANE), Ok(key) if key == ReplayKey::from_raw(0));
+
+    assert_matches!(
+        server.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ServerEvent::Disconnected { client_key: key, .. }] if *key == client_key
+    );
+    assert!(server.client_state(client_key).is_disconnected());
+    assert_matches!(
+        server.send(client_key, S2C, LANE),
+        Err(ReplayError::ClientNotConnected)
+    );
+    assert!(server.is_finished());
+}
+
+#[test]
+fn wrong_side() {
+    let (client_rec, server_rec) = record();
+    assert_matches!(
+        read_server_records(client_rec.as_slice()),
+        Err(ReadError::WrongSide)
+    );
+    assert_matches!(
+        ReplayClient::read(server_rec.as_slice()),
+        Err(ReadError::WrongSide)
+    );
+    assert_matches!(
+        ReplayClient::read(&b"not a recording"[..]),
+        Err(ReadError::InvalidHeader)
+    );
+}
+
+#[test]
+fn truncated() {
+    let (client_rec, _) = record();
+    assert_matches!(
+        read_client_records(&client_rec[..client_rec.len() - 1]),
+        Err(ReadError::Truncated)
+    );
+}
@@ -0,0 +1,219 @@
+//! Tests for request/response calls over channel transports.
+
+mod common;
+
+use std::time::Duration;
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport},
+    lane::LaneIndex,
+    rpc::{RpcClient, RpcComplete, RpcError, RpcRequest, RpcServer},
+    server::{ServerEvent, ServerTransport},
+};
+use aeronet_channel::{
+    client::ChannelClient,
+    server::{ChannelServer, ClientKey},
+};
+use assert_matches::assert_matches;
+use bytes::Bytes;
+use common::DT;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+const RPC_LANE: LaneIndex = LaneIndex::from_raw(0);
+
+const OTHER_LANE: LaneIndex = LaneIndex::from_raw(1);
+
+fn poll_server(
+    rpc: &RpcServer,
+    server: &mut ChannelServer,
+) -> (Vec<RpcRequest<ClientKey>>, Vec<ServerEvent<ChannelServer>>) {
+    let mut requests = Vec::new();
+    let mut events = Vec::new();
+    for event in server.poll(DT) {
+        match rpc.handle(event) {
+            Ok(request) => requests.push(request),
+            Err(event) => events.push(event),
+        }
+    }
+    (requests, events)
+}
+
+fn poll_client(
+    rpc: &mut RpcClient,
+    client: &mut ChannelClient,
+) -> (Vec<RpcComplete>, Vec<ClientEvent<ChannelClient>>) {
+    let events = client
+        .poll(DT)
+        .filter_map(|event| rpc.handle(event))
+        .collect();
+    (rpc.drain().collect(), events)
+}
+
+#[test]
+fn call_and_respond() {
+    let (mut client, mut server, client_key) = common::open();
+    let mut rpc_client = RpcClient::new(RPC_LANE, TIMEOUT);
+    let rpc_server = RpcServer::new(RPC_LANE);
+
+    let id = rpc_client.call(&mut client, b"ping").unwrap();
+    client
+        .send(Bytes::from_static(b"other"), OTHER_LANE)
+        .unwrap();
+    assert!(rpc_client.is_pending(id));
+
+    let (requests, events) = poll_server(&rpc_server, &mut server);
+    assert_eq!(
+        vec![RpcRequest {
+            client_key,
+            id,
+            msg: Bytes::from_static(b"ping"),
+        }],
+        requests
+    );
+    assert_matches!(
+        events.as_slice(),
+        [ServerEvent::Recv { msg, lane: OTHER_LANE, .. }] if msg == b"other".as_slice()
+    );
+
+    rpc_server
+        .respond(&mut server, client_key, id, b"pong")
+        .unwrap();
+    server
+        .send(client_key, Bytes::from_static(b"other"), OTHER_LANE)
+        .unwrap();
+
+    let (completed, events) = poll_client(&mut rpc_client, &mut client);
+    assert_eq!(
+        vec![RpcComplete {
+            id,
+            result: Ok(Bytes::from_static(b"pong")),
+        }],
+        completed
+    );
+    assert_matches!(
+        events.as_slice(),
+        [ClientEvent::Recv { msg, lane: OTHER_LANE }] if msg == b"other".as_slice()
+    );
+    assert_eq!(0, rpc_client.num_pending());
+}
+
+#[test]
+fn responses_out_of_order() {
+    let (mut client, mut server, _) = common::open();
+    let mut rpc_client = RpcClient::new(RPC_LANE, TIMEOUT);
+    let rpc_server = RpcServer::new(RPC_LANE);
+
+    let id1 = rpc_client.call(&mut client, b"1").unwrap();
+    let id2 = rpc_client.call(&mut client, b"2").unwrap();
+    assert_ne!(id1, id2);
+
+    let (requests, _) = poll_server(&rpc_server, &mut server);
+    assert_eq!(2, requests.len());
+    for request in requests.iter().rev() {
+        rpc_server
+            .respond(&mut server, request.client_key, request.id, &request.msg)
+            .unwrap();
+    }
+
+    let (completed, _) = poll_client(&mut rpc_client, &mut client);
+    assert_eq!(
+        vec![
+            RpcComplete {
+                id: id2,
+                result: Ok(Bytes::from_static(b"2")),
+            },
+            RpcComplete {
+                id: id1,
+                result: Ok(Bytes::from_static(b"1")),
+            },
+        ],
+        completed
+    );
+}
+
+#[test]
+fn timeout() {
+    let (mut client, mut server, client_key) = common::open();
+    let mut rpc_client = RpcClient::new(RPC_LANE, TIMEOUT);
+    let rpc_server = RpcServer::new(RPC_LANE);
+
+    let id = rpc_client
+        .call_with_timeout(&mut client, b"ping", Duration::from_secs(1))
+        .unwrap();
+    let other_id = rpc_client.call(&mut client, b"ping").unwrap();
+
+    rpc_client.update(Duration::from_millis(500));
+    assert_eq!(0, rpc_client.drain().count());
+    rpc_client.update(Duration::from_millis(500));
+    assert_eq!(
+        vec![RpcComplete {
+            id,
+            result: Err(RpcError::TimedOut),
+        }],
+        rpc_client.drain().collect::<Vec<_>>()
+    );
+    assert!(rpc_client.is_pending(other_id));
+
+    // late response is dropped
+    let (requests, _) = poll_server(&rpc_server, &mut server);
+    rpc_server
+        .respond(&mut server, client_key, requests[0].id, b"pong")
+        .unwrap();
+    let (completed, events) = poll_client(&mut rpc_client, &mut client);
+    assert_eq!(Vec::<RpcComplete>::new(), completed);
+    assert_eq!(0, events.len());
+}
+
+#[test]
+fn disconnect_fails_pending() {
+    let (mut client, mut server, client_key) = common::open();
+    let mut rpc_client = RpcClient::new(RPC_LANE, TIMEOUT);
+
+    let id1 = rpc_client.call(&mut client, b"1").unwrap();
+    let id2 = rpc_client.call(&mut client, b"2").unwrap();
+    server.disconnect(client_key, "disconnected").unwrap();
+
+    let (completed, events) = poll_client(&mut rpc_client, &mut client);
+    assert_eq!(
+        vec![
+            RpcComplete {
+                id: id1,
+                result: Err(RpcError::Disconnected),
+            },
+            RpcComplete {
+                id: id2,
+                result: Err(RpcError::Disconnected),
+            },
+        ],
+        completed
+    );
+    assert_matches!(events.as_slice(), [ClientEvent::Disconnected { .. }]);
+    assert_eq!(0, rpc_client.num_pending());
+}
+
+#[test]
+fn cancel() {
+    let (mut client, _server, _) = common::open();
+    let mut rpc_client = RpcClient::new(RPC_LANE, TIMEOUT);
+
+    let id = rpc_client.call(&mut client, b"ping").unwrap();
+    assert!(rpc_client.cancel(id));
+    assert!(!rpc_client.cancel(id));
+    assert_eq!(
+        vec![RpcComplete {
+            id,
+            result: Err(RpcError::Cancelled),
+        }],
+        rpc_client.drain().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn call_while_disconnected() {
+    let mut client = ChannelClient::new();
+    let mut rpc_client = RpcClient::new(RPC_LANE, TIMEOUT);
+
+    assert!(rpc_client.call(&mut client, b"ping").is_err());
+    assert_eq!(0, rpc_client.num_pending());
+}
@@ -0,0 +1,183 @@
+//! Tests for session channel transports, opened with a `SessionConfig`.
+
+mod common;
+
+use std::time::Duration;
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport},
+    lane::{LaneIndex, LaneKind},
+    server::{ServerEvent, ServerTransport},
+    testing::{Conformance, TransportPair},
+};
+use aeronet_channel::{
+    client::ClientError,
+    network::ChannelNetwork,
+    server::{ClientKey, ServerError},
+    session::{SessionChannelClient, SessionChannelServer},
+};
+use aeronet_proto::{
+    session::{MessageKey, SessionConfig},
+    ty::{MessageSeq, Seq},
+};
+use assert_matches::assert_matches;
+use common::DT;
+
+const MSG: &[u8] = b"hello";
+
+const CLIENT_LANES: &[LaneKind] = &[LaneKind::ReliableOrdered];
+
+const SERVER_LANES: &[LaneKind] = &[LaneKind::UnreliableUnordered, LaneKind::ReliableOrdered];
+
+fn config() -> SessionConfig {
+    SessionConfig::default()
+        .with_client_lanes(CLIENT_LANES.iter().copied())
+        .with_server_lanes(SERVER_LANES.iter().copied())
+}
+
+const fn key(lane: u64, seq: u16) -> MessageKey {
+    MessageKey::from_raw(LaneIndex::from_raw(lane), MessageSeq(Seq(seq)))
+}
+
+fn open(config: SessionConfig) -> (SessionChannelClient, SessionChannelServer, ClientKey) {
+    let mut server = SessionChannelServer::new();
+    server.open(config).unwrap();
+    let mut client = SessionChannelClient::new();
+    client.connect(&mut server).unwrap();
+
+    let client_key = common::connected(&mut client, &mut server, DT);
+    (client, server, client_key)
+}
+
+#[test]
+fn message_keys() {
+    let (mut client, mut server, client_key) = open(config());
+
+    assert_eq!(key(0, 0), client.send(MSG, LaneIndex::from_raw(0)).unwrap());
+    assert_eq!(key(0, 1), client.send(MSG, LaneIndex::from_raw(0)).unwrap());
+
+    let lane = LaneIndex::from_raw(1);
+    assert_eq!(key(1, 0), server.send(client_key, MSG, lane).unwrap());
+    let lane = LaneIndex::from_raw(0);
+    assert_eq!(key(0, 0), server.send(client_key, MSG, lane).unwrap());
+}
+
+#[test]
+fn invalid_lane() {
+    let (mut client, mut server, client_key) = open(config());
+
+    let lane = LaneIndex::from_raw(1);
+    assert_matches!(
+        client.send(MSG, lane),
+        Err(ClientError::InvalidLane { lane: l }) if l == lane
+    );
+    let lane = LaneIndex::from_raw(2);
+    assert_matches!(
+        server.send(client_key, MSG, lane),
+        Err(ServerError::InvalidLane { lane: l }) if l == lane
+    );
+
+    // invalid sends do not disconnect either side
+    assert!(client.state().is_connected());
+    assert!(server.client_state(client_key).is_connected());
+    assert!(client.poll(DT).next().is_none());
+    assert!(server.poll(DT).next().is_none());
+}
+
+#[test]
+fn ack_after_peer_polls() {
+    let (mut client, mut server, client_key) = open(config());
+
+    let msg_key = client.send(MSG, LaneIndex::from_raw(0)).unwrap();
+    assert!(client.poll(DT).next().is_none());
+
+    assert_matches!(
+        server.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ServerEvent::Recv { msg, .. }] if msg == MSG
+    );
+    assert_matches!(
+        client.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ClientEvent::Ack { msg_key: acked }] if *acked == msg_key
+    );
+
+    let msg_key = server
+        .send(client_key, MSG, LaneIndex::from_raw(1))
+        .unwrap();
+    assert_matches!(
+        client.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ClientEvent::Recv { msg, .. }] if msg == MSG
+    );
+    assert_matches!(
+        server.poll(DT).collect::<Vec<_>>().as_slice(),
+        [ServerEvent::Ack { client_key: key, msg_key: acked }] if *key == client_key && *acked == msg_key
+    );
+}
+
+#[test]
+fn listen() {
+    let network = ChannelNetwork::new();
+    let mut server = SessionChannelServer::new();
+    server.listen(&network, "server", config()).unwrap();
+    let mut client = SessionChannelClient::new();
+    client.connect_to(&network, "server").unwrap();
+
+    // the connection request is accepted when the server is polled
+    assert!(client.poll(DT).next().is_none());
+    assert!(server.poll(DT).next().is_some());
+    assert_matches!(client.poll(DT).next(), Some(ClientEvent::Connected));
+
+    assert_eq!(key(0, 0), client.send(MSG, LaneIndex::from_raw(0)).unwrap());
+    assert_matches!(
+        client.send(MSG, LaneIndex::from_raw(1)),
+        Err(ClientError::InvalidLane { .. })
+    );
+}
+
+#[test]
+fn no_acks_without_config() {
+    let (mut client, mut server, client_key) = common::open();
+
+    // any lane is allowed
+    client.send(MSG, LaneIndex::from_raw(5)).unwrap();
+    assert_eq!(1, server.poll(DT).count());
+    assert!(client.poll(DT).next().is_none());
+
+    server
+        .send(client_key, MSG, LaneIndex::from_raw(5))
+        .unwrap();
+    assert_eq!(1, client.poll(DT).count());
+    assert!(server.poll(DT).next().is_none());
+}
+
+struct SessionPair;
+
+impl TransportPair for SessionPair {
+    type Client = SessionChannelClient;
+
+    type Server = SessionChannelServer;
+
+    fn lanes(&self) -> &[LaneKind] {
+        CLIENT_LANES
+    }
+
+    fn open_server(&mut self) -> Self::Server {
+        let mut server = SessionChannelServer::new();
+        server
+            .open(SessionConfig::default().with_lanes(CLIENT_LANES.iter().copied()))
+            .unwrap();
+        server
+    }
+
+    fn connect_client(&mut self, server: &mut Self::Server) -> Self::Client {
+        let mut client = SessionChannelClient::new();
+        client.connect(server).unwrap();
+        client
+    }
+}
+
+#[test]
+fn conformance() {
+    Conformance::new(SessionPair)
+        .with_poll_interval(Duration::ZERO)
+        .run_all();
+}
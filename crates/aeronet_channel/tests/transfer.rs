@@ -0,0 +1,362 @@
+//! Tests for bulk transfers over channel transports.
+
+mod common;
+
+use std::time::Duration;
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport},
+    lane::LaneIndex,
+    server::{ServerEvent, ServerTransport},
+    transfer::{CancelReason, TransferConfig, TransferDirection, TransferEvent, Transfers},
+};
+use aeronet_channel::{
+    client::ChannelClient,
+    server::{ChannelServer, ClientKey},
+};
+use assert_matches::assert_matches;
+use bytes::Bytes;
+use common::DT;
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+const CHUNK_LEN: usize = 1024;
+
+struct Peers {
+    client: ChannelClient,
+    server: ChannelServer,
+    client_key: ClientKey,
+    client_transfers: Transfers<()>,
+    server_transfers: Transfers<ClientKey>,
+}
+
+const fn config() -> TransferConfig {
+    TransferConfig {
+        chunk_len: CHUNK_LEN,
+        bandwidth: 4 * CHUNK_LEN,
+        max_recv_len: 1024 * 1024,
+    }
+}
+
+fn open() -> Peers {
+    let (client, server, client_key) = common::open();
+    Peers {
+        client,
+        server,
+        client_key,
+        client_transfers: Transfers::new(LANE, config()),
+        server_transfers: Transfers::new(LANE, config()),
+    }
+}
+
+fn payload(len: usize) -> Bytes {
+    (0..len).map(|i| i as u8).collect()
+}
+
+impl Peers {
+    fn update(
+        &mut self,
+        delta_time: Duration,
+    ) -> (Vec<TransferEvent<()>>, Vec<TransferEvent<ClientKey>>) {
+        self.client_transfers.update(delta_time);
+        self.server_transfers.update(delta_time);
+        self.client_transfers
+            .flush_client(&mut self.client)
+            .unwrap();
+        self.server_transfers
+            .flush_server(&mut self.server)
+            .unwrap();
+
+        for event in self.client.poll(DT) {
+            assert_matches!(self.client_transfers.handle_client(event), None);
+        }
+        for event in self.server.poll(DT) {
+            assert_matches!(self.server_transfers.handle_server(event), None);
+        }
+        (
+            self.client_transfers.drain().collect(),
+            self.server_transfers.drain().collect(),
+        )
+    }
+}
+
+#[test]
+fn larger_than_chunk() {
+    let mut peers = open();
+    let payload = payload(3 * CHUNK_LEN + 100);
+    let id = peers.client_transfers.send((), payload.clone());
+
+    let (client_events, server_events) = peers.update(Duration::from_secs(10));
+    let client_key = peers.client_key;
+
+    let sent = client_events
+        .iter()
+        .filter_map(|event| match event {
+            TransferEvent::SendProgress {
+                id: event_id,
+                sent,
+                total,
+                ..
+            } if *event_id == id => Some((*sent, *total)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec![
+            (CHUNK_LEN, payload.len()),
+            (2 * CHUNK_LEN, payload.len()),
+            (3 * CHUNK_LEN, payload.len()),
+            (payload.len(), payload.len()),
+        ],
+        sent
+    );
+    assert_eq!(
+        Some(&TransferEvent::Sent { peer: (), id }),
+        client_events.last()
+    );
+
+    assert_eq!(
+        TransferEvent::RecvStarted {
+            peer: client_key,
+            id,
+            total: payload.len(),
+        },
+        server_events[0]
+    );
+    assert_eq!(
+        Some(&TransferEvent::Received {
+            peer: client_key,
+            id,
+            payload,
+        }),
+        server_events.last()
+    );
+    assert_eq!(0, peers.client_transfers.num_sending());
+    assert_eq!(0, peers.server_transfers.num_receiving());
+}
+
+#[test]
+fn bandwidth_limited() {
+    let mut peers = open();
+    let payload = payload(8 * CHUNK_LEN);
+    let client_key = peers.client_key;
+    let id = peers.server_transfers.send(client_key, payload.clone());
+
+    let (client_events, server_events) = peers.update(Duration::from_millis(500));
+    assert_matches!(
+        server_events.last(),
+        Some(TransferEvent::SendProgress { sent, .. }) if *sent == 2 * CHUNK_LEN
+    );
+    assert_matches!(
+        client_events.last(),
+        Some(TransferEvent::RecvProgress { received, .. }) if *received == 2 * CHUNK_LEN
+    );
+
+    let (client_events, server_events) = peers.update(DT);
+    assert_eq!(Vec::<TransferEvent<()>>::new(), client_events);
+    assert_eq!(Vec::<TransferEvent<ClientKey>>::new(), server_events);
+
+    // unused bandwidth is capped at one second's worth
+    let (_, server_events) = peers.update(Duration::from_secs(10));
+    assert_matches!(
+        server_events.last(),
+        Some(TransferEvent::SendProgress { sent, .. }) if *sent == 6 * CHUNK_LEN
+    );
+
+    let (client_events, server_events) = peers.update(Duration::from_secs(1));
+    assert_eq!(
+        Some(&TransferEvent::Sent {
+            peer: client_key,
+            id
+        }),
+        server_events.last()
+    );
+    assert_eq!(
+        Some(&TransferEvent::Received {
+            peer: (),
+            id,
+            payload
+        }),
+        client_events.last()
+    );
+}
+
+#[test]
+fn cancelled_by_sender() {
+    let mut peers = open();
+    let client_key = peers.client_key;
+    let id = peers
+        .server_transfers
+        .send(client_key, payload(8 * CHUNK_LEN));
+    peers.update(Duration::from_millis(500));
+
+    assert!(peers.server_transfers.cancel_send(id));
+    assert!(!peers.server_transfers.cancel_send(id));
+    let (client_events, server_events) = peers.update(Duration::from_secs(1));
+    assert_eq!(
+        vec![TransferEvent::Cancelled {
+            peer: client_key,
+            id,
+            direction: TransferDirection::Send,
+            reason: CancelReason::Local,
+        }],
+        server_events
+    );
+    assert_eq!(
+        vec![TransferEvent::Cancelled {
+            peer: (),
+            id,
+            direction: TransferDirection::Recv,
+            reason: CancelReason::Remote,
+        }],
+        client_events
+    );
+    assert_eq!(0, peers.client_transfers.num_receiving());
+}
+
+#[test]
+fn cancelled_by_receiver() {
+    let mut peers = open();
+    let client_key = peers.client_key;
+    let id = peers
+        .server_transfers
+        .send(client_key, payload(8 * CHUNK_LEN));
+    peers.update(Duration::from_millis(500));
+
+    assert!(peers.client_transfers.cancel_recv((), id));
+    assert!(!peers.client_transfers.cancel_recv((), id));
+    let (client_events, server_events) = peers.update(DT);
+    assert_eq!(
+        vec![TransferEvent::Cancelled {
+            peer: (),
+            id,
+            direction: TransferDirection::Recv,
+            reason: CancelReason::Local,
+        }],
+        client_events
+    );
+    assert_eq!(
+        vec![TransferEvent::Cancelled {
+            peer: client_key,
+            id,
+            direction: TransferDirection::Send,
+            reason: CancelReason::Remote,
+        }],
+        server_events
+    );
+    assert_eq!(0, peers.server_transfers.num_sending());
+}
+
+#[test]
+fn too_large() {
+    let mut peers = open();
+    let client_key = peers.client_key;
+    let id = peers
+        .client_transfers
+        .send((), payload(config().max_recv_len + 1));
+
+    let (_, server_events) = peers.update(DT);
+    assert_eq!(
+        vec![TransferEvent::Cancelled {
+            peer: client_key,
+            id,
+            direction: TransferDirection::Recv,
+            reason: CancelReason::TooLarge,
+        }],
+        server_events
+    );
+
+    let (client_events, _) = peers.update(DT);
+    assert_eq!(
+        vec![TransferEvent::Cancelled {
+            peer: (),
+            id,
+            direction: TransferDirection::Send,
+            reason: CancelReason::Remote,
+        }],
+        client_events
+    );
+}
+
+#[test]
+fn empty_payload() {
+    let mut peers = open();
+    let client_key = peers.client_key;
+    let id = peers.client_transfers.send((), Bytes::new());
+
+    let (client_events, server_events) = peers.update(DT);
+    assert_eq!(vec![TransferEvent::Sent { peer: (), id }], client_events);
+    assert_eq!(
+        vec![
+            TransferEvent::RecvStarted {
+                peer: client_key,
+                id,
+                total: 0,
+            },
+            TransferEvent::Received {
+                peer: client_key,
+                id,
+                payload: Bytes::new(),
+            },
+        ],
+        server_events
+    );
+}
+
+#[test]
+fn disconnect_cancels() {
+    let mut peers = open();
+    let client_key = peers.client_key;
+    let send_id = peers.client_transfers.send((), payload(8 * CHUNK_LEN));
+    let recv_id = peers
+        .server_transfers
+        .send(client_key, payload(8 * CHUNK_LEN));
+    peers.update(Duration::from_millis(500));
+
+    peers.server.disconnect(client_key, "disconnected").unwrap();
+    let events = peers
+        .client
+        .poll(DT)
+        .filter_map(|event| peers.client_transfers.handle_client(event))
+        .collect::<Vec<_>>();
+    assert_matches!(events.as_slice(), [ClientEvent::Disconnected { .. }]);
+    assert_eq!(
+        vec![
+            TransferEvent::Cancelled {
+                peer: (),
+                id: send_id,
+                direction: TransferDirection::Send,
+                reason: CancelReason::Disconnected,
+            },
+            TransferEvent::Cancelled {
+                peer: (),
+                id: recv_id,
+                direction: TransferDirection::Recv,
+                reason: CancelReason::Disconnected,
+            },
+        ],
+        peers.client_transfers.drain().collect::<Vec<_>>()
+    );
+    assert_eq!(0, peers.client_transfers.num_sending());
+    assert_eq!(0, peers.client_transfers.num_receiving());
+}
+
+#[test]
+fn other_lanes_pass_through() {
+    let mut peers = open();
+    let other_lane = LaneIndex::from_raw(1);
+    peers
+        .client
+        .send(Bytes::from_static(b"hello"), other_lane)
+        .unwrap();
+
+    let events = peers
+        .server
+        .poll(DT)
+        .filter_map(|event| peers.server_transfers.handle_server(event))
+        .collect::<Vec<_>>();
+    assert_matches!(
+        events.as_slice(),
+        [ServerEvent::Recv { msg, lane, .. }] if msg == b"hello".as_slice() && *lane == other_lane
+    );
+}
@@ -0,0 +1,101 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{spanned::Spanned, Data, DeriveInput, Error, Fields, Ident, Result};
+
+/// Names of the variants of `aeronet::lane::LaneKind`.
+const KINDS: &[&str] = &[
+    "UnreliableUnordered",
+    "UnreliableSequenced",
+    "ReliableUnordered",
+    "ReliableOrdered",
+];
+
+pub fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(
+            input.span(),
+            "`Lanes` can only be derived on enums",
+        ));
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "`Lanes` cannot be derived on generic enums",
+        ));
+    }
+
+    let mut variants = Vec::with_capacity(data.variants.len());
+    let mut kinds = Vec::with_capacity(data.variants.len());
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new(
+                variant.fields.span(),
+                "lane variants cannot have fields",
+            ));
+        }
+        if let Some((_, discriminant)) = &variant.discriminant {
+            return Err(Error::new(
+                discriminant.span(),
+                "lane variants cannot have explicit discriminants, since lane indices are \
+                 assigned in declaration order",
+            ));
+        }
+
+        variants.push(&variant.ident);
+        kinds.push(lane_kind(&variant.ident, &variant.attrs)?);
+    }
+
+    let name = &input.ident;
+    let indices = (0..variants.len() as u64).collect::<Vec<_>>();
+    Ok(quote! {
+        #[automatically_derived]
+        impl ::core::convert::From<#name> for ::aeronet::lane::LaneIndex {
+            fn from(value: #name) -> Self {
+                match value {
+                    #(#name::#variants => Self::from_raw(#indices),)*
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl ::core::convert::From<#name> for ::aeronet::lane::LaneKind {
+            fn from(value: #name) -> Self {
+                match value {
+                    #(#name::#variants => Self::#kinds,)*
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl ::aeronet::lane::Lanes for #name {
+            const KINDS: &'static [::aeronet::lane::LaneKind] = &[
+                #(::aeronet::lane::LaneKind::#kinds,)*
+            ];
+        }
+    })
+}
+
+fn lane_kind(variant: &Ident, attrs: &[syn::Attribute]) -> Result<Ident> {
+    let mut kind = None::<Ident>;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("lane")) {
+        if kind.is_some() {
+            return Err(Error::new(attr.span(), "duplicate `#[lane(..)]` attribute"));
+        }
+
+        let ident = attr.parse_args::<Ident>()?;
+        if !KINDS.iter().any(|name| ident == name) {
+            return Err(Error::new(
+                ident.span(),
+                format!("unknown lane kind, expected one of: {}", KINDS.join(", ")),
+            ));
+        }
+        kind = Some(ident);
+    }
+
+    kind.ok_or_else(|| {
+        Error::new(
+            variant.span(),
+            "missing `#[lane(..)]` attribute specifying the kind of this lane",
+        )
+    })
+}
@@ -0,0 +1,18 @@
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+#![doc = include_str!("../README.md")]
+
+mod lanes;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Declares the lanes of an app as a fieldless enum.
+///
+/// See the [crate-level documentation](crate).
+#[proc_macro_derive(Lanes, attributes(lane))]
+pub fn derive_lanes(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    lanes::derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
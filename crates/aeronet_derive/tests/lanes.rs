@@ -0,0 +1,61 @@
+//! Tests for `#[derive(Lanes)]`.
+
+use aeronet::lane::{LaneIndex, LaneKind, Lanes};
+
+#[derive(Debug, Clone, Copy, Lanes)]
+enum AppLane {
+    #[lane(UnreliableUnordered)]
+    Movement,
+    #[lane(UnreliableSequenced)]
+    Position,
+    #[lane(ReliableUnordered)]
+    Events,
+    #[lane(ReliableOrdered)]
+    Chat,
+}
+
+#[derive(Debug, Clone, Copy, Lanes)]
+enum NoLanes {}
+
+#[test]
+fn indices() {
+    assert_eq!(LaneIndex::from_raw(0), AppLane::Movement.into());
+    assert_eq!(LaneIndex::from_raw(1), AppLane::Position.into());
+    assert_eq!(LaneIndex::from_raw(2), AppLane::Events.into());
+    assert_eq!(LaneIndex::from_raw(3), AppLane::Chat.into());
+}
+
+#[test]
+fn kinds() {
+    assert_eq!(LaneKind::UnreliableUnordered, AppLane::Movement.into());
+    assert_eq!(LaneKind::UnreliableSequenced, AppLane::Position.into());
+    assert_eq!(LaneKind::ReliableUnordered, AppLane::Events.into());
+    assert_eq!(LaneKind::ReliableOrdered, AppLane::Chat.into());
+}
+
+#[test]
+fn kinds_in_index_order() {
+    assert_eq!(
+        &[
+            LaneKind::UnreliableUnordered,
+            LaneKind::UnreliableSequenced,
+            LaneKind::ReliableUnordered,
+            LaneKind::ReliableOrdered,
+        ],
+        AppLane::KINDS
+    );
+    for lane in [
+        AppLane::Movement,
+        AppLane::Position,
+        AppLane::Events,
+        AppLane::Chat,
+    ] {
+        let index = LaneIndex::from(lane).into_raw() as usize;
+        assert_eq!(AppLane::KINDS[index], LaneKind::from(lane));
+    }
+}
+
+#[test]
+fn empty() {
+    assert_eq!(&[] as &[LaneKind], NoLanes::KINDS);
+}
@@ -0,0 +1,145 @@
+//! Command-line tool for dissecting packet captures written by
+//! `aeronet_proto::capture::PacketCapture`.
+
+use std::{fs::File, io::BufReader, path::PathBuf, process::ExitCode};
+
+use aeronet::error::pretty_error;
+use aeronet_proto::{
+    capture::{read_capture, CaptureRecord, Direction},
+    dissect::{dissect, Timeline},
+};
+
+/// Dissects an aeronet packet capture.
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// Path to the capture file.
+    path: PathBuf,
+    /// Only show records for this connection ID.
+    #[arg(long)]
+    conn: Option<u64>,
+    /// Show the timeline of messages on each connection, instead of every
+    /// packet.
+    #[arg(long)]
+    timeline: bool,
+}
+
+fn main() -> ExitCode {
+    let args = <Args as clap::Parser>::parse();
+    let capture = match File::open(&args.path)
+        .map_err(aeronet_proto::capture::CaptureReadError::Io)
+        .and_then(|file| read_capture(BufReader::new(file)))
+    {
+        Ok(capture) => capture,
+        Err(err) => {
+            eprintln!(
+                "Failed to read {}: {:#}",
+                args.path.display(),
+                pretty_error(&err)
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let records = capture
+        .records
+        .iter()
+        .filter(|record| args.conn.map_or(true, |conn| record.conn() == conn));
+    if args.timeline {
+        print_timeline(&Timeline::new(records));
+    } else {
+        for record in records {
+            print_record(record);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+const fn arrow(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Send => "->",
+        Direction::Recv => "<-",
+    }
+}
+
+fn print_record(record: &CaptureRecord) {
+    match record {
+        CaptureRecord::Open { conn, at, side } => {
+            println!(
+                "[{:>12.6}s] conn {conn} opened as {side:?}",
+                at.as_secs_f64()
+            );
+        }
+        CaptureRecord::Packet {
+            conn,
+            at,
+            direction,
+            packet,
+        } => {
+            let prefix = format!(
+                "[{:>12.6}s] conn {conn} {} {:>5}B",
+                at.as_secs_f64(),
+                arrow(*direction),
+                packet.len()
+            );
+            let packet = match dissect(packet.clone()) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    println!("{prefix} invalid: {:#}", pretty_error(&err));
+                    return;
+                }
+            };
+            let acks = packet.acks().map(|seq| seq.0 .0).collect::<Vec<_>>();
+            println!("{prefix} packet {} acks {acks:?}", packet.header.seq.0 .0);
+            for frag in &packet.frags {
+                println!(
+                    "{:>17}lane {} msg {} frag {}{} {}B",
+                    "",
+                    frag.header.lane_index.into_raw(),
+                    frag.header.msg_seq.0 .0,
+                    frag.header.marker.index(),
+                    if frag.header.marker.is_last() {
+                        " (last)"
+                    } else {
+                        ""
+                    },
+                    frag.payload.len()
+                );
+            }
+        }
+    }
+}
+
+fn print_timeline(timeline: &Timeline) {
+    for (id, conn) in &timeline.conns {
+        let side = conn
+            .side
+            .map_or_else(|| "unknown side".to_owned(), |side| format!("{side:?}"));
+        println!(
+            "conn {id} ({side}): {} packets ({} invalid), {} messages",
+            conn.packets,
+            conn.invalid_packets,
+            conn.msgs.len()
+        );
+        for msg in &conn.msgs {
+            let fmt_at = |at: Option<_>| {
+                at.map_or_else(
+                    || "-".to_owned(),
+                    |at: web_time::Duration| format!("{:.6}s", at.as_secs_f64()),
+                )
+            };
+            println!(
+                "  [{:>12.6}s] {} lane {} msg {}: {}B in {} frags ({} resent), complete {}, acked {}",
+                msg.first_seen_at.as_secs_f64(),
+                arrow(msg.direction),
+                msg.lane.into_raw(),
+                msg.seq.0 .0,
+                msg.len,
+                msg.num_frags
+                    .map_or_else(|| "?".to_owned(), |n| n.to_string()),
+                msg.resent_frags(),
+                fmt_at(msg.completed_at),
+                fmt_at(msg.acked_at),
+            );
+        }
+    }
+}
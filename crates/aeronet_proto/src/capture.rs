@@ -0,0 +1,402 @@
+//! Capture format for recording the raw packets sent and received by
+//! [`Session`]s, for offline debugging.
+//!
+//! **This is for debugging purposes only!** A capture holds the full contents
+//! of every packet, so be careful about where you store it.
+//!
+//! # Usage
+//!
+//! Create a [`PacketCapture`] which writes to a file, and set it as the
+//! [`SessionConfig::capture`] of the config you pass to your transport. Every
+//! session created from this config - on a server, one per connected client -
+//! will then write each packet it flushes or receives into the capture,
+//! tagged with a connection ID, the time, and the direction.
+//!
+//! Since packets are captured at the [`Session`] level, they are captured
+//! before being passed through any encryption layer (see `crypto`).
+//!
+//! The capture can be read back using [`read_capture`], and decoded using the
+//! [`dissect`] module, or the `aeronet_dissect` command-line tool (enabled by
+//! the `dissect-cli` feature).
+//!
+//! # Format
+//!
+//! All integers are little-endian. The capture starts with a header:
+//!
+//! ```text
+//! magic: [u8; 4] = b"AERC"
+//! version: u8 = 1
+//! start_unix_nanos: u64
+//! ```
+//!
+//! Followed by any number of records:
+//!
+//! ```text
+//! kind: u8 (0 = open, 1 = packet)
+//! conn: u64
+//! at_nanos: u64 (time since the start of the capture)
+//! if kind == open:
+//!     side: u8 (0 = client, 1 = server)
+//! if kind == packet:
+//!     direction: u8 (0 = send, 1 = recv)
+//!     len: u32
+//!     packet: [u8; len]
+//! ```
+//!
+//! [`Session`]: crate::session::Session
+//! [`SessionConfig::capture`]: crate::session::SessionConfig::capture
+//! [`dissect`]: crate::dissect
+
+use std::{
+    fmt, io,
+    sync::{Arc, Mutex},
+};
+
+use octs::{Buf, Bytes};
+use web_time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MAGIC: [u8; 4] = *b"AERC";
+
+const VERSION: u8 = 1;
+
+const HEADER_LEN: usize = MAGIC.len() + 1 + 8;
+
+const KIND_OPEN: u8 = 0;
+
+const KIND_PACKET: u8 = 1;
+
+/// Which side of a connection a [`Session`] is on.
+///
+/// [`Session`]: crate::session::Session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// Session was created with [`Session::client`].
+    ///
+    /// [`Session::client`]: crate::session::Session::client
+    Client,
+    /// Session was created with [`Session::server`].
+    ///
+    /// [`Session::server`]: crate::session::Session::server
+    Server,
+}
+
+/// Whether a captured packet was sent or received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Packet was produced by [`Session::flush`].
+    ///
+    /// [`Session::flush`]: crate::session::Session::flush
+    Send,
+    /// Packet was passed into [`Session::recv`].
+    ///
+    /// [`Session::recv`]: crate::session::Session::recv
+    Recv,
+}
+
+/// Writes the packets of [`Session`]s into a capture.
+///
+/// This is a cheaply cloneable handle, and all clones write into the same
+/// capture. Two handles are equal if they write into the same capture.
+///
+/// Writing the capture never causes session operations to fail. If writing
+/// fails, capturing stops, and the error is returned from the next
+/// [`PacketCapture::flush`].
+///
+/// See the [module-level documentation](self).
+///
+/// [`Session`]: crate::session::Session
+#[derive(Clone)]
+pub struct PacketCapture {
+    start: Instant,
+    writer: Arc<Mutex<CaptureWriter>>,
+}
+
+struct CaptureWriter {
+    writer: Box<dyn io::Write + Send>,
+    buf: Vec<u8>,
+    error: Option<io::Error>,
+    next_conn: u64,
+}
+
+impl fmt::Debug for PacketCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketCapture")
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for PacketCapture {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.writer, &other.writer)
+    }
+}
+
+impl Eq for PacketCapture {}
+
+impl CaptureWriter {
+    fn write(&mut self, f: impl FnOnce(&mut Vec<u8>)) {
+        if self.error.is_some() {
+            return;
+        }
+        self.buf.clear();
+        f(&mut self.buf);
+        if let Err(err) = self.writer.write_all(&self.buf) {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl PacketCapture {
+    /// Creates a new capture which writes into `writer`.
+    ///
+    /// The timestamps of all records are relative to `start`.
+    pub fn new(start: Instant, writer: impl io::Write + Send + 'static) -> Self {
+        let start_unix_nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| {
+                u64::try_from(since.as_nanos()).unwrap_or(u64::MAX)
+            });
+
+        let mut writer = CaptureWriter {
+            writer: Box::new(writer),
+            buf: Vec::new(),
+            error: None,
+            next_conn: 0,
+        };
+        writer.write(|buf| {
+            buf.extend_from_slice(&MAGIC);
+            buf.push(VERSION);
+            buf.extend_from_slice(&start_unix_nanos.to_le_bytes());
+        });
+        Self {
+            start,
+            writer: Arc::new(Mutex::new(writer)),
+        }
+    }
+
+    /// Flushes the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Errors if writing to or flushing the writer has failed. After an error,
+    /// no more records are written.
+    #[allow(clippy::missing_panics_doc)] // shouldn't panic
+    pub fn flush(&self) -> io::Result<()> {
+        let mut writer = self.writer.lock().expect("lock should not be poisoned");
+        if let Some(err) = writer.error.take() {
+            // keep capturing stopped, but report the original error once
+            writer.error = Some(io::Error::new(err.kind(), "capture stopped"));
+            return Err(err);
+        }
+        let res = writer.writer.flush();
+        if let Err(err) = &res {
+            writer.error = Some(io::Error::new(err.kind(), "capture stopped"));
+        }
+        res
+    }
+
+    fn at_nanos(&self, now: Instant) -> u64 {
+        u64::try_from(now.saturating_duration_since(self.start).as_nanos()).unwrap_or(u64::MAX)
+    }
+
+    pub(crate) fn open(&self, now: Instant, side: Side) -> SessionCapture {
+        let at_nanos = self.at_nanos(now);
+        let mut writer = self.writer.lock().expect("lock should not be poisoned");
+        let conn = writer.next_conn;
+        writer.next_conn += 1;
+        writer.write(|buf| {
+            buf.push(KIND_OPEN);
+            buf.extend_from_slice(&conn.to_le_bytes());
+            buf.extend_from_slice(&at_nanos.to_le_bytes());
+            buf.push(match side {
+                Side::Client => 0,
+                Side::Server => 1,
+            });
+        });
+        drop(writer);
+
+        SessionCapture {
+            capture: self.clone(),
+            conn,
+        }
+    }
+}
+
+/// [`PacketCapture`] handle owned by a single session.
+#[derive(Debug)]
+pub(crate) struct SessionCapture {
+    capture: PacketCapture,
+    conn: u64,
+}
+
+impl SessionCapture {
+    pub fn record(&self, now: Instant, direction: Direction, packet: &[u8]) {
+        let at_nanos = self.capture.at_nanos(now);
+        let Ok(len) = u32::try_from(packet.len()) else {
+            return;
+        };
+        let mut writer = self
+            .capture
+            .writer
+            .lock()
+            .expect("lock should not be poisoned");
+        writer.write(|buf| {
+            buf.push(KIND_PACKET);
+            buf.extend_from_slice(&self.conn.to_le_bytes());
+            buf.extend_from_slice(&at_nanos.to_le_bytes());
+            buf.push(match direction {
+                Direction::Send => 0,
+                Direction::Recv => 1,
+            });
+            buf.extend_from_slice(&len.to_le_bytes());
+            buf.extend_from_slice(packet);
+        });
+    }
+}
+
+/// Capture read back by [`read_capture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// When the capture was started, as the time since the Unix epoch.
+    pub started_at: Duration,
+    /// Records in the order they were written.
+    pub records: Vec<CaptureRecord>,
+}
+
+/// Single entry in a [`Capture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureRecord {
+    /// A [`Session`] was created.
+    ///
+    /// [`Session`]: crate::session::Session
+    Open {
+        /// ID of the connection that the session manages.
+        conn: u64,
+        /// Time since the start of the capture.
+        at: Duration,
+        /// Which side of the connection the session is on.
+        side: Side,
+    },
+    /// A session sent or received a packet.
+    Packet {
+        /// ID of the connection that the packet was sent or received on.
+        conn: u64,
+        /// Time since the start of the capture.
+        at: Duration,
+        /// Whether the packet was sent or received.
+        direction: Direction,
+        /// Raw packet bytes.
+        packet: Bytes,
+    },
+}
+
+impl CaptureRecord {
+    /// Gets the ID of the connection that this record belongs to.
+    #[must_use]
+    pub const fn conn(&self) -> u64 {
+        match self {
+            Self::Open { conn, .. } | Self::Packet { conn, .. } => *conn,
+        }
+    }
+
+    /// Gets the time since the start of the capture at which this record was
+    /// written.
+    #[must_use]
+    pub const fn at(&self) -> Duration {
+        match self {
+            Self::Open { at, .. } | Self::Packet { at, .. } => *at,
+        }
+    }
+}
+
+/// Failed to read a capture using [`read_capture`].
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureReadError {
+    /// Failed to read from the underlying reader.
+    #[error("failed to read capture")]
+    Io(#[source] io::Error),
+    /// Capture does not start with a valid header.
+    #[error("invalid header")]
+    InvalidHeader,
+    /// Capture ended in the middle of a record.
+    #[error("capture is truncated")]
+    Truncated,
+    /// Capture contains a record which could not be decoded.
+    #[error("invalid record")]
+    InvalidRecord,
+}
+
+/// Reads a capture written by a [`PacketCapture`].
+///
+/// A capture which was cut off in the middle of a record, i.e. because the
+/// app was killed while it was capturing, fails with
+/// [`CaptureReadError::Truncated`].
+///
+/// # Errors
+///
+/// Errors if the capture could not be read, or is not a valid capture.
+pub fn read_capture(mut reader: impl io::Read) -> Result<Capture, CaptureReadError> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).map_err(CaptureReadError::Io)?;
+    let mut buf = Bytes::from(buf);
+
+    if buf.len() < HEADER_LEN
+        || buf.split_to(MAGIC.len()) != MAGIC.as_slice()
+        || buf.get_u8() != VERSION
+    {
+        return Err(CaptureReadError::InvalidHeader);
+    }
+    let started_at = Duration::from_nanos(buf.get_u64_le());
+
+    let mut records = Vec::new();
+    while buf.has_remaining() {
+        records.push(read_record(&mut buf)?);
+    }
+    Ok(Capture {
+        started_at,
+        records,
+    })
+}
+
+fn read_record(buf: &mut Bytes) -> Result<CaptureRecord, CaptureReadError> {
+    const PREFIX_LEN: usize = 1 + 8 + 8 + 1;
+
+    if buf.remaining() < PREFIX_LEN {
+        return Err(CaptureReadError::Truncated);
+    }
+    let kind = buf.get_u8();
+    let conn = buf.get_u64_le();
+    let at = Duration::from_nanos(buf.get_u64_le());
+    match kind {
+        KIND_OPEN => {
+            let side = match buf.get_u8() {
+                0 => Side::Client,
+                1 => Side::Server,
+                _ => return Err(CaptureReadError::InvalidRecord),
+            };
+            Ok(CaptureRecord::Open { conn, at, side })
+        }
+        KIND_PACKET => {
+            let direction = match buf.get_u8() {
+                0 => Direction::Send,
+                1 => Direction::Recv,
+                _ => return Err(CaptureReadError::InvalidRecord),
+            };
+            if buf.remaining() < 4 {
+                return Err(CaptureReadError::Truncated);
+            }
+            let len = buf.get_u32_le() as usize;
+            if buf.remaining() < len {
+                return Err(CaptureReadError::Truncated);
+            }
+            Ok(CaptureRecord::Packet {
+                conn,
+                at,
+                direction,
+                packet: buf.split_to(len),
+            })
+        }
+        _ => Err(CaptureReadError::InvalidRecord),
+    }
+}
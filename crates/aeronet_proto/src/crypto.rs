@@ -0,0 +1,503 @@
+//! Optional authenticated encryption layer for packets produced and consumed
+//! by a [`Session`].
+//!
+//! Transports which send packets over an unencrypted medium (i.e. raw UDP) can
+//! use this to make sure that packets can't be read or tampered with by a
+//! third party. Transports which already provide encryption, like
+//! WebTransport, don't need this.
+//!
+//! # Usage
+//!
+//! 1. Each side creates a [`KeyExchange`] and sends its
+//!    [`KeyExchange::public_key`] to the peer, as part of the transport's
+//!    connection handshake.
+//! 2. Once the peer's public key is received, each side calls
+//!    [`KeyExchange::finish`] to derive a [`PacketCipher`] holding the keys for
+//!    this connection.
+//! 3. Pass the cipher to [`Session::with_cipher`] right after creating the
+//!    session.
+//!
+//! From then on, every packet produced by [`Session::flush`] is sealed, and
+//! every packet passed to [`Session::recv`] is opened first. Packets which fail
+//! to open are dropped without any further processing.
+//!
+//! # Protocol
+//!
+//! Packets are encrypted using ChaCha20-Poly1305, with separate keys for the
+//! client-to-server and server-to-client directions. These keys are derived
+//! from an X25519 shared secret using HKDF-SHA256.
+//!
+//! The nonce of each packet is the sequence number from its [`PacketHeader`],
+//! extended to 64 bits so that it never wraps around for the lifetime of a
+//! connection. This is prefixed to the sealed packet, and the receiving side
+//! uses a sliding window to reject packets which it has already opened.
+//!
+//! [`Session`]: crate::session::Session
+//! [`Session::with_cipher`]: crate::session::Session::with_cipher
+//! [`Session::flush`]: crate::session::Session::flush
+//! [`Session::recv`]: crate::session::Session::recv
+//! [`PacketHeader`]: crate::ty::PacketHeader
+
+use std::fmt;
+
+use chacha20poly1305::{aead::AeadInPlace, ChaCha20Poly1305, Key, KeyInit, Nonce, Tag};
+use hkdf::Hkdf;
+use octs::{Bytes, BytesMut, FixedEncodeLen, Read, Write};
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::ty::PacketSeq;
+
+/// Length in bytes of a public key exchanged in a [`KeyExchange`].
+pub const PUBLIC_KEY_LEN: usize = 32;
+
+/// Length in bytes of a symmetric key used by a [`PacketCipher`].
+pub const KEY_LEN: usize = 32;
+
+/// Length in bytes of the extended packet sequence prefixed to each sealed
+/// packet.
+pub const SEQ_LEN: usize = u64::ENCODE_LEN;
+
+/// Length in bytes of the authentication tag appended to each sealed packet.
+pub const TAG_LEN: usize = 16;
+
+/// How many more bytes a packet sealed by [`PacketCipher::seal`] takes up than
+/// its plaintext.
+///
+/// The MTU passed to the session must be reduced by this amount.
+pub const OVERHEAD: usize = SEQ_LEN + TAG_LEN;
+
+/// How many packets behind the latest received packet we will still accept.
+const REPLAY_WINDOW_LEN: u64 = u64::BITS as u64;
+
+/// How much the extended sequence advances each time a [`PacketSeq`] wraps
+/// around.
+const SEQ_EPOCH_LEN: u64 = 1 << u16::BITS;
+
+const CLIENT_TO_SERVER_INFO: &[u8] = b"aeronet client to server";
+const SERVER_TO_CLIENT_INFO: &[u8] = b"aeronet server to client";
+
+/// Which side of a connection a [`KeyExchange`] is performed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// The side which initiated the connection.
+    Client,
+    /// The side which accepted the connection.
+    Server,
+}
+
+/// Failed to [`KeyExchange::finish`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum KeyExchangeError {
+    /// The peer's public key was a low-order point, so the shared secret does
+    /// not depend on our secret key.
+    ///
+    /// This is a sign that the peer is acting maliciously.
+    #[error("peer public key is not contributory")]
+    NonContributory,
+}
+
+/// Failed to [`PacketCipher::open`] a packet.
+///
+/// The packet must be dropped, but the connection does not need to be closed,
+/// since anyone who can send packets to us can cause these errors.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum OpenError {
+    /// Packet was too short to contain a packet sequence and tag.
+    #[error("packet too short")]
+    TooShort,
+    /// Packet has the sequence of a packet which we have already opened, or
+    /// which is too old to be tracked.
+    #[error("packet replayed or too old")]
+    Replayed,
+    /// Packet failed authentication, i.e. it was tampered with or was not
+    /// sealed with the peer's key.
+    #[error("packet failed authentication")]
+    Authentication,
+}
+
+/// Ephemeral X25519 key exchange used to derive a [`PacketCipher`] for a
+/// single connection.
+///
+/// See the [module-level documentation](self).
+pub struct KeyExchange {
+    secret: EphemeralSecret,
+    public: PublicKey,
+}
+
+impl fmt::Debug for KeyExchange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyExchange")
+            .field("public", &self.public)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for KeyExchange {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KeyExchange {
+    /// Generates a new random key pair using the OS random number generator.
+    #[must_use]
+    pub fn new() -> Self {
+        let secret = EphemeralSecret::random();
+        let public = PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    /// Gets the public key which must be sent to the peer.
+    #[must_use]
+    pub fn public_key(&self) -> [u8; PUBLIC_KEY_LEN] {
+        self.public.to_bytes()
+    }
+
+    /// Completes the key exchange using the peer's public key, and derives the
+    /// keys used for this connection.
+    ///
+    /// `side` is the side of the connection that *we* are on.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the peer's public key is invalid.
+    #[allow(clippy::missing_panics_doc)] // shouldn't panic
+    pub fn finish(
+        self,
+        side: Side,
+        peer_public_key: [u8; PUBLIC_KEY_LEN],
+    ) -> Result<PacketCipher, KeyExchangeError> {
+        let peer_public = PublicKey::from(peer_public_key);
+        let shared = self.secret.diffie_hellman(&peer_public);
+        if !shared.was_contributory() {
+            return Err(KeyExchangeError::NonContributory);
+        }
+
+        // bind the derived keys to both public keys
+        let (client_public, server_public) = match side {
+            Side::Client => (self.public, peer_public),
+            Side::Server => (peer_public, self.public),
+        };
+        let mut salt = [0; PUBLIC_KEY_LEN * 2];
+        salt[..PUBLIC_KEY_LEN].copy_from_slice(client_public.as_bytes());
+        salt[PUBLIC_KEY_LEN..].copy_from_slice(server_public.as_bytes());
+
+        let hkdf = Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes());
+        let mut client_to_server = [0; KEY_LEN];
+        let mut server_to_client = [0; KEY_LEN];
+        hkdf.expand(CLIENT_TO_SERVER_INFO, &mut client_to_server)
+            .expect("key length should be a valid HKDF-SHA256 output length");
+        hkdf.expand(SERVER_TO_CLIENT_INFO, &mut server_to_client)
+            .expect("key length should be a valid HKDF-SHA256 output length");
+
+        Ok(match side {
+            Side::Client => PacketCipher::new(client_to_server, server_to_client),
+            Side::Server => PacketCipher::new(server_to_client, client_to_server),
+        })
+    }
+}
+
+/// Seals outgoing packets and opens incoming packets for a single connection.
+///
+/// This is usually created by [`KeyExchange::finish`].
+///
+/// This intentionally does not implement [`Clone`], since two copies of a
+/// cipher would seal different packets using the same nonce.
+///
+/// See the [module-level documentation](self).
+pub struct PacketCipher {
+    send: ChaCha20Poly1305,
+    recv: ChaCha20Poly1305,
+    last_send_seq: Option<u64>,
+    replay: ReplayWindow,
+}
+
+impl fmt::Debug for PacketCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PacketCipher")
+            .field("last_send_seq", &self.last_send_seq)
+            .field("replay", &self.replay)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PacketCipher {
+    /// Creates a cipher from already-derived keys.
+    ///
+    /// The peer must use our `send_key` as its receive key, and vice versa.
+    /// The same keys must never be used for more than one connection.
+    #[must_use]
+    pub fn new(send_key: [u8; KEY_LEN], recv_key: [u8; KEY_LEN]) -> Self {
+        Self {
+            send: ChaCha20Poly1305::new(Key::from_slice(&send_key)),
+            recv: ChaCha20Poly1305::new(Key::from_slice(&recv_key)),
+            last_send_seq: None,
+            replay: ReplayWindow::default(),
+        }
+    }
+
+    /// Encrypts and authenticates a packet with the sequence `seq`.
+    ///
+    /// `seq` should be the sequence in the packet's [`PacketHeader`], and is
+    /// used as the nonce after extending it to 64 bits. If `seq` is not newer
+    /// than the last sealed packet's sequence, it is treated as having wrapped
+    /// around, so a nonce is never reused even if the same `seq` is passed
+    /// twice.
+    ///
+    /// The output is [`OVERHEAD`] bytes longer than `packet`.
+    ///
+    /// [`PacketHeader`]: crate::ty::PacketHeader
+    #[allow(clippy::missing_panics_doc)] // shouldn't panic
+    pub fn seal(&mut self, seq: PacketSeq, packet: &[u8]) -> Bytes {
+        let seq = self.extend_send_seq(seq);
+
+        let mut buf = BytesMut::with_capacity(packet.len() + OVERHEAD);
+        buf.write(seq)
+            .expect("BytesMut should grow the buffer when writing over capacity");
+        buf.extend_from_slice(packet);
+        let tag = self
+            .send
+            .encrypt_in_place_detached(&nonce(seq), &[], &mut buf[SEQ_LEN..])
+            .expect("packet should not be too long to encrypt");
+        buf.extend_from_slice(&tag);
+        buf.freeze()
+    }
+
+    fn extend_send_seq(&mut self, seq: PacketSeq) -> u64 {
+        let seq = u64::from(seq.0 .0);
+        let extended = self.last_send_seq.map_or(seq, |last| {
+            let extended = (last & !(SEQ_EPOCH_LEN - 1)) | seq;
+            if extended > last {
+                extended
+            } else {
+                extended + SEQ_EPOCH_LEN
+            }
+        });
+        self.last_send_seq = Some(extended);
+        extended
+    }
+
+    /// Authenticates and decrypts a packet sealed by the peer.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the packet could not be opened. In this case, the packet must
+    /// be dropped.
+    pub fn open(&mut self, packet: &[u8]) -> Result<Bytes, OpenError> {
+        if packet.len() < OVERHEAD {
+            return Err(OpenError::TooShort);
+        }
+        let (mut header, rest) = packet.split_at(SEQ_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+        let seq = header.read::<u64>().map_err(|_| OpenError::TooShort)?;
+
+        // cheap check before doing any crypto work
+        if !self.replay.can_accept(seq) {
+            return Err(OpenError::Replayed);
+        }
+
+        let mut buf = BytesMut::from(ciphertext);
+        self.recv
+            .decrypt_in_place_detached(&nonce(seq), &[], &mut buf, Tag::from_slice(tag))
+            .map_err(|_| OpenError::Authentication)?;
+        // only track the sequence once we know the packet is authentic,
+        // otherwise an attacker could advance our window
+        self.replay.accept(seq);
+        Ok(buf.freeze())
+    }
+}
+
+fn nonce(seq: u64) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[4..].copy_from_slice(&seq.to_le_bytes());
+    nonce
+}
+
+/// Tracks which packet sequences have been received recently.
+#[derive(Debug, Clone, Default)]
+struct ReplayWindow {
+    /// Highest sequence received so far.
+    last_recv: Option<u64>,
+    /// Bit `n` is set if the packet `last_recv - n` has been received.
+    bits: u64,
+}
+
+impl ReplayWindow {
+    const fn can_accept(&self, seq: u64) -> bool {
+        let Some(last_recv) = self.last_recv else {
+            return true;
+        };
+        if seq > last_recv {
+            return true;
+        }
+        let delta = last_recv - seq;
+        delta < REPLAY_WINDOW_LEN && self.bits & (1 << delta) == 0
+    }
+
+    fn accept(&mut self, seq: u64) {
+        match self.last_recv {
+            Some(last_recv) if seq <= last_recv => {
+                self.bits |= 1 << (last_recv - seq);
+            }
+            Some(last_recv) => {
+                let shift = seq - last_recv;
+                self.bits = if shift < REPLAY_WINDOW_LEN {
+                    self.bits << shift
+                } else {
+                    0
+                };
+                self.bits |= 1;
+                self.last_recv = Some(seq);
+            }
+            None => {
+                self.bits = 1;
+                self.last_recv = Some(seq);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn ciphers() -> (PacketCipher, PacketCipher) {
+        let client = KeyExchange::new();
+        let server = KeyExchange::new();
+        let client_public = client.public_key();
+        let server_public = server.public_key();
+        (
+            client.finish(Side::Client, server_public).unwrap(),
+            server.finish(Side::Server, client_public).unwrap(),
+        )
+    }
+
+    #[test]
+    fn round_trip() {
+        let (mut client, mut server) = ciphers();
+
+        let sealed = client.seal(PacketSeq::new(0), b"hello");
+        assert_eq!(b"hello".len() + OVERHEAD, sealed.len());
+        assert_eq!(b"hello"[..], server.open(&sealed).unwrap());
+
+        let sealed = server.seal(PacketSeq::new(0), b"world");
+        assert_eq!(b"world"[..], client.open(&sealed).unwrap());
+    }
+
+    #[test]
+    fn empty_packet() {
+        let (mut client, mut server) = ciphers();
+        let sealed = client.seal(PacketSeq::new(0), &[]);
+        assert_eq!(OVERHEAD, sealed.len());
+        assert!(server.open(&sealed).unwrap().is_empty());
+    }
+
+    #[test]
+    fn directions_use_different_keys() {
+        let (mut client, mut server) = ciphers();
+        let sealed = client.seal(PacketSeq::new(0), b"hello");
+        assert_matches!(client.open(&sealed), Err(OpenError::Authentication));
+        assert_eq!(b"hello"[..], server.open(&sealed).unwrap());
+    }
+
+    #[test]
+    fn tampered() {
+        let (mut client, mut server) = ciphers();
+        let sealed = client.seal(PacketSeq::new(0), b"hello");
+
+        let mut tampered = sealed.to_vec();
+        tampered[SEQ_LEN] ^= 1;
+        assert_matches!(server.open(&tampered), Err(OpenError::Authentication));
+
+        let mut tampered = sealed.to_vec();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_matches!(server.open(&tampered), Err(OpenError::Authentication));
+
+        // changing the sequence changes the nonce
+        let mut tampered = sealed.to_vec();
+        tampered[SEQ_LEN - 1] ^= 1;
+        assert_matches!(server.open(&tampered), Err(OpenError::Authentication));
+
+        // failed packets don't affect the real one
+        assert_eq!(b"hello"[..], server.open(&sealed).unwrap());
+    }
+
+    #[test]
+    fn too_short() {
+        let (_, mut server) = ciphers();
+        assert_matches!(server.open(&[0; OVERHEAD - 1]), Err(OpenError::TooShort));
+    }
+
+    #[test]
+    fn wrong_keys() {
+        let (mut client, _) = ciphers();
+        let (_, mut other_server) = ciphers();
+        let sealed = client.seal(PacketSeq::new(0), b"hello");
+        assert_matches!(other_server.open(&sealed), Err(OpenError::Authentication));
+    }
+
+    #[test]
+    fn replayed() {
+        let (mut client, mut server) = ciphers();
+        let sealed = client.seal(PacketSeq::new(0), b"hello");
+        server.open(&sealed).unwrap();
+        assert_matches!(server.open(&sealed), Err(OpenError::Replayed));
+    }
+
+    #[test]
+    fn out_of_order() {
+        let (mut client, mut server) = ciphers();
+        let packets = (0..4u8)
+            .map(|i| client.seal(PacketSeq::new(u16::from(i)), &[i]))
+            .collect::<Vec<_>>();
+
+        assert_eq!([3][..], server.open(&packets[3]).unwrap());
+        assert_eq!([1][..], server.open(&packets[1]).unwrap());
+        assert_eq!([0][..], server.open(&packets[0]).unwrap());
+        assert_eq!([2][..], server.open(&packets[2]).unwrap());
+        assert_matches!(server.open(&packets[1]), Err(OpenError::Replayed));
+    }
+
+    #[test]
+    fn too_old() {
+        let (mut client, mut server) = ciphers();
+        let old = client.seal(PacketSeq::new(0), b"old");
+        for seq in 1..=REPLAY_WINDOW_LEN {
+            let sealed = client.seal(PacketSeq::new(seq as u16), b"new");
+            server.open(&sealed).unwrap();
+        }
+        assert_matches!(server.open(&old), Err(OpenError::Replayed));
+    }
+
+    #[test]
+    fn seq_wraps_around() {
+        let (mut client, mut server) = ciphers();
+        let before = client.seal(PacketSeq::new(u16::MAX), b"before");
+        let after = client.seal(PacketSeq::new(0), b"after");
+        assert_eq!(u64::from(u16::MAX).to_be_bytes(), before[..SEQ_LEN]);
+        assert_eq!(SEQ_EPOCH_LEN.to_be_bytes(), after[..SEQ_LEN]);
+        assert_eq!(b"before"[..], server.open(&before).unwrap());
+        assert_eq!(b"after"[..], server.open(&after).unwrap());
+    }
+
+    #[test]
+    fn same_seq_uses_new_nonce() {
+        let (mut client, mut server) = ciphers();
+        let first = client.seal(PacketSeq::new(3), b"first");
+        let second = client.seal(PacketSeq::new(3), b"second");
+        assert_ne!(first[..SEQ_LEN], second[..SEQ_LEN]);
+        assert_eq!(b"first"[..], server.open(&first).unwrap());
+        assert_eq!(b"second"[..], server.open(&second).unwrap());
+    }
+
+    #[test]
+    fn low_order_public_key() {
+        assert_matches!(
+            KeyExchange::new().finish(Side::Client, [0; PUBLIC_KEY_LEN]),
+            Err(KeyExchangeError::NonContributory)
+        );
+    }
+}
@@ -0,0 +1,397 @@
+//! Decodes captured packets into their protocol-level parts, for offline
+//! debugging.
+//!
+//! Use [`dissect`] to decode a single packet into its [`PacketHeader`] and
+//! [`Fragment`]s, or [`Timeline`] to follow the messages sent over each
+//! connection in a [`Capture`].
+//!
+//! [`Capture`]: crate::capture::Capture
+
+use std::{collections::BTreeMap, convert::Infallible};
+
+use aeronet::lane::LaneIndex;
+use ahash::{AHashMap, AHashSet};
+use octs::{Buf, BufTooShortOr, Bytes, Read};
+use web_time::Duration;
+
+use crate::{
+    capture::{CaptureRecord, Direction, Side},
+    msg::FragmentDecodeError,
+    ty::{Fragment, MessageSeq, PacketHeader, PacketSeq},
+};
+
+/// Packet decoded by [`dissect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DissectedPacket {
+    /// Header of the packet.
+    pub header: PacketHeader,
+    /// Fragments contained in the packet, in the order they were written.
+    pub frags: Vec<Fragment>,
+}
+
+impl DissectedPacket {
+    /// Gets the sequence numbers of the peer's packets which this packet
+    /// acknowledges.
+    pub fn acks(&self) -> impl Iterator<Item = PacketSeq> {
+        self.header.acks.seqs()
+    }
+}
+
+/// Failed to [`dissect`] a packet.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum DissectError {
+    /// Failed to decode the packet header.
+    #[error("failed to decode header")]
+    Header(#[source] BufTooShortOr<Infallible>),
+    /// Failed to decode a fragment.
+    #[error("failed to decode fragment {index}")]
+    Fragment {
+        /// Index of the fragment in the packet.
+        index: usize,
+        /// Why the fragment could not be decoded.
+        #[source]
+        source: BufTooShortOr<FragmentDecodeError>,
+    },
+}
+
+/// Decodes a single packet produced by [`Session::flush`].
+///
+/// # Errors
+///
+/// Errors if the packet is not a valid protocol packet.
+///
+/// [`Session::flush`]: crate::session::Session::flush
+pub fn dissect(packet: impl Into<Bytes>) -> Result<DissectedPacket, DissectError> {
+    let mut packet: Bytes = packet.into();
+    let header = packet
+        .read::<PacketHeader>()
+        .map_err(DissectError::Header)?;
+    let mut frags = Vec::new();
+    while packet.has_remaining() {
+        let frag = packet
+            .read::<Fragment>()
+            .map_err(|source| DissectError::Fragment {
+                index: frags.len(),
+                source,
+            })?;
+        frags.push(frag);
+    }
+    Ok(DissectedPacket { header, frags })
+}
+
+/// Timeline of the messages sent over each connection in a capture.
+///
+/// Messages are identified by their direction, lane, and message sequence
+/// number, and are built up from the fragments seen in the captured packets:
+/// * a message is *complete* once every one of its fragments has been seen at
+///   least once
+/// * a message is *acked* once every one of its fragments has been in a
+///   packet which the peer acknowledged
+///
+/// For sent messages, seeing a fragment more than once means that it was
+/// re-sent. For received messages, it means the peer re-sent it or the
+/// packet was duplicated in transit.
+///
+/// Packets which could not be [dissected](dissect) are counted, but otherwise
+/// ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timeline {
+    /// Timelines of each connection, keyed by connection ID.
+    pub conns: BTreeMap<u64, ConnectionTimeline>,
+}
+
+/// Timeline of the messages sent over a single connection.
+///
+/// See [`Timeline`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionTimeline {
+    /// Which side of the connection the capturing session was on, if the
+    /// session's creation was captured.
+    pub side: Option<Side>,
+    /// When the session was created, if it was captured.
+    pub opened_at: Option<Duration>,
+    /// Number of packets captured on this connection.
+    pub packets: usize,
+    /// Number of packets which could not be dissected.
+    pub invalid_packets: usize,
+    /// Messages sent over this connection, in the order that their first
+    /// fragment was seen.
+    pub msgs: Vec<MessageTimeline>,
+}
+
+/// Timeline of a single message.
+///
+/// See [`Timeline`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageTimeline {
+    /// Whether the message was sent or received by the capturing session.
+    pub direction: Direction,
+    /// Lane on which the message was sent.
+    pub lane: LaneIndex,
+    /// Sequence number of the message on its lane.
+    pub seq: MessageSeq,
+    /// Number of fragments in the message, if its last fragment has been
+    /// seen.
+    pub num_frags: Option<usize>,
+    /// Total payload length of the distinct fragments seen.
+    pub len: usize,
+    /// Number of fragments seen, including ones which were seen more than
+    /// once.
+    pub frags_seen: usize,
+    /// When the first fragment of this message was seen.
+    pub first_seen_at: Duration,
+    /// When the message became complete.
+    pub completed_at: Option<Duration>,
+    /// When the message was acked by the receiver.
+    pub acked_at: Option<Duration>,
+}
+
+impl MessageTimeline {
+    /// Gets how many fragments were seen more than once.
+    #[must_use]
+    pub fn resent_frags(&self) -> usize {
+        self.frags_seen - self.num_frags.unwrap_or(self.frags_seen)
+    }
+}
+
+type MessagePath = (Direction, LaneIndex, MessageSeq);
+
+#[derive(Debug, Default)]
+struct ConnectionBuilder {
+    timeline: ConnectionTimeline,
+    // messages which have not been acked yet
+    active_msgs: AHashMap<MessagePath, MessageBuilder>,
+    // which fragments were sent in each packet, so we can mark them as acked
+    packet_frags: AHashMap<(Direction, PacketSeq), Vec<(MessagePath, u8)>>,
+}
+
+#[derive(Debug)]
+struct MessageBuilder {
+    index: usize,
+    seen_frags: AHashSet<u8>,
+    acked_frags: AHashSet<u8>,
+}
+
+impl Timeline {
+    /// Builds the timeline of the connections in a capture's records.
+    #[must_use]
+    pub fn new<'a>(records: impl IntoIterator<Item = &'a CaptureRecord>) -> Self {
+        let mut conns = BTreeMap::<u64, ConnectionBuilder>::new();
+        for record in records {
+            let conn = conns.entry(record.conn()).or_default();
+            match record {
+                CaptureRecord::Open { at, side, .. } => {
+                    conn.timeline.side = Some(*side);
+                    conn.timeline.opened_at = Some(*at);
+                }
+                CaptureRecord::Packet {
+                    at,
+                    direction,
+                    packet,
+                    ..
+                } => conn.on_packet(*at, *direction, packet.clone()),
+            }
+        }
+
+        Self {
+            conns: conns
+                .into_iter()
+                .map(|(id, conn)| (id, conn.timeline))
+                .collect(),
+        }
+    }
+}
+
+const fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::Send => Direction::Recv,
+        Direction::Recv => Direction::Send,
+    }
+}
+
+impl ConnectionBuilder {
+    fn on_packet(&mut self, at: Duration, direction: Direction, packet: Bytes) {
+        self.timeline.packets += 1;
+        let Ok(packet) = dissect(packet) else {
+            self.timeline.invalid_packets += 1;
+            return;
+        };
+
+        // this packet acks packets which went in the opposite direction
+        for acked_seq in packet.acks() {
+            let Some(frags) = self.packet_frags.remove(&(opposite(direction), acked_seq)) else {
+                continue;
+            };
+            for (path, frag_index) in frags {
+                self.on_frag_acked(at, path, frag_index);
+            }
+        }
+
+        let mut frags = Vec::with_capacity(packet.frags.len());
+        for frag in packet.frags {
+            let path = (direction, frag.header.lane_index, frag.header.msg_seq);
+            self.on_frag(at, path, &frag);
+            frags.push((path, frag.header.marker.index()));
+        }
+        self.packet_frags
+            .insert((direction, packet.header.seq), frags);
+    }
+
+    fn on_frag(&mut self, at: Duration, path: MessagePath, frag: &Fragment) {
+        let msgs = &mut self.timeline.msgs;
+        let builder = self.active_msgs.entry(path).or_insert_with(|| {
+            let (direction, lane, seq) = path;
+            msgs.push(MessageTimeline {
+                direction,
+                lane,
+                seq,
+                num_frags: None,
+                len: 0,
+                frags_seen: 0,
+                first_seen_at: at,
+                completed_at: None,
+                acked_at: None,
+            });
+            MessageBuilder {
+                index: msgs.len() - 1,
+                seen_frags: AHashSet::new(),
+                acked_frags: AHashSet::new(),
+            }
+        });
+        let msg = &mut msgs[builder.index];
+
+        msg.frags_seen += 1;
+        let marker = frag.header.marker;
+        if builder.seen_frags.insert(marker.index()) {
+            msg.len += frag.payload.len();
+        }
+        if marker.is_last() {
+            msg.num_frags = Some(usize::from(marker.index()) + 1);
+        }
+        if msg.completed_at.is_none() && msg.num_frags == Some(builder.seen_frags.len()) {
+            msg.completed_at = Some(at);
+        }
+    }
+
+    fn on_frag_acked(&mut self, at: Duration, path: MessagePath, frag_index: u8) {
+        let Some(builder) = self.active_msgs.get_mut(&path) else {
+            return;
+        };
+        builder.acked_frags.insert(frag_index);
+
+        let msg = &mut self.timeline.msgs[builder.index];
+        if msg.num_frags == Some(builder.acked_frags.len()) {
+            msg.acked_at = Some(at);
+            // a later message with the same path, after the sequence number
+            // wraps around, is a different message
+            self.active_msgs.remove(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use aeronet::lane::LaneKind;
+    use web_time::Instant;
+
+    use crate::{
+        capture::{read_capture, PacketCapture},
+        session::{Session, SessionConfig},
+    };
+
+    use super::*;
+
+    const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+    #[derive(Debug, Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dissect_flushed_packet() {
+        let now = Instant::now();
+        let config = SessionConfig::default().with_lanes([LaneKind::ReliableOrdered]);
+        let mut session = Session::client(now, config, 1024, 1024).unwrap();
+        session.send(now, &b"hello"[..], LANE).unwrap();
+        session.send(now, &b"world"[..], LANE).unwrap();
+        let packet = session.flush(now).next().unwrap();
+
+        let packet = dissect(packet).unwrap();
+        assert_eq!(PacketSeq::new(0), packet.header.seq);
+        assert_eq!(0, packet.acks().count());
+        assert_eq!(2, packet.frags.len());
+        for frag in &packet.frags {
+            assert_eq!(LANE, frag.header.lane_index);
+            assert!(frag.header.marker.is_last());
+        }
+    }
+
+    #[test]
+    fn dissect_invalid() {
+        assert!(matches!(dissect(&[1, 2][..]), Err(DissectError::Header(_))));
+    }
+
+    #[test]
+    fn capture_timeline() {
+        let now = Instant::now();
+        let buf = SharedBuf::default();
+        let capture = PacketCapture::new(now, buf.clone());
+        let config = SessionConfig::default()
+            .with_lanes([LaneKind::ReliableOrdered])
+            .with_capture(capture.clone());
+
+        let mut client = Session::client(now, config.clone(), 1024, 1024).unwrap();
+        let mut server = Session::server(now, config, 1024, 1024).unwrap();
+
+        // long enough to be split into multiple fragments
+        client.send(now, vec![0; 2000], LANE).unwrap();
+        let later = now + Duration::from_millis(10);
+        for packet in client.flush(later) {
+            let (_, msgs) = server.recv(later, packet).unwrap();
+            msgs.for_each_msg(|res| drop(res.unwrap()));
+        }
+        let latest = later + Duration::from_millis(10);
+        for packet in server.flush(latest) {
+            let (acks, _) = client.recv(latest, packet).unwrap();
+            assert_eq!(1, acks.count());
+        }
+        capture.flush().unwrap();
+
+        let capture = read_capture(buf.0.lock().unwrap().as_slice()).unwrap();
+        let timeline = Timeline::new(&capture.records);
+        assert_eq!(2, timeline.conns.len());
+
+        let client_conn = &timeline.conns[&0];
+        assert_eq!(Some(Side::Client), client_conn.side);
+        assert_eq!(0, client_conn.invalid_packets);
+        let [msg] = client_conn.msgs.as_slice() else {
+            panic!("expected 1 message");
+        };
+        assert_eq!(Direction::Send, msg.direction);
+        assert_eq!(2000, msg.len);
+        assert_eq!(Some(later), msg.completed_at.map(|at| now + at));
+        assert_eq!(Some(latest), msg.acked_at.map(|at| now + at));
+        assert_eq!(0, msg.resent_frags());
+
+        let server_conn = &timeline.conns[&1];
+        assert_eq!(Some(Side::Server), server_conn.side);
+        let [msg] = server_conn.msgs.as_slice() else {
+            panic!("expected 1 message");
+        };
+        assert_eq!(Direction::Recv, msg.direction);
+        assert_eq!(msg.num_frags, client_conn.msgs[0].num_frags);
+        assert_eq!(Some(later), msg.completed_at.map(|at| now + at));
+        assert_eq!(Some(latest), msg.acked_at.map(|at| now + at));
+    }
+}
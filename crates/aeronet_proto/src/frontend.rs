@@ -0,0 +1,176 @@
+//! Frontend half of a transport which drives a [`Session`] over a connection
+//! managed by a backend task.
+//!
+//! Many transports are split into a *frontend*, which the user polls and sends
+//! messages through, and a *backend* async task, which owns the underlying
+//! connection. The frontend turns messages into packets using a [`Session`],
+//! passes them to the backend to send, and reads the packets which the backend
+//! receives. [`SessionFrontend`] implements this frontend side, so that each
+//! transport only has to implement its backend and map the events.
+
+use std::num::Saturating;
+
+use aeronet::{client::DisconnectReason, error::pretty_error, lane::LaneIndex};
+use futures::channel::{mpsc, oneshot};
+use octs::Bytes;
+use tracing::{debug, trace};
+use web_time::{Duration, Instant};
+
+use crate::session::{FatalSendError, MessageKey, OutOfMemory, SendError, Session};
+
+/// Frontend state of a connection, which exchanges packets produced by its
+/// [`Session`] with a backend task.
+///
+/// `E` is the transport's error type, which the backend reports the
+/// connection closing with.
+#[derive(Debug)]
+pub struct SessionFrontend<E> {
+    /// Session which messages are sent and received through.
+    pub session: Session,
+    /// Receives the reason for the connection closing from the backend.
+    pub recv_dc: oneshot::Receiver<DisconnectReason<E>>,
+    /// Sends flushed packets to the backend.
+    pub send_packets: mpsc::UnboundedSender<Bytes>,
+    /// Receives packets from the backend.
+    pub recv_packets: mpsc::Receiver<Bytes>,
+    /// Tells the backend to close the connection with the given reason.
+    pub send_local_dc: oneshot::Sender<String>,
+    /// Error which caused the session to become unusable while sending, if
+    /// any.
+    pub fatal_error: Option<FatalSendError>,
+}
+
+/// Event raised by [`SessionFrontend::poll`].
+#[derive(Debug)]
+pub enum FrontendEvent {
+    /// The peer acknowledged a message which we sent.
+    Ack {
+        /// Key of the acknowledged message.
+        msg_key: MessageKey,
+    },
+    /// We received a message from the peer.
+    Recv {
+        /// Message received.
+        msg: Bytes,
+        /// Lane on which the message was received.
+        lane: LaneIndex,
+    },
+}
+
+/// Error raised by a [`SessionFrontend`].
+///
+/// Intentionally does not implement [`std::error::Error`], so that transports
+/// are forced to map each variant to their own error variant.
+#[derive(Debug)]
+pub enum FrontendError<E> {
+    /// Transport-specific error reported by the backend.
+    Spec(E),
+    /// Backend task was closed without reporting a reason.
+    BackendClosed,
+    /// See [`OutOfMemory`].
+    OutOfMemory(OutOfMemory),
+    /// See [`SendError`].
+    Send(SendError),
+    /// See [`FatalSendError`].
+    FatalSend(FatalSendError),
+}
+
+impl<E> SessionFrontend<E> {
+    /// Buffers up a message to be sent on the next [`SessionFrontend::flush`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the message could not be buffered. If this is a
+    /// [`FrontendError::FatalSend`], it is also stored in
+    /// [`SessionFrontend::fatal_error`], and the connection should be closed.
+    pub fn send(&mut self, msg: Bytes, lane: LaneIndex) -> Result<MessageKey, FrontendError<E>> {
+        let err = match self.session.send(Instant::now(), msg, lane) {
+            Ok(key) => {
+                return Ok(key);
+            }
+            Err(err) => err,
+        };
+
+        match err.narrow::<FatalSendError, _>() {
+            Ok(err) => {
+                self.fatal_error = Some(err.clone());
+                Err(FrontendError::FatalSend(err))
+            }
+            Err(err) => Err(FrontendError::Send(err.take())),
+        }
+    }
+
+    /// Flushes all packets which the session wants to send out to the backend.
+    pub fn flush(&mut self) {
+        let mut bytes_sent = Saturating(0usize);
+        for packet in self.session.flush(Instant::now()) {
+            bytes_sent += packet.len();
+            // ignore errors here, pick them up in `poll`
+            let _ = self.send_packets.unbounded_send(packet);
+        }
+
+        let bytes_sent = bytes_sent.0;
+        if bytes_sent > 0 {
+            trace!(bytes_sent, "Flushed packets");
+        }
+    }
+
+    /// Reads all packets received by the backend, passing the events which
+    /// they produce to `cb`, and updates the session.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the connection was closed, in which case it should be
+    /// dropped.
+    pub fn poll(
+        &mut self,
+        delta_time: Duration,
+        mut cb: impl FnMut(FrontendEvent),
+    ) -> Result<(), DisconnectReason<FrontendError<E>>> {
+        if let Some(reason) = self
+            .recv_dc
+            .try_recv()
+            .map_err(|_| FrontendError::BackendClosed)?
+        {
+            return Err(reason.map_err(FrontendError::Spec));
+        }
+
+        let mut bytes_recv = Saturating(0usize);
+        while let Ok(Some(packet)) = self.recv_packets.try_next() {
+            bytes_recv += packet.len();
+            let (acks, msgs) = match self.session.recv(Instant::now(), packet) {
+                Ok(x) => x,
+                Err(err) => {
+                    debug!("Error while reading packet: {:#}", pretty_error(&err));
+                    continue;
+                }
+            };
+
+            for (lane, seq) in acks {
+                cb(FrontendEvent::Ack {
+                    msg_key: MessageKey::from_raw(lane, seq),
+                });
+            }
+
+            msgs.for_each_msg(|res| match res {
+                Ok((msg, lane)) => {
+                    cb(FrontendEvent::Recv { msg, lane });
+                }
+                Err(err) => {
+                    debug!("Error while reading packet: {:#}", pretty_error(&err));
+                }
+            });
+        }
+
+        self.session
+            .update(delta_time)
+            .map_err(FrontendError::OutOfMemory)?;
+
+        let bytes_recv = bytes_recv.0;
+        if bytes_recv > 0 {
+            trace!(bytes_recv, "Received packets");
+        }
+
+        Ok(())
+    }
+}
@@ -7,13 +7,25 @@ pub use terrors;
 pub mod ty;
 
 pub mod ack;
+pub mod capture;
+pub mod dissect;
 pub mod limit;
 pub mod msg;
 pub mod packet;
 pub mod rtt;
 pub mod seq;
 pub mod session;
+pub mod sim;
 pub mod stats;
 
+#[cfg(feature = "crypto")]
+pub mod crypto;
+
+#[cfg(feature = "frontend")]
+pub mod frontend;
+
+#[cfg(feature = "token")]
+pub mod token;
+
 #[cfg(feature = "visualizer")]
 pub mod visualizer;
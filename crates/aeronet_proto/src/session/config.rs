@@ -1,5 +1,7 @@
 use aeronet::lane::LaneKind;
 
+use crate::capture::PacketCapture;
+
 /// Configuration for a [`Session`].
 ///
 /// Not all session-specific configurations are exposed here. Transport-specific
@@ -50,6 +52,13 @@ pub struct SessionConfig {
     /// [`Session::flush`]: crate::session::Session::flush
     /// [`Session::update`]: crate::session::Session::update
     pub send_bytes_per_sec: usize,
+    /// Capture which every session created from this config writes its
+    /// packets into.
+    ///
+    /// By default, this is [`None`], so packets are not captured.
+    ///
+    /// See [`capture`](crate::capture).
+    pub capture: Option<PacketCapture>,
 }
 
 impl Default for SessionConfig {
@@ -59,6 +68,7 @@ impl Default for SessionConfig {
             server_lanes: Vec::new(),
             max_memory_usage: 4 * 1024 * 1024,
             send_bytes_per_sec: usize::MAX,
+            capture: None,
         }
     }
 }
@@ -118,4 +128,11 @@ impl SessionConfig {
         self.send_bytes_per_sec = send_bytes_per_sec;
         self
     }
+
+    /// Sets [`SessionConfig::capture`] on this value.
+    #[must_use]
+    pub fn with_capture(mut self, capture: PacketCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
 }
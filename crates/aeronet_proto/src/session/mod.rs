@@ -15,7 +15,10 @@ use derivative::Derivative;
 use octs::{Bytes, FixedEncodeLenHint};
 use web_time::{Duration, Instant};
 
+#[cfg(feature = "crypto")]
+use crate::crypto::{self, PacketCipher};
 use crate::{
+    capture::{SessionCapture, Side},
     limit::TokenBucket,
     msg::{FragmentReceiver, MessageSplitter},
     rtt::{RttEstimator, INITIAL_RTT},
@@ -59,6 +62,12 @@ pub struct Session {
     #[data_size(skip)]
     bytes_recv: Saturating<usize>,
     rtt: RttEstimator,
+
+    #[data_size(skip)]
+    capture: Option<SessionCapture>,
+    #[cfg(feature = "crypto")]
+    #[data_size(skip)]
+    cipher: Option<PacketCipher>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, DataSize)]
@@ -310,6 +319,12 @@ impl Session {
             packets_acked: Saturating(0),
             bytes_recv: Saturating(0),
             rtt: RttEstimator::new(INITIAL_RTT),
+
+            capture: config
+                .capture
+                .map(|capture| capture.open(now, if CLIENT { Side::Client } else { Side::Server })),
+            #[cfg(feature = "crypto")]
+            cipher: None,
         })
     }
 
@@ -404,6 +419,65 @@ impl Session {
         }
     }
 
+    /// Encrypts and authenticates every packet of this session using `cipher`.
+    ///
+    /// From now on, every packet produced by [`Session::flush`] is sealed,
+    /// and every packet passed to [`Session::recv`] is opened first. Packets
+    /// which fail to open are rejected with [`RecvError::Open`], and must be
+    /// dropped. The peer's session must also use a cipher.
+    ///
+    /// Sealing makes each packet [`crypto::OVERHEAD`] bytes longer, so the
+    /// session fits that much less data into each packet. The MTUs of this
+    /// session should still be those of the underlying transport.
+    ///
+    /// See [`crypto`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the minimum MTU is too small to fit both [`OVERHEAD`] and
+    /// [`crypto::OVERHEAD`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if this session has already sent any packets or messages. This
+    /// must be called right after the session is created.
+    #[cfg(feature = "crypto")]
+    pub fn with_cipher(mut self, cipher: PacketCipher) -> Result<Self, MtuTooSmall> {
+        assert!(
+            self.packets_sent.0 == 0
+                && self.send_lanes.iter().all(|lane| lane.sent_msgs.is_empty()),
+            "cipher must be set before sending anything"
+        );
+        let min = OVERHEAD + crypto::OVERHEAD;
+        if self.min_mtu < min {
+            return Err(MtuTooSmall {
+                min,
+                mtu: self.min_mtu,
+            });
+        }
+
+        // both sides must split and reassemble messages with the same payload
+        // length, which the peer also reduces when it sets its cipher
+        let max_payload_len = self.min_mtu - min;
+        self.splitter = MessageSplitter::new(max_payload_len);
+        for lane in &mut self.recv_lanes {
+            lane.frags = FragmentReceiver::new(max_payload_len);
+        }
+        self.cipher = Some(cipher);
+        Ok(self)
+    }
+
+    /// Gets how many bytes of each packet, on top of [`OVERHEAD`], are
+    /// reserved for the transformations applied after flushing it.
+    #[cfg_attr(not(feature = "crypto"), allow(clippy::unused_self))]
+    const fn packet_overhead(&self) -> usize {
+        #[cfg(feature = "crypto")]
+        if self.cipher.is_some() {
+            return crypto::OVERHEAD;
+        }
+        0
+    }
+
     /// Gets the current RTT estimation state.
     #[must_use]
     pub const fn rtt(&self) -> &RttEstimator {
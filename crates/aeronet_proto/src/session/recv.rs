@@ -7,6 +7,7 @@ use tracing::{field, trace, trace_span};
 use web_time::Instant;
 
 use crate::{
+    capture::Direction,
     msg::{FragmentDecodeError, ReassembleError},
     rtt::RttEstimator,
     seq::SeqBuf,
@@ -33,6 +34,12 @@ pub enum RecvError {
     /// Failed to reassemble a fragment into a message.
     #[error("failed to reassemble message")]
     Reassemble(#[source] ReassembleError),
+    /// Failed to open a packet sealed by the peer's cipher.
+    ///
+    /// See [`Session::with_cipher`].
+    #[cfg(feature = "crypto")]
+    #[error("failed to open packet")]
+    Open(#[source] crate::crypto::OpenError),
 }
 
 impl Session {
@@ -77,7 +84,8 @@ impl Session {
     ///
     /// # Errors
     ///
-    /// Errors if the packet header is invalid.
+    /// Errors if the packet header is invalid, or if this session uses a
+    /// cipher and the packet could not be opened.
     pub fn recv(
         &mut self,
         now: Instant,
@@ -90,8 +98,17 @@ impl Session {
         RecvError,
     > {
         let mut packet: Bytes = packet.into();
+        let packet_len = packet.len();
+        #[cfg(feature = "crypto")]
+        if let Some(cipher) = &mut self.cipher {
+            // drop the packet before it can affect any of our state
+            packet = cipher.open(&packet).map_err(RecvError::Open)?;
+        }
+        if let Some(capture) = &self.capture {
+            capture.record(now, Direction::Recv, &packet);
+        }
         self.packets_recv += 1;
-        self.bytes_recv += packet.len();
+        self.bytes_recv += packet_len;
 
         let header = packet
             .read::<PacketHeader>()
@@ -7,6 +7,7 @@ use tracing::{trace, trace_span};
 use web_time::Instant;
 
 use crate::{
+    capture::Direction,
     limit::Limit,
     msg::MessageTooLarge,
     rtt::RttEstimator,
@@ -192,7 +193,11 @@ impl Session {
             .collect::<Vec<_>>();
 
         // sort by oldest sent to newest
-        frag_paths.sort_unstable_by(|(_, sent_at_a), (_, sent_at_b)| sent_at_a.cmp(sent_at_b));
+        // break ties by the frag's path, so that the packets we build don't
+        // depend on the iteration order of the lanes' hash maps
+        frag_paths.sort_unstable_by_key(|(path, sent_at)| {
+            (*sent_at, path.lane_index, path.msg_seq, path.frag_index)
+        });
 
         let mut frag_paths = frag_paths
             .into_iter()
@@ -214,7 +219,8 @@ impl Session {
 
             // we can't put more than either `mtu` or `bytes_left`
             // bytes into this packet, so we track this as well
-            let mut bytes_left = (&mut self.bytes_left).min_of(self.mtu);
+            let max_packet_len = self.mtu - self.packet_overhead();
+            let mut bytes_left = (&mut self.bytes_left).min_of(max_packet_len);
             let packet_seq = self.next_packet_seq;
             bytes_left.consume(PacketHeader::ENCODE_LEN).ok()?;
             packet
@@ -270,6 +276,14 @@ impl Session {
             );
 
             let packet = packet.freeze();
+            if let Some(capture) = &self.capture {
+                capture.record(now, Direction::Send, &packet);
+            }
+            #[cfg(feature = "crypto")]
+            let packet = match &mut self.cipher {
+                Some(cipher) => cipher.seal(packet_seq, &packet),
+                None => packet,
+            };
             self.packets_sent += 1;
             self.bytes_sent += packet.len();
             self.next_packet_seq += PacketSeq::ONE;
@@ -279,6 +293,32 @@ impl Session {
         })
     }
 
+    /// Prepares this session to continue over a new connection to the same
+    /// peer, after the previous connection was lost.
+    ///
+    /// Every fragment which has not been acknowledged yet is marked to be
+    /// sent again on the next [`Session::flush`], since the packets it was
+    /// last sent in were probably lost along with the old connection. The peer
+    /// must also resume its own session, rather than creating a new one, so
+    /// that the message and packet sequence numbers of both sides still line
+    /// up.
+    ///
+    /// This does not reset any statistics or the RTT estimate.
+    pub fn resume(&mut self, now: Instant) {
+        for lane in &mut self.send_lanes {
+            for frag in lane
+                .sent_msgs
+                .values_mut()
+                .flat_map(|msg| msg.frags.iter_mut())
+                .flatten()
+            {
+                frag.next_flush_at = now;
+            }
+        }
+        // let the peer know about our acks straight away
+        self.next_ack_at = now;
+    }
+
     fn frag_paths_in_lane(
         now: Instant,
         lane_index: usize,
@@ -377,3 +417,142 @@ impl Session {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use aeronet::lane::LaneKind;
+    use web_time::Duration;
+
+    use crate::session::SessionConfig;
+
+    use super::*;
+
+    const MTU: usize = 1024;
+    const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+    fn session(now: Instant) -> Session {
+        let config = SessionConfig::default().with_lanes([LaneKind::ReliableOrdered]);
+        Session::client(now, config, MTU, MTU).unwrap()
+    }
+
+    #[test]
+    fn unacked_not_resent_before_pto() {
+        let now = Instant::now();
+        let mut session = session(now);
+        session.send(now, b"hello".as_slice(), LANE).unwrap();
+        assert_eq!(1, session.flush(now).count());
+
+        let now = now + Duration::from_millis(1);
+        assert_eq!(0, session.flush(now).count());
+    }
+
+    #[test]
+    fn resume_resends_unacked() {
+        let now = Instant::now();
+        let mut session = session(now);
+        session.send(now, b"hello".as_slice(), LANE).unwrap();
+        assert_eq!(1, session.flush(now).count());
+
+        let now = now + Duration::from_millis(1);
+        session.resume(now);
+        let packets = session.flush(now).collect::<Vec<_>>();
+        assert_eq!(1, packets.len());
+        assert!(packets[0].ends_with(b"hello"));
+    }
+
+    #[cfg(feature = "crypto")]
+    mod crypto {
+        use assert_matches::assert_matches;
+        use octs::Read;
+
+        use crate::{
+            crypto::{KeyExchange, OpenError, Side, OVERHEAD as CIPHER_OVERHEAD, SEQ_LEN},
+            session::{RecvError, OVERHEAD},
+        };
+
+        use super::*;
+
+        fn sessions(now: Instant) -> (Session, Session) {
+            let config = SessionConfig::default().with_lanes([LaneKind::ReliableOrdered]);
+            let client_kx = KeyExchange::new();
+            let server_kx = KeyExchange::new();
+            let client_public = client_kx.public_key();
+            let server_public = server_kx.public_key();
+            (
+                Session::client(now, config.clone(), MTU, MTU)
+                    .unwrap()
+                    .with_cipher(client_kx.finish(Side::Client, server_public).unwrap())
+                    .unwrap(),
+                Session::server(now, config, MTU, MTU)
+                    .unwrap()
+                    .with_cipher(server_kx.finish(Side::Server, client_public).unwrap())
+                    .unwrap(),
+            )
+        }
+
+        #[test]
+        fn round_trip() {
+            let now = Instant::now();
+            let (mut client, mut server) = sessions(now);
+            client.send(now, b"hello".as_slice(), LANE).unwrap();
+            let packets = client.flush(now).collect::<Vec<_>>();
+            assert_eq!(1, packets.len());
+            assert!(!packets[0].windows(5).any(|w| w == b"hello"));
+
+            let (_, msgs) = server.recv(now, packets[0].clone()).unwrap();
+            let mut recv = Vec::new();
+            msgs.for_each_msg(|res| recv.push(res.unwrap()));
+            assert_eq!(vec![(Bytes::from_static(b"hello"), LANE)], recv);
+        }
+
+        #[test]
+        fn nonce_is_packet_seq() {
+            let now = Instant::now();
+            let (mut client, _) = sessions(now);
+            for seq in 0..3u64 {
+                client.next_ack_at = now;
+                let mut packet = client.flush(now).next().unwrap();
+                assert_eq!(seq, packet.read::<u64>().unwrap());
+            }
+        }
+
+        #[test]
+        fn tampered_dropped() {
+            let now = Instant::now();
+            let (mut client, mut server) = sessions(now);
+            client.send(now, b"hello".as_slice(), LANE).unwrap();
+            let packet = client.flush(now).next().unwrap();
+
+            let mut tampered = packet.to_vec();
+            tampered[SEQ_LEN] ^= 1;
+            assert_matches!(
+                server.recv(now, tampered).map(|_| ()),
+                Err(RecvError::Open(OpenError::Authentication))
+            );
+            assert_eq!(0, server.packets_recv.0);
+            assert!(server.recv(now, packet).is_ok());
+        }
+
+        #[test]
+        fn min_mtu_too_small() {
+            let now = Instant::now();
+            let cipher = KeyExchange::new()
+                .finish(Side::Client, KeyExchange::new().public_key())
+                .unwrap();
+            let session =
+                Session::client(now, SessionConfig::default(), OVERHEAD + 1, OVERHEAD + 1).unwrap();
+            let err = session.with_cipher(cipher).unwrap_err();
+            assert_eq!(OVERHEAD + CIPHER_OVERHEAD, err.min);
+        }
+
+        #[test]
+        fn packets_fit_in_mtu() {
+            let now = Instant::now();
+            let (mut client, _) = sessions(now);
+            client.send(now, vec![0; MTU * 4], LANE).unwrap();
+            let packets = client.flush(now).collect::<Vec<_>>();
+            assert!(packets.len() > 1);
+            assert!(packets.iter().all(|packet| packet.len() <= MTU));
+        }
+    }
+}
@@ -0,0 +1,872 @@
+//! Deterministic simulation of a client and server [`Session`] talking over a
+//! virtual network.
+//!
+//! **This is for testing purposes only!**
+//!
+//! A [`Sim`] owns a client and a server session, and connects them through a
+//! pair of virtual links - one for each direction. Time in the simulation only
+//! moves forward when you call [`Sim::step`], and all randomness comes from a
+//! seed, so running the same simulation with the same seed always produces the
+//! exact same result. This makes it useful for reproducibly testing how the
+//! protocol behaves under packet loss, reordering, duplication and latency.
+//!
+//! Every message sent through [`Sim::send`], and every message and ack
+//! received by each session, is logged. After running the simulation, use
+//! [`Sim::check_delivery`] and [`Sim::check_acked`] to verify that each lane
+//! kept its guarantees.
+//!
+//! # Usage
+//!
+//! ```
+//! # use aeronet::lane::{LaneIndex, LaneKind};
+//! # use aeronet_proto::{capture::Side, session::SessionConfig, sim::{Sim, SimConfig}};
+//! # use web_time::Duration;
+//! let session_config = SessionConfig::default().with_lanes([LaneKind::ReliableOrdered]);
+//! let sim_config = SimConfig {
+//!     loss_rate: 0.2,
+//!     duplicate_rate: 0.05,
+//!     latency: Duration::from_millis(50),
+//!     jitter: Duration::from_millis(20),
+//!     ..Default::default()
+//! };
+//! let mut sim = Sim::new(session_config, sim_config, 1234).unwrap();
+//!
+//! for i in 0..100u32 {
+//!     sim.send(Side::Client, i.to_le_bytes().to_vec(), LaneIndex::from_raw(0))
+//!         .unwrap();
+//! }
+//! sim.run(Duration::from_secs(10), Duration::from_millis(10)).unwrap();
+//!
+//! sim.check_delivery(Side::Client).unwrap();
+//! sim.check_acked(Side::Client).unwrap();
+//! ```
+//!
+//! [`Session`]: crate::session::Session
+
+use std::collections::BTreeMap;
+
+use aeronet::lane::{LaneIndex, LaneKind};
+use ahash::AHashMap;
+use octs::Bytes;
+use terrors::OneOf;
+use web_time::{Duration, Instant};
+
+use crate::{
+    capture::Side,
+    session::{
+        FatalSendError, MessageKey, MtuTooSmall, OutOfMemory, SendError, Session, SessionConfig,
+    },
+};
+
+/// Configuration for the virtual network of a [`Sim`].
+///
+/// The same conditions apply to both directions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimConfig {
+    /// Maximum length of a packet, used as both the minimum and initial MTU
+    /// of both sessions.
+    ///
+    /// Packets longer than this are dropped by the network.
+    ///
+    /// By default, this is 1200.
+    pub mtu: usize,
+    /// Chance of a packet being dropped in transit, in the range `0.0..=1.0`.
+    pub loss_rate: f32,
+    /// Chance of a packet being delivered twice, in the range `0.0..=1.0`.
+    pub duplicate_rate: f32,
+    /// Chance of a packet being held back by an extra [`SimConfig::reorder_delay`],
+    /// letting packets sent after it overtake it, in the range `0.0..=1.0`.
+    pub reorder_rate: f32,
+    /// Extra delay applied to packets which are reordered.
+    pub reorder_delay: Duration,
+    /// Time that each packet takes to reach the peer.
+    pub latency: Duration,
+    /// Maximum extra time, chosen uniformly at random, that each packet takes
+    /// to reach the peer on top of [`SimConfig::latency`].
+    pub jitter: Duration,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            mtu: 1200,
+            loss_rate: 0.0,
+            duplicate_rate: 0.0,
+            reorder_rate: 0.0,
+            reorder_delay: Duration::ZERO,
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+        }
+    }
+}
+
+/// Message logged by a [`SimPeer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimMessage {
+    /// Lane that the message was sent or received on.
+    pub lane: LaneIndex,
+    /// Message payload.
+    pub msg: Bytes,
+    /// Time since the start of the simulation at which the message was sent
+    /// or received.
+    pub at: Duration,
+}
+
+/// Counters for packets passing through one direction of the virtual network.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    /// Packets flushed into the link.
+    pub sent: usize,
+    /// Packets dropped due to [`SimConfig::loss_rate`].
+    pub lost: usize,
+    /// Packets dropped because they were longer than [`SimConfig::mtu`].
+    pub too_large: usize,
+    /// Extra copies of packets created due to [`SimConfig::duplicate_rate`].
+    pub duplicated: usize,
+    /// Packets held back due to [`SimConfig::reorder_rate`].
+    pub reordered: usize,
+    /// Packets received by the peer session.
+    pub delivered: usize,
+}
+
+/// One of the two peers in a [`Sim`].
+#[derive(Debug)]
+pub struct SimPeer {
+    session: Session,
+    lanes: Vec<LaneKind>,
+    sent: Vec<(SimMessage, MessageKey)>,
+    recv: Vec<SimMessage>,
+    acked: Vec<MessageKey>,
+    recv_errors: usize,
+}
+
+impl SimPeer {
+    /// Gets the session of this peer.
+    #[must_use]
+    pub const fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Gets a mutable reference to the session of this peer.
+    ///
+    /// Messages sent directly on the session, rather than through
+    /// [`Sim::send`], are not logged.
+    pub fn session_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    /// Gets all messages sent by this peer through [`Sim::send`], along with
+    /// the key of each message.
+    #[must_use]
+    pub fn sent(&self) -> &[(SimMessage, MessageKey)] {
+        &self.sent
+    }
+
+    /// Gets all messages received by this peer, in the order they were
+    /// received.
+    #[must_use]
+    pub fn recv(&self) -> &[SimMessage] {
+        &self.recv
+    }
+
+    /// Gets the keys of this peer's sent messages which were acked by the
+    /// other peer, in the order they were acked.
+    #[must_use]
+    pub fn acked(&self) -> &[MessageKey] {
+        &self.acked
+    }
+
+    /// Gets how many errors this peer's session reported while receiving
+    /// packets.
+    #[must_use]
+    pub const fn recv_errors(&self) -> usize {
+        self.recv_errors
+    }
+}
+
+/// A lane did not keep its guarantees, as found by [`Sim::check_delivery`]
+/// or [`Sim::check_acked`].
+///
+/// The `index` of each variant is the index of the message among all messages
+/// sent on that lane.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DeliveryError {
+    /// A message on a reliable lane was never received.
+    #[error("message {index} on lane {} was never received", lane.into_raw())]
+    Missing {
+        /// Lane of the message.
+        lane: LaneIndex,
+        /// Index of the message.
+        index: usize,
+    },
+    /// A message was received more than once.
+    #[error("message {index} on lane {} was received more than once", lane.into_raw())]
+    Duplicated {
+        /// Lane of the message.
+        lane: LaneIndex,
+        /// Index of the message.
+        index: usize,
+    },
+    /// A message on an ordered or sequenced lane was received after a message
+    /// which was sent later.
+    #[error("message {index} on lane {} was received out of order", lane.into_raw())]
+    OutOfOrder {
+        /// Lane of the message.
+        lane: LaneIndex,
+        /// Index of the message.
+        index: usize,
+    },
+    /// A message was received which was never sent.
+    #[error("received a message on lane {} which was never sent", lane.into_raw())]
+    Unexpected {
+        /// Lane of the message.
+        lane: LaneIndex,
+    },
+    /// A message on a reliable lane was never acked.
+    #[error("message {index} on lane {} was never acked", lane.into_raw())]
+    Unacked {
+        /// Lane of the message.
+        lane: LaneIndex,
+        /// Index of the message.
+        index: usize,
+    },
+}
+
+/// Deterministic simulation of a client and server [`Session`] talking over a
+/// virtual network.
+///
+/// See the [module-level documentation](self).
+#[derive(Debug)]
+pub struct Sim {
+    config: SimConfig,
+    rng: SplitMix64,
+    start: Instant,
+    now: Instant,
+    client: SimPeer,
+    server: SimPeer,
+    to_server: Link,
+    to_client: Link,
+}
+
+#[derive(Debug, Default)]
+struct Link {
+    // keyed by delivery time, then by the order the packet was scheduled in,
+    // so that packets with the same delivery time keep a deterministic order
+    in_flight: BTreeMap<(Instant, u64), Bytes>,
+    next_id: u64,
+    stats: LinkStats,
+}
+
+impl Sim {
+    /// Creates a new simulation with a freshly created client and server
+    /// session.
+    ///
+    /// All randomness in the simulation is derived from `seed`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if [`SimConfig::mtu`] is too small to create a session.
+    pub fn new(
+        session_config: SessionConfig,
+        config: SimConfig,
+        seed: u64,
+    ) -> Result<Self, MtuTooSmall> {
+        let now = Instant::now();
+        let peer = |session, lanes| SimPeer {
+            session,
+            lanes,
+            sent: Vec::new(),
+            recv: Vec::new(),
+            acked: Vec::new(),
+            recv_errors: 0,
+        };
+        let client_lanes = session_config.client_lanes.clone();
+        let server_lanes = session_config.server_lanes.clone();
+        let client = Session::client(now, session_config.clone(), config.mtu, config.mtu)?;
+        let server = Session::server(now, session_config, config.mtu, config.mtu)?;
+
+        Ok(Self {
+            config,
+            rng: SplitMix64(seed),
+            start: now,
+            now,
+            client: peer(client, client_lanes),
+            server: peer(server, server_lanes),
+            to_server: Link::default(),
+            to_client: Link::default(),
+        })
+    }
+
+    /// Gets the configuration of the virtual network.
+    #[must_use]
+    pub const fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// Sets the configuration of the virtual network, affecting packets which
+    /// are sent from now on.
+    ///
+    /// # Errors
+    ///
+    /// Errors if [`SimConfig::mtu`] is smaller than the minimum MTU of the
+    /// sessions, which is the MTU that the simulation was created with.
+    pub fn set_config(&mut self, config: SimConfig) -> Result<(), MtuTooSmall> {
+        self.client.session.set_mtu(config.mtu)?;
+        self.server.session.set_mtu(config.mtu)?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Gets the current simulated time.
+    #[must_use]
+    pub const fn now(&self) -> Instant {
+        self.now
+    }
+
+    /// Gets how much simulated time has passed since the simulation started.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.now - self.start
+    }
+
+    /// Gets the client peer.
+    #[must_use]
+    pub const fn client(&self) -> &SimPeer {
+        &self.client
+    }
+
+    /// Gets the client peer mutably.
+    pub fn client_mut(&mut self) -> &mut SimPeer {
+        &mut self.client
+    }
+
+    /// Gets the server peer.
+    #[must_use]
+    pub const fn server(&self) -> &SimPeer {
+        &self.server
+    }
+
+    /// Gets the server peer mutably.
+    pub fn server_mut(&mut self) -> &mut SimPeer {
+        &mut self.server
+    }
+
+    /// Gets a peer by its side.
+    #[must_use]
+    pub const fn peer(&self, side: Side) -> &SimPeer {
+        match side {
+            Side::Client => &self.client,
+            Side::Server => &self.server,
+        }
+    }
+
+    /// Gets the stats of the link which carries packets sent by `from`.
+    #[must_use]
+    pub const fn link_stats(&self, from: Side) -> &LinkStats {
+        match from {
+            Side::Client => &self.to_server.stats,
+            Side::Server => &self.to_client.stats,
+        }
+    }
+
+    /// Gets how many packets are currently in transit from `from`.
+    #[must_use]
+    pub fn in_flight(&self, from: Side) -> usize {
+        match from {
+            Side::Client => self.to_server.in_flight.len(),
+            Side::Server => self.to_client.in_flight.len(),
+        }
+    }
+
+    /// Sends a message from the given peer, logging it so that its delivery
+    /// can be checked later.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the session could not send the message - see
+    /// [`Session::send`].
+    pub fn send(
+        &mut self,
+        from: Side,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<MessageKey, OneOf<(SendError, FatalSendError)>> {
+        let at = self.elapsed();
+        let peer = match from {
+            Side::Client => &mut self.client,
+            Side::Server => &mut self.server,
+        };
+        let msg = msg.into();
+        let lane = lane.into();
+        let key = peer.session.send(self.now, msg.clone(), lane)?;
+        peer.sent.push((SimMessage { lane, msg, at }, key));
+        Ok(key)
+    }
+
+    /// Advances the simulation by `delta_time`.
+    ///
+    /// This moves the clock forward, updates both sessions, delivers all
+    /// packets which have arrived by the new time, then flushes both sessions
+    /// into the network.
+    ///
+    /// # Errors
+    ///
+    /// Errors if either session runs out of memory - see [`Session::update`].
+    pub fn step(&mut self, delta_time: Duration) -> Result<(), OutOfMemory> {
+        self.now += delta_time;
+        self.client.session.update(delta_time)?;
+        self.server.session.update(delta_time)?;
+
+        let at = self.elapsed();
+        Self::deliver(self.now, at, &mut self.to_client, &mut self.client);
+        Self::deliver(self.now, at, &mut self.to_server, &mut self.server);
+
+        for packet in self.client.session.flush(self.now) {
+            Self::schedule(
+                &self.config,
+                &mut self.rng,
+                self.now,
+                &mut self.to_server,
+                packet,
+            );
+        }
+        for packet in self.server.session.flush(self.now) {
+            Self::schedule(
+                &self.config,
+                &mut self.rng,
+                self.now,
+                &mut self.to_client,
+                packet,
+            );
+        }
+        Ok(())
+    }
+
+    /// Repeatedly [steps](Sim::step) the simulation by `step` until
+    /// `duration` of simulated time has passed.
+    ///
+    /// # Errors
+    ///
+    /// Errors if either session runs out of memory - see [`Session::update`].
+    pub fn run(&mut self, duration: Duration, step: Duration) -> Result<(), OutOfMemory> {
+        let end = self.now + duration;
+        while self.now < end {
+            self.step(step)?;
+        }
+        Ok(())
+    }
+
+    /// Repeatedly [steps](Sim::step) the simulation by `step` until `done`
+    /// returns `true`, or until `max_duration` of simulated time has passed.
+    ///
+    /// Returns whether `done` returned `true`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if either session runs out of memory - see [`Session::update`].
+    pub fn run_until(
+        &mut self,
+        max_duration: Duration,
+        step: Duration,
+        mut done: impl FnMut(&Self) -> bool,
+    ) -> Result<bool, OutOfMemory> {
+        let end = self.now + max_duration;
+        while self.now < end {
+            if done(self) {
+                return Ok(true);
+            }
+            self.step(step)?;
+        }
+        Ok(done(self))
+    }
+
+    fn schedule(
+        config: &SimConfig,
+        rng: &mut SplitMix64,
+        now: Instant,
+        link: &mut Link,
+        packet: Bytes,
+    ) {
+        link.stats.sent += 1;
+        if packet.len() > config.mtu {
+            link.stats.too_large += 1;
+            return;
+        }
+        if rng.chance(config.loss_rate) {
+            link.stats.lost += 1;
+            return;
+        }
+
+        let copies = if rng.chance(config.duplicate_rate) {
+            link.stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let mut delay = config.latency + config.jitter.mul_f32(rng.next_f32());
+            if rng.chance(config.reorder_rate) {
+                link.stats.reordered += 1;
+                delay += config.reorder_delay;
+            }
+            link.in_flight
+                .insert((now + delay, link.next_id), packet.clone());
+            link.next_id += 1;
+        }
+    }
+
+    fn deliver(now: Instant, at: Duration, link: &mut Link, peer: &mut SimPeer) {
+        while let Some(entry) = link.in_flight.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let packet = entry.remove();
+            link.stats.delivered += 1;
+
+            let Ok((acks, msgs)) = peer.session.recv(now, packet) else {
+                peer.recv_errors += 1;
+                continue;
+            };
+            peer.acked
+                .extend(acks.map(|(lane, seq)| MessageKey::from_raw(lane, seq)));
+            msgs.for_each_msg(|res| match res {
+                Ok((msg, lane)) => peer.recv.push(SimMessage { lane, msg, at }),
+                Err(_) => peer.recv_errors += 1,
+            });
+        }
+    }
+
+    /// Checks that every message sent by `from` through [`Sim::send`] was
+    /// received by the other peer according to the guarantees of its lane:
+    /// * all lanes: no message is received which was never sent
+    /// * all lanes except unreliable unordered: no message is received more
+    ///   than once
+    /// * reliable lanes: every message has been received
+    /// * ordered and sequenced lanes: messages are received in the order they
+    ///   were sent
+    ///
+    /// Messages are matched up by their payload, so each message sent on a
+    /// lane should have a distinct payload.
+    ///
+    /// Since messages may still be in transit, you should run the simulation
+    /// for long enough that all reliable messages have been received before
+    /// checking.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the first violation found.
+    pub fn check_delivery(&self, from: Side) -> Result<(), DeliveryError> {
+        let (sender, receiver) = self.sender_receiver(from);
+        for (lane_index, kind) in sender.lanes.iter().enumerate() {
+            let lane = LaneIndex::from_raw(lane_index as u64);
+            check_lane(sender, receiver, lane, *kind)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that every message sent by `from` through [`Sim::send`] on a
+    /// reliable lane has been acked by the other peer.
+    ///
+    /// # Errors
+    ///
+    /// Errors with the first message which has not been acked.
+    pub fn check_acked(&self, from: Side) -> Result<(), DeliveryError> {
+        let (sender, _) = self.sender_receiver(from);
+        for (lane_index, kind) in sender.lanes.iter().enumerate() {
+            if !is_reliable(*kind) {
+                continue;
+            }
+            let lane = LaneIndex::from_raw(lane_index as u64);
+            let unacked = sender
+                .sent
+                .iter()
+                .filter(|(msg, _)| msg.lane == lane)
+                .position(|(_, key)| !sender.acked.contains(key));
+            if let Some(index) = unacked {
+                return Err(DeliveryError::Unacked { lane, index });
+            }
+        }
+        Ok(())
+    }
+
+    const fn sender_receiver(&self, from: Side) -> (&SimPeer, &SimPeer) {
+        match from {
+            Side::Client => (&self.client, &self.server),
+            Side::Server => (&self.server, &self.client),
+        }
+    }
+}
+
+const fn is_reliable(kind: LaneKind) -> bool {
+    matches!(
+        kind,
+        LaneKind::ReliableUnordered | LaneKind::ReliableOrdered
+    )
+}
+
+fn check_lane(
+    sender: &SimPeer,
+    receiver: &SimPeer,
+    lane: LaneIndex,
+    kind: LaneKind,
+) -> Result<(), DeliveryError> {
+    // map each payload to the indices of the messages sent with it, and how
+    // many of those messages have been received so far
+    let mut sent = AHashMap::<&Bytes, (Vec<usize>, usize)>::new();
+    let mut num_sent = 0;
+    for (index, (msg, _)) in sender
+        .sent
+        .iter()
+        .filter(|(msg, _)| msg.lane == lane)
+        .enumerate()
+    {
+        sent.entry(&msg.msg).or_default().0.push(index);
+        num_sent = index + 1;
+    }
+    let mut received = vec![false; num_sent];
+
+    let mut last_index = None;
+    for msg in receiver.recv.iter().filter(|msg| msg.lane == lane) {
+        let Some((indices, num_received)) = sent.get_mut(&msg.msg) else {
+            return Err(DeliveryError::Unexpected { lane });
+        };
+        let Some(&index) = indices.get(*num_received) else {
+            if kind == LaneKind::UnreliableUnordered {
+                continue;
+            }
+            let index = *indices
+                .last()
+                .expect("should have sent at least one message");
+            return Err(DeliveryError::Duplicated { lane, index });
+        };
+        *num_received += 1;
+        received[index] = true;
+
+        if matches!(
+            kind,
+            LaneKind::UnreliableSequenced | LaneKind::ReliableOrdered
+        ) {
+            if last_index.is_some_and(|last| index < last) {
+                return Err(DeliveryError::OutOfOrder { lane, index });
+            }
+            last_index = Some(index);
+        }
+    }
+
+    if is_reliable(kind) {
+        if let Some(index) = received.iter().position(|received| !received) {
+            return Err(DeliveryError::Missing { lane, index });
+        }
+    }
+    Ok(())
+}
+
+/// Small, portable pseudo-random number generator.
+///
+/// We don't use `rand` here since its seedable RNGs are not guaranteed to
+/// produce the same values across versions, and a simulation must always
+/// produce the same result for the same seed.
+///
+/// See <https://prng.di.unimi.it/splitmix64.c>.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        // use the top 24 bits, which is all the precision an f32 mantissa has
+        (self.next_u64() >> 40) as f32 / (1u32 << 24) as f32
+    }
+
+    fn chance(&mut self, rate: f32) -> bool {
+        self.next_f32() < rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    fn lossy() -> SimConfig {
+        SimConfig {
+            loss_rate: 0.3,
+            duplicate_rate: 0.1,
+            reorder_rate: 0.2,
+            reorder_delay: Duration::from_millis(80),
+            latency: Duration::from_millis(30),
+            jitter: Duration::from_millis(30),
+            ..Default::default()
+        }
+    }
+
+    fn sim(lanes: impl IntoIterator<Item = LaneKind>, config: SimConfig, seed: u64) -> Sim {
+        let session_config = SessionConfig::default().with_lanes(lanes);
+        Sim::new(session_config, config, seed).unwrap()
+    }
+
+    fn send_many(sim: &mut Sim, from: Side, lane: LaneIndex, count: u32, len: usize) {
+        for i in 0..count {
+            let mut msg = vec![0; len];
+            msg[..4].copy_from_slice(&i.to_le_bytes());
+            sim.send(from, msg, lane).unwrap();
+        }
+    }
+
+    #[test]
+    fn perfect_network() {
+        const LANE: LaneIndex = LaneIndex::from_raw(0);
+        let mut sim = sim([LaneKind::ReliableOrdered], SimConfig::default(), 0);
+        send_many(&mut sim, Side::Client, LANE, 10, 16);
+
+        // client flushes, then server receives and flushes an ack
+        sim.step(STEP).unwrap();
+        sim.step(STEP).unwrap();
+        assert_eq!(10, sim.server().recv().len());
+        sim.check_delivery(Side::Client).unwrap();
+
+        // client receives the ack
+        sim.step(STEP).unwrap();
+        sim.check_acked(Side::Client).unwrap();
+        assert_eq!(0, sim.link_stats(Side::Client).lost);
+    }
+
+    #[test]
+    fn reliable_lanes_on_lossy_network() {
+        for seed in 0..8 {
+            let mut sim = sim(
+                [LaneKind::ReliableOrdered, LaneKind::ReliableUnordered],
+                lossy(),
+                seed,
+            );
+            for lane in [LaneIndex::from_raw(0), LaneIndex::from_raw(1)] {
+                // include messages large enough to be fragmented
+                send_many(&mut sim, Side::Client, lane, 50, 16);
+                send_many(&mut sim, Side::Server, lane, 5, 3000);
+            }
+
+            let done = sim
+                .run_until(Duration::from_secs(60), STEP, |sim| {
+                    sim.check_acked(Side::Client).is_ok() && sim.check_acked(Side::Server).is_ok()
+                })
+                .unwrap();
+            assert!(done, "seed {seed} did not finish");
+            sim.check_delivery(Side::Client).unwrap();
+            sim.check_delivery(Side::Server).unwrap();
+            assert!(sim.link_stats(Side::Client).lost > 0);
+        }
+    }
+
+    #[test]
+    fn unreliable_lanes_on_lossy_network() {
+        let mut sim = sim(
+            [LaneKind::UnreliableUnordered, LaneKind::UnreliableSequenced],
+            lossy(),
+            0,
+        );
+        for lane in [LaneIndex::from_raw(0), LaneIndex::from_raw(1)] {
+            send_many(&mut sim, Side::Client, lane, 100, 16);
+        }
+        sim.run(Duration::from_secs(2), STEP).unwrap();
+
+        sim.check_delivery(Side::Client).unwrap();
+        assert!(sim.server().recv().len() < 200);
+    }
+
+    #[test]
+    fn same_seed_same_result() {
+        let run = |seed| {
+            let mut sim = sim([LaneKind::ReliableOrdered], lossy(), seed);
+            send_many(&mut sim, Side::Client, LaneIndex::from_raw(0), 20, 500);
+            sim.run(Duration::from_secs(5), STEP).unwrap();
+            (
+                sim.link_stats(Side::Client).clone(),
+                sim.server().recv().to_vec(),
+            )
+        };
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42).0, run(43).0);
+    }
+
+    #[test]
+    fn rtt_converges_to_latency() {
+        let config = SimConfig {
+            latency: Duration::from_millis(50),
+            ..Default::default()
+        };
+        let mut sim = sim([LaneKind::ReliableOrdered], config, 0);
+        for _ in 0..100 {
+            sim.send(Side::Client, &b"ping"[..], LaneIndex::from_raw(0))
+                .unwrap();
+            sim.run(Duration::from_millis(100), STEP).unwrap();
+        }
+        let rtt = sim.client().session().rtt().get();
+        assert!(
+            (Duration::from_millis(100)..=Duration::from_millis(120)).contains(&rtt),
+            "{rtt:?}"
+        );
+    }
+
+    #[test]
+    fn detects_violations() {
+        const LANE: LaneIndex = LaneIndex::from_raw(0);
+        let mut sim = sim([LaneKind::ReliableOrdered], SimConfig::default(), 0);
+        send_many(&mut sim, Side::Client, LANE, 2, 16);
+        assert_eq!(
+            Err(DeliveryError::Missing {
+                lane: LANE,
+                index: 0
+            }),
+            sim.check_delivery(Side::Client)
+        );
+        assert_eq!(
+            Err(DeliveryError::Unacked {
+                lane: LANE,
+                index: 0
+            }),
+            sim.check_acked(Side::Client)
+        );
+
+        sim.step(STEP).unwrap();
+        sim.step(STEP).unwrap();
+        sim.check_delivery(Side::Client).unwrap();
+        sim.server.recv.swap(0, 1);
+        assert_eq!(
+            Err(DeliveryError::OutOfOrder {
+                lane: LANE,
+                index: 0
+            }),
+            sim.check_delivery(Side::Client)
+        );
+        let dup = sim.server.recv[0].clone();
+        sim.server.recv.push(dup);
+        sim.server.recv.swap(0, 1);
+        assert_matches::assert_matches!(
+            sim.check_delivery(Side::Client),
+            Err(DeliveryError::Duplicated { lane: LANE, .. })
+        );
+    }
+
+    #[test]
+    fn drops_packets_over_mtu() {
+        let mut sim = sim([LaneKind::UnreliableUnordered], SimConfig::default(), 0);
+        sim.set_config(SimConfig {
+            mtu: 1300,
+            ..Default::default()
+        })
+        .unwrap();
+        sim.send(Side::Client, vec![0; 1250], LaneIndex::from_raw(0))
+            .unwrap();
+        sim.config.mtu = 1200;
+        sim.step(STEP).unwrap();
+        assert_eq!(1, sim.link_stats(Side::Client).too_large);
+    }
+}
@@ -0,0 +1,484 @@
+//! Signed, expiring connect tokens used to prove that a client is allowed to
+//! connect to a server.
+//!
+//! This follows the same idea as [netcode.io] connect tokens: a trusted
+//! service (i.e. a matchmaker) which shares a secret [`TokenKey`] with the game
+//! server issues a [`ConnectToken`] to a client, holding the client's user ID
+//! and any extra data that the server should know about. The client passes the
+//! [`SignedToken`] to the server when connecting, and the server verifies it
+//! before accepting the client, giving it a trusted identity for that client.
+//!
+//! # Secrecy
+//!
+//! The [`ConnectToken::payload`] is only signed, so the client can read it.
+//! Data which the client must not be able to read goes in the
+//! [`ConnectToken::private_payload`] instead, which is encrypted using
+//! ChaCha20-Poly1305 with a key derived from the [`TokenKey`].
+//!
+//! # Replay protection
+//!
+//! Every token is issued with a random [`ConnectToken::nonce`]. A server
+//! records the nonce of every token it accepts in a [`ReplayCache`], and
+//! rejects any token whose nonce it has already seen, so a token which is
+//! intercepted after being used can't be used again. Tokens should still be
+//! given a short lifetime, since the cache only remembers each nonce until the
+//! token expires, and should only be sent over a transport which is already
+//! encrypted, or over a [`crypto`]-protected session.
+//!
+//! # Transports
+//!
+//! This module is transport-agnostic:
+//! - transports with an HTTP request step, like WebTransport and WebSocket,
+//!   can carry the token in the request using the helpers in [`request`]
+//! - [`Session`]-backed transports with their own handshake can write the
+//!   [`SignedToken`] directly into a handshake packet, since it implements
+//!   [`Encode`] and [`Decode`]
+//!
+//! [netcode.io]: https://github.com/networkprotocol/netcode
+//! [`crypto`]: crate::crypto
+//! [`Session`]: crate::session::Session
+
+pub mod request;
+
+use std::{collections::HashMap, convert::Infallible, fmt};
+
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+use hmac::{Hmac, Mac};
+use octs::{
+    BufTooShortOr, Bytes, BytesMut, Decode, Encode, EncodeLen, Read, VarInt, VarIntTooLarge, Write,
+};
+use sha2::Sha256;
+use web_time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Length in bytes of a [`TokenKey`].
+pub const KEY_LEN: usize = 32;
+
+/// Length in bytes of a [`ConnectToken::nonce`].
+pub const NONCE_LEN: usize = 12;
+
+/// Length in bytes of the authentication tag appended to the encrypted
+/// [`ConnectToken::private_payload`].
+pub const TAG_LEN: usize = 16;
+
+/// Input used to derive the key which encrypts private payloads from a
+/// [`TokenKey`].
+///
+/// This is shorter than any encoded token, so a token's signature can never
+/// be the same as the derived key.
+const ENCRYPTION_KEY_INFO: &[u8] = b"aeronet connect token encryption";
+
+/// Length in bytes of the signature appended to a [`SignedToken`].
+pub const SIGNATURE_LEN: usize = 32;
+
+/// Secret key shared between the token issuer and the server, used to sign and
+/// verify [`ConnectToken`]s.
+///
+/// This should be generated from a cryptographically secure random number
+/// generator, and must never be sent to clients.
+#[derive(Clone)]
+pub struct TokenKey([u8; KEY_LEN]);
+
+impl fmt::Debug for TokenKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenKey").finish_non_exhaustive()
+    }
+}
+
+impl TokenKey {
+    /// Creates a key from its raw bytes.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        <HmacSha256 as Mac>::new_from_slice(&self.0).expect("HMAC should accept keys of any length")
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        let mut mac = self.mac();
+        mac.update(ENCRYPTION_KEY_INFO);
+        ChaCha20Poly1305::new(Key::from_slice(&mac.finalize().into_bytes()))
+    }
+}
+
+/// Failed to verify a [`SignedToken`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum TokenError {
+    /// Token was too short to contain a signature.
+    #[error("token too short")]
+    TooShort,
+    /// Token's signature does not match its contents, i.e. it was not issued
+    /// using our key, or has been tampered with.
+    #[error("invalid signature")]
+    InvalidSignature,
+    /// Token was signed correctly, but its contents could not be decoded.
+    #[error("failed to decode token")]
+    Decode(#[source] BufTooShortOr<VarIntTooLarge>),
+    /// Token was valid, but has already expired.
+    #[error("token expired")]
+    Expired,
+    /// Token was valid, but a token with the same nonce has already been used.
+    ///
+    /// See [`ReplayCache`].
+    #[error("token already used")]
+    Replayed,
+}
+
+/// Identity and extra data given to a client by a trusted issuer.
+///
+/// Use [`ConnectToken::sign`] to create a [`SignedToken`] which can be given
+/// to the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectToken {
+    /// User ID of the client that this token was issued for.
+    pub user_id: u64,
+    /// When this token stops being valid.
+    ///
+    /// This is only stored with a precision of seconds.
+    pub expires_at: SystemTime,
+    /// Random value unique to this token, used to reject tokens which have
+    /// already been used.
+    ///
+    /// This is also the nonce used to encrypt the private payload, so it must
+    /// never be reused for two tokens signed with the same [`TokenKey`].
+    ///
+    /// See [`ReplayCache`].
+    pub nonce: [u8; NONCE_LEN],
+    /// Arbitrary application-specific data which the server can trust.
+    ///
+    /// The client can read this data, so it must not contain secrets.
+    pub payload: Bytes,
+    /// Arbitrary application-specific data which the server can trust, and
+    /// which the client can't read.
+    pub private_payload: Bytes,
+}
+
+impl ConnectToken {
+    /// Creates a token which expires `valid_for` after `now`, with a random
+    /// nonce and no private payload.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the OS random number generator is not available.
+    #[must_use]
+    pub fn new(
+        user_id: u64,
+        payload: impl Into<Bytes>,
+        now: SystemTime,
+        valid_for: Duration,
+    ) -> Self {
+        let mut nonce = [0; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).expect("should be able to generate a random nonce");
+        Self {
+            user_id,
+            expires_at: now + valid_for,
+            nonce,
+            payload: payload.into(),
+            private_payload: Bytes::new(),
+        }
+    }
+
+    /// Sets the data which the server can read from this token, but the client
+    /// can't.
+    #[must_use]
+    pub fn with_private_payload(self, private_payload: impl Into<Bytes>) -> Self {
+        Self {
+            private_payload: private_payload.into(),
+            ..self
+        }
+    }
+
+    /// Gets if this token has expired at the time `now`.
+    #[must_use]
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Signs this token using `key`, producing a token which can be given to
+    /// the client.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // shouldn't panic
+    pub fn sign(&self, key: &TokenKey) -> SignedToken {
+        let mut buf = BytesMut::new();
+        buf.write(&self.user_id)
+            .expect("BytesMut should grow the buffer when writing over capacity");
+        buf.write(&unix_secs(self.expires_at))
+            .expect("BytesMut should grow the buffer when writing over capacity");
+        buf.extend_from_slice(&self.nonce);
+        buf.write(VarInt(self.payload.len()))
+            .expect("BytesMut should grow the buffer when writing over capacity");
+        buf.extend_from_slice(&self.payload);
+
+        let sealed = key
+            .cipher()
+            .encrypt(Nonce::from_slice(&self.nonce), &self.private_payload[..])
+            .expect("encrypting should not fail");
+        buf.write(VarInt(sealed.len()))
+            .expect("BytesMut should grow the buffer when writing over capacity");
+        buf.extend_from_slice(&sealed);
+
+        let mut mac = key.mac();
+        mac.update(&buf);
+        buf.extend_from_slice(&mac.finalize().into_bytes());
+        SignedToken(buf.freeze())
+    }
+}
+
+/// Encoded [`ConnectToken`] along with a signature of its contents.
+///
+/// This is opaque to the client, which should pass it to the server unchanged.
+/// The server uses [`SignedToken::verify`] to get back the original token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedToken(pub Bytes);
+
+impl SignedToken {
+    /// Verifies that this token was signed with `key` and has not expired,
+    /// then decodes the original [`ConnectToken`].
+    ///
+    /// This doesn't check if the token has already been used - use a
+    /// [`ReplayCache`] for that.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the token is invalid or has expired, in which case the client
+    /// must not be allowed to connect.
+    pub fn verify(&self, key: &TokenKey, now: SystemTime) -> Result<ConnectToken, TokenError> {
+        let len = self
+            .0
+            .len()
+            .checked_sub(SIGNATURE_LEN)
+            .ok_or(TokenError::TooShort)?;
+        let (body, signature) = self.0.split_at(len);
+
+        let mut mac = key.mac();
+        mac.update(body);
+        mac.verify_slice(signature)
+            .map_err(|_| TokenError::InvalidSignature)?;
+
+        let mut body = self.0.slice(..len);
+        let token = (|| {
+            let user_id = body.read::<u64>()?;
+            let expires_at = body.read::<u64>()?;
+            let mut nonce = [0; NONCE_LEN];
+            nonce.copy_from_slice(&body.read_next(NONCE_LEN)?);
+            let payload_len = body.read::<VarInt<usize>>()?.0;
+            let payload = body.read_next(payload_len)?;
+            let sealed_len = body.read::<VarInt<usize>>()?.0;
+            let sealed = body.read_next(sealed_len)?;
+            Ok::<_, BufTooShortOr<VarIntTooLarge>>((user_id, expires_at, nonce, payload, sealed))
+        })()
+        .map_err(TokenError::Decode)?;
+        let (user_id, expires_at, nonce, payload, sealed) = token;
+
+        // the signature already covers the sealed payload, so this can only
+        // fail if the token was signed with a different key derivation
+        let private_payload = key
+            .cipher()
+            .decrypt(Nonce::from_slice(&nonce), &sealed[..])
+            .map_err(|_| TokenError::InvalidSignature)?;
+
+        let token = ConnectToken {
+            user_id,
+            expires_at: UNIX_EPOCH + Duration::from_secs(expires_at),
+            nonce,
+            payload,
+            private_payload: private_payload.into(),
+        };
+        if token.is_expired(now) {
+            return Err(TokenError::Expired);
+        }
+        Ok(token)
+    }
+}
+
+impl EncodeLen for SignedToken {
+    fn encode_len(&self) -> usize {
+        VarInt(self.0.len()).encode_len() + self.0.len()
+    }
+}
+
+impl Encode for SignedToken {
+    type Error = Infallible;
+
+    fn encode(&self, mut dst: impl Write) -> Result<(), BufTooShortOr<Self::Error>> {
+        dst.write(VarInt(self.0.len()))?;
+        dst.write_from(self.0.clone())?;
+        Ok(())
+    }
+}
+
+impl Decode for SignedToken {
+    type Error = VarIntTooLarge;
+
+    fn decode(mut src: impl Read) -> Result<Self, BufTooShortOr<Self::Error>> {
+        let len = src.read::<VarInt<usize>>()?.0;
+        Ok(Self(src.read_next(len)?))
+    }
+}
+
+/// Nonces of the tokens which a server has already accepted, used to reject
+/// tokens which are used more than once.
+///
+/// Each nonce is only remembered until its token expires, since an expired
+/// token fails [`SignedToken::verify`] anyway.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayCache {
+    used: HashMap<[u8; NONCE_LEN], SystemTime>,
+}
+
+impl ReplayCache {
+    /// Creates a cache which has not seen any tokens.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets how many nonces this cache currently remembers.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.used.len()
+    }
+
+    /// Gets if this cache currently remembers no nonces.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty()
+    }
+
+    /// Records that `token` has been used at the time `now`, and forgets any
+    /// tokens which have expired.
+    ///
+    /// Call this only after the token has been verified, otherwise anyone can
+    /// use up the nonce of a token which they don't have.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a token with the same nonce has already been used, in which
+    /// case the client must not be allowed to connect.
+    pub fn check(&mut self, token: &ConnectToken, now: SystemTime) -> Result<(), TokenError> {
+        self.used.retain(|_, expires_at| now < *expires_at);
+        if self.used.contains_key(&token.nonce) {
+            return Err(TokenError::Replayed);
+        }
+        self.used.insert(token.nonce, token.expires_at);
+        Ok(())
+    }
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use octs::test::*;
+
+    use super::*;
+
+    const KEY: TokenKey = TokenKey::from_bytes([1; KEY_LEN]);
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_000_000)
+    }
+
+    fn token() -> ConnectToken {
+        ConnectToken::new(1234, &b"payload"[..], now(), Duration::from_secs(30))
+    }
+
+    #[test]
+    fn sign_verify() {
+        let token = token();
+        assert_eq!(token, token.sign(&KEY).verify(&KEY, now()).unwrap());
+    }
+
+    #[test]
+    fn random_nonce() {
+        assert_ne!(token().nonce, token().nonce);
+    }
+
+    #[test]
+    fn private_payload() {
+        let token = token().with_private_payload(&b"secret"[..]);
+        let signed = token.sign(&KEY);
+        assert_eq!(token, signed.verify(&KEY, now()).unwrap());
+        // the client can't read the private payload
+        assert!(!signed.0.windows(6).any(|window| window == b"secret"));
+    }
+
+    #[test]
+    fn replayed() {
+        let mut cache = ReplayCache::new();
+        let token = token();
+        cache.check(&token, now()).unwrap();
+        assert_matches!(cache.check(&token, now()), Err(TokenError::Replayed));
+        // other tokens are still accepted
+        cache.check(&self::token(), now()).unwrap();
+        assert_eq!(2, cache.len());
+    }
+
+    #[test]
+    fn replay_cache_forgets_expired() {
+        let mut cache = ReplayCache::new();
+        cache.check(&token(), now()).unwrap();
+        cache
+            .check(&token(), now() + Duration::from_secs(30))
+            .unwrap();
+        assert_eq!(1, cache.len());
+    }
+
+    #[test]
+    fn empty_payload() {
+        let token = ConnectToken::new(0, Bytes::new(), now(), Duration::from_secs(30));
+        assert_eq!(token, token.sign(&KEY).verify(&KEY, now()).unwrap());
+    }
+
+    #[test]
+    fn expired() {
+        let signed = token().sign(&KEY);
+        assert_matches!(
+            signed.verify(&KEY, now() + Duration::from_secs(30)),
+            Err(TokenError::Expired)
+        );
+    }
+
+    #[test]
+    fn wrong_key() {
+        let signed = token().sign(&KEY);
+        let other = TokenKey::from_bytes([2; KEY_LEN]);
+        assert_matches!(
+            signed.verify(&other, now()),
+            Err(TokenError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn tampered() {
+        let signed = token().sign(&KEY);
+        for i in 0..signed.0.len() {
+            let mut tampered = signed.0.to_vec();
+            tampered[i] ^= 1;
+            assert_matches!(
+                SignedToken(tampered.into()).verify(&KEY, now()),
+                Err(TokenError::InvalidSignature)
+            );
+        }
+    }
+
+    #[test]
+    fn too_short() {
+        let signed = SignedToken(Bytes::from_static(&[0; SIGNATURE_LEN - 1]));
+        assert_matches!(signed.verify(&KEY, now()), Err(TokenError::TooShort));
+    }
+
+    #[test]
+    fn encode_decode() {
+        round_trip(&token().sign(&KEY));
+        round_trip(&SignedToken(Bytes::new()));
+    }
+}
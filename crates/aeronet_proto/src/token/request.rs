@@ -0,0 +1,91 @@
+//! Helpers for passing a [`SignedToken`] from a client to a server in an HTTP
+//! request, like a WebTransport session request or a WebSocket upgrade
+//! request.
+//!
+//! Browsers don't allow setting custom headers on these requests, so
+//! clients should use [`with_token`] to add the token to the query string of
+//! the URL they connect to. Native clients and proxies may instead set the
+//! [`HEADER`] header. The server checks both in [`find`].
+//!
+//! Tokens are encoded using URL-safe base 64, without padding.
+
+use std::{collections::HashMap, hash::BuildHasher};
+
+use base64::Engine;
+
+use super::{SignedToken, TokenError};
+
+/// Name of the header which may hold the encoded token.
+pub const HEADER: &str = "aeronet-connect-token";
+
+/// Name of the query string parameter which may hold the encoded token.
+pub const QUERY_PARAM: &str = "connect_token";
+
+const BASE64: base64::engine::GeneralPurpose = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+/// Failed to get a valid [`ConnectToken`] from a client's request.
+///
+/// [`ConnectToken`]: super::ConnectToken
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ConnectTokenError {
+    /// Request did not contain a token in either the header or query string.
+    #[error("no token in request")]
+    Missing,
+    /// Token was not valid base 64.
+    #[error("failed to decode base 64")]
+    Base64(#[source] base64::DecodeError),
+    /// Token failed verification.
+    #[error("failed to verify token")]
+    Verify(#[source] TokenError),
+}
+
+/// Encodes a token into a string which can be placed in a header or URL.
+#[must_use]
+pub fn encode(token: &SignedToken) -> String {
+    BASE64.encode(&token.0)
+}
+
+/// Decodes a token string created by [`encode`].
+///
+/// # Errors
+///
+/// Errors if the string is not valid base 64.
+pub fn decode(token: &str) -> Result<SignedToken, base64::DecodeError> {
+    BASE64.decode(token).map(|bytes| SignedToken(bytes.into()))
+}
+
+/// Adds a token to the query string of `target`, the URL that a client
+/// connects to.
+///
+/// ```
+/// # use aeronet_proto::token::{request, SignedToken};
+/// let signed = SignedToken(vec![1, 2, 3].into());
+/// assert_eq!(
+///     "https://[::1]:1234/game?connect_token=AQID",
+///     request::with_token("https://[::1]:1234/game", &signed),
+/// );
+/// ```
+#[must_use]
+pub fn with_token(target: &str, token: &SignedToken) -> String {
+    let sep = if target.contains('?') { '&' } else { '?' };
+    format!("{target}{sep}{QUERY_PARAM}={}", encode(token))
+}
+
+/// Finds the encoded token in a request, checking the [`HEADER`]
+/// header first, then the [`QUERY_PARAM`] parameter of `path`.
+#[must_use]
+pub fn find<'a, S: BuildHasher>(
+    path: &'a str,
+    headers: &'a HashMap<String, String, S>,
+) -> Option<&'a str> {
+    if let Some(token) = headers.get(HEADER) {
+        return Some(token);
+    }
+
+    let (_, query) = path.split_once('?')?;
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == QUERY_PARAM)
+        .map(|(_, value)| value)
+}
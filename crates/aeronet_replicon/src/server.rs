@@ -8,7 +8,8 @@ use aeronet::{
     lane::LaneIndex,
     server::{
         server_open, RemoteClientConnected, RemoteClientConnecting, RemoteClientDisconnected,
-        ServerClosed, ServerEvent, ServerOpened, ServerState, ServerTransport, ServerTransportSet,
+        ServerClosed, ServerEvent, ServerOpened, ServerReloaded, ServerState, ServerTransport,
+        ServerTransportSet,
     },
 };
 use bevy_app::prelude::*;
@@ -33,6 +34,7 @@ use tracing::{debug, warn};
 /// Events:
 /// * [`ServerOpened`]
 /// * [`ServerClosed`]
+/// * [`ServerReloaded`]
 /// * [`RemoteClientConnecting`]
 /// * [`RemoteClientConnected`]
 /// * [`RemoteRepliconClientConnected`]
@@ -67,6 +69,7 @@ impl<T: ServerTransport + Resource> Plugin for RepliconServerPlugin<T> {
     fn build(&self, app: &mut App) {
         app.add_event::<ServerOpened<T>>()
             .add_event::<ServerClosed<T>>()
+            .add_event::<ServerReloaded<T>>()
             .add_event::<RemoteClientConnecting<T>>()
             .add_event::<RemoteClientConnected<T>>()
             .add_event::<RemoteRepliconClientConnected<T>>()
@@ -177,6 +180,7 @@ struct Events<'w, T: ServerTransport + Resource> {
     replicon: EventWriter<'w, RepliconEvent>,
     opened: EventWriter<'w, ServerOpened<T>>,
     closed: EventWriter<'w, ServerClosed<T>>,
+    reloaded: EventWriter<'w, ServerReloaded<T>>,
     connecting: EventWriter<'w, RemoteClientConnecting<T>>,
     connected: EventWriter<'w, RemoteClientConnected<T>>,
     replicon_connected: EventWriter<'w, RemoteRepliconClientConnected<T>>,
@@ -199,6 +203,9 @@ impl<T: ServerTransport + Resource> RepliconServerPlugin<T> {
                 ServerEvent::Closed { reason: error } => {
                     events.closed.send(ServerClosed { error });
                 }
+                ServerEvent::Reloaded { result } => {
+                    events.reloaded.send(ServerReloaded { result });
+                }
                 ServerEvent::Connecting { client_key } => {
                     events
                         .connecting
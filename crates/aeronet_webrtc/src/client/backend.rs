@@ -0,0 +1,91 @@
+use aeronet::client::DisconnectReason;
+use aeronet_proto::session::{Session, SessionConfig};
+use bytes::Bytes;
+use futures::{
+    channel::{mpsc, oneshot},
+    never::Never,
+};
+use tracing::debug;
+use web_time::Instant;
+use webrtc::{api::APIBuilder, peer_connection::RTCPeerConnection};
+
+use crate::{
+    internal::{self, ChannelEvent, MTU},
+    signal::ClientSignaller,
+};
+
+use super::{ClientConfig, ClientError, ToConnected};
+
+pub async fn start(
+    net_config: ClientConfig,
+    session_config: SessionConfig,
+    signaller: impl ClientSignaller,
+    send_connected: oneshot::Sender<ToConnected>,
+) -> Result<Never, DisconnectReason<ClientError>> {
+    let api = APIBuilder::new()
+        .with_setting_engine(net_config.settings)
+        .build();
+    let pc = api
+        .new_peer_connection(net_config.rtc)
+        .await
+        .map_err(ClientError::Rtc)?;
+
+    let res = connect(&pc, session_config, signaller, send_connected).await;
+    // make sure that all of the peer connection's tasks are stopped
+    let _ = pc.close().await;
+    res
+}
+
+async fn connect(
+    pc: &RTCPeerConnection,
+    session_config: SessionConfig,
+    mut signaller: impl ClientSignaller,
+    send_connected: oneshot::Sender<ToConnected>,
+) -> Result<Never, DisconnectReason<ClientError>> {
+    let (send_event, mut recv_event) = mpsc::unbounded::<ChannelEvent>();
+    let (send_s2c, recv_s2c) = mpsc::channel::<Bytes>(internal::MSG_BUF_CAP);
+    internal::watch_peer(pc, send_event.clone());
+    let dc = pc
+        .create_data_channel(internal::CHANNEL_LABEL, Some(internal::channel_init()))
+        .await
+        .map_err(ClientError::Rtc)?;
+    internal::watch_channel(&dc, send_event, send_s2c);
+
+    let offer = pc.create_offer(None).await.map_err(ClientError::Rtc)?;
+    let offer = internal::gather_local_description(pc, offer)
+        .await
+        .map_err(ClientError::Rtc)?;
+
+    debug!("Created offer, signalling server");
+    let answer = signaller
+        .exchange(offer)
+        .await
+        .map_err(ClientError::Signal)?;
+    pc.set_remote_description(answer)
+        .await
+        .map_err(ClientError::Rtc)?;
+
+    debug!("Received answer, waiting for data channel to open");
+    internal::wait_open(&mut recv_event)
+        .await
+        .map_err(ClientError::from)?;
+
+    let session = Session::client(Instant::now(), session_config, MTU, MTU)
+        .expect("MTU should be large enough for the session");
+
+    let (send_c2s, recv_c2s) = mpsc::unbounded::<Bytes>();
+    let (send_local_dc, recv_local_dc) = oneshot::channel::<String>();
+    send_connected
+        .send(ToConnected {
+            send_c2s,
+            recv_s2c,
+            send_local_dc,
+            session,
+        })
+        .map_err(|_| ClientError::FrontendClosed)?;
+
+    debug!("Starting connection loop");
+    internal::handle_connection(dc, recv_event, recv_c2s, recv_local_dc)
+        .await
+        .map_err(|reason| reason.map_err(From::from))
+}
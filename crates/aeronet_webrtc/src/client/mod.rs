@@ -0,0 +1,183 @@
+//! Client-side transport implementation.
+
+mod backend;
+mod frontend;
+
+use std::fmt;
+
+use aeronet::{
+    client::DisconnectReason,
+    stats::{ConnectedAt, MessageStats, Rtt},
+};
+use aeronet_proto::{
+    frontend::{FrontendError, SessionFrontend},
+    session::{FatalSendError, OutOfMemory, SendError, Session},
+};
+use bytes::Bytes;
+use futures::channel::{mpsc, oneshot};
+use web_time::{Duration, Instant};
+use webrtc::{
+    api::setting_engine::SettingEngine, peer_connection::configuration::RTCConfiguration,
+};
+
+use crate::{internal::InternalError, signal::SignalError};
+
+/// Client network configuration.
+#[derive(Clone, Default)]
+pub struct ClientConfig {
+    /// Configuration of the peer connection, i.e. which ICE servers to use.
+    pub rtc: RTCConfiguration,
+    /// Lower-level `webrtc` settings, i.e. which network interfaces to gather
+    /// candidates from.
+    pub settings: SettingEngine,
+}
+
+impl fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientConfig")
+            .field("ice_servers", &self.rtc.ice_servers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// WebRTC implementation of [`ClientTransport`].
+///
+/// See the [crate-level documentation](crate).
+///
+/// [`ClientTransport`]: aeronet::client::ClientTransport
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct WebRtcClient {
+    state: State,
+}
+
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)] // most of the time we'll be connected anyway
+enum State {
+    Disconnected,
+    Connecting(Connecting),
+    Connected(Connected),
+    Disconnecting { reason: String },
+}
+
+/// Error type for operations on a [`WebRtcClient`].
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    // frontend
+    /// Backend client task was cancelled, dropping the underlying connection.
+    #[error("backend closed")]
+    BackendClosed,
+    /// Client is already connecting or connected.
+    #[error("already connecting or connected")]
+    AlreadyConnected,
+    /// Client is already disconnected.
+    #[error("already disconnected")]
+    AlreadyDisconnected,
+    /// Client is not connected.
+    #[error("not connected")]
+    NotConnected,
+    /// See [`SendError`].
+    #[error(transparent)]
+    Send(SendError),
+    /// See [`FatalSendError`].
+    #[error(transparent)]
+    FatalSend(FatalSendError),
+    /// See [`OutOfMemory`].
+    #[error(transparent)]
+    OutOfMemory(OutOfMemory),
+
+    // backend
+    /// Client frontend was closed.
+    #[error("frontend closed")]
+    FrontendClosed,
+    /// Failed to exchange session descriptions with the server.
+    #[error("failed to signal server")]
+    Signal(#[source] SignalError),
+    /// Error in the underlying WebRTC peer connection.
+    #[error("webrtc error")]
+    Rtc(#[source] webrtc::Error),
+
+    // connection
+    /// Lost connection.
+    #[error("connection lost")]
+    ConnectionLost,
+}
+
+impl From<InternalError<Self>> for ClientError {
+    fn from(value: InternalError<Self>) -> Self {
+        match value {
+            InternalError::Spec(e) => e,
+            InternalError::FrontendClosed => Self::FrontendClosed,
+            InternalError::Rtc(err) => Self::Rtc(err),
+            InternalError::ConnectionLost => Self::ConnectionLost,
+        }
+    }
+}
+
+impl From<FrontendError<Self>> for ClientError {
+    fn from(value: FrontendError<Self>) -> Self {
+        match value {
+            FrontendError::Spec(err) => err,
+            FrontendError::BackendClosed => Self::BackendClosed,
+            FrontendError::OutOfMemory(err) => Self::OutOfMemory(err),
+            FrontendError::Send(err) => Self::Send(err),
+            FrontendError::FatalSend(err) => Self::FatalSend(err),
+        }
+    }
+}
+
+/// State of a [`WebRtcClient`] when it is [`ClientState::Connecting`].
+///
+/// [`ClientState::Connecting`]: aeronet::client::ClientState::Connecting
+#[derive(Debug)]
+pub struct Connecting {
+    recv_connected: oneshot::Receiver<ToConnected>,
+    recv_dc: oneshot::Receiver<DisconnectReason<ClientError>>,
+}
+
+#[derive(Debug)]
+struct ToConnected {
+    send_c2s: mpsc::UnboundedSender<Bytes>,
+    recv_s2c: mpsc::Receiver<Bytes>,
+    send_local_dc: oneshot::Sender<String>,
+    session: Session,
+}
+
+/// State of a [`WebRtcClient`] when it is [`ClientState::Connected`].
+///
+/// [`ClientState::Connected`]: aeronet::client::ClientState::Connected
+#[derive(Debug)]
+pub struct Connected {
+    inner: SessionFrontend<ClientError>,
+}
+
+impl Connected {
+    /// Provides access to the underlying [`Session`] for reading more detailed
+    /// network statistics.
+    #[must_use]
+    pub const fn session(&self) -> &Session {
+        &self.inner.session
+    }
+}
+
+impl ConnectedAt for Connected {
+    fn connected_at(&self) -> Instant {
+        self.session().connected_at()
+    }
+}
+
+impl Rtt for Connected {
+    fn rtt(&self) -> Duration {
+        self.session().rtt().get()
+    }
+}
+
+impl MessageStats for Connected {
+    fn bytes_sent(&self) -> usize {
+        self.session().bytes_sent()
+    }
+
+    fn bytes_recv(&self) -> usize {
+        self.session().bytes_recv()
+    }
+}
@@ -0,0 +1,158 @@
+use std::{sync::Arc, time::Duration};
+
+use aeronet::client::DisconnectReason;
+use bytes::Bytes;
+use futures::{
+    channel::{mpsc, oneshot},
+    never::Never,
+    StreamExt,
+};
+use tracing::debug;
+use webrtc::{
+    data_channel::{data_channel_init::RTCDataChannelInit, RTCDataChannel},
+    peer_connection::{peer_connection_state::RTCPeerConnectionState, RTCPeerConnection},
+};
+
+use crate::shared::SessionDescription;
+
+use super::InternalError;
+
+/// Label of the data channel which packets are sent over.
+pub const CHANNEL_LABEL: &str = "aeronet";
+
+/// How long to wait for a disconnect reason to be sent before closing the
+/// peer connection.
+const CLOSE_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub fn channel_init() -> RTCDataChannelInit {
+    // unordered and unreliable, so that this behaves like UDP -
+    // reliability and ordering is handled by the session
+    RTCDataChannelInit {
+        ordered: Some(false),
+        max_retransmits: Some(0),
+        ..Default::default()
+    }
+}
+
+#[derive(Debug)]
+pub enum ChannelEvent {
+    Open,
+    Remote(String),
+    Closed,
+    Failed,
+}
+
+pub fn watch_peer(pc: &RTCPeerConnection, send_event: mpsc::UnboundedSender<ChannelEvent>) {
+    pc.on_peer_connection_state_change(Box::new(move |state| {
+        debug!("Peer connection state changed to {state}");
+        if matches!(
+            state,
+            RTCPeerConnectionState::Failed | RTCPeerConnectionState::Closed
+        ) {
+            let _ = send_event.unbounded_send(ChannelEvent::Failed);
+        }
+        Box::pin(async {})
+    }));
+}
+
+pub fn watch_channel(
+    dc: &RTCDataChannel,
+    send_event: mpsc::UnboundedSender<ChannelEvent>,
+    mut send_r: mpsc::Sender<Bytes>,
+) {
+    dc.on_open(Box::new({
+        let send_event = send_event.clone();
+        move || {
+            let _ = send_event.unbounded_send(ChannelEvent::Open);
+            Box::pin(async {})
+        }
+    }));
+    dc.on_close(Box::new({
+        let send_event = send_event.clone();
+        move || {
+            let _ = send_event.unbounded_send(ChannelEvent::Closed);
+            Box::pin(async {})
+        }
+    }));
+    dc.on_message(Box::new(move |msg| {
+        if msg.is_string {
+            // we only send text messages to inform the peer of why we're
+            // disconnecting
+            let reason = String::from_utf8_lossy(&msg.data).into_owned();
+            let _ = send_event.unbounded_send(ChannelEvent::Remote(reason));
+        } else {
+            // if the frontend can't keep up, drop the packet,
+            // and let the session resend it later
+            let _ = send_r.try_send(msg.data);
+        }
+        Box::pin(async {})
+    }));
+}
+
+/// Creates an offer or answer, and waits for all ICE candidates to be gathered
+/// so that the description can be sent to the peer in one go.
+pub async fn gather_local_description(
+    pc: &RTCPeerConnection,
+    desc: SessionDescription,
+) -> Result<SessionDescription, webrtc::Error> {
+    let mut gather_complete = pc.gathering_complete_promise().await;
+    pc.set_local_description(desc).await?;
+    let _ = gather_complete.recv().await;
+    pc.local_description()
+        .await
+        .ok_or(webrtc::Error::ErrConnectionClosed)
+}
+
+pub async fn wait_open<E>(
+    recv_event: &mut mpsc::UnboundedReceiver<ChannelEvent>,
+) -> Result<(), InternalError<E>> {
+    loop {
+        match recv_event.next().await {
+            Some(ChannelEvent::Open) => return Ok(()),
+            Some(ChannelEvent::Remote(_)) => {}
+            Some(ChannelEvent::Closed | ChannelEvent::Failed) | None => {
+                return Err(InternalError::ConnectionLost)
+            }
+        }
+    }
+}
+
+pub async fn handle_connection<E>(
+    dc: Arc<RTCDataChannel>,
+    mut recv_event: mpsc::UnboundedReceiver<ChannelEvent>,
+    mut recv_s: mpsc::UnboundedReceiver<Bytes>,
+    mut recv_local_dc: oneshot::Receiver<String>,
+) -> Result<Never, DisconnectReason<InternalError<E>>> {
+    loop {
+        futures::select! {
+            packet = recv_s.next() => {
+                let packet = packet.ok_or(InternalError::FrontendClosed)?;
+                dc.send(&packet).await.map_err(InternalError::Rtc)?;
+            }
+            event = recv_event.next() => {
+                match event {
+                    Some(ChannelEvent::Open) => {}
+                    Some(ChannelEvent::Remote(reason)) => {
+                        return Err(DisconnectReason::Remote(reason));
+                    }
+                    Some(ChannelEvent::Closed | ChannelEvent::Failed) | None => {
+                        return Err(InternalError::ConnectionLost.into());
+                    }
+                }
+            }
+            reason = recv_local_dc => {
+                if let Ok(reason) = reason {
+                    // we're disconnecting anyway, don't care if this fails
+                    let _ = dc.send_text(reason).await;
+                    let flushed = async {
+                        while dc.buffered_amount().await > 0 {
+                            tokio::time::sleep(Duration::from_millis(1)).await;
+                        }
+                    };
+                    let _ = tokio::time::timeout(CLOSE_TIMEOUT, flushed).await;
+                }
+                return Err(InternalError::FrontendClosed.into());
+            }
+        }
+    }
+}
@@ -0,0 +1,23 @@
+mod backend;
+
+pub use backend::*;
+
+pub const MSG_BUF_CAP: usize = 256;
+
+// data channel messages are sent over SCTP over DTLS over UDP, so keep packets
+// small enough to avoid IP fragmentation on most paths
+pub const MTU: usize = 1024;
+
+// intentionally don't derive Error so that consumers are forced to map each
+// variant to their own error variant
+#[derive(Debug)]
+pub enum InternalError<E> {
+    Spec(E),
+
+    // backend
+    FrontendClosed,
+    Rtc(webrtc::Error),
+
+    // connection
+    ConnectionLost,
+}
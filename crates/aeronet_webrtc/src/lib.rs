@@ -0,0 +1,17 @@
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+#![doc = include_str!("../README.md")]
+
+pub use {aeronet_proto as proto, webrtc};
+
+pub mod runtime;
+pub mod shared;
+pub mod signal;
+
+#[cfg(any(feature = "client", feature = "server"))]
+mod internal;
+
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(feature = "server")]
+pub mod server;
@@ -0,0 +1,55 @@
+//! See [`WebRtcRuntime`].
+
+use std::{future::Future, time::Duration};
+
+/// Provides a way of spawning futures required to drive a WebRTC endpoint.
+///
+/// Connecting a WebRTC client or opening a WebRTC server returns [`Future`]s
+/// which must be spawned on an async runtime. `webrtc` currently only supports
+/// `tokio`, so this holds a handle to a `tokio` runtime. The [`Default`] impl
+/// will create and leak a new `tokio` runtime, and store a handle to this
+/// leaked runtime.
+///
+/// If you already have a runtime handle, you can use
+/// `WebRtcRuntime::from(handle)` to create a runtime from that handle.
+///
+/// If using Bevy, you can use this as a resource in your systems.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct WebRtcRuntime {
+    runtime: tokio::runtime::Handle,
+}
+
+impl Default for WebRtcRuntime {
+    fn default() -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("failed to create tokio runtime");
+        let runtime = Box::leak(Box::new(runtime));
+        Self {
+            runtime: runtime.handle().clone(),
+        }
+    }
+}
+
+impl From<tokio::runtime::Handle> for WebRtcRuntime {
+    fn from(value: tokio::runtime::Handle) -> Self {
+        Self { runtime: value }
+    }
+}
+
+impl WebRtcRuntime {
+    /// Spawns a future on the task runtime.
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.runtime.spawn(future);
+    }
+
+    /// Pauses execution for the given duration.
+    pub async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
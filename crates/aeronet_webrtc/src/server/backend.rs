@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use aeronet::{client::DisconnectReason, error::pretty_error};
+use aeronet_proto::session::{Session, SessionConfig};
+use bytes::Bytes;
+use futures::{
+    channel::{mpsc, oneshot},
+    never::Never,
+    FutureExt, SinkExt,
+};
+use tracing::{debug, debug_span, field, Instrument};
+use web_time::Instant;
+use webrtc::{
+    api::{APIBuilder, API},
+    data_channel::RTCDataChannel,
+    peer_connection::{configuration::RTCConfiguration, RTCPeerConnection},
+};
+
+use crate::{
+    internal::{self, ChannelEvent, MTU},
+    runtime::WebRtcRuntime,
+    signal::{ServerSignaller, SignalRequest},
+};
+
+use super::{ClientKey, ServerConfig, ServerError, ToConnected, ToConnecting, ToOpen};
+
+pub async fn start(
+    runtime: WebRtcRuntime,
+    net_config: ServerConfig,
+    session_config: SessionConfig,
+    mut signaller: impl ServerSignaller,
+    send_open: oneshot::Sender<ToOpen>,
+) -> Result<Never, ServerError> {
+    let api = Arc::new(
+        APIBuilder::new()
+            .with_setting_engine(net_config.settings)
+            .build(),
+    );
+
+    let (send_closed, mut recv_closed) = oneshot::channel::<()>();
+    let (send_connecting, recv_connecting) = mpsc::channel::<ToConnecting>(4);
+    send_open
+        .send(ToOpen {
+            recv_connecting,
+            send_closed,
+        })
+        .map_err(|_| ServerError::FrontendClosed)?;
+
+    loop {
+        let req = futures::select! {
+            _ = recv_closed => return Err(ServerError::FrontendClosed),
+            x = signaller.accept().fuse() => x,
+        }
+        .map_err(ServerError::Signal)?;
+
+        let api = api.clone();
+        let rtc_config = net_config.rtc.clone();
+        let session_config = session_config.clone();
+        let send_connecting = send_connecting.clone();
+        runtime.spawn(async move {
+            if let Err(err) =
+                start_handle_session(api, rtc_config, session_config, send_connecting, req).await
+            {
+                debug!("Failed to start handling session: {:#}", pretty_error(&err));
+            }
+        });
+    }
+}
+
+async fn start_handle_session(
+    api: Arc<API>,
+    rtc_config: RTCConfiguration,
+    session_config: SessionConfig,
+    mut send_connecting: mpsc::Sender<ToConnecting>,
+    req: SignalRequest,
+) -> Result<(), ServerError> {
+    let (send_key, recv_key) = oneshot::channel::<ClientKey>();
+    let (send_dc, recv_dc) = oneshot::channel::<DisconnectReason<ServerError>>();
+    let (send_connected, recv_connected) = oneshot::channel::<ToConnected>();
+    send_connecting
+        .send(ToConnecting {
+            offer: req.offer().clone(),
+            send_key,
+            recv_dc,
+            recv_connected,
+        })
+        .await
+        .map_err(|_| ServerError::FrontendClosed)?;
+    let client_key = recv_key.await.map_err(|_| ServerError::FrontendClosed)?;
+
+    let err = async move {
+        let err = handle_session(api, rtc_config, session_config, req, send_connected)
+            .await
+            .unwrap_err();
+        match &err {
+            DisconnectReason::Error(ServerError::FrontendClosed) => {
+                debug!("Session closed");
+            }
+            err => {
+                debug!("Session closed: {:#}", pretty_error(err));
+            }
+        }
+        err
+    }
+    .instrument(debug_span!(
+        "session",
+        client = field::debug(slotmap::Key::data(&client_key))
+    ))
+    .await;
+    let _ = send_dc.send(err);
+    Ok(())
+}
+
+async fn handle_session(
+    api: Arc<API>,
+    rtc_config: RTCConfiguration,
+    session_config: SessionConfig,
+    req: SignalRequest,
+    send_connected: oneshot::Sender<ToConnected>,
+) -> Result<Never, DisconnectReason<ServerError>> {
+    let pc = api
+        .new_peer_connection(rtc_config)
+        .await
+        .map_err(ServerError::Rtc)?;
+
+    let res = accept(&pc, session_config, req, send_connected).await;
+    // make sure that all of the peer connection's tasks are stopped
+    let _ = pc.close().await;
+    res
+}
+
+async fn accept(
+    pc: &RTCPeerConnection,
+    session_config: SessionConfig,
+    req: SignalRequest,
+    send_connected: oneshot::Sender<ToConnected>,
+) -> Result<Never, DisconnectReason<ServerError>> {
+    let (send_event, mut recv_event) = mpsc::unbounded::<ChannelEvent>();
+    let (send_c2s, recv_c2s) = mpsc::channel::<Bytes>(internal::MSG_BUF_CAP);
+    let (send_channel, mut recv_channel) = mpsc::unbounded::<Arc<RTCDataChannel>>();
+    internal::watch_peer(pc, send_event.clone());
+    pc.on_data_channel(Box::new(move |dc| {
+        if dc.label() == internal::CHANNEL_LABEL {
+            // send the channel before watching it, so that it's available by
+            // the time the channel opens
+            let _ = send_channel.unbounded_send(dc.clone());
+            internal::watch_channel(&dc, send_event.clone(), send_c2s.clone());
+        } else {
+            debug!("Ignoring unknown data channel {:?}", dc.label());
+        }
+        Box::pin(async {})
+    }));
+
+    pc.set_remote_description(req.offer().clone())
+        .await
+        .map_err(ServerError::Rtc)?;
+    let answer = pc.create_answer(None).await.map_err(ServerError::Rtc)?;
+    let answer = internal::gather_local_description(pc, answer)
+        .await
+        .map_err(ServerError::Rtc)?;
+    debug!("Created answer, waiting for data channel to open");
+    req.answer(answer);
+
+    internal::wait_open(&mut recv_event)
+        .await
+        .map_err(ServerError::from)?;
+    let dc = recv_channel
+        .try_next()
+        .ok()
+        .flatten()
+        .expect("data channel should be sent before it is opened");
+
+    let session = Session::server(Instant::now(), session_config, MTU, MTU)
+        .expect("MTU should be large enough for the session");
+
+    let (send_s2c, recv_s2c) = mpsc::unbounded::<Bytes>();
+    let (send_local_dc, recv_local_dc) = oneshot::channel::<String>();
+    send_connected
+        .send(ToConnected {
+            recv_c2s,
+            send_s2c,
+            send_local_dc,
+            session,
+        })
+        .map_err(|_| ServerError::FrontendClosed)?;
+
+    debug!("Starting connection loop");
+    internal::handle_connection(dc, recv_event, recv_s2c, recv_local_dc)
+        .await
+        .map_err(|err| err.map_err(From::from))
+}
@@ -0,0 +1,394 @@
+use std::mem;
+
+use aeronet::{
+    client::ClientState,
+    error::pretty_error,
+    lane::LaneIndex,
+    server::{CloseReason, ServerEvent, ServerState, ServerTransport},
+    shared::DROP_DISCONNECT_REASON,
+};
+use aeronet_proto::{
+    frontend::{FrontendEvent, SessionFrontend},
+    session::{MessageKey, SessionConfig},
+};
+use bytes::Bytes;
+use futures::channel::oneshot;
+use slotmap::SlotMap;
+use tracing::{debug, field, trace_span};
+use web_time::Duration;
+
+use crate::{runtime::WebRtcRuntime, signal::ServerSignaller};
+
+use super::{
+    backend, Client, ClientKey, Connected, Connecting, Open, Opening, ServerConfig, ServerError,
+    State, ToOpen, WebRtcServer,
+};
+
+impl Default for WebRtcServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebRtcServer {
+    /// Creates a new server which is not open for connections.
+    ///
+    /// Use [`WebRtcServer::open`] to open this server for clients.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: State::Closed,
+        }
+    }
+
+    /// Starts opening this server for client connections.
+    ///
+    /// `signaller` is used to receive offers from clients, and send back this
+    /// server's answers. See [`signal`](crate::signal).
+    ///
+    /// This automatically spawns the backend task on the runtime provided.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the server is already opening or open.
+    pub fn open(
+        &mut self,
+        runtime: &WebRtcRuntime,
+        net_config: ServerConfig,
+        session_config: SessionConfig,
+        signaller: impl ServerSignaller,
+    ) -> Result<(), ServerError> {
+        if !matches!(self.state, State::Closed) {
+            return Err(ServerError::AlreadyOpen);
+        }
+
+        let (send_open, recv_open) = oneshot::channel::<ToOpen>();
+        let (send_err, recv_err) = oneshot::channel::<ServerError>();
+
+        let runtime_clone = runtime.clone();
+        runtime.spawn(async move {
+            debug!("Started server backend");
+            match backend::start(
+                runtime_clone,
+                net_config,
+                session_config,
+                signaller,
+                send_open,
+            )
+            .await
+            {
+                Err(ServerError::FrontendClosed) => {
+                    debug!("Server closed by frontend");
+                }
+                Err(err) => {
+                    debug!("Server closed: {:#}", pretty_error(&err));
+                    let _ = send_err.send(err);
+                }
+                Ok(_) => unreachable!(),
+            }
+        });
+
+        self.state = State::Opening(Opening {
+            recv_open,
+            recv_err,
+        });
+
+        debug!("Opened server");
+        Ok(())
+    }
+}
+
+impl ServerTransport for WebRtcServer {
+    type Error = ServerError;
+
+    type Opening<'this> = &'this Opening;
+
+    type Open<'this> = &'this Open;
+
+    type Connecting<'this> = &'this Connecting;
+
+    type Connected<'this> = &'this Connected;
+
+    type ClientKey = ClientKey;
+
+    type MessageKey = MessageKey;
+
+    fn state(&self) -> ServerState<Self::Opening<'_>, Self::Open<'_>> {
+        match &self.state {
+            State::Closed | State::Closing { .. } => ServerState::Closed,
+            State::Opening(server) => ServerState::Opening(server),
+            State::Open(server) => ServerState::Open(server),
+        }
+    }
+
+    fn client_state(
+        &self,
+        client_key: Self::ClientKey,
+    ) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
+        let State::Open(server) = &self.state else {
+            return ClientState::Disconnected;
+        };
+        server
+            .clients
+            .get(client_key)
+            .map_or(ClientState::Disconnected, ClientState::as_ref)
+    }
+
+    fn client_keys(&self) -> impl Iterator<Item = Self::ClientKey> + '_ {
+        match &self.state {
+            State::Closed | State::Closing { .. } | State::Opening(_) => None,
+            State::Open(server) => Some(server.clients.keys()),
+        }
+        .into_iter()
+        .flatten()
+    }
+
+    fn poll(&mut self, delta_time: Duration) -> impl Iterator<Item = ServerEvent<Self>> {
+        let mut events = Vec::new();
+        replace_with::replace_with_or_abort(&mut self.state, |state| match state {
+            State::Closed => State::Closed,
+            State::Opening(server) => Self::poll_opening(server, &mut events),
+            State::Open(server) => Self::poll_open(server, &mut events, delta_time),
+            State::Closing { reason } => {
+                events.push(ServerEvent::Closed {
+                    reason: CloseReason::Local(reason),
+                });
+                State::Closed
+            }
+        });
+        events.into_iter()
+    }
+
+    fn send(
+        &mut self,
+        client_key: Self::ClientKey,
+        msg: impl Into<Bytes>,
+        lane: impl Into<LaneIndex>,
+    ) -> Result<Self::MessageKey, Self::Error> {
+        let State::Open(server) = &mut self.state else {
+            return Err(ServerError::NotOpen);
+        };
+        let Some(Client::Connected(client)) = server.clients.get_mut(client_key) else {
+            return Err(ServerError::ClientNotConnected);
+        };
+
+        let msg = msg.into();
+        let lane = lane.into();
+        client.inner.send(msg, lane).map_err(From::from)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        let State::Open(server) = &mut self.state else {
+            return Err(ServerError::NotOpen);
+        };
+
+        for (client_key, client) in &mut server.clients {
+            let span = trace_span!(
+                "client",
+                key = field::debug(slotmap::Key::data(&client_key))
+            );
+            let _ = span.enter();
+
+            let Client::Connected(client) = client else {
+                continue;
+            };
+            client.inner.flush();
+        }
+        Ok(())
+    }
+
+    fn disconnect(
+        &mut self,
+        client_key: Self::ClientKey,
+        reason: impl Into<String>,
+    ) -> Result<(), Self::Error> {
+        let State::Open(server) = &mut self.state else {
+            return Err(ServerError::NotOpen);
+        };
+
+        let client = server
+            .clients
+            .remove(client_key)
+            .ok_or(ServerError::ClientNotConnected)?;
+        if let Client::Connected(client) = client {
+            let reason = reason.into();
+            let _ = client.inner.send_local_dc.send(reason);
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, reason: impl Into<String>) -> Result<(), Self::Error> {
+        let reason = reason.into();
+        match mem::replace(
+            &mut self.state,
+            State::Closing {
+                reason: reason.clone(),
+            },
+        ) {
+            State::Open(server) => {
+                for (_, client) in server.clients {
+                    if let Client::Connected(client) = client {
+                        let _ = client.inner.send_local_dc.send(reason.clone());
+                    }
+                }
+                Ok(())
+            }
+            State::Opening(_) => Ok(()),
+            State::Closed | State::Closing { .. } => Err(ServerError::AlreadyClosed),
+        }
+    }
+}
+
+impl WebRtcServer {
+    fn poll_opening(mut server: Opening, events: &mut Vec<ServerEvent<Self>>) -> State {
+        if let Ok(Some(err)) = server.recv_err.try_recv() {
+            events.push(ServerEvent::Closed { reason: err.into() });
+            return State::Closed;
+        }
+
+        match server.recv_open.try_recv() {
+            Ok(None) => State::Opening(server),
+            Ok(Some(next)) => {
+                events.push(ServerEvent::Opened);
+                State::Open(Open {
+                    recv_connecting: next.recv_connecting,
+                    clients: SlotMap::default(),
+                    _send_closed: next.send_closed,
+                })
+            }
+            Err(_) => {
+                events.push(ServerEvent::Closed {
+                    reason: ServerError::BackendClosed.into(),
+                });
+                State::Closed
+            }
+        }
+    }
+
+    fn poll_open(
+        mut server: Open,
+        events: &mut Vec<ServerEvent<Self>>,
+        delta_time: Duration,
+    ) -> State {
+        let res = (|| {
+            while let Ok(client) = server.recv_connecting.try_next() {
+                let client = client.ok_or(ServerError::BackendClosed)?;
+                let client_key = server.clients.insert(Client::Connecting(Connecting {
+                    offer: client.offer,
+                    recv_dc: client.recv_dc,
+                    recv_connected: client.recv_connected,
+                }));
+                let _ = client.send_key.send(client_key);
+                events.push(ServerEvent::Connecting { client_key });
+            }
+
+            for (client_key, client) in &mut server.clients {
+                let span = trace_span!(
+                    "client",
+                    key = field::debug(slotmap::Key::data(&client_key))
+                );
+                let _span = span.enter();
+
+                replace_with::replace_with_or_abort(client, |client_state| match client_state {
+                    Client::Disconnected => ClientState::Disconnected,
+                    Client::Connecting(client) => Self::poll_connecting(events, client_key, client),
+                    Client::Connected(client) => {
+                        Self::poll_connected(events, client_key, client, delta_time)
+                    }
+                });
+            }
+
+            server
+                .clients
+                .retain(|_, client| !matches!(client, Client::Disconnected));
+
+            Ok::<_, ServerError>(())
+        })();
+
+        match res {
+            Ok(()) => State::Open(server),
+            Err(err) => {
+                events.push(ServerEvent::Closed { reason: err.into() });
+                State::Closed
+            }
+        }
+    }
+
+    fn poll_connecting(
+        events: &mut Vec<ServerEvent<Self>>,
+        client_key: ClientKey,
+        mut client: Connecting,
+    ) -> Client {
+        let res = (|| {
+            if let Some(err) = client
+                .recv_dc
+                .try_recv()
+                .map_err(|_| ServerError::BackendClosed)?
+            {
+                return Err(err);
+            }
+
+            if let Ok(Some(next)) = client.recv_connected.try_recv() {
+                events.push(ServerEvent::Connected { client_key });
+                Ok(Client::Connected(Connected {
+                    inner: SessionFrontend {
+                        session: next.session,
+                        recv_dc: client.recv_dc,
+                        recv_packets: next.recv_c2s,
+                        send_packets: next.send_s2c,
+                        send_local_dc: next.send_local_dc,
+                        fatal_error: None,
+                    },
+                }))
+            } else {
+                Ok(Client::Connecting(client))
+            }
+        })();
+
+        match res {
+            Ok(client) => client,
+            Err(reason) => {
+                events.push(ServerEvent::Disconnected { client_key, reason });
+                Client::Disconnected
+            }
+        }
+    }
+
+    fn poll_connected(
+        events: &mut Vec<ServerEvent<Self>>,
+        client_key: ClientKey,
+        mut client: Connected,
+        delta_time: Duration,
+    ) -> Client {
+        let res = client.inner.poll(delta_time, |event| {
+            events.push(match event {
+                FrontendEvent::Ack { msg_key } => ServerEvent::Ack {
+                    client_key,
+                    msg_key,
+                },
+                FrontendEvent::Recv { msg, lane } => ServerEvent::Recv {
+                    client_key,
+                    msg,
+                    lane,
+                },
+            });
+        });
+
+        match res {
+            Ok(()) => Client::Connected(client),
+            Err(reason) => {
+                events.push(ServerEvent::Disconnected {
+                    client_key,
+                    reason: reason.map_err(From::from),
+                });
+                Client::Disconnected
+            }
+        }
+    }
+}
+
+impl Drop for WebRtcServer {
+    fn drop(&mut self) {
+        let _ = self.close(DROP_DISCONNECT_REASON);
+    }
+}
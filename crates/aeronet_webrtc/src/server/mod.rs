@@ -0,0 +1,232 @@
+//! Server-side transport implementation.
+
+mod backend;
+mod frontend;
+
+use std::fmt;
+
+use aeronet::{
+    client::{ClientState, DisconnectReason},
+    stats::{ConnectedAt, MessageStats, Rtt},
+};
+use aeronet_proto::{
+    frontend::{FrontendError, SessionFrontend},
+    session::{FatalSendError, OutOfMemory, SendError, Session},
+};
+use bytes::Bytes;
+use futures::channel::{mpsc, oneshot};
+use slotmap::SlotMap;
+use web_time::{Duration, Instant};
+use webrtc::{
+    api::setting_engine::SettingEngine, peer_connection::configuration::RTCConfiguration,
+};
+
+use crate::{internal::InternalError, shared::SessionDescription, signal::SignalError};
+
+/// Server network configuration.
+#[derive(Clone, Default)]
+pub struct ServerConfig {
+    /// Configuration of each peer connection, i.e. which ICE servers to use.
+    pub rtc: RTCConfiguration,
+    /// Lower-level `webrtc` settings, i.e. which network interfaces to gather
+    /// candidates from, or which UDP ports to use.
+    pub settings: SettingEngine,
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfig")
+            .field("ice_servers", &self.rtc.ice_servers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// WebRTC implementation of [`ServerTransport`].
+///
+/// See the [crate-level documentation](crate).
+///
+/// [`ServerTransport`]: aeronet::server::ServerTransport
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct WebRtcServer {
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed,
+    Opening(Opening),
+    Open(Open),
+    Closing { reason: String },
+}
+
+/// Error type for operations on a [`WebRtcServer`].
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    // frontend
+    /// Backend server task was cancelled, dropping the underlying connections.
+    #[error("backend closed")]
+    BackendClosed,
+    /// Server is already opening or open.
+    #[error("already opening or open")]
+    AlreadyOpen,
+    /// Server is already closed.
+    #[error("already closed")]
+    AlreadyClosed,
+    /// Server is not open.
+    #[error("not open")]
+    NotOpen,
+    /// Given client is not connected.
+    #[error("client not connected")]
+    ClientNotConnected,
+    /// See [`SendError`].
+    #[error(transparent)]
+    Send(SendError),
+    /// See [`FatalSendError`].
+    #[error(transparent)]
+    FatalSend(FatalSendError),
+    /// See [`OutOfMemory`].
+    #[error(transparent)]
+    OutOfMemory(OutOfMemory),
+
+    // backend
+    /// Server frontend was closed.
+    #[error("frontend closed")]
+    FrontendClosed,
+    /// Failed to receive offers from clients.
+    #[error("failed to receive signals")]
+    Signal(#[source] SignalError),
+    /// Error in the underlying WebRTC peer connection.
+    #[error("webrtc error")]
+    Rtc(#[source] webrtc::Error),
+
+    // connection
+    /// Lost connection.
+    #[error("connection lost")]
+    ConnectionLost,
+}
+
+impl From<InternalError<Self>> for ServerError {
+    fn from(value: InternalError<Self>) -> Self {
+        match value {
+            InternalError::Spec(err) => err,
+            InternalError::FrontendClosed => Self::FrontendClosed,
+            InternalError::Rtc(err) => Self::Rtc(err),
+            InternalError::ConnectionLost => Self::ConnectionLost,
+        }
+    }
+}
+
+impl From<FrontendError<Self>> for ServerError {
+    fn from(value: FrontendError<Self>) -> Self {
+        match value {
+            FrontendError::Spec(err) => err,
+            FrontendError::BackendClosed => Self::BackendClosed,
+            FrontendError::OutOfMemory(err) => Self::OutOfMemory(err),
+            FrontendError::Send(err) => Self::Send(err),
+            FrontendError::FatalSend(err) => Self::FatalSend(err),
+        }
+    }
+}
+
+slotmap::new_key_type! {
+    /// Key uniquely identifying a client in a [`WebRtcServer`].
+    ///
+    /// If the same physical client disconnects and reconnects (i.e. the same
+    /// process), this counts as a new client.
+    pub struct ClientKey;
+}
+
+/// State of a [`WebRtcServer`] when it is [`ServerState::Opening`].
+///
+/// [`ServerState::Opening`]: aeronet::server::ServerState::Opening
+#[derive(Debug)]
+pub struct Opening {
+    recv_open: oneshot::Receiver<ToOpen>,
+    recv_err: oneshot::Receiver<ServerError>,
+}
+
+#[derive(Debug)]
+struct ToOpen {
+    recv_connecting: mpsc::Receiver<ToConnecting>,
+    send_closed: oneshot::Sender<()>,
+}
+
+/// State of a [`WebRtcServer`] when it is [`ServerState::Open`].
+///
+/// [`ServerState::Open`]: aeronet::server::ServerState::Open
+#[derive(Debug)]
+pub struct Open {
+    recv_connecting: mpsc::Receiver<ToConnecting>,
+    clients: SlotMap<ClientKey, Client>,
+    _send_closed: oneshot::Sender<()>,
+}
+
+type Client = ClientState<Connecting, Connected>;
+
+#[derive(Debug)]
+struct ToConnecting {
+    offer: SessionDescription,
+    send_key: oneshot::Sender<ClientKey>,
+    recv_dc: oneshot::Receiver<DisconnectReason<ServerError>>,
+    recv_connected: oneshot::Receiver<ToConnected>,
+}
+
+/// State of a client connected to a [`WebRtcServer`] when it is
+/// [`ClientState::Connecting`].
+///
+/// The client has sent its offer to the server, but the peer connection has
+/// not been established yet.
+#[derive(Debug)]
+pub struct Connecting {
+    /// Offer which the client sent to the server.
+    pub offer: SessionDescription,
+    recv_dc: oneshot::Receiver<DisconnectReason<ServerError>>,
+    recv_connected: oneshot::Receiver<ToConnected>,
+}
+
+#[derive(Debug)]
+struct ToConnected {
+    recv_c2s: mpsc::Receiver<Bytes>,
+    send_s2c: mpsc::UnboundedSender<Bytes>,
+    send_local_dc: oneshot::Sender<String>,
+    session: Session,
+}
+
+/// State of a client connected to a [`WebRtcServer`] when it is
+/// [`ClientState::Connected`].
+#[derive(Debug)]
+pub struct Connected {
+    inner: SessionFrontend<ServerError>,
+}
+
+impl Connected {
+    /// Provides access to the underlying [`Session`] for reading more detailed
+    /// network statistics.
+    #[must_use]
+    pub const fn session(&self) -> &Session {
+        &self.inner.session
+    }
+}
+
+impl ConnectedAt for Connected {
+    fn connected_at(&self) -> Instant {
+        self.session().connected_at()
+    }
+}
+
+impl Rtt for Connected {
+    fn rtt(&self) -> Duration {
+        self.session().rtt().get()
+    }
+}
+
+impl MessageStats for Connected {
+    fn bytes_sent(&self) -> usize {
+        self.session().bytes_sent()
+    }
+
+    fn bytes_recv(&self) -> usize {
+        self.session().bytes_recv()
+    }
+}
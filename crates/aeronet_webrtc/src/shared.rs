@@ -0,0 +1,4 @@
+//! Items shared between the client and server.
+
+pub use aeronet_proto::session::MessageKey;
+pub use webrtc::peer_connection::sdp::session_description::RTCSessionDescription as SessionDescription;
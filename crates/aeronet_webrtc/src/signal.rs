@@ -0,0 +1,160 @@
+//! Abstraction over how clients and servers exchange session descriptions.
+//!
+//! Before a WebRTC connection can be established, the client and server must
+//! exchange [`SessionDescription`]s over some other channel - this is called
+//! *signalling*. WebRTC does not specify how this should be done, so this
+//! crate leaves it up to you: a client provides a [`ClientSignaller`] which
+//! sends its offer to the server and waits for the answer, and a server
+//! provides a [`ServerSignaller`] which yields offers from clients as
+//! [`SignalRequest`]s.
+//!
+//! ICE candidates are gathered in full before an offer or answer is produced,
+//! so signalling is always a single offer/answer exchange - there is no need
+//! to trickle candidates.
+//!
+//! [`SessionDescription`] implements `serde`'s `Serialize` and `Deserialize`,
+//! so it can be sent over e.g. an HTTP request or a WebSocket.
+//!
+//! For in-process connections and tests, use [`ChannelServerSignaller`].
+
+use std::error::Error;
+
+use futures::{
+    channel::{mpsc, oneshot},
+    future::BoxFuture,
+    FutureExt, StreamExt,
+};
+
+pub use crate::shared::SessionDescription;
+
+/// Error produced by a signaller.
+pub type SignalError = Box<dyn Error + Send + Sync>;
+
+/// Sends a client's offer to a server and receives the server's answer.
+pub trait ClientSignaller: Send + 'static {
+    /// Sends `offer` to the server, and waits for the server to respond with
+    /// its answer.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the offer could not be sent, or the answer could not be
+    /// received. This will make the client fail to connect.
+    fn exchange(
+        &mut self,
+        offer: SessionDescription,
+    ) -> BoxFuture<'_, Result<SessionDescription, SignalError>>;
+}
+
+/// Receives offers from clients attempting to connect to a server.
+pub trait ServerSignaller: Send + 'static {
+    /// Waits for the next client to send its offer.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the signaller can no longer receive offers. This will close
+    /// the server.
+    fn accept(&mut self) -> BoxFuture<'_, Result<SignalRequest, SignalError>>;
+}
+
+/// Offer sent by a client, which a server must [answer].
+///
+/// [answer]: SignalRequest::answer
+#[derive(Debug)]
+pub struct SignalRequest {
+    offer: SessionDescription,
+    send_answer: oneshot::Sender<SessionDescription>,
+}
+
+impl SignalRequest {
+    /// Creates a new request from a client's offer.
+    ///
+    /// The returned receiver resolves once the server has created its answer,
+    /// which should then be sent back to the client.
+    pub fn new(offer: SessionDescription) -> (Self, oneshot::Receiver<SessionDescription>) {
+        let (send_answer, recv_answer) = oneshot::channel();
+        (Self { offer, send_answer }, recv_answer)
+    }
+
+    /// Gets the client's offer.
+    #[must_use]
+    pub const fn offer(&self) -> &SessionDescription {
+        &self.offer
+    }
+
+    /// Responds to this request with the server's answer.
+    pub fn answer(self, answer: SessionDescription) {
+        // if the receiver was dropped, the client will fail to connect anyway
+        let _ = self.send_answer.send(answer);
+    }
+}
+
+/// In-process [`ServerSignaller`] which receives offers from
+/// [`ChannelClientSignaller`]s.
+///
+/// Use [`ChannelServerSignaller::client`] to create signallers for clients.
+#[derive(Debug)]
+pub struct ChannelServerSignaller {
+    send_req: mpsc::UnboundedSender<SignalRequest>,
+    recv_req: mpsc::UnboundedReceiver<SignalRequest>,
+}
+
+/// In-process [`ClientSignaller`] which sends offers to a
+/// [`ChannelServerSignaller`].
+#[derive(Debug, Clone)]
+pub struct ChannelClientSignaller {
+    send_req: mpsc::UnboundedSender<SignalRequest>,
+}
+
+impl Default for ChannelServerSignaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChannelServerSignaller {
+    /// Creates a new signaller with no clients.
+    #[must_use]
+    pub fn new() -> Self {
+        let (send_req, recv_req) = mpsc::unbounded();
+        Self { send_req, recv_req }
+    }
+
+    /// Creates a signaller which a client can use to send offers to this
+    /// server.
+    #[must_use]
+    pub fn client(&self) -> ChannelClientSignaller {
+        ChannelClientSignaller {
+            send_req: self.send_req.clone(),
+        }
+    }
+}
+
+impl ServerSignaller for ChannelServerSignaller {
+    fn accept(&mut self) -> BoxFuture<'_, Result<SignalRequest, SignalError>> {
+        async move {
+            Ok(self
+                .recv_req
+                .next()
+                .await
+                .expect("we hold a sender, so the channel should never close"))
+        }
+        .boxed()
+    }
+}
+
+impl ClientSignaller for ChannelClientSignaller {
+    fn exchange(
+        &mut self,
+        offer: SessionDescription,
+    ) -> BoxFuture<'_, Result<SessionDescription, SignalError>> {
+        async move {
+            let (req, recv_answer) = SignalRequest::new(offer);
+            self.send_req
+                .unbounded_send(req)
+                .map_err(|_| "server signaller closed")?;
+            let answer = recv_answer.await.map_err(|_| "server did not answer")?;
+            Ok(answer)
+        }
+        .boxed()
+    }
+}
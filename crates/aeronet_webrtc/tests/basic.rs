@@ -0,0 +1,200 @@
+//! Loopback tests for the WebRTC client and server, using in-process
+//! signalling.
+#![cfg(all(feature = "client", feature = "server"))]
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use aeronet::{
+    client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
+    lane::{LaneIndex, LaneKind},
+    server::{ServerEvent, ServerTransport},
+};
+use aeronet_proto::session::SessionConfig;
+use aeronet_webrtc::{
+    client::{ClientConfig, WebRtcClient},
+    runtime::WebRtcRuntime,
+    server::{ClientKey, ServerConfig, WebRtcServer},
+    signal::ChannelServerSignaller,
+};
+use assert_matches::assert_matches;
+
+const C2S: &[u8] = b"hello server";
+const S2C: &[u8] = b"hello client";
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+const DT: Duration = Duration::from_millis(10);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const REASON: &str = "disconnection reason here";
+
+fn session_config() -> SessionConfig {
+    SessionConfig::default().with_lanes([LaneKind::ReliableOrdered])
+}
+
+/// Polls the client and server until `f` returns [`Some`], or panics if this
+/// takes too long.
+fn poll_until<T>(
+    client: &mut WebRtcClient,
+    server: &mut WebRtcServer,
+    mut f: impl FnMut(Vec<ClientEvent<WebRtcClient>>, Vec<ServerEvent<WebRtcServer>>) -> Option<T>,
+) -> T {
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        let client_events = client.poll(DT).collect::<Vec<_>>();
+        let server_events = server.poll(DT).collect::<Vec<_>>();
+        if let Some(value) = f(client_events, server_events) {
+            return value;
+        }
+        let _ = client.flush();
+        let _ = server.flush();
+        thread::sleep(DT);
+    }
+}
+
+fn open() -> (WebRtcClient, WebRtcServer, ClientKey) {
+    let runtime = WebRtcRuntime::default();
+
+    let signaller = ChannelServerSignaller::new();
+    let client_signaller = signaller.client();
+
+    let mut server = WebRtcServer::new();
+    server
+        .open(
+            &runtime,
+            ServerConfig::default(),
+            session_config(),
+            signaller,
+        )
+        .unwrap();
+
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        let mut events = server.poll(DT);
+        if let Some(event) = events.next() {
+            assert_matches!(event, ServerEvent::Opened);
+            assert!(events.next().is_none());
+            break;
+        }
+        thread::sleep(DT);
+    }
+
+    let mut client = WebRtcClient::new();
+    client
+        .connect(
+            &runtime,
+            ClientConfig::default(),
+            session_config(),
+            client_signaller,
+        )
+        .unwrap();
+
+    let mut client_connected = false;
+    let mut server_connecting = None;
+    let target_key = poll_until(&mut client, &mut server, |client_events, server_events| {
+        let mut server_connected = None;
+        for event in client_events {
+            assert_matches!(event, ClientEvent::Connected);
+            client_connected = true;
+        }
+        for event in server_events {
+            match event {
+                ServerEvent::Connecting { client_key } => {
+                    assert!(server_connecting.is_none());
+                    server_connecting = Some(client_key);
+                }
+                ServerEvent::Connected { client_key } => {
+                    assert_eq!(Some(client_key), server_connecting);
+                    server_connected = Some(client_key);
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        server_connected.filter(|_| client_connected)
+    });
+
+    assert_matches!(client.state(), ClientState::Connected(_));
+    assert_matches!(server.client_state(target_key), ClientState::Connected(_));
+
+    (client, server, target_key)
+}
+
+#[test]
+fn send_recv() {
+    let (mut client, mut server, target_key) = open();
+
+    client.send(C2S, LANE).unwrap();
+    server.send(target_key, S2C, LANE).unwrap();
+    client.flush().unwrap();
+    server.flush().unwrap();
+
+    let mut client_recv = false;
+    let mut server_recv = false;
+    poll_until(&mut client, &mut server, |client_events, server_events| {
+        for event in client_events {
+            if let ClientEvent::Recv { msg, lane } = event {
+                assert_eq!(S2C, msg);
+                assert_eq!(LANE, lane);
+                client_recv = true;
+            }
+        }
+        for event in server_events {
+            if let ServerEvent::Recv {
+                client_key,
+                msg,
+                lane,
+            } = event
+            {
+                assert_eq!(target_key, client_key);
+                assert_eq!(C2S, msg);
+                assert_eq!(LANE, lane);
+                server_recv = true;
+            }
+        }
+        (client_recv && server_recv).then_some(())
+    });
+}
+
+#[test]
+fn client_disconnect() {
+    let (mut client, mut server, target_key) = open();
+
+    client.disconnect(REASON).unwrap();
+    poll_until(&mut client, &mut server, |client_events, server_events| {
+        for event in client_events {
+            assert_matches!(
+                event,
+                ClientEvent::Disconnected { reason: DisconnectReason::Local(reason) } if reason == REASON
+            );
+        }
+        server_events.into_iter().find_map(|event| match event {
+            ServerEvent::Disconnected { client_key, reason } => {
+                assert_eq!(target_key, client_key);
+                assert_matches!(reason, DisconnectReason::Remote(reason) if reason == REASON);
+                Some(())
+            }
+            _ => None,
+        })
+    });
+}
+
+#[test]
+fn server_disconnect() {
+    let (mut client, mut server, target_key) = open();
+
+    server.disconnect(target_key, REASON).unwrap();
+    poll_until(&mut client, &mut server, |client_events, server_events| {
+        assert!(server_events.is_empty());
+        client_events.into_iter().find_map(|event| match event {
+            ClientEvent::Disconnected { reason } => {
+                assert_matches!(reason, DisconnectReason::Remote(reason) if reason == REASON);
+                Some(())
+            }
+            _ => None,
+        })
+    });
+}
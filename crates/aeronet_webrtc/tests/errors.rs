@@ -0,0 +1,149 @@
+//! Tests for how the WebRTC client and server handle failures while
+//! signalling and establishing the peer connection.
+#![cfg(all(feature = "client", feature = "server"))]
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport, DisconnectReason},
+    lane::LaneKind,
+    server::{CloseReason, ServerEvent, ServerTransport},
+};
+use aeronet_proto::session::SessionConfig;
+use aeronet_webrtc::{
+    client::{ClientConfig, ClientError, WebRtcClient},
+    runtime::WebRtcRuntime,
+    server::{ServerConfig, ServerError, WebRtcServer},
+    signal::{
+        ChannelServerSignaller, ClientSignaller, ServerSignaller, SessionDescription, SignalError,
+        SignalRequest,
+    },
+};
+use assert_matches::assert_matches;
+use futures::{future::BoxFuture, FutureExt};
+
+const DT: Duration = Duration::from_millis(10);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn session_config() -> SessionConfig {
+    SessionConfig::default().with_lanes([LaneKind::ReliableOrdered])
+}
+
+/// Polls `server` until `f` returns [`Some`], or panics if this takes too
+/// long.
+fn poll_server_until<T>(
+    server: &mut WebRtcServer,
+    mut f: impl FnMut(ServerEvent<WebRtcServer>) -> Option<T>,
+) -> T {
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        for event in server.poll(DT) {
+            if let Some(value) = f(event) {
+                return value;
+            }
+        }
+        thread::sleep(DT);
+    }
+}
+
+/// [`ServerSignaller`] which can never receive offers.
+struct BrokenSignaller;
+
+impl ServerSignaller for BrokenSignaller {
+    fn accept(&mut self) -> BoxFuture<'_, Result<SignalRequest, SignalError>> {
+        async { Err("signalling server unreachable".into()) }.boxed()
+    }
+}
+
+#[test]
+fn client_signal_fails() {
+    let runtime = WebRtcRuntime::default();
+    // no server will ever answer this client
+    let client_signaller = ChannelServerSignaller::new().client();
+
+    let mut client = WebRtcClient::new();
+    client
+        .connect(
+            &runtime,
+            ClientConfig::default(),
+            session_config(),
+            client_signaller,
+        )
+        .unwrap();
+
+    let start = Instant::now();
+    let reason = loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        if let Some(ClientEvent::Disconnected { reason }) = client.poll(DT).next() {
+            break reason;
+        }
+        thread::sleep(DT);
+    };
+    assert_matches!(reason, DisconnectReason::Error(ClientError::Signal(_)));
+}
+
+#[test]
+fn server_signal_fails() {
+    let runtime = WebRtcRuntime::default();
+    let mut server = WebRtcServer::new();
+    server
+        .open(
+            &runtime,
+            ServerConfig::default(),
+            session_config(),
+            BrokenSignaller,
+        )
+        .unwrap();
+
+    let reason = poll_server_until(&mut server, |event| match event {
+        ServerEvent::Opened => None,
+        ServerEvent::Closed { reason } => Some(reason),
+        event => panic!("unexpected event {event:?}"),
+    });
+    assert_matches!(reason, CloseReason::Error(ServerError::Signal(_)));
+}
+
+#[test]
+fn invalid_offer() {
+    let runtime = WebRtcRuntime::default();
+    let signaller = ChannelServerSignaller::new();
+    let mut client_signaller = signaller.client();
+
+    let mut server = WebRtcServer::new();
+    server
+        .open(
+            &runtime,
+            ServerConfig::default(),
+            session_config(),
+            signaller,
+        )
+        .unwrap();
+
+    // the server drops the request without answering, so this finishes with
+    // an error once the server rejects the offer
+    let exchange = thread::spawn(move || {
+        futures::executor::block_on(client_signaller.exchange(SessionDescription::default()))
+    });
+
+    let mut connecting = None;
+    let reason = poll_server_until(&mut server, |event| match event {
+        ServerEvent::Opened => None,
+        ServerEvent::Connecting { client_key } => {
+            connecting = Some(client_key);
+            None
+        }
+        ServerEvent::Disconnected { client_key, reason } => {
+            assert_eq!(connecting, Some(client_key));
+            Some(reason)
+        }
+        event => panic!("unexpected event {event:?}"),
+    });
+    assert_matches!(reason, DisconnectReason::Error(ServerError::Rtc(_)));
+    assert!(exchange.join().unwrap().is_err());
+    // one bad offer must not take down the whole server
+    assert!(server.state().is_open());
+}
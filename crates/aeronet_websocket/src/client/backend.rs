@@ -0,0 +1,194 @@
+use aeronet::client::DisconnectReason;
+use aeronet_proto::session::{Session, SessionConfig};
+use bytes::Bytes;
+use futures::{
+    channel::{mpsc, oneshot},
+    never::Never,
+};
+use tracing::debug;
+use web_time::Instant;
+
+use crate::internal::{self, MTU};
+
+use super::{ClientConfig, ClientError, ToConnected};
+
+#[cfg(not(target_family = "wasm"))]
+pub async fn start(
+    net_config: ClientConfig,
+    session_config: SessionConfig,
+    target: String,
+    send_connected: oneshot::Sender<ToConnected>,
+) -> Result<Never, DisconnectReason<ClientError>> {
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::tungstenite::{client::IntoClientRequest, error::UrlError, Error};
+
+    debug!("Connecting to {target:?}");
+    let request = target
+        .into_client_request()
+        .map_err(|err| ClientError::Connect(Box::new(err)))?;
+    let uri = request.uri();
+    let host = uri
+        .host()
+        .ok_or_else(|| ClientError::Connect(Box::new(Error::Url(UrlError::NoHostName))))?;
+    let port = match (uri.port_u16(), uri.scheme_str()) {
+        (Some(port), _) => port,
+        (None, Some("wss")) => 443,
+        (None, Some("ws")) => 80,
+        (None, _) => {
+            return Err(
+                ClientError::Connect(Box::new(Error::Url(UrlError::UnsupportedUrlScheme))).into(),
+            )
+        }
+    };
+
+    let socket = TcpStream::connect(format!("{host}:{port}"))
+        .await
+        .map_err(|err| ClientError::Connect(Box::new(Error::Io(err))))?;
+    socket
+        .set_nodelay(net_config.nodelay)
+        .map_err(|err| ClientError::Connect(Box::new(Error::Io(err))))?;
+    let local_addr = socket.local_addr().map_err(ClientError::GetLocalAddr)?;
+    let remote_addr = socket
+        .peer_addr()
+        .map_err(|err| ClientError::Connect(Box::new(Error::Io(err))))?;
+
+    let (stream, _) = tokio_tungstenite::client_async_tls_with_config(
+        request,
+        socket,
+        Some(net_config.socket),
+        net_config.connector,
+    )
+    .await
+    .map_err(|err| ClientError::Connect(Box::new(err)))?;
+
+    let session = Session::client(Instant::now(), session_config, MTU, MTU)
+        .expect("MTU should be large enough for the session");
+
+    let (send_c2s, recv_c2s) = mpsc::unbounded::<Bytes>();
+    let (send_s2c, recv_s2c) = mpsc::channel::<Bytes>(internal::MSG_BUF_CAP);
+    let (send_local_dc, recv_local_dc) = oneshot::channel::<String>();
+    send_connected
+        .send(ToConnected {
+            local_addr,
+            remote_addr,
+            send_c2s,
+            recv_s2c,
+            send_local_dc,
+            session,
+        })
+        .map_err(|_| ClientError::FrontendClosed)?;
+
+    debug!("Starting connection loop");
+    internal::handle_connection(stream, recv_c2s, send_s2c, recv_local_dc)
+        .await
+        .map_err(|reason| reason.map_err(From::from))
+}
+
+#[cfg(target_family = "wasm")]
+pub async fn start(
+    net_config: ClientConfig,
+    session_config: SessionConfig,
+    target: String,
+    send_connected: oneshot::Sender<ToConnected>,
+) -> Result<Never, DisconnectReason<ClientError>> {
+    use futures::StreamExt;
+    use js_sys::{ArrayBuffer, Uint8Array};
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+    use crate::{internal::InternalError, shared::JsError};
+
+    // close code for a normal closure
+    // https://developer.mozilla.org/en-US/docs/Web/API/CloseEvent/code
+    const NORMAL_CLOSURE: u16 = 1000;
+
+    let _ = net_config;
+
+    debug!("Connecting to {target:?}");
+    let socket = WebSocket::new(&target).map_err(|err| ClientError::Connect(err.into()))?;
+    socket.set_binary_type(BinaryType::Arraybuffer);
+
+    let (send_open, mut recv_open) = oneshot::channel::<()>();
+    let (send_close, mut recv_close) = mpsc::unbounded::<CloseEvent>();
+    let (mut send_s2c, recv_s2c) = mpsc::channel::<Bytes>(internal::MSG_BUF_CAP);
+
+    let on_open = Closure::once(move || {
+        let _ = send_open.send(());
+    });
+    let on_message = Closure::<dyn FnMut(_)>::new(move |event: MessageEvent| {
+        let Ok(buf) = event.data().dyn_into::<ArrayBuffer>() else {
+            // we never send text frames
+            return;
+        };
+        let packet = Bytes::from(Uint8Array::new(&buf).to_vec());
+        // if the frontend can't keep up, drop the packet,
+        // and let the session resend it later
+        let _ = send_s2c.try_send(packet);
+    });
+    let on_close = Closure::<dyn FnMut(_)>::new(move |event: CloseEvent| {
+        let _ = send_close.unbounded_send(event);
+    });
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+    let close_reason = |event: Option<CloseEvent>| -> JsError {
+        event.map_or_else(
+            || JsError("socket closed".into()),
+            |event| JsError(format!("socket closed with code {}", event.code())),
+        )
+    };
+
+    futures::select! {
+        res = recv_open => res.map_err(|_| ClientError::BackendClosed)?,
+        event = recv_close.next() => {
+            return Err(ClientError::Connect(close_reason(event)).into());
+        }
+    }
+
+    let session = Session::client(Instant::now(), session_config, MTU, MTU)
+        .expect("MTU should be large enough for the session");
+
+    let (send_c2s, mut recv_c2s) = mpsc::unbounded::<Bytes>();
+    let (send_local_dc, mut recv_local_dc) = oneshot::channel::<String>();
+    send_connected
+        .send(ToConnected {
+            send_c2s,
+            recv_s2c,
+            send_local_dc,
+            session,
+        })
+        .map_err(|_| ClientError::FrontendClosed)?;
+
+    debug!("Starting connection loop");
+    let reason = loop {
+        futures::select! {
+            packet = recv_c2s.next() => {
+                let Some(packet) = packet else {
+                    break DisconnectReason::Error(InternalError::FrontendClosed);
+                };
+                if let Err(err) = socket.send_with_u8_array(&packet) {
+                    break DisconnectReason::Error(InternalError::ConnectionLost(err.into()));
+                }
+            }
+            event = recv_close.next() => {
+                break match event {
+                    Some(event) if event.was_clean() => DisconnectReason::Remote(event.reason()),
+                    event => DisconnectReason::Error(InternalError::ConnectionLost(close_reason(event))),
+                };
+            }
+            reason = recv_local_dc => {
+                if let Ok(reason) = reason {
+                    let _ = socket.close_with_code_and_reason(NORMAL_CLOSURE, &reason);
+                }
+                break DisconnectReason::Error(InternalError::FrontendClosed);
+            }
+        }
+    };
+
+    socket.set_onopen(None);
+    socket.set_onmessage(None);
+    socket.set_onclose(None);
+    drop((on_open, on_message, on_close));
+    Err(reason.map_err(From::from))
+}
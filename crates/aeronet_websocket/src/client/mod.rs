@@ -0,0 +1,237 @@
+//! Client-side transport implementation.
+
+mod backend;
+mod frontend;
+
+use aeronet::{
+    client::DisconnectReason,
+    stats::{ConnectedAt, MessageStats, Rtt},
+};
+use aeronet_proto::{
+    frontend::{FrontendError, SessionFrontend},
+    session::{FatalSendError, OutOfMemory, SendError, Session},
+};
+use bytes::Bytes;
+use futures::channel::{mpsc, oneshot};
+use web_time::{Duration, Instant};
+
+use crate::internal::InternalError;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_family = "wasm")] {
+        use crate::shared::JsError;
+
+        type ConnectError = JsError;
+        type ConnectionLostError = JsError;
+
+        /// Client network configuration.
+        ///
+        /// Browsers do not expose any configuration for WebSocket connections,
+        /// so this type is only used to keep the API the same across
+        /// platforms.
+        #[derive(Debug, Clone, Default)]
+        pub struct ClientConfig {
+            _priv: (),
+        }
+    } else {
+        use std::{io, net::SocketAddr};
+
+        use aeronet::stats::{LocalAddr, RemoteAddr};
+        use tokio_tungstenite::{tungstenite::protocol::WebSocketConfig, Connector};
+
+        type ConnectError = Box<tokio_tungstenite::tungstenite::Error>;
+        type ConnectionLostError = Box<tokio_tungstenite::tungstenite::Error>;
+
+        /// Client network configuration.
+        #[derive(Clone, Default)]
+        pub struct ClientConfig {
+            /// Configuration of the underlying WebSocket protocol.
+            pub socket: WebSocketConfig,
+            /// Whether to set `TCP_NODELAY` on the underlying socket, disabling
+            /// Nagle's algorithm.
+            ///
+            /// This is almost always what you want for a game, since it
+            /// reduces latency at the cost of sending more TCP segments.
+            pub nodelay: bool,
+            /// How to establish a TLS connection when connecting to a `wss://`
+            /// target.
+            ///
+            /// If [`None`], a default `rustls` configuration using the
+            /// `webpki` root certificates is used.
+            pub connector: Option<Connector>,
+        }
+
+        impl std::fmt::Debug for ClientConfig {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct("ClientConfig")
+                    .field("socket", &self.socket)
+                    .field("nodelay", &self.nodelay)
+                    .finish_non_exhaustive()
+            }
+        }
+    }
+}
+
+/// WebSocket implementation of [`ClientTransport`].
+///
+/// See the [crate-level documentation](crate).
+///
+/// [`ClientTransport`]: aeronet::client::ClientTransport
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct WebSocketClient {
+    state: State,
+}
+
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)] // most of the time we'll be connected anyway
+enum State {
+    Disconnected,
+    Connecting(Connecting),
+    Connected(Connected),
+    Disconnecting { reason: String },
+}
+
+/// Error type for operations on a [`WebSocketClient`].
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    // frontend
+    /// Backend client task was cancelled, dropping the underlying connection.
+    #[error("backend closed")]
+    BackendClosed,
+    /// Client is already connecting or connected.
+    #[error("already connecting or connected")]
+    AlreadyConnected,
+    /// Client is already disconnected.
+    #[error("already disconnected")]
+    AlreadyDisconnected,
+    /// Client is not connected.
+    #[error("not connected")]
+    NotConnected,
+    /// See [`SendError`].
+    #[error(transparent)]
+    Send(SendError),
+    /// See [`FatalSendError`].
+    #[error(transparent)]
+    FatalSend(FatalSendError),
+    /// See [`OutOfMemory`].
+    #[error(transparent)]
+    OutOfMemory(OutOfMemory),
+
+    // backend
+    /// Client frontend was closed.
+    #[error("frontend closed")]
+    FrontendClosed,
+    /// Failed to connect to the target.
+    #[error("failed to connect")]
+    Connect(#[source] ConnectError),
+    /// Failed to get the local address of the socket.
+    #[cfg(not(target_family = "wasm"))]
+    #[error("failed to get local address")]
+    GetLocalAddr(#[source] io::Error),
+
+    // connection
+    /// Lost connection.
+    #[error("connection lost")]
+    ConnectionLost(#[source] ConnectionLostError),
+}
+
+impl From<InternalError<Self>> for ClientError {
+    fn from(value: InternalError<Self>) -> Self {
+        match value {
+            InternalError::Spec(e) => e,
+            InternalError::FrontendClosed => Self::FrontendClosed,
+            InternalError::ConnectionLost(err) => Self::ConnectionLost(err),
+        }
+    }
+}
+
+impl From<FrontendError<Self>> for ClientError {
+    fn from(value: FrontendError<Self>) -> Self {
+        match value {
+            FrontendError::Spec(err) => err,
+            FrontendError::BackendClosed => Self::BackendClosed,
+            FrontendError::OutOfMemory(err) => Self::OutOfMemory(err),
+            FrontendError::Send(err) => Self::Send(err),
+            FrontendError::FatalSend(err) => Self::FatalSend(err),
+        }
+    }
+}
+
+/// State of a [`WebSocketClient`] when it is [`ClientState::Connecting`].
+///
+/// [`ClientState::Connecting`]: aeronet::client::ClientState::Connecting
+#[derive(Debug)]
+pub struct Connecting {
+    recv_connected: oneshot::Receiver<ToConnected>,
+    recv_dc: oneshot::Receiver<DisconnectReason<ClientError>>,
+}
+
+#[derive(Debug)]
+struct ToConnected {
+    #[cfg(not(target_family = "wasm"))]
+    local_addr: SocketAddr,
+    #[cfg(not(target_family = "wasm"))]
+    remote_addr: SocketAddr,
+    send_c2s: mpsc::UnboundedSender<Bytes>,
+    recv_s2c: mpsc::Receiver<Bytes>,
+    send_local_dc: oneshot::Sender<String>,
+    session: Session,
+}
+
+/// State of a [`WebSocketClient`] when it is [`ClientState::Connected`].
+///
+/// [`ClientState::Connected`]: aeronet::client::ClientState::Connected
+#[derive(Debug)]
+pub struct Connected {
+    #[cfg(not(target_family = "wasm"))]
+    local_addr: SocketAddr,
+    #[cfg(not(target_family = "wasm"))]
+    remote_addr: SocketAddr,
+    inner: SessionFrontend<ClientError>,
+}
+
+impl Connected {
+    /// Provides access to the underlying [`Session`] for reading more detailed
+    /// network statistics.
+    #[must_use]
+    pub const fn session(&self) -> &Session {
+        &self.inner.session
+    }
+}
+
+impl ConnectedAt for Connected {
+    fn connected_at(&self) -> Instant {
+        self.session().connected_at()
+    }
+}
+
+impl Rtt for Connected {
+    fn rtt(&self) -> Duration {
+        self.session().rtt().get()
+    }
+}
+
+impl MessageStats for Connected {
+    fn bytes_sent(&self) -> usize {
+        self.session().bytes_sent()
+    }
+
+    fn bytes_recv(&self) -> usize {
+        self.session().bytes_recv()
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl LocalAddr for Connected {
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl RemoteAddr for Connected {
+    fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+}
@@ -0,0 +1,79 @@
+use aeronet::client::DisconnectReason;
+use bytes::Bytes;
+use futures::{
+    channel::{mpsc, oneshot},
+    never::Never,
+    SinkExt, StreamExt,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message,
+    },
+    WebSocketStream,
+};
+use tracing::trace;
+
+use super::InternalError;
+
+pub async fn handle_connection<S, E>(
+    stream: WebSocketStream<S>,
+    mut recv_s: mpsc::UnboundedReceiver<Bytes>,
+    mut send_r: mpsc::Sender<Bytes>,
+    mut recv_local_dc: oneshot::Receiver<String>,
+) -> Result<Never, DisconnectReason<InternalError<E>>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (mut sink, stream) = stream.split();
+    let mut stream = stream.fuse();
+
+    loop {
+        futures::select! {
+            packet = recv_s.next() => {
+                let packet = packet.ok_or(InternalError::FrontendClosed)?;
+                sink.send(Message::binary(packet))
+                    .await
+                    .map_err(|err| InternalError::ConnectionLost(Box::new(err)))?;
+            }
+            msg = stream.next() => {
+                let msg = msg
+                    .unwrap_or(Err(tungstenite::Error::ConnectionClosed))
+                    .map_err(|err| InternalError::ConnectionLost(Box::new(err)))?;
+                match msg {
+                    Message::Binary(packet) => {
+                        send_r
+                            .send(Bytes::from(packet))
+                            .await
+                            .map_err(|_| InternalError::FrontendClosed)?;
+                    }
+                    Message::Close(frame) => {
+                        let reason = frame
+                            .map(|frame| frame.reason.into_owned())
+                            .unwrap_or_default();
+                        return Err(DisconnectReason::Remote(reason));
+                    }
+                    // pings and pongs are handled by tungstenite itself,
+                    // and we never send text frames
+                    msg => {
+                        trace!("Ignoring non-binary message {msg:?}");
+                    }
+                }
+            }
+            reason = recv_local_dc => {
+                if let Ok(reason) = reason {
+                    let frame = CloseFrame {
+                        code: CloseCode::Normal,
+                        reason: reason.into(),
+                    };
+                    // we're disconnecting anyway, don't care if this fails
+                    let _ = sink.send(Message::Close(Some(frame))).await;
+                    let _ = sink.close().await;
+                }
+                return Err(InternalError::FrontendClosed.into());
+            }
+        }
+    }
+}
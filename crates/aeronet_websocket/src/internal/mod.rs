@@ -0,0 +1,34 @@
+#[cfg(not(target_family = "wasm"))]
+mod backend;
+
+#[cfg(not(target_family = "wasm"))]
+pub use backend::*;
+
+pub const MSG_BUF_CAP: usize = 256;
+
+// WebSocket messages are carried over a reliable byte stream, so there is no
+// real path MTU to discover - this just bounds how large a single packet
+// produced by the session can be
+pub const MTU: usize = 16 * 1024;
+
+cfg_if::cfg_if! {
+    if #[cfg(target_family = "wasm")] {
+        pub type ConnectionError = crate::shared::JsError;
+    } else {
+        // boxed since the error type is very large
+        pub type ConnectionError = Box<tokio_tungstenite::tungstenite::Error>;
+    }
+}
+
+// intentionally don't derive Error so that consumers are forced to map each
+// variant to their own error variant
+#[derive(Debug)]
+pub enum InternalError<E> {
+    Spec(E),
+
+    // backend
+    FrontendClosed,
+
+    // connection
+    ConnectionLost(ConnectionError),
+}
@@ -0,0 +1,23 @@
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+#![cfg_attr(target_family = "wasm", allow(clippy::future_not_send))]
+#![doc = include_str!("../README.md")]
+
+pub use aeronet_proto as proto;
+
+#[cfg(not(target_family = "wasm"))]
+pub use {rustls, tokio_tungstenite};
+
+pub mod runtime;
+pub mod shared;
+
+#[cfg(any(feature = "client", feature = "server"))]
+mod internal;
+
+#[cfg(feature = "token")]
+pub mod token;
+
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(all(feature = "server", not(target_family = "wasm")))]
+pub mod server;
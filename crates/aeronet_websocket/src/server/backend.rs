@@ -0,0 +1,305 @@
+use std::{collections::HashMap, net::SocketAddr};
+
+use aeronet::{client::DisconnectReason, error::pretty_error};
+use aeronet_proto::session::{Session, SessionConfig};
+use bytes::Bytes;
+use futures::{
+    channel::{mpsc, oneshot},
+    never::Never,
+    FutureExt, SinkExt,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::TlsAcceptor;
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{Request, Response},
+    protocol::WebSocketConfig,
+};
+use tracing::{debug, debug_span, field, Instrument};
+use web_time::Instant;
+
+use crate::{
+    internal::{self, MTU},
+    runtime::WebSocketRuntime,
+};
+
+use super::{ClientKey, ServerConfig, ServerError, ToConnected, ToConnecting, ToOpen};
+
+#[cfg(feature = "token")]
+use {
+    crate::token::{self, ConnectTokenError},
+    aeronet_proto::token::{ConnectToken, ReplayCache, TokenKey},
+    std::sync::{Arc, Mutex},
+    tokio_tungstenite::tungstenite::{handshake::server::ErrorResponse, http::StatusCode},
+    web_time::SystemTime,
+};
+
+/// Verifies the connect tokens of clients connecting to a single open server,
+/// rejecting tokens which have already been used.
+#[cfg(feature = "token")]
+#[derive(Debug)]
+struct Tokens {
+    key: TokenKey,
+    used: Mutex<ReplayCache>,
+}
+
+#[cfg(feature = "token")]
+impl Tokens {
+    fn verify(
+        &self,
+        path: &str,
+        headers: &HashMap<String, String>,
+    ) -> Result<ConnectToken, ConnectTokenError> {
+        let token = token::find(path, headers).ok_or(ConnectTokenError::Missing)?;
+        let token = token::decode(token).map_err(ConnectTokenError::Base64)?;
+        let now = SystemTime::now();
+        let token = token
+            .verify(&self.key, now)
+            .map_err(ConnectTokenError::Verify)?;
+        self.used
+            .lock()
+            .expect("replay cache should not be poisoned")
+            .check(&token, now)
+            .map_err(ConnectTokenError::Verify)?;
+        Ok(token)
+    }
+}
+
+pub async fn start(
+    runtime: WebSocketRuntime,
+    net_config: ServerConfig,
+    session_config: SessionConfig,
+    send_open: oneshot::Sender<ToOpen>,
+) -> Result<Never, ServerError> {
+    let listener = TcpListener::bind(net_config.addr)
+        .await
+        .map_err(ServerError::Bind)?;
+    let local_addr = listener.local_addr().map_err(ServerError::GetLocalAddr)?;
+    let acceptor = net_config.tls.map(TlsAcceptor::from);
+    #[cfg(feature = "token")]
+    let tokens = net_config.token_key.map(|key| {
+        Arc::new(Tokens {
+            key,
+            used: Mutex::new(ReplayCache::new()),
+        })
+    });
+
+    let (send_closed, mut recv_closed) = oneshot::channel::<()>();
+    let (send_connecting, recv_connecting) = mpsc::channel::<ToConnecting>(4);
+    send_open
+        .send(ToOpen {
+            local_addr,
+            recv_connecting,
+            send_closed,
+        })
+        .map_err(|_| ServerError::FrontendClosed)?;
+
+    loop {
+        let (stream, remote_addr) = futures::select! {
+            _ = recv_closed => return Err(ServerError::FrontendClosed),
+            x = listener.accept().fuse() => match x {
+                Ok(x) => x,
+                Err(err) => {
+                    debug!("Failed to accept connection: {:#}", pretty_error(&err));
+                    continue;
+                }
+            },
+        };
+        if let Err(err) = stream.set_nodelay(net_config.nodelay) {
+            debug!("Failed to set TCP_NODELAY: {:#}", pretty_error(&err));
+        }
+
+        let socket_config = net_config.socket;
+        let acceptor = acceptor.clone();
+        let send_connecting = send_connecting.clone();
+        let session_config = session_config.clone();
+        #[cfg(feature = "token")]
+        let tokens = tokens.clone();
+        runtime.spawn(async move {
+            if let Err(err) = start_handle_session(
+                socket_config,
+                acceptor,
+                #[cfg(feature = "token")]
+                tokens,
+                session_config,
+                send_connecting,
+                stream,
+                remote_addr,
+            )
+            .await
+            {
+                debug!("Failed to start handling session: {:#}", pretty_error(&err));
+            }
+        });
+    }
+}
+
+async fn start_handle_session(
+    socket_config: WebSocketConfig,
+    acceptor: Option<TlsAcceptor>,
+    #[cfg(feature = "token")] tokens: Option<Arc<Tokens>>,
+    session_config: SessionConfig,
+    mut send_connecting: mpsc::Sender<ToConnecting>,
+    stream: TcpStream,
+    remote_addr: SocketAddr,
+) -> Result<(), ServerError> {
+    let (send_key, recv_key) = oneshot::channel::<ClientKey>();
+    let (send_dc, recv_dc) = oneshot::channel::<DisconnectReason<ServerError>>();
+    let (send_connected, recv_connected) = oneshot::channel::<ToConnected>();
+    send_connecting
+        .send(ToConnecting {
+            remote_addr,
+            send_key,
+            recv_dc,
+            recv_connected,
+        })
+        .await
+        .map_err(|_| ServerError::FrontendClosed)?;
+    let client_key = recv_key.await.map_err(|_| ServerError::FrontendClosed)?;
+
+    let err = async move {
+        let err = handle_session(
+            socket_config,
+            acceptor,
+            #[cfg(feature = "token")]
+            tokens,
+            session_config,
+            stream,
+            send_connected,
+        )
+        .await
+        .unwrap_err();
+        match &err {
+            DisconnectReason::Error(ServerError::FrontendClosed) => {
+                debug!("Session closed");
+            }
+            err => {
+                debug!("Session closed: {:#}", pretty_error(err));
+            }
+        }
+        err
+    }
+    .instrument(debug_span!(
+        "session",
+        client = field::debug(slotmap::Key::data(&client_key))
+    ))
+    .await;
+    let _ = send_dc.send(err);
+    Ok(())
+}
+
+async fn handle_session(
+    socket_config: WebSocketConfig,
+    acceptor: Option<TlsAcceptor>,
+    #[cfg(feature = "token")] tokens: Option<Arc<Tokens>>,
+    session_config: SessionConfig,
+    stream: TcpStream,
+    send_connected: oneshot::Sender<ToConnected>,
+) -> Result<Never, DisconnectReason<ServerError>> {
+    if let Some(acceptor) = acceptor {
+        let stream = acceptor.accept(stream).await.map_err(ServerError::Tls)?;
+        handle_stream(
+            socket_config,
+            #[cfg(feature = "token")]
+            tokens,
+            session_config,
+            stream,
+            send_connected,
+        )
+        .await
+    } else {
+        handle_stream(
+            socket_config,
+            #[cfg(feature = "token")]
+            tokens,
+            session_config,
+            stream,
+            send_connected,
+        )
+        .await
+    }
+}
+
+async fn handle_stream<S>(
+    socket_config: WebSocketConfig,
+    #[cfg(feature = "token")] tokens: Option<Arc<Tokens>>,
+    session_config: SessionConfig,
+    stream: S,
+    send_connected: oneshot::Sender<ToConnected>,
+) -> Result<Never, DisconnectReason<ServerError>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = None;
+    #[cfg(feature = "token")]
+    let mut token_result = None;
+    let stream = tokio_tungstenite::accept_hdr_async_with_config(
+        stream,
+        #[allow(clippy::result_large_err)] // signature required by tungstenite
+        |req: &Request, resp: Response| {
+            #[cfg_attr(not(feature = "token"), allow(unused_mut))]
+            let mut headers = req
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_owned()))
+                })
+                .collect::<HashMap<_, _>>();
+
+            #[cfg(feature = "token")]
+            if let Some(tokens) = &tokens {
+                let path = req.uri().path_and_query().map_or("", |path| path.as_str());
+                let result = tokens.verify(path, &headers);
+                let rejected = result.is_err();
+                token_result = Some(result);
+                if rejected {
+                    let mut resp = ErrorResponse::new(None);
+                    *resp.status_mut() = StatusCode::FORBIDDEN;
+                    return Err(resp);
+                }
+            }
+            #[cfg(feature = "token")]
+            headers.remove(token::HEADER);
+
+            request = Some((req.uri().path().to_owned(), headers));
+            Ok(resp)
+        },
+        Some(socket_config),
+    )
+    .await;
+    #[cfg(feature = "token")]
+    let identity = match token_result {
+        Some(Err(err)) => return Err(ServerError::ConnectToken(err).into()),
+        Some(Ok(token)) => Some(token),
+        None => None,
+    };
+    let stream = stream.map_err(|err| ServerError::Handshake(Box::new(err)))?;
+    let (path, headers) = request.unwrap_or_default();
+    debug!("New session request for {path}");
+
+    let session = Session::server(Instant::now(), session_config, MTU, MTU)
+        .expect("MTU should be large enough for the session");
+
+    let (send_c2s, recv_c2s) = mpsc::channel::<Bytes>(internal::MSG_BUF_CAP);
+    let (send_s2c, recv_s2c) = mpsc::unbounded::<Bytes>();
+    let (send_local_dc, recv_local_dc) = oneshot::channel::<String>();
+    send_connected
+        .send(ToConnected {
+            path,
+            headers,
+            #[cfg(feature = "token")]
+            identity,
+            recv_c2s,
+            send_s2c,
+            send_local_dc,
+            session,
+        })
+        .map_err(|_| ServerError::FrontendClosed)?;
+
+    debug!("Starting connection loop");
+    internal::handle_connection(stream, recv_s2c, send_c2s, recv_local_dc)
+        .await
+        .map_err(|err| err.map_err(From::from))
+}
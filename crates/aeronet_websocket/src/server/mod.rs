@@ -0,0 +1,320 @@
+//! Server-side transport implementation.
+
+mod backend;
+mod frontend;
+
+use std::{collections::HashMap, io, net::SocketAddr, sync::Arc};
+
+use aeronet::{
+    client::{ClientState, DisconnectReason},
+    stats::{ConnectedAt, MessageStats, RemoteAddr, Rtt},
+};
+use aeronet_proto::{
+    frontend::{FrontendError, SessionFrontend},
+    session::{FatalSendError, OutOfMemory, SendError, Session},
+};
+use bytes::Bytes;
+use futures::channel::{mpsc, oneshot};
+use slotmap::SlotMap;
+use tokio_tungstenite::tungstenite::{self, protocol::WebSocketConfig};
+use web_time::{Duration, Instant};
+
+use crate::internal::InternalError;
+
+#[cfg(feature = "token")]
+use {
+    crate::token::ConnectTokenError,
+    aeronet_proto::token::{ConnectToken, TokenKey},
+};
+
+/// Server network configuration.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Address to bind the listening socket to.
+    pub addr: SocketAddr,
+    /// Configuration of the underlying WebSocket protocol.
+    pub socket: WebSocketConfig,
+    /// Whether to set `TCP_NODELAY` on accepted sockets, disabling Nagle's
+    /// algorithm.
+    pub nodelay: bool,
+    /// TLS configuration used to accept `wss://` connections.
+    ///
+    /// If [`None`], the server only accepts plain `ws://` connections. Note
+    /// that browsers will refuse to connect to a `ws://` server from a page
+    /// served over HTTPS.
+    pub tls: Option<Arc<rustls::ServerConfig>>,
+    /// Key used to verify the connect token which each client must send in
+    /// its WebSocket request.
+    ///
+    /// If [`Some`], clients without a valid token, or with a token which has
+    /// already been used to connect, are rejected during the handshake with
+    /// `403 Forbidden`. See [`crate::token`].
+    #[cfg(feature = "token")]
+    pub token_key: Option<TokenKey>,
+}
+
+impl ServerConfig {
+    /// Creates a configuration for a server listening on `addr` with no TLS.
+    #[must_use]
+    pub fn new(addr: impl Into<SocketAddr>) -> Self {
+        Self {
+            addr: addr.into(),
+            socket: WebSocketConfig::default(),
+            nodelay: true,
+            tls: None,
+            #[cfg(feature = "token")]
+            token_key: None,
+        }
+    }
+
+    /// Sets the TLS configuration used to accept `wss://` connections.
+    #[must_use]
+    pub fn with_tls(self, tls: impl Into<Arc<rustls::ServerConfig>>) -> Self {
+        Self {
+            tls: Some(tls.into()),
+            ..self
+        }
+    }
+
+    /// Sets the key used to verify clients' connect tokens, rejecting any
+    /// client without a valid token.
+    #[cfg(feature = "token")]
+    #[must_use]
+    pub fn with_token_key(self, token_key: TokenKey) -> Self {
+        Self {
+            token_key: Some(token_key),
+            ..self
+        }
+    }
+}
+
+/// WebSocket implementation of [`ServerTransport`].
+///
+/// See the [crate-level documentation](crate).
+///
+/// [`ServerTransport`]: aeronet::server::ServerTransport
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct WebSocketServer {
+    state: State,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed,
+    Opening(Opening),
+    Open(Open),
+    Closing { reason: String },
+}
+
+/// Error type for operations on a [`WebSocketServer`].
+#[derive(Debug, thiserror::Error)]
+pub enum ServerError {
+    // frontend
+    /// Backend server task was cancelled, dropping the underlying connections.
+    #[error("backend closed")]
+    BackendClosed,
+    /// Server is already opening or open.
+    #[error("already opening or open")]
+    AlreadyOpen,
+    /// Server is already closed.
+    #[error("already closed")]
+    AlreadyClosed,
+    /// Server is not open.
+    #[error("not open")]
+    NotOpen,
+    /// Given client is not connected.
+    #[error("client not connected")]
+    ClientNotConnected,
+    /// See [`SendError`].
+    #[error(transparent)]
+    Send(SendError),
+    /// See [`FatalSendError`].
+    #[error(transparent)]
+    FatalSend(FatalSendError),
+    /// See [`OutOfMemory`].
+    #[error(transparent)]
+    OutOfMemory(OutOfMemory),
+
+    // backend
+    /// Server frontend was closed.
+    #[error("frontend closed")]
+    FrontendClosed,
+    /// Failed to bind the listening socket.
+    #[error("failed to bind socket")]
+    Bind(#[source] io::Error),
+    /// Failed to get our socket's local address.
+    #[error("failed to get local address")]
+    GetLocalAddr(#[source] io::Error),
+    /// Failed to perform the TLS handshake with the client.
+    #[error("failed to perform TLS handshake")]
+    Tls(#[source] io::Error),
+    /// Failed to perform the WebSocket handshake with the client.
+    #[error("failed to perform WebSocket handshake")]
+    Handshake(#[source] Box<tungstenite::Error>),
+    /// Client did not send a valid connect token in its WebSocket request.
+    #[cfg(feature = "token")]
+    #[error("invalid connect token")]
+    ConnectToken(#[source] ConnectTokenError),
+
+    // connection
+    /// Lost connection.
+    #[error("connection lost")]
+    ConnectionLost(#[source] Box<tungstenite::Error>),
+}
+
+impl From<InternalError<Self>> for ServerError {
+    fn from(value: InternalError<Self>) -> Self {
+        match value {
+            InternalError::Spec(err) => err,
+            InternalError::FrontendClosed => Self::FrontendClosed,
+            InternalError::ConnectionLost(err) => Self::ConnectionLost(err),
+        }
+    }
+}
+
+impl From<FrontendError<Self>> for ServerError {
+    fn from(value: FrontendError<Self>) -> Self {
+        match value {
+            FrontendError::Spec(err) => err,
+            FrontendError::BackendClosed => Self::BackendClosed,
+            FrontendError::OutOfMemory(err) => Self::OutOfMemory(err),
+            FrontendError::Send(err) => Self::Send(err),
+            FrontendError::FatalSend(err) => Self::FatalSend(err),
+        }
+    }
+}
+
+slotmap::new_key_type! {
+    /// Key uniquely identifying a client in a [`WebSocketServer`].
+    ///
+    /// If the same physical client disconnects and reconnects (i.e. the same
+    /// process), this counts as a new client.
+    pub struct ClientKey;
+}
+
+/// State of a [`WebSocketServer`] when it is [`ServerState::Opening`].
+///
+/// [`ServerState::Opening`]: aeronet::server::ServerState::Opening
+#[derive(Debug)]
+pub struct Opening {
+    recv_open: oneshot::Receiver<ToOpen>,
+    recv_err: oneshot::Receiver<ServerError>,
+}
+
+#[derive(Debug)]
+struct ToOpen {
+    local_addr: SocketAddr,
+    recv_connecting: mpsc::Receiver<ToConnecting>,
+    send_closed: oneshot::Sender<()>,
+}
+
+/// State of a [`WebSocketServer`] when it is [`ServerState::Open`].
+///
+/// [`ServerState::Open`]: aeronet::server::ServerState::Open
+#[derive(Debug)]
+pub struct Open {
+    /// Address of the local socket that this server is bound to.
+    pub local_addr: SocketAddr,
+    recv_connecting: mpsc::Receiver<ToConnecting>,
+    clients: SlotMap<ClientKey, Client>,
+    _send_closed: oneshot::Sender<()>,
+}
+
+type Client = ClientState<Connecting, Connected>;
+
+#[derive(Debug)]
+struct ToConnecting {
+    remote_addr: SocketAddr,
+    send_key: oneshot::Sender<ClientKey>,
+    recv_dc: oneshot::Receiver<DisconnectReason<ServerError>>,
+    recv_connected: oneshot::Receiver<ToConnected>,
+}
+
+/// State of a client connected to a [`WebSocketServer`] when it is
+/// [`ClientState::Connecting`].
+///
+/// The client has opened a TCP connection to the server, but has not yet
+/// finished the TLS and WebSocket handshakes.
+#[derive(Debug)]
+pub struct Connecting {
+    /// Address of the client's socket.
+    pub remote_addr: SocketAddr,
+    recv_dc: oneshot::Receiver<DisconnectReason<ServerError>>,
+    recv_connected: oneshot::Receiver<ToConnected>,
+}
+
+#[derive(Debug)]
+struct ToConnected {
+    path: String,
+    headers: HashMap<String, String>,
+    #[cfg(feature = "token")]
+    identity: Option<ConnectToken>,
+    recv_c2s: mpsc::Receiver<Bytes>,
+    send_s2c: mpsc::UnboundedSender<Bytes>,
+    send_local_dc: oneshot::Sender<String>,
+    session: Session,
+}
+
+/// State of a client connected to a [`WebSocketServer`] when it is
+/// [`ClientState::Connected`].
+#[derive(Debug)]
+pub struct Connected {
+    /// Path of the HTTP request which the client used to open the WebSocket.
+    pub path: String,
+    /// All headers present in the HTTP request which the client used to open
+    /// the WebSocket.
+    ///
+    /// The connect token header is removed from this.
+    pub headers: HashMap<String, String>,
+    #[cfg(feature = "token")]
+    identity: Option<ConnectToken>,
+    remote_addr: SocketAddr,
+    inner: SessionFrontend<ServerError>,
+}
+
+impl Connected {
+    /// Gets the verified connect token which this client connected with.
+    ///
+    /// This is [`None`] if the server has no [`ServerConfig::token_key`].
+    #[cfg(feature = "token")]
+    #[must_use]
+    pub const fn identity(&self) -> Option<&ConnectToken> {
+        self.identity.as_ref()
+    }
+
+    /// Provides access to the underlying [`Session`] for reading more detailed
+    /// network statistics.
+    #[must_use]
+    pub const fn session(&self) -> &Session {
+        &self.inner.session
+    }
+}
+
+impl ConnectedAt for Connected {
+    fn connected_at(&self) -> Instant {
+        self.session().connected_at()
+    }
+}
+
+impl Rtt for Connected {
+    fn rtt(&self) -> Duration {
+        self.session().rtt().get()
+    }
+}
+
+impl MessageStats for Connected {
+    fn bytes_sent(&self) -> usize {
+        self.session().bytes_sent()
+    }
+
+    fn bytes_recv(&self) -> usize {
+        self.session().bytes_recv()
+    }
+}
+
+impl RemoteAddr for Connected {
+    fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+}
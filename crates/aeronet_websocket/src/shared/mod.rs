@@ -0,0 +1,8 @@
+//! Items shared between the client and server.
+
+#[cfg(target_family = "wasm")]
+mod js_error;
+#[cfg(target_family = "wasm")]
+pub use js_error::*;
+
+pub use aeronet_proto::session::MessageKey;
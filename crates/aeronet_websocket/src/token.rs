@@ -0,0 +1,16 @@
+//! Helpers for passing a [`SignedToken`] from a client to a server in the
+//! WebSocket upgrade request.
+//!
+//! Browsers don't allow setting custom headers on a WebSocket request, so
+//! clients should use [`with_token`] to add the token to the query string of
+//! the URL they connect to. Native clients and proxies may instead set the
+//! [`HEADER`] header.
+//!
+//! These are the same helpers as in [`aeronet_proto::token::request`], so the
+//! same token URL works for any transport with an HTTP request step.
+//!
+//! [`SignedToken`]: aeronet_proto::token::SignedToken
+
+pub use aeronet_proto::token::request::{
+    decode, encode, find, with_token, ConnectTokenError, HEADER, QUERY_PARAM,
+};
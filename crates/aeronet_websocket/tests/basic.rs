@@ -0,0 +1,202 @@
+//! Loopback tests for the native WebSocket client and server.
+#![cfg(all(feature = "client", feature = "server"))]
+
+use std::{
+    net::Ipv4Addr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use aeronet::{
+    client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
+    lane::{LaneIndex, LaneKind},
+    server::{ServerEvent, ServerState, ServerTransport},
+    stats::RemoteAddr,
+};
+use aeronet_proto::session::SessionConfig;
+use aeronet_websocket::{
+    client::{ClientConfig, WebSocketClient},
+    runtime::WebSocketRuntime,
+    server::{ClientKey, ServerConfig, WebSocketServer},
+};
+use assert_matches::assert_matches;
+
+const C2S: &[u8] = b"hello server";
+const S2C: &[u8] = b"hello client";
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+const DT: Duration = Duration::from_millis(10);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+const REASON: &str = "disconnection reason here";
+
+fn session_config() -> SessionConfig {
+    SessionConfig::default().with_lanes([LaneKind::ReliableOrdered])
+}
+
+/// Polls the client and server until `f` returns [`Some`], or panics if this
+/// takes too long.
+fn poll_until<T>(
+    client: &mut WebSocketClient,
+    server: &mut WebSocketServer,
+    mut f: impl FnMut(Vec<ClientEvent<WebSocketClient>>, Vec<ServerEvent<WebSocketServer>>) -> Option<T>,
+) -> T {
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        let client_events = client.poll(DT).collect::<Vec<_>>();
+        let server_events = server.poll(DT).collect::<Vec<_>>();
+        if let Some(value) = f(client_events, server_events) {
+            return value;
+        }
+        let _ = client.flush();
+        let _ = server.flush();
+        thread::sleep(DT);
+    }
+}
+
+fn open() -> (WebSocketClient, WebSocketServer, ClientKey) {
+    let runtime = WebSocketRuntime::default();
+
+    let mut server = WebSocketServer::new();
+    server
+        .open(
+            &runtime,
+            ServerConfig::new((Ipv4Addr::LOCALHOST, 0)),
+            session_config(),
+        )
+        .unwrap();
+
+    let start = Instant::now();
+    let local_addr = loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        for event in server.poll(DT) {
+            assert_matches!(event, ServerEvent::Opened);
+        }
+        if let ServerState::Open(server) = server.state() {
+            break server.local_addr;
+        }
+        thread::sleep(DT);
+    };
+
+    let mut client = WebSocketClient::new();
+    client
+        .connect(
+            &runtime,
+            ClientConfig::default(),
+            session_config(),
+            format!("ws://{local_addr}"),
+        )
+        .unwrap();
+
+    let mut client_connected = false;
+    let mut server_connecting = None;
+    let target_key = poll_until(&mut client, &mut server, |client_events, server_events| {
+        let mut server_connected = None;
+        for event in client_events {
+            assert_matches!(event, ClientEvent::Connected);
+            client_connected = true;
+        }
+        for event in server_events {
+            match event {
+                ServerEvent::Connecting { client_key } => {
+                    assert!(server_connecting.is_none());
+                    server_connecting = Some(client_key);
+                }
+                ServerEvent::Connected { client_key } => {
+                    assert_eq!(Some(client_key), server_connecting);
+                    server_connected = Some(client_key);
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        server_connected.filter(|_| client_connected)
+    });
+
+    let ClientState::Connected(connected) = client.state() else {
+        panic!("client should be connected");
+    };
+    assert_eq!(local_addr, connected.remote_addr());
+    let ClientState::Connected(connected) = server.client_state(target_key) else {
+        panic!("client should be connected to server");
+    };
+    assert_eq!("/", connected.path);
+
+    (client, server, target_key)
+}
+
+#[test]
+fn send_recv() {
+    let (mut client, mut server, target_key) = open();
+
+    client.send(C2S, LANE).unwrap();
+    server.send(target_key, S2C, LANE).unwrap();
+    client.flush().unwrap();
+    server.flush().unwrap();
+
+    let mut client_recv = false;
+    let mut server_recv = false;
+    poll_until(&mut client, &mut server, |client_events, server_events| {
+        for event in client_events {
+            if let ClientEvent::Recv { msg, lane } = event {
+                assert_eq!(S2C, msg);
+                assert_eq!(LANE, lane);
+                client_recv = true;
+            }
+        }
+        for event in server_events {
+            if let ServerEvent::Recv {
+                client_key,
+                msg,
+                lane,
+            } = event
+            {
+                assert_eq!(target_key, client_key);
+                assert_eq!(C2S, msg);
+                assert_eq!(LANE, lane);
+                server_recv = true;
+            }
+        }
+        (client_recv && server_recv).then_some(())
+    });
+}
+
+#[test]
+fn client_disconnect() {
+    let (mut client, mut server, target_key) = open();
+
+    client.disconnect(REASON).unwrap();
+    poll_until(&mut client, &mut server, |client_events, server_events| {
+        for event in client_events {
+            assert_matches!(
+                event,
+                ClientEvent::Disconnected { reason: DisconnectReason::Local(reason) } if reason == REASON
+            );
+        }
+        server_events.into_iter().find_map(|event| match event {
+            ServerEvent::Disconnected { client_key, reason } => {
+                assert_eq!(target_key, client_key);
+                assert_matches!(reason, DisconnectReason::Remote(reason) if reason == REASON);
+                Some(())
+            }
+            _ => None,
+        })
+    });
+}
+
+#[test]
+fn server_disconnect() {
+    let (mut client, mut server, target_key) = open();
+
+    server.disconnect(target_key, REASON).unwrap();
+    poll_until(&mut client, &mut server, |client_events, server_events| {
+        assert!(server_events.is_empty());
+        client_events.into_iter().find_map(|event| match event {
+            ClientEvent::Disconnected { reason } => {
+                assert_matches!(reason, DisconnectReason::Remote(reason) if reason == REASON);
+                Some(())
+            }
+            _ => None,
+        })
+    });
+}
@@ -0,0 +1,177 @@
+//! Tests for how the native WebSocket client and server handle failing
+//! connections and misbehaving peers.
+#![cfg(all(feature = "client", feature = "server"))]
+
+use std::{
+    io::Write,
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport, DisconnectReason},
+    lane::LaneKind,
+    server::{CloseReason, ServerEvent, ServerState, ServerTransport},
+};
+use aeronet_proto::session::SessionConfig;
+use aeronet_websocket::{
+    client::{ClientConfig, ClientError, WebSocketClient},
+    runtime::WebSocketRuntime,
+    server::{ServerConfig, ServerError, WebSocketServer},
+};
+use assert_matches::assert_matches;
+use tokio_tungstenite::tungstenite;
+
+const DT: Duration = Duration::from_millis(10);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn session_config() -> SessionConfig {
+    SessionConfig::default().with_lanes([LaneKind::ReliableOrdered])
+}
+
+/// Polls `server` until `f` returns [`Some`], or panics if this takes too
+/// long.
+fn poll_server_until<T>(
+    server: &mut WebSocketServer,
+    mut f: impl FnMut(ServerEvent<WebSocketServer>) -> Option<T>,
+) -> T {
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        for event in server.poll(DT) {
+            if let Some(value) = f(event) {
+                return value;
+            }
+        }
+        thread::sleep(DT);
+    }
+}
+
+fn open_server(runtime: &WebSocketRuntime) -> (WebSocketServer, SocketAddr) {
+    let mut server = WebSocketServer::new();
+    server
+        .open(
+            runtime,
+            ServerConfig::new((Ipv4Addr::LOCALHOST, 0)),
+            session_config(),
+        )
+        .unwrap();
+    poll_server_until(&mut server, |event| {
+        assert_matches!(event, ServerEvent::Opened);
+        Some(())
+    });
+    let ServerState::Open(open) = server.state() else {
+        panic!("server should be open");
+    };
+    let local_addr = open.local_addr;
+    (server, local_addr)
+}
+
+/// Gets an address which nothing is listening on.
+fn unused_addr() -> SocketAddr {
+    TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+#[test]
+fn connect_refused() {
+    let runtime = WebSocketRuntime::default();
+    let mut client = WebSocketClient::new();
+    client
+        .connect(
+            &runtime,
+            ClientConfig::default(),
+            session_config(),
+            format!("ws://{}", unused_addr()),
+        )
+        .unwrap();
+
+    let start = Instant::now();
+    let reason = loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        if let Some(ClientEvent::Disconnected { reason }) = client.poll(DT).next() {
+            break reason;
+        }
+        thread::sleep(DT);
+    };
+    assert_matches!(reason, DisconnectReason::Error(ClientError::Connect(_)));
+}
+
+#[test]
+fn bind_in_use() {
+    let runtime = WebSocketRuntime::default();
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+
+    let mut server = WebSocketServer::new();
+    server
+        .open(
+            &runtime,
+            ServerConfig::new(listener.local_addr().unwrap()),
+            session_config(),
+        )
+        .unwrap();
+    let reason = poll_server_until(&mut server, |event| match event {
+        ServerEvent::Closed { reason } => Some(reason),
+        event => panic!("unexpected event {event:?}"),
+    });
+    assert_matches!(reason, CloseReason::Error(ServerError::Bind(_)));
+}
+
+#[test]
+fn invalid_handshake() {
+    let runtime = WebSocketRuntime::default();
+    let (mut server, local_addr) = open_server(&runtime);
+
+    let mut stream = TcpStream::connect(local_addr).unwrap();
+    stream
+        .write_all(b"not a websocket request\r\n\r\n")
+        .unwrap();
+
+    let mut connecting = None;
+    let reason = poll_server_until(&mut server, |event| match event {
+        ServerEvent::Connecting { client_key } => {
+            connecting = Some(client_key);
+            None
+        }
+        ServerEvent::Disconnected { client_key, reason } => {
+            assert_eq!(connecting, Some(client_key));
+            Some(reason)
+        }
+        event => panic!("unexpected event {event:?}"),
+    });
+    assert_matches!(reason, DisconnectReason::Error(ServerError::Handshake(_)));
+    // one bad peer must not take down the whole server
+    assert!(server.state().is_open());
+}
+
+#[test]
+fn peer_drops_without_close_frame() {
+    let runtime = WebSocketRuntime::default();
+    let (mut server, local_addr) = open_server(&runtime);
+
+    // the handshake only completes once the server is polled
+    let handshake = thread::spawn(move || {
+        let stream = TcpStream::connect(local_addr).unwrap();
+        tungstenite::client(format!("ws://{local_addr}"), stream).unwrap()
+    });
+    poll_server_until(&mut server, |event| match event {
+        ServerEvent::Connecting { .. } => None,
+        ServerEvent::Connected { .. } => Some(()),
+        event => panic!("unexpected event {event:?}"),
+    });
+    let (socket, _) = handshake.join().unwrap();
+
+    // drop the TCP connection without sending a close frame
+    drop(socket);
+    let reason = poll_server_until(&mut server, |event| match event {
+        ServerEvent::Disconnected { reason, .. } => Some(reason),
+        _ => None,
+    });
+    assert_matches!(
+        reason,
+        DisconnectReason::Error(ServerError::ConnectionLost(_))
+    );
+}
@@ -0,0 +1,163 @@
+//! Tests for verifying connect tokens in the WebSocket handshake.
+#![cfg(all(feature = "client", feature = "server", feature = "token"))]
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use aeronet::{
+    client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
+    lane::LaneKind,
+    server::{ServerEvent, ServerState, ServerTransport},
+};
+use aeronet_proto::{
+    session::SessionConfig,
+    token::{ConnectToken, SignedToken, TokenError, TokenKey},
+};
+use aeronet_websocket::{
+    client::{ClientConfig, WebSocketClient},
+    runtime::WebSocketRuntime,
+    server::{ClientKey, ServerConfig, ServerError, WebSocketServer},
+    token::{self, ConnectTokenError},
+};
+use assert_matches::assert_matches;
+
+const DT: Duration = Duration::from_millis(10);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+const USER_ID: u64 = 42;
+
+fn session_config() -> SessionConfig {
+    SessionConfig::default().with_lanes([LaneKind::ReliableOrdered])
+}
+
+const fn key() -> TokenKey {
+    TokenKey::from_bytes([3; 32])
+}
+
+fn signed_token() -> SignedToken {
+    ConnectToken::new(USER_ID, "lobby", SystemTime::now(), Duration::from_secs(60)).sign(&key())
+}
+
+fn open_server(runtime: &WebSocketRuntime) -> (WebSocketServer, SocketAddr) {
+    let mut server = WebSocketServer::new();
+    server
+        .open(
+            runtime,
+            ServerConfig::new((Ipv4Addr::LOCALHOST, 0)).with_token_key(key()),
+            session_config(),
+        )
+        .unwrap();
+
+    let start = Instant::now();
+    let local_addr = loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        for event in server.poll(DT) {
+            assert_matches!(event, ServerEvent::Opened);
+        }
+        if let ServerState::Open(server) = server.state() {
+            break server.local_addr;
+        }
+        thread::sleep(DT);
+    };
+    (server, local_addr)
+}
+
+fn connect(runtime: &WebSocketRuntime, target: String) -> WebSocketClient {
+    let mut client = WebSocketClient::new();
+    client
+        .connect(runtime, ClientConfig::default(), session_config(), target)
+        .unwrap();
+    client
+}
+
+/// Polls the server until it finishes handling a client's handshake,
+/// returning the client's key and whether it was accepted or rejected.
+fn handshake(
+    server: &mut WebSocketServer,
+) -> (ClientKey, Result<(), DisconnectReason<ServerError>>) {
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        for event in server.poll(DT) {
+            match event {
+                ServerEvent::Connecting { .. } => {}
+                ServerEvent::Connected { client_key } => return (client_key, Ok(())),
+                ServerEvent::Disconnected { client_key, reason } => {
+                    return (client_key, Err(reason))
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        thread::sleep(DT);
+    }
+}
+
+#[test]
+fn valid_token() {
+    let runtime = WebSocketRuntime::default();
+    let (mut server, local_addr) = open_server(&runtime);
+
+    let target = token::with_token(&format!("ws://{local_addr}/lobby"), &signed_token());
+    let _client = connect(&runtime, target);
+    let (client_key, result) = handshake(&mut server);
+    result.unwrap();
+
+    let ClientState::Connected(connected) = server.client_state(client_key) else {
+        panic!("client should be connected");
+    };
+    assert_eq!("/lobby", connected.path);
+    let identity = connected.identity().unwrap();
+    assert_eq!(USER_ID, identity.user_id);
+    assert_eq!(&b"lobby"[..], identity.payload);
+}
+
+#[test]
+fn missing_token() {
+    let runtime = WebSocketRuntime::default();
+    let (mut server, local_addr) = open_server(&runtime);
+
+    let mut client = connect(&runtime, format!("ws://{local_addr}/lobby"));
+    let (_, result) = handshake(&mut server);
+    assert_matches!(
+        result,
+        Err(DisconnectReason::Error(ServerError::ConnectToken(
+            ConnectTokenError::Missing
+        )))
+    );
+
+    // the client sees the handshake being refused
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        if client
+            .poll(DT)
+            .any(|event| matches!(event, ClientEvent::Disconnected { .. }))
+        {
+            break;
+        }
+        thread::sleep(DT);
+    }
+}
+
+#[test]
+fn replayed_token() {
+    let runtime = WebSocketRuntime::default();
+    let (mut server, local_addr) = open_server(&runtime);
+    let target = token::with_token(&format!("ws://{local_addr}/lobby"), &signed_token());
+
+    let _first = connect(&runtime, target.clone());
+    let (_, result) = handshake(&mut server);
+    result.unwrap();
+
+    let _second = connect(&runtime, target);
+    let (_, result) = handshake(&mut server);
+    assert_matches!(
+        result,
+        Err(DisconnectReason::Error(ServerError::ConnectToken(
+            ConnectTokenError::Verify(TokenError::Replayed)
+        )))
+    );
+}
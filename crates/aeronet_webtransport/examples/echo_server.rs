@@ -22,7 +22,7 @@ use aeronet_webtransport::{
 };
 use bevy::{log::LogPlugin, prelude::*};
 use bevy_ecs::system::SystemId;
-use web_time::Duration;
+use web_time::{Duration, SystemTime};
 
 #[derive(Debug, Clone, Copy)]
 struct AppLane;
@@ -76,8 +76,33 @@ fn setup_one_shot_systems(world: &mut World) {
     world.insert_resource(SendMessage(send_message));
 }
 
+const CERT_PATH: &str = "echo_server_cert.pem";
+const KEY_PATH: &str = "echo_server_key.pem";
+
+// reuse the certificate from the last run if it's still valid,
+// so that clients don't need a new certificate hash every time
+fn identity() -> wtransport::Identity {
+    if let Ok(identity) = cert::read_pem_files(CERT_PATH, KEY_PATH) {
+        let cert = &identity.certificate_chain().as_slice()[0];
+        if cert::validity(cert).is_some_and(|validity| validity.contains(&SystemTime::now())) {
+            return identity;
+        }
+    }
+
+    let identity = cert::self_signed(
+        ["localhost", "127.0.0.1", "::1"],
+        SystemTime::now(),
+        cert::MAX_SELF_SIGNED_VALIDITY,
+    )
+    .unwrap();
+    if let Err(err) = cert::write_pem_files(&identity, CERT_PATH, KEY_PATH) {
+        warn!("Failed to save certificate: {err:#}");
+    }
+    identity
+}
+
 fn setup_server(mut server: ResMut<WebTransportServer>, runtime: Res<WebTransportRuntime>) {
-    let identity = wtransport::Identity::self_signed(["localhost", "127.0.0.1", "::1"]).unwrap();
+    let identity = identity();
     let cert = &identity.certificate_chain().as_slice()[0];
     let spki_fingerprint = cert::spki_fingerprint_b64(cert).unwrap();
     let cert_hash = cert::hash_to_b64(cert.hash());
@@ -108,6 +133,10 @@ fn poll_server(
             ServerEvent::Closed { reason: error } => {
                 info!("Server closed: {:#}", pretty_error(&error));
             }
+            ServerEvent::Reloaded { result } => match result {
+                Ok(()) => info!("Server reloaded"),
+                Err(err) => warn!("Failed to reload server: {:#}", pretty_error(&err)),
+            },
             ServerEvent::Connecting { client_key } => {
                 info!("{client_key:?} connecting");
                 commands.run_system_with_input(accept_client.0, client_key);
@@ -0,0 +1,166 @@
+use std::{
+    ops::Range,
+    time::{Duration, SystemTime},
+};
+
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair, PKCS_ECDSA_P256_SHA256};
+use spki::der::Decode;
+use time::OffsetDateTime;
+use wtransport::{
+    tls::{Certificate, CertificateChain, PrivateKey},
+    Identity,
+};
+
+/// Maximum validity period of a self-signed certificate which browsers will
+/// accept by its certificate hash.
+///
+/// See the [WebTransport documentation](https://developer.mozilla.org/en-US/docs/Web/API/WebTransport/WebTransport#servercertificatehashes).
+pub const MAX_SELF_SIGNED_VALIDITY: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Failed to generate a self-signed certificate using [`self_signed`].
+#[derive(Debug, thiserror::Error)]
+pub enum SelfSignedError {
+    /// One of the subject alternative names was not a valid DNS name or IP
+    /// address.
+    #[error("invalid subject alternative name")]
+    InvalidSan,
+    /// Requested validity period was longer than
+    /// [`MAX_SELF_SIGNED_VALIDITY`].
+    #[error("validity period longer than {MAX_SELF_SIGNED_VALIDITY:?}")]
+    ValidityTooLong,
+    /// Requested rotation period was not shorter than the validity period.
+    #[error("rotation period not shorter than validity period")]
+    RotateBeforeTooLong,
+    /// Identity given to [`CertificateRotation::from_identities`] had no
+    /// certificates in its chain.
+    ///
+    /// [`CertificateRotation::from_identities`]: super::CertificateRotation::from_identities
+    #[error("identity has no certificates")]
+    EmptyCertificateChain,
+    /// Failed to generate the key pair or certificate.
+    #[error("failed to generate certificate")]
+    Generate(#[source] rcgen::Error),
+}
+
+/// Generates a self-signed certificate and private key which browsers will
+/// accept by its certificate hash.
+///
+/// The certificate uses an ECDSA P-256 key, and is valid from `not_before`
+/// for `validity`, which must be no longer than [`MAX_SELF_SIGNED_VALIDITY`].
+///
+/// Unlike [`Identity::self_signed`], this lets you choose when the certificate
+/// starts being valid, so that you can generate a certificate ahead of time to
+/// rotate to later.
+///
+/// # Errors
+///
+/// Errors if the subject alternative names or validity are invalid, or if the
+/// certificate could not be generated.
+///
+/// # Panics
+///
+/// Panics if `wtransport` can't parse the generated certificate, which should
+/// never happen.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// use aeronet_webtransport::cert;
+///
+/// let identity = cert::self_signed(
+///     ["localhost", "127.0.0.1", "::1"],
+///     SystemTime::now(),
+///     Duration::from_secs(7 * 24 * 60 * 60),
+/// )
+/// .unwrap();
+/// ```
+pub fn self_signed(
+    subject_alt_names: impl IntoIterator<Item = impl Into<String>>,
+    not_before: SystemTime,
+    validity: Duration,
+) -> Result<Identity, SelfSignedError> {
+    if validity > MAX_SELF_SIGNED_VALIDITY {
+        return Err(SelfSignedError::ValidityTooLong);
+    }
+
+    let subject_alt_names = subject_alt_names
+        .into_iter()
+        .map(Into::into)
+        .collect::<Vec<_>>();
+    let mut params =
+        CertificateParams::new(subject_alt_names).map_err(|_| SelfSignedError::InvalidSan)?;
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, "aeronet self-signed");
+    params.distinguished_name = name;
+    params.not_before = OffsetDateTime::from(not_before);
+    params.not_after = OffsetDateTime::from(not_before + validity);
+
+    let key_pair =
+        KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256).map_err(SelfSignedError::Generate)?;
+    let cert = params
+        .self_signed(&key_pair)
+        .map_err(SelfSignedError::Generate)?;
+
+    let cert = Certificate::from_der(cert.der().to_vec())
+        .expect("certificate generated by rcgen should be valid");
+    Ok(Identity::new(
+        CertificateChain::single(cert),
+        PrivateKey::from_der_pkcs8(key_pair.serialize_der()),
+    ))
+}
+
+/// Gets the period of time during which a certificate is valid.
+///
+/// Returns [`None`] if the certificate cannot be converted to an
+/// [`x509_cert::Certificate`].
+#[must_use]
+pub fn validity(cert: &Certificate) -> Option<Range<SystemTime>> {
+    let cert = x509_cert::Certificate::from_der(cert.der()).ok()?;
+    let validity = cert.tbs_certificate.validity;
+    Some(validity.not_before.to_system_time()..validity.not_after.to_system_time())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    fn not_before() -> SystemTime {
+        // certificate times only have second precision
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    #[test]
+    fn validity_too_long() {
+        assert_matches!(
+            self_signed(
+                ["localhost"],
+                not_before(),
+                MAX_SELF_SIGNED_VALIDITY + Duration::from_secs(1),
+            ),
+            Err(SelfSignedError::ValidityTooLong)
+        );
+    }
+
+    #[test]
+    fn max_validity() {
+        let identity = self_signed(["localhost"], not_before(), MAX_SELF_SIGNED_VALIDITY).unwrap();
+        let certs = identity.certificate_chain().as_slice();
+        assert_eq!(1, certs.len());
+        assert_eq!(
+            Some(not_before()..not_before() + MAX_SELF_SIGNED_VALIDITY),
+            validity(&certs[0])
+        );
+    }
+
+    #[test]
+    fn invalid_san() {
+        assert_matches!(
+            self_signed(["bücher.example"], not_before(), MAX_SELF_SIGNED_VALIDITY),
+            Err(SelfSignedError::InvalidSan)
+        );
+    }
+}
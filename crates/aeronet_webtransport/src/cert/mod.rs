@@ -1,9 +1,17 @@
 //! Utilities for working with X509 certificates.
 
+#[cfg(not(target_family = "wasm"))]
+mod generate;
 #[cfg(not(target_family = "wasm"))]
 mod native;
 #[cfg(not(target_family = "wasm"))]
-pub use native::*;
+mod pem;
+#[cfg(not(target_family = "wasm"))]
+mod rotation;
+#[cfg(not(target_family = "wasm"))]
+pub use {generate::*, native::*, pem::*, rotation::*};
+
+use std::{fmt, str::FromStr};
 
 use base64::Engine;
 
@@ -38,3 +46,66 @@ pub fn hash_from_b64(input: &str) -> Result<CertificateHash, DecodeHashError> {
     let hash = CertificateHash::try_from(hash).map_err(|_| DecodeHashError::InvalidLength)?;
     Ok(hash)
 }
+
+/// Hashes of the certificate which a server is currently using, and the
+/// certificate which it will rotate to next.
+///
+/// A client which trusts both hashes can connect to the server both before
+/// and after its next certificate rotation. On WASM, pass both hashes as the
+/// `server_certificate_hashes` of the `WebTransportOptions`.
+///
+/// Use `CertificateRotation::hashes` on the server to get the hashes. These
+/// can be published to clients as text using [`Display`], e.g. by serving them
+/// over HTTPS, and parsed back on the client using [`FromStr`]. The text form
+/// is the base 64 encoded current hash, followed by a newline, then the base
+/// 64 encoded next hash.
+///
+/// [`Display`]: fmt::Display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CertificateHashes {
+    /// Hash of the certificate which the server is currently using.
+    pub current: CertificateHash,
+    /// Hash of the certificate which the server will rotate to next.
+    pub next: CertificateHash,
+}
+
+impl CertificateHashes {
+    /// Gets both hashes, current first.
+    #[must_use]
+    pub const fn to_array(self) -> [CertificateHash; 2] {
+        [self.current, self.next]
+    }
+}
+
+impl fmt::Display for CertificateHashes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", BASE64.encode(self.current))?;
+        writeln!(f, "{}", BASE64.encode(self.next))
+    }
+}
+
+/// Failed to parse [`CertificateHashes`] from its text form.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ParseHashesError {
+    /// Text did not contain exactly two lines.
+    #[error("expected 2 lines")]
+    WrongLineCount,
+    /// Failed to decode one of the hashes.
+    #[error("failed to decode hash")]
+    Hash(#[source] DecodeHashError),
+}
+
+impl FromStr for CertificateHashes {
+    type Err = ParseHashesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().map(str::trim).filter(|line| !line.is_empty());
+        let (Some(current), Some(next), None) = (lines.next(), lines.next(), lines.next()) else {
+            return Err(ParseHashesError::WrongLineCount);
+        };
+        Ok(Self {
+            current: hash_from_b64(current).map_err(ParseHashesError::Hash)?,
+            next: hash_from_b64(next).map_err(ParseHashesError::Hash)?,
+        })
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+use spki::der::{self, Encode};
+use wtransport::{
+    tls::{error::InvalidCertificate, Certificate, CertificateChain, PrivateKey},
+    Identity,
+};
+
+/// Failed to read an [`Identity`] from PEM files using [`read_pem_files`].
+#[derive(Debug, thiserror::Error)]
+pub enum ReadPemError {
+    /// Failed to read one of the files.
+    #[error("failed to read file")]
+    Io(#[source] io::Error),
+    /// Failed to parse the certificate chain file.
+    #[error("failed to parse certificate chain")]
+    ParseCertificate(#[source] der::Error),
+    /// Certificate chain file did not contain any certificates.
+    #[error("no certificates in certificate chain")]
+    NoCertificates,
+    /// Parsed certificate was not accepted by `wtransport`.
+    #[error("invalid certificate")]
+    InvalidCertificate(#[source] InvalidCertificate),
+    /// Failed to parse the private key file.
+    #[error("failed to parse private key")]
+    ParseKey(#[source] der::Error),
+    /// Private key file was not a PKCS #8 private key, labelled
+    /// `PRIVATE KEY`.
+    #[error("private key is not PKCS #8, but `{label}`")]
+    UnsupportedKey {
+        /// Label of the PEM block in the private key file.
+        label: String,
+    },
+}
+
+/// Writes an [`Identity`]'s certificate chain and private key to PEM files.
+///
+/// The certificate chain is written to `cert_path`, and the private key is
+/// written to `key_path` in PKCS #8 format. On Unix, the private key file is
+/// created with permissions that only allow the owner to read and write it.
+///
+/// Both files can be read back using [`read_pem_files`].
+///
+/// # Errors
+///
+/// Errors if either file could not be written.
+pub fn write_pem_files(
+    identity: &Identity,
+    cert_path: impl AsRef<Path>,
+    key_path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let certs = identity
+        .certificate_chain()
+        .as_slice()
+        .iter()
+        .map(Certificate::to_pem)
+        .collect::<String>();
+    fs::write(cert_path, certs)?;
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut key_file = options.open(key_path)?;
+    key_file.write_all(identity.private_key().to_secret_pem().as_bytes())?;
+    Ok(())
+}
+
+/// Reads an [`Identity`] from a PEM certificate chain file and a PEM PKCS #8
+/// private key file, such as ones written by [`write_pem_files`].
+///
+/// # Errors
+///
+/// Errors if either file could not be read, or did not contain a valid
+/// certificate chain or private key.
+pub fn read_pem_files(
+    cert_path: impl AsRef<Path>,
+    key_path: impl AsRef<Path>,
+) -> Result<Identity, ReadPemError> {
+    let certs = fs::read(cert_path).map_err(ReadPemError::Io)?;
+    // `load_pem_chain` panics on empty input
+    if certs.trim_ascii().is_empty() {
+        return Err(ReadPemError::NoCertificates);
+    }
+    let certs = x509_cert::Certificate::load_pem_chain(&certs)
+        .map_err(ReadPemError::ParseCertificate)?
+        .into_iter()
+        .map(|cert| {
+            let der = cert.to_der().map_err(ReadPemError::ParseCertificate)?;
+            Certificate::from_der(der).map_err(ReadPemError::InvalidCertificate)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if certs.is_empty() {
+        return Err(ReadPemError::NoCertificates);
+    }
+
+    let key = fs::read(key_path).map_err(ReadPemError::Io)?;
+    let (label, key) =
+        der::pem::decode_vec(&key).map_err(|err| ReadPemError::ParseKey(err.into()))?;
+    if label != "PRIVATE KEY" {
+        return Err(ReadPemError::UnsupportedKey {
+            label: label.to_owned(),
+        });
+    }
+
+    Ok(Identity::new(
+        CertificateChain::new(certs),
+        PrivateKey::from_der_pkcs8(key),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::SystemTime};
+
+    use assert_matches::assert_matches;
+
+    use super::*;
+    use crate::cert::{self_signed, MAX_SELF_SIGNED_VALIDITY};
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "aeronet_webtransport-{name}-{}",
+                std::process::id()
+            ));
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn identity() -> Identity {
+        self_signed(["localhost"], SystemTime::now(), MAX_SELF_SIGNED_VALIDITY).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::new("round_trip");
+        let (cert_path, key_path) = (dir.0.join("cert.pem"), dir.0.join("key.pem"));
+        let identity = identity();
+
+        write_pem_files(&identity, &cert_path, &key_path).unwrap();
+        let read = read_pem_files(&cert_path, &key_path).unwrap();
+
+        let ders = |identity: &Identity| {
+            identity
+                .certificate_chain()
+                .as_slice()
+                .iter()
+                .map(|cert| cert.der().to_vec())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ders(&identity), ders(&read));
+        assert_eq!(
+            identity.private_key().secret_der(),
+            read.private_key().secret_der()
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(&key_path).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+    }
+
+    #[test]
+    fn no_certificates() {
+        let dir = TempDir::new("no_certificates");
+        let (cert_path, key_path) = (dir.0.join("cert.pem"), dir.0.join("key.pem"));
+        write_pem_files(&identity(), &cert_path, &key_path).unwrap();
+        fs::write(&cert_path, "").unwrap();
+
+        assert_matches!(
+            read_pem_files(&cert_path, &key_path),
+            Err(ReadPemError::NoCertificates)
+        );
+    }
+
+    #[test]
+    fn unsupported_key() {
+        let dir = TempDir::new("unsupported_key");
+        let (cert_path, key_path) = (dir.0.join("cert.pem"), dir.0.join("key.pem"));
+        let identity = identity();
+        write_pem_files(&identity, &cert_path, &key_path).unwrap();
+        let key = der::pem::encode_string(
+            "EC PRIVATE KEY",
+            der::pem::LineEnding::LF,
+            identity.private_key().secret_der(),
+        )
+        .unwrap();
+        fs::write(&key_path, key).unwrap();
+
+        assert_matches!(
+            read_pem_files(&cert_path, &key_path),
+            Err(ReadPemError::UnsupportedKey { label }) if label == "EC PRIVATE KEY"
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use std::time::{Duration, SystemTime};
+
+use wtransport::Identity;
+
+use super::{self_signed, validity, CertificateHash, CertificateHashes, SelfSignedError};
+
+/// Keeps a self-signed [`Identity`] up to date by generating a new one before
+/// the current one expires.
+///
+/// Browsers only accept self-signed certificates by their hash if they are
+/// valid for at most [`MAX_SELF_SIGNED_VALIDITY`], so a long-running server
+/// must regularly switch to a new certificate. This keeps track of the
+/// *current* identity, which the server should use now, and the *next*
+/// identity, which the server will switch to `rotate_before` the current one
+/// expires. The next certificate is generated ahead of time, and is valid
+/// from the moment it is rotated to, so its hash can be given to clients
+/// before the server starts using it - see [`CertificateRotation::hashes`].
+///
+/// Call [`CertificateRotation::update`] regularly to rotate the identity when
+/// it is due.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+///
+/// use aeronet_webtransport::cert::CertificateRotation;
+///
+/// let mut rotation = CertificateRotation::new(
+///     ["localhost", "127.0.0.1", "::1"],
+///     Duration::from_secs(14 * 24 * 60 * 60),
+///     Duration::from_secs(2 * 24 * 60 * 60),
+/// )
+/// .unwrap();
+/// let hashes = rotation.hashes();
+///
+/// // 13 days later...
+/// let now = SystemTime::now() + Duration::from_secs(13 * 24 * 60 * 60);
+/// let new_identity = rotation.update(now).unwrap();
+/// assert!(new_identity.is_some());
+/// assert_eq!(hashes.next, rotation.hashes().current);
+/// ```
+///
+/// [`MAX_SELF_SIGNED_VALIDITY`]: super::MAX_SELF_SIGNED_VALIDITY
+#[derive(Debug)]
+pub struct CertificateRotation {
+    subject_alt_names: Vec<String>,
+    validity: Duration,
+    rotate_before: Duration,
+    current: Identity,
+    next: Identity,
+    hashes: CertificateHashes,
+    rotates_at: SystemTime,
+}
+
+impl CertificateRotation {
+    /// Generates a new current and next identity.
+    ///
+    /// Each certificate is valid for `validity`, and is rotated out
+    /// `rotate_before` it expires.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `rotate_before` is not shorter than `validity`, or if the
+    /// identities could not be generated - see [`self_signed`].
+    pub fn new(
+        subject_alt_names: impl IntoIterator<Item = impl Into<String>>,
+        validity: Duration,
+        rotate_before: Duration,
+    ) -> Result<Self, SelfSignedError> {
+        let subject_alt_names = subject_alt_names
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        let current = self_signed(subject_alt_names.clone(), SystemTime::now(), validity)?;
+        Self::from_identities(subject_alt_names, validity, rotate_before, current, None)
+    }
+
+    /// Creates a rotation starting from existing identities, e.g. ones read
+    /// from PEM files using [`read_pem_files`].
+    ///
+    /// If `next` is [`None`], a new next identity is generated.
+    ///
+    /// If the validity of `current` can't be read, it is treated as already
+    /// expired, and is rotated out on the next [`CertificateRotation::update`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if `rotate_before` is not shorter than `validity`, if `current`
+    /// or `next` has no certificates, or if the next identity could not be
+    /// generated - see [`self_signed`].
+    ///
+    /// [`read_pem_files`]: super::read_pem_files
+    pub fn from_identities(
+        subject_alt_names: impl IntoIterator<Item = impl Into<String>>,
+        validity: Duration,
+        rotate_before: Duration,
+        current: Identity,
+        next: Option<Identity>,
+    ) -> Result<Self, SelfSignedError> {
+        if rotate_before >= validity {
+            return Err(SelfSignedError::RotateBeforeTooLong);
+        }
+
+        let subject_alt_names = subject_alt_names
+            .into_iter()
+            .map(Into::into)
+            .collect::<Vec<_>>();
+        let rotates_at = rotates_at(&current, rotate_before);
+        let next = match next {
+            Some(next) => next,
+            None => self_signed(subject_alt_names.clone(), rotates_at, validity)?,
+        };
+        let hashes = CertificateHashes {
+            current: hash(&current)?,
+            next: hash(&next)?,
+        };
+        Ok(Self {
+            subject_alt_names,
+            validity,
+            rotate_before,
+            current,
+            next,
+            hashes,
+            rotates_at,
+        })
+    }
+
+    /// Gets the identity which the server should currently use.
+    #[must_use]
+    pub const fn current(&self) -> &Identity {
+        &self.current
+    }
+
+    /// Gets the identity which will become current after the next rotation.
+    #[must_use]
+    pub const fn next(&self) -> &Identity {
+        &self.next
+    }
+
+    /// Gets when the current identity will be rotated out.
+    #[must_use]
+    pub const fn rotates_at(&self) -> SystemTime {
+        self.rotates_at
+    }
+
+    /// Gets the hashes of the current and next certificates, which clients
+    /// can trust to connect to the server both before and after the next
+    /// rotation.
+    #[must_use]
+    pub const fn hashes(&self) -> CertificateHashes {
+        self.hashes
+    }
+
+    /// Rotates to the next identity if the current one is due to be rotated out
+    /// at `now`, and generates a new next identity.
+    ///
+    /// Returns the new current identity if a rotation happened, which the
+    /// server should switch to.
+    ///
+    /// If the next identity is also due to be rotated out, e.g. if this wasn't
+    /// called for a long time, both identities are replaced with newly
+    /// generated ones.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a new identity could not be generated - see [`self_signed`].
+    pub fn update(&mut self, now: SystemTime) -> Result<Option<&Identity>, SelfSignedError> {
+        if now < self.rotates_at {
+            return Ok(None);
+        }
+
+        let (next, next_hash) = if now < rotates_at(&self.next, self.rotate_before) {
+            (self.next.clone_identity(), self.hashes.next)
+        } else {
+            let next = self_signed(self.subject_alt_names.clone(), now, self.validity)?;
+            let next_hash = hash(&next)?;
+            (next, next_hash)
+        };
+        let rotates_at = rotates_at(&next, self.rotate_before);
+        let new_next = self_signed(self.subject_alt_names.clone(), rotates_at, self.validity)?;
+
+        self.hashes = CertificateHashes {
+            current: next_hash,
+            next: hash(&new_next)?,
+        };
+        self.current = next;
+        self.next = new_next;
+        self.rotates_at = rotates_at;
+        Ok(Some(&self.current))
+    }
+}
+
+fn hash(identity: &Identity) -> Result<CertificateHash, SelfSignedError> {
+    identity
+        .certificate_chain()
+        .as_slice()
+        .first()
+        .map(|cert| *cert.hash().as_ref())
+        .ok_or(SelfSignedError::EmptyCertificateChain)
+}
+
+fn rotates_at(identity: &Identity, rotate_before: Duration) -> SystemTime {
+    identity
+        .certificate_chain()
+        .as_slice()
+        .first()
+        .and_then(validity)
+        .map_or(SystemTime::UNIX_EPOCH, |validity| {
+            validity
+                .end
+                .checked_sub(rotate_before)
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use wtransport::tls::{Certificate, CertificateChain};
+
+    use super::*;
+
+    const SANS: [&str; 1] = ["localhost"];
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn leaf_validity(identity: &Identity) -> std::ops::Range<SystemTime> {
+        validity(&identity.certificate_chain().as_slice()[0]).unwrap()
+    }
+
+    #[test]
+    fn rotate_before_too_long() {
+        assert_matches!(
+            CertificateRotation::new(SANS, DAY, DAY),
+            Err(SelfSignedError::RotateBeforeTooLong)
+        );
+    }
+
+    #[test]
+    fn empty_chain() {
+        let identity = self_signed(SANS, SystemTime::now(), DAY).unwrap();
+        let empty = Identity::new(
+            CertificateChain::new(Vec::new()),
+            identity.private_key().clone_key(),
+        );
+
+        assert_matches!(
+            CertificateRotation::from_identities(SANS, DAY, HOUR, empty.clone_identity(), None),
+            Err(SelfSignedError::EmptyCertificateChain)
+        );
+        assert_matches!(
+            CertificateRotation::from_identities(SANS, DAY, HOUR, identity, Some(empty)),
+            Err(SelfSignedError::EmptyCertificateChain)
+        );
+    }
+
+    #[test]
+    fn update_before_due() {
+        let mut rotation = CertificateRotation::new(SANS, DAY, HOUR).unwrap();
+        let hashes = rotation.hashes();
+
+        assert!(rotation
+            .update(rotation.rotates_at() - Duration::from_secs(1))
+            .unwrap()
+            .is_none());
+        assert_eq!(hashes, rotation.hashes());
+    }
+
+    #[test]
+    fn update_rotates_to_next() {
+        let mut rotation = CertificateRotation::new(SANS, DAY, HOUR).unwrap();
+        let hashes = rotation.hashes();
+        let now = rotation.rotates_at();
+
+        assert!(rotation.update(now).unwrap().is_some());
+        assert_eq!(hashes.next, rotation.hashes().current);
+        assert!(leaf_validity(rotation.current()).contains(&now));
+    }
+
+    #[test]
+    fn update_both_expired() {
+        let mut rotation = CertificateRotation::new(SANS, DAY, HOUR).unwrap();
+        let hashes = rotation.hashes();
+        let now = SystemTime::now() + 3 * DAY;
+
+        assert!(rotation.update(now).unwrap().is_some());
+        let new_hashes = rotation.hashes();
+        assert_ne!(hashes.current, new_hashes.current);
+        assert_ne!(hashes.next, new_hashes.current);
+        assert_ne!(hashes.next, new_hashes.next);
+        assert!(leaf_validity(rotation.current()).contains(&now));
+        assert!(now < rotation.rotates_at());
+        assert_eq!(rotation.rotates_at(), leaf_validity(rotation.next()).start);
+    }
+
+    #[test]
+    fn unparsable_validity() {
+        let identity = self_signed(SANS, SystemTime::now(), DAY).unwrap();
+        // trailing data is accepted by `wtransport`, but not when reading the
+        // validity
+        let mut der = identity.certificate_chain().as_slice()[0].der().to_vec();
+        der.push(0);
+        let cert = Certificate::from_der(der).unwrap();
+        assert!(validity(&cert).is_none());
+        let current = Identity::new(
+            CertificateChain::single(cert),
+            identity.private_key().clone_key(),
+        );
+
+        let mut rotation =
+            CertificateRotation::from_identities(SANS, DAY, HOUR, current, None).unwrap();
+        assert_eq!(SystemTime::UNIX_EPOCH, rotation.rotates_at());
+        let hashes = rotation.hashes();
+
+        let now = SystemTime::now();
+        assert!(rotation.update(now).unwrap().is_some());
+        assert_ne!(hashes.current, rotation.hashes().current);
+        assert!(leaf_validity(rotation.current()).contains(&now));
+    }
+}
@@ -1,4 +1,4 @@
-use aeronet::client::DisconnectReason;
+use aeronet::{client::DisconnectReason, lane::LaneIndex};
 use aeronet_proto::session::{Session, SessionConfig};
 use bytes::Bytes;
 use futures::{
@@ -12,7 +12,9 @@ use xwt_core::prelude::*;
 use crate::{
     client::ToConnected,
     internal::{self, ConnectionMeta, MIN_MTU},
+    resume::ResumeRequest,
     runtime::WebTransportRuntime,
+    shared::OutgoingQueueConfig,
 };
 
 use super::{ClientConfig, ClientError};
@@ -21,6 +23,8 @@ pub async fn start(
     runtime: WebTransportRuntime,
     net_config: ClientConfig,
     session_config: SessionConfig,
+    stream_lanes: Vec<LaneIndex>,
+    outgoing_queue: OutgoingQueueConfig,
     target: String,
     send_connected: oneshot::Sender<ToConnected>,
 ) -> Result<Never, DisconnectReason<ClientError>> {
@@ -39,6 +43,14 @@ pub async fn start(
     let Some(mtu) = conn.max_datagram_size() else {
         return Err(ClientError::DatagramsNotSupported.into());
     };
+    let (streams, stream_backend) = internal::stream_lanes(
+        &stream_lanes,
+        &session_config.client_lanes,
+        session_config.server_lanes.len(),
+        session_config.max_memory_usage,
+        // the server only sends us a token if we asked for one
+        ResumeRequest::find(&target).is_some(),
+    );
     let session = Session::client(Instant::now(), session_config, MIN_MTU, mtu)
         .map_err(ClientError::MtuTooSmall)?;
 
@@ -53,7 +65,7 @@ pub async fn start(
     }
 
     let (send_meta, recv_meta) = mpsc::channel::<ConnectionMeta>(1);
-    let (send_c2s, recv_c2s) = mpsc::unbounded::<Bytes>();
+    let (send_c2s, recv_c2s) = internal::outgoing_queue(outgoing_queue);
     let (send_s2c, recv_s2c) = mpsc::channel::<Bytes>(internal::MSG_BUF_CAP);
     let (send_local_dc, recv_local_dc) = oneshot::channel::<String>();
     send_connected
@@ -67,13 +79,22 @@ pub async fn start(
             recv_meta,
             send_c2s,
             recv_s2c,
+            streams,
             send_local_dc,
             session,
         })
         .map_err(|_| ClientError::FrontendClosed)?;
 
     debug!("Starting connection loop");
-    internal::handle_connection(runtime, conn, recv_c2s, send_s2c, send_meta, recv_local_dc)
-        .await
-        .map_err(|reason| reason.map_err(From::from))
+    internal::handle_connection(
+        runtime,
+        conn,
+        recv_c2s,
+        send_s2c,
+        send_meta,
+        stream_backend,
+        recv_local_dc,
+    )
+    .await
+    .map_err(|reason| reason.map_err(From::from))
 }
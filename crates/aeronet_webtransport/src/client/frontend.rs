@@ -10,15 +10,17 @@ use aeronet_proto::session::{MessageKey, Session, SessionBacked, SessionConfig};
 use bytes::Bytes;
 use futures::channel::oneshot;
 use tracing::debug;
-use web_time::Duration;
+use web_time::{Duration, Instant};
 
 use crate::{
     internal::{ConnectionInner, PollEvent},
+    resume::ResumeRequest,
     runtime::WebTransportRuntime,
+    shared::OutgoingQueueConfig,
 };
 
 use super::{
-    backend, ClientConfig, ClientError, Connected, Connecting, State, ToConnected,
+    backend, ClientConfig, ClientError, Connected, Connecting, State, Suspended, ToConnected,
     WebTransportClient,
 };
 
@@ -36,9 +38,94 @@ impl WebTransportClient {
     pub const fn new() -> Self {
         Self {
             state: State::Disconnected,
+            resumable: false,
+            suspended: None,
+            stream_lanes: Vec::new(),
+            outgoing_queue: OutgoingQueueConfig::new(),
         }
     }
 
+    /// Gets if this client will attempt to resume its session after losing
+    /// its connection.
+    ///
+    /// See [`WebTransportClient::set_resumable`].
+    #[must_use]
+    pub const fn resumable(&self) -> bool {
+        self.resumable
+    }
+
+    /// Sets if this client will attempt to resume its session after losing
+    /// its connection.
+    ///
+    /// If enabled, when this client connects it asks the server to issue it a
+    /// [`ResumeToken`]. If the server issued one, and the connection is then
+    /// lost due to an error, this client keeps its session, and the next call
+    /// to [`WebTransportClient::connect`] with the same target attempts to
+    /// resume that session, re-sending any reliable messages which the server
+    /// has not acknowledged yet. This pairs well with a [`ReconnectingClient`].
+    ///
+    /// [`ClientEvent::Disconnected`] and [`ClientEvent::Connected`] are still
+    /// emitted when the connection is lost and when it is resumed. If the
+    /// server rejects the resumption, e.g. because its resume grace period has
+    /// passed, the next connection attempt starts a new session.
+    ///
+    /// Messages on [stream lanes] can't be re-sent, so this has no effect while
+    /// this client uses any stream lanes. Disabling this discards any session
+    /// which is waiting to be resumed.
+    ///
+    /// See [`resume`].
+    ///
+    /// [`ResumeToken`]: crate::resume::ResumeToken
+    /// [`ReconnectingClient`]: aeronet::client::ReconnectingClient
+    /// [stream lanes]: WebTransportClient::set_stream_lanes
+    /// [`resume`]: crate::resume
+    pub fn set_resumable(&mut self, resumable: bool) {
+        self.resumable = resumable;
+        if !resumable {
+            self.suspended = None;
+        }
+    }
+
+    /// Gets which lanes this client sends messages on over WebTransport
+    /// streams, rather than datagrams.
+    ///
+    /// See [`WebTransportClient::set_stream_lanes`].
+    #[must_use]
+    pub fn stream_lanes(&self) -> &[LaneIndex] {
+        &self.stream_lanes
+    }
+
+    /// Sets which lanes this client sends messages on over WebTransport
+    /// streams, rather than datagrams.
+    ///
+    /// See [`stream lanes`] for how these lanes are sent. This takes effect on
+    /// the next call to [`WebTransportClient::connect`].
+    ///
+    /// [`stream lanes`]: crate#stream-lanes
+    pub fn set_stream_lanes(&mut self, lanes: impl IntoIterator<Item = impl Into<LaneIndex>>) {
+        self.stream_lanes = lanes.into_iter().map(Into::into).collect();
+    }
+
+    /// Gets the configuration of the queue of packets waiting to be sent to
+    /// the server.
+    ///
+    /// See [`WebTransportClient::set_outgoing_queue`].
+    #[must_use]
+    pub const fn outgoing_queue(&self) -> OutgoingQueueConfig {
+        self.outgoing_queue
+    }
+
+    /// Sets the configuration of the queue of packets waiting to be sent to
+    /// the server.
+    ///
+    /// See [`outgoing queue`] for how this queue is used. This takes effect on
+    /// the next call to [`WebTransportClient::connect`].
+    ///
+    /// [`outgoing queue`]: crate#outgoing-queue
+    pub fn set_outgoing_queue(&mut self, config: OutgoingQueueConfig) {
+        self.outgoing_queue = config;
+    }
+
     /// Starts connecting this client to a server.
     ///
     /// `target` must be given in the form of a URL, i.e. `https://[::1]:1234`.
@@ -62,15 +149,29 @@ impl WebTransportClient {
         let (send_connected, recv_connected) = oneshot::channel::<ToConnected>();
         let (send_dc, recv_dc) = oneshot::channel::<DisconnectReason<ClientError>>();
         let target = target.into();
+        let resumable = self.resumable && self.stream_lanes.is_empty();
+        let (resume_token, resumed_session, url) = match self.suspended.take() {
+            Some(suspended) if resumable && suspended.target == target => {
+                let url = ResumeRequest::Resume(suspended.token).add_to(&target);
+                (Some(suspended.token), Some(suspended.session), url)
+            }
+            // the server sends us a token once it has accepted us
+            _ if resumable => (None, None, ResumeRequest::Issue.add_to(&target)),
+            _ => (None, None, target.clone()),
+        };
 
-        let runtime_clone = runtime.clone();
+        let stream_lanes = self.stream_lanes.clone();
+        let outgoing_queue = self.outgoing_queue;
+        let runtime_clone = runtime.detached();
         runtime.spawn(async move {
             debug!("Started client backend");
             match backend::start(
                 runtime_clone,
                 net_config,
                 session_config,
-                target,
+                stream_lanes,
+                outgoing_queue,
+                url,
                 send_connected,
             )
             .await
@@ -86,10 +187,13 @@ impl WebTransportClient {
             }
         });
 
-        self.state = State::Connecting(Connecting {
+        self.state = State::Connecting(Box::new(Connecting {
             recv_connected,
             recv_dc,
-        });
+            target,
+            resume_token,
+            resumed_session,
+        }));
 
         Ok(())
     }
@@ -107,8 +211,8 @@ impl ClientTransport for WebTransportClient {
     fn state(&self) -> ClientState<Self::Connecting<'_>, Self::Connected<'_>> {
         match &self.state {
             State::Disconnected | State::Disconnecting { .. } => ClientState::Disconnected,
-            State::Connecting(client) => ClientState::Connecting(client),
-            State::Connected(client) => ClientState::Connected(client),
+            State::Connecting(client) => ClientState::Connecting(client.as_ref()),
+            State::Connected(client) => ClientState::Connected(client.as_ref()),
         }
     }
 
@@ -116,8 +220,12 @@ impl ClientTransport for WebTransportClient {
         let mut events = Vec::new();
         replace_with::replace_with_or_abort(&mut self.state, |state| match state {
             State::Disconnected => state,
-            State::Connecting(client) => Self::poll_connecting(client, &mut events),
-            State::Connected(client) => Self::poll_connected(client, &mut events, delta_time),
+            State::Connecting(client) => {
+                Self::poll_connecting(client, &mut events, &mut self.suspended)
+            }
+            State::Connected(client) => {
+                Self::poll_connected(client, &mut events, delta_time, &mut self.suspended)
+            }
             State::Disconnecting { reason } => {
                 events.push(ClientEvent::Disconnected {
                     reason: DisconnectReason::Local(reason),
@@ -172,8 +280,22 @@ impl ClientTransport for WebTransportClient {
 }
 
 impl WebTransportClient {
-    fn poll_connecting(mut client: Connecting, events: &mut Vec<ClientEvent<Self>>) -> State {
+    fn poll_connecting(
+        mut client: Box<Connecting>,
+        events: &mut Vec<ClientEvent<Self>>,
+        suspended: &mut Option<Suspended>,
+    ) -> State {
         if let Ok(Some(reason)) = client.recv_dc.try_recv() {
+            if let (Some(token), Some(session)) = (client.resume_token, client.resumed_session) {
+                // if we couldn't reach the server, try resuming again next time
+                if !resume_rejected(&reason) {
+                    *suspended = Some(Suspended {
+                        target: client.target,
+                        token,
+                        session,
+                    });
+                }
+            }
             events.push(ClientEvent::Disconnected { reason });
             return State::Disconnected;
         }
@@ -181,8 +303,17 @@ impl WebTransportClient {
         match client.recv_connected.try_recv() {
             Ok(None) => State::Connecting(client),
             Ok(Some(next)) => {
+                let session = match client.resumed_session {
+                    Some(mut session) => {
+                        // both sessions were created with the same min MTU
+                        let _ = session.set_mtu(next.session.mtu());
+                        session.resume(Instant::now());
+                        session
+                    }
+                    None => next.session,
+                };
                 events.push(ClientEvent::Connected);
-                State::Connected(Connected {
+                State::Connected(Box::new(Connected {
                     #[cfg(not(target_family = "wasm"))]
                     local_addr: next.local_addr,
                     inner: ConnectionInner {
@@ -190,15 +321,18 @@ impl WebTransportClient {
                         remote_addr: next.initial_remote_addr,
                         #[cfg(not(target_family = "wasm"))]
                         raw_rtt: next.initial_rtt,
-                        session: next.session,
+                        session,
                         recv_dc: client.recv_dc,
                         recv_meta: next.recv_meta,
                         send_msgs: next.send_c2s,
                         recv_msgs: next.recv_s2c,
+                        streams: next.streams,
                         send_local_dc: next.send_local_dc,
                         fatal_error: None,
                     },
-                })
+                    target: client.target,
+                    resume_token: client.resume_token,
+                }))
             }
             Err(_) => {
                 events.push(ClientEvent::Disconnected {
@@ -210,29 +344,61 @@ impl WebTransportClient {
     }
 
     fn poll_connected(
-        mut client: Connected,
+        mut client: Box<Connected>,
         events: &mut Vec<ClientEvent<Self>>,
         delta_time: Duration,
+        suspended: &mut Option<Suspended>,
     ) -> State {
-        let res = client.inner.poll(delta_time, |event| {
-            events.push(match event {
-                PollEvent::Ack { msg_key } => ClientEvent::Ack { msg_key },
-                PollEvent::Recv { msg, lane } => ClientEvent::Recv { msg, lane },
-            });
+        let res = client.inner.poll(delta_time, |event| match event {
+            PollEvent::Ack { msg_key } => events.push(ClientEvent::Ack { msg_key }),
+            PollEvent::Recv { msg, lane } => events.push(ClientEvent::Recv { msg, lane }),
+            PollEvent::ResumeToken(token) => {
+                debug!("Server issued resume token");
+                client.resume_token = Some(token);
+            }
         });
 
-        match res {
+        match res.map_err(|reason| reason.map_err(ClientError::from)) {
             Ok(()) => State::Connected(client),
             Err(reason) => {
-                events.push(ClientEvent::Disconnected {
-                    reason: reason.map_err(From::from),
-                });
+                if let (Some(token), DisconnectReason::Error(ClientError::ConnectionLost(_))) =
+                    (client.resume_token, &reason)
+                {
+                    *suspended = Some(Suspended {
+                        target: client.target,
+                        token,
+                        session: client.inner.session,
+                    });
+                }
+                events.push(ClientEvent::Disconnected { reason });
                 State::Disconnected
             }
         }
     }
 }
 
+/// Gets if a failed attempt at resuming a session failed because the server
+/// refused to resume it, rather than because we couldn't reach the server.
+const fn resume_rejected(reason: &DisconnectReason<ClientError>) -> bool {
+    #[cfg(target_family = "wasm")]
+    {
+        // browsers don't tell us why a connection failed,
+        // so assume the worst and start a new session next time
+        let _ = reason;
+        true
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    {
+        use wtransport::error::ConnectingError;
+
+        matches!(
+            reason,
+            DisconnectReason::Error(ClientError::Connect(ConnectingError::SessionRejected))
+        )
+    }
+}
+
 impl SessionBacked for WebTransportClient {
     fn get_session(&self) -> Option<&Session> {
         if let State::Connected(client) = &self.state {
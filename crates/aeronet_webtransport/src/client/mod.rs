@@ -7,6 +7,7 @@ use std::io;
 
 use aeronet::{
     client::DisconnectReason,
+    lane::LaneIndex,
     stats::{ConnectedAt, MessageStats, Rtt},
 };
 use aeronet_proto::session::{FatalSendError, MtuTooSmall, OutOfMemory, SendError, Session};
@@ -14,7 +15,11 @@ use bytes::Bytes;
 use futures::channel::{mpsc, oneshot};
 use web_time::{Duration, Instant};
 
-use crate::internal::{ConnectionInner, ConnectionMeta, InternalError};
+use crate::{
+    internal::{ConnectionInner, ConnectionMeta, InternalError, OutgoingQueue, StreamLanes},
+    resume::ResumeToken,
+    shared::{OutgoingQueueConfig, OutgoingQueueStats, StreamError},
+};
 
 cfg_if::cfg_if! {
     if #[cfg(target_family = "wasm")] {
@@ -50,13 +55,26 @@ cfg_if::cfg_if! {
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
 pub struct WebTransportClient {
     state: State,
+    resumable: bool,
+    suspended: Option<Suspended>,
+    stream_lanes: Vec<LaneIndex>,
+    outgoing_queue: OutgoingQueueConfig,
+}
+
+/// Session of a resumable client which lost its connection, kept so that it
+/// can be resumed on the next connection attempt.
+#[derive(Debug)]
+struct Suspended {
+    target: String,
+    token: ResumeToken,
+    session: Session,
 }
 
 #[derive(Debug)]
 enum State {
     Disconnected,
-    Connecting(Connecting),
-    Connected(Connected),
+    Connecting(Box<Connecting>),
+    Connected(Box<Connected>),
     Disconnecting { reason: String },
 }
 
@@ -111,6 +129,10 @@ pub enum ClientError {
     #[error("failed to get endpoint local address")]
     GetLocalAddr(#[source] io::Error),
 
+    /// See [`StreamError`].
+    #[error(transparent)]
+    Stream(StreamError),
+
     // connection
     /// Lost connection.
     #[error("connection lost")]
@@ -128,6 +150,7 @@ impl From<InternalError<Self>> for ClientError {
             InternalError::FatalSend(err) => Self::FatalSend(err),
             InternalError::FrontendClosed => Self::FrontendClosed,
             InternalError::DatagramsNotSupported => Self::DatagramsNotSupported,
+            InternalError::Stream(err) => Self::Stream(err),
             InternalError::ConnectionLost(err) => Self::ConnectionLost(err),
         }
     }
@@ -140,6 +163,9 @@ impl From<InternalError<Self>> for ClientError {
 pub struct Connecting {
     recv_connected: oneshot::Receiver<ToConnected>,
     recv_dc: oneshot::Receiver<DisconnectReason<ClientError>>,
+    target: String,
+    resume_token: Option<ResumeToken>,
+    resumed_session: Option<Session>,
 }
 
 #[derive(Debug)]
@@ -151,8 +177,9 @@ struct ToConnected {
     #[cfg(not(target_family = "wasm"))]
     initial_rtt: Duration,
     recv_meta: mpsc::Receiver<ConnectionMeta>,
-    send_c2s: mpsc::UnboundedSender<Bytes>,
+    send_c2s: OutgoingQueue,
     recv_s2c: mpsc::Receiver<Bytes>,
+    streams: StreamLanes,
     send_local_dc: oneshot::Sender<String>,
     session: Session,
 }
@@ -165,6 +192,8 @@ pub struct Connected {
     #[cfg(not(target_family = "wasm"))]
     local_addr: SocketAddr,
     inner: ConnectionInner<ClientError>,
+    target: String,
+    resume_token: Option<ResumeToken>,
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -175,6 +204,15 @@ impl LocalAddr for Connected {
 }
 
 impl Connected {
+    /// Gets if this client can resume its session if its connection is lost,
+    /// i.e. if the server has issued it a [`ResumeToken`].
+    ///
+    /// See [`WebTransportClient::set_resumable`].
+    #[must_use]
+    pub const fn is_resumable(&self) -> bool {
+        self.resume_token.is_some()
+    }
+
     /// Provides access to the underlying [`Session`] for reading more detailed
     /// network statistics.
     #[must_use]
@@ -218,3 +256,13 @@ impl RawRtt for Connected {
         self.inner.raw_rtt
     }
 }
+
+impl OutgoingQueueStats for Connected {
+    fn outgoing_queue_len(&self) -> usize {
+        self.inner.send_msgs.len()
+    }
+
+    fn outgoing_packets_dropped(&self) -> usize {
+        self.inner.send_msgs.packets_dropped
+    }
+}
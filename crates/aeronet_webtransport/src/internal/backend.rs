@@ -15,7 +15,10 @@ use crate::{
     runtime::WebTransportRuntime,
 };
 
-use super::{ClientEndpoint, Connection, ConnectionMeta, InternalError};
+use super::{
+    spawn_stream_loops, ClientEndpoint, Connection, ConnectionMeta, InternalError, OutgoingBackend,
+    StreamBackend,
+};
 
 const STATS_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
 
@@ -40,9 +43,10 @@ pub fn create_client_endpoint(config: ClientConfig) -> Result<ClientEndpoint, Cl
 pub async fn handle_connection<E: maybe::Send + 'static>(
     runtime: WebTransportRuntime,
     conn: Connection,
-    recv_s: mpsc::UnboundedReceiver<Bytes>,
+    recv_s: OutgoingBackend,
     send_r: mpsc::Sender<Bytes>,
     send_meta: mpsc::Sender<ConnectionMeta>,
+    streams: StreamBackend,
     mut recv_local_dc: oneshot::Receiver<String>,
 ) -> Result<Never, DisconnectReason<InternalError<E>>> {
     let conn = Arc::new(conn);
@@ -72,6 +76,8 @@ pub async fn handle_connection<E: maybe::Send + 'static>(
         }
     });
 
+    let _stream_loops_closed = spawn_stream_loops(&runtime, &conn, streams, &send_err);
+
     let (_send_meta_closed, recv_meta_closed) = oneshot::channel();
     runtime.spawn({
         let runtime = runtime.clone();
@@ -149,11 +155,11 @@ pub async fn handle_connection<E: maybe::Send + 'static>(
 async fn send_loop<E>(
     conn: Arc<Connection>,
     mut recv_closed: oneshot::Receiver<()>,
-    mut recv_s: mpsc::UnboundedReceiver<Bytes>,
+    recv_s: OutgoingBackend,
 ) -> Result<(), InternalError<E>> {
     loop {
         let packet = futures::select! {
-            x = recv_s.next() => x,
+            x = recv_s.next().fuse() => x,
             _ = recv_closed => return Ok(()),
         }
         .ok_or(InternalError::FrontendClosed)?;
@@ -1,12 +1,12 @@
 use std::num::Saturating;
 
 use aeronet::{client::DisconnectReason, error::pretty_error, lane::LaneIndex};
-use aeronet_proto::session::FatalSendError;
+use aeronet_proto::session::{FatalSendError, OutOfMemory};
 use bytes::Bytes;
 use tracing::{debug, trace};
 use web_time::{Duration, Instant};
 
-use crate::shared::MessageKey;
+use crate::{resume::ResumeToken, shared::MessageKey};
 
 use super::{ConnectionInner, InternalError};
 
@@ -14,10 +14,18 @@ use super::{ConnectionInner, InternalError};
 pub enum PollEvent {
     Ack { msg_key: MessageKey },
     Recv { msg: Bytes, lane: LaneIndex },
+    ResumeToken(ResumeToken),
 }
 
 impl<E> ConnectionInner<E> {
     pub fn send(&mut self, msg: Bytes, lane: LaneIndex) -> Result<MessageKey, InternalError<E>> {
+        let msg = match self.streams.send(msg, lane) {
+            Ok(key) => {
+                return Ok(key);
+            }
+            Err(msg) => msg,
+        };
+
         let err = match self.session.send(Instant::now(), msg, lane) {
             Ok(key) => {
                 return Ok(key);
@@ -35,11 +43,16 @@ impl<E> ConnectionInner<E> {
     }
 
     pub fn flush(&mut self) {
-        let mut bytes_sent = Saturating(0usize);
-        for packet in self.session.flush(Instant::now()) {
+        let mut bytes_sent = Saturating(self.streams.flush());
+        let mut packets = self.session.flush(Instant::now());
+        // if the queue is full, leave the rest of the packets unbuilt,
+        // so that their frags stay in the session until the next flush
+        while !self.send_msgs.is_blocked() {
+            let Some(packet) = packets.next() else {
+                break;
+            };
             bytes_sent += packet.len();
-            // ignore errors here, pick them up in `poll`
-            let _ = self.send_msgs.unbounded_send(packet);
+            self.send_msgs.push(packet);
         }
 
         let bytes_sent = bytes_sent.0;
@@ -105,9 +118,21 @@ impl<E> ConnectionInner<E> {
             });
         }
 
+        while let Ok(Some(event)) = self.streams.recv.try_next() {
+            if let PollEvent::Recv { msg, .. } = &event {
+                bytes_recv += msg.len();
+            }
+            cb(event);
+        }
+
         self.session
             .update(delta_time)
             .map_err(InternalError::OutOfMemory)?;
+        if self.session.memory_usage() + self.streams.memory_usage()
+            > self.session.max_memory_usage()
+        {
+            return Err(InternalError::OutOfMemory(OutOfMemory).into());
+        }
 
         let bytes_recv = bytes_recv.0;
         if bytes_recv > 0 {
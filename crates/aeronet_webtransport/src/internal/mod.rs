@@ -1,13 +1,17 @@
 mod backend;
 mod frontend;
+mod outgoing;
+mod stream;
 
-pub use {backend::*, frontend::*};
+pub use {backend::*, frontend::*, outgoing::*, stream::*};
 
 use aeronet::client::DisconnectReason;
 use aeronet_proto::session::{FatalSendError, MtuTooSmall, OutOfMemory, SendError, Session};
 use bytes::Bytes;
 use futures::channel::{mpsc, oneshot};
 
+use crate::shared::StreamError;
+
 pub const MSG_BUF_CAP: usize = 256;
 
 // conservative estimate based on
@@ -51,8 +55,9 @@ pub struct ConnectionInner<E> {
     pub session: Session,
     pub recv_dc: oneshot::Receiver<DisconnectReason<E>>,
     pub recv_meta: mpsc::Receiver<ConnectionMeta>,
-    pub send_msgs: mpsc::UnboundedSender<Bytes>,
+    pub send_msgs: OutgoingQueue,
     pub recv_msgs: mpsc::Receiver<Bytes>,
+    pub streams: StreamLanes,
     pub send_local_dc: oneshot::Sender<String>,
     pub fatal_error: Option<FatalSendError>,
 }
@@ -73,6 +78,7 @@ pub enum InternalError<E> {
     // backend
     FrontendClosed,
     DatagramsNotSupported,
+    Stream(StreamError),
 
     // connection
     ConnectionLost(ConnectionError),
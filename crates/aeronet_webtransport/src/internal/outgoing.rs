@@ -0,0 +1,236 @@
+use std::{
+    collections::VecDeque,
+    future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    task::Poll,
+};
+
+use bytes::Bytes;
+use futures::task::AtomicWaker;
+
+use crate::shared::{OutgoingQueueConfig, OutgoingQueuePolicy};
+
+/// Frontend half of the bounded queue of packets waiting to be sent out as
+/// datagrams.
+///
+/// Unlike a bounded channel, the frontend can drop packets which are already
+/// queued up to make room for new ones, depending on the
+/// [`OutgoingQueuePolicy`].
+#[derive(Debug)]
+pub struct OutgoingQueue {
+    shared: Arc<Shared>,
+    capacity: usize,
+    policy: OutgoingQueuePolicy,
+    pub packets_dropped: usize,
+}
+
+/// Backend half of [`OutgoingQueue`].
+#[derive(Debug)]
+pub struct OutgoingBackend {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    packets: Mutex<VecDeque<Bytes>>,
+    waker: AtomicWaker,
+    frontend_closed: AtomicBool,
+}
+
+impl Shared {
+    fn packets(&self) -> MutexGuard<'_, VecDeque<Bytes>> {
+        // a panic while holding the lock can't leave the queue in an invalid
+        // state, so we can ignore poisoning
+        self.packets
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// Creates the queue for sending packets, configured by `config`.
+pub fn outgoing_queue(config: OutgoingQueueConfig) -> (OutgoingQueue, OutgoingBackend) {
+    let shared = Arc::new(Shared::default());
+    (
+        OutgoingQueue {
+            shared: shared.clone(),
+            capacity: config.capacity.max(1),
+            policy: config.policy,
+            packets_dropped: 0,
+        },
+        OutgoingBackend { shared },
+    )
+}
+
+impl OutgoingQueue {
+    /// Gets the number of packets waiting to be sent.
+    pub fn len(&self) -> usize {
+        self.shared.packets().len()
+    }
+
+    /// Gets if no more packets should be pushed until the backend has sent
+    /// some, because the queue is full and uses
+    /// [`OutgoingQueuePolicy::Backpressure`].
+    pub fn is_blocked(&self) -> bool {
+        self.policy == OutgoingQueuePolicy::Backpressure && self.len() >= self.capacity
+    }
+
+    /// Queues up `packet` to be sent by the backend.
+    ///
+    /// If the queue is full and uses [`OutgoingQueuePolicy::DropOldestPacket`],
+    /// the oldest packet is dropped to make room.
+    pub fn push(&mut self, packet: Bytes) {
+        {
+            let mut packets = self.shared.packets();
+            if self.policy == OutgoingQueuePolicy::DropOldestPacket {
+                while packets.len() >= self.capacity {
+                    packets.pop_front();
+                    self.packets_dropped += 1;
+                }
+            }
+            packets.push_back(packet);
+        }
+        self.shared.waker.wake();
+    }
+}
+
+impl Drop for OutgoingQueue {
+    fn drop(&mut self) {
+        self.shared.frontend_closed.store(true, Ordering::Release);
+        self.shared.waker.wake();
+    }
+}
+
+impl OutgoingBackend {
+    /// Waits for the next packet to send.
+    ///
+    /// Returns [`None`] once the frontend is closed.
+    pub async fn next(&self) -> Option<Bytes> {
+        future::poll_fn(|cx| {
+            // register before checking, so that we can't miss a wake-up which
+            // happens in between
+            self.shared.waker.register(cx.waker());
+            let packet = self.shared.packets().pop_front();
+            if let Some(packet) = packet {
+                return Poll::Ready(Some(packet));
+            }
+            if self.shared.frontend_closed.load(Ordering::Acquire) {
+                return Poll::Ready(None);
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::pin,
+        sync::atomic::AtomicUsize,
+        task::{Context, Poll},
+    };
+
+    use futures::{
+        executor::block_on,
+        task::{waker, ArcWake},
+        Future,
+    };
+
+    use super::*;
+
+    fn queue(capacity: usize, policy: OutgoingQueuePolicy) -> (OutgoingQueue, OutgoingBackend) {
+        outgoing_queue(
+            OutgoingQueueConfig::new()
+                .with_capacity(capacity)
+                .with_policy(policy),
+        )
+    }
+
+    fn packet(n: u8) -> Bytes {
+        Bytes::from(vec![n])
+    }
+
+    #[derive(Default)]
+    struct CountWakes(AtomicUsize);
+
+    impl ArcWake for CountWakes {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn zero_capacity_is_one() {
+        let (mut queue, _backend) = queue(0, OutgoingQueuePolicy::Backpressure);
+        assert!(!queue.is_blocked());
+        queue.push(packet(0));
+        assert!(queue.is_blocked());
+    }
+
+    #[test]
+    fn backpressure_blocks_when_full() {
+        let (mut queue, backend) = queue(2, OutgoingQueuePolicy::Backpressure);
+        queue.push(packet(0));
+        assert!(!queue.is_blocked());
+        queue.push(packet(1));
+        assert!(queue.is_blocked());
+        assert_eq!(2, queue.len());
+
+        // backpressure never drops packets itself
+        assert_eq!(Some(packet(0)), block_on(backend.next()));
+        assert!(!queue.is_blocked());
+        assert_eq!(0, queue.packets_dropped);
+    }
+
+    #[test]
+    fn drop_oldest_packet() {
+        let (mut queue, backend) = queue(2, OutgoingQueuePolicy::DropOldestPacket);
+        for n in 0..5 {
+            queue.push(packet(n));
+            // this policy never blocks
+            assert!(!queue.is_blocked());
+        }
+        assert_eq!(2, queue.len());
+        assert_eq!(3, queue.packets_dropped);
+
+        assert_eq!(Some(packet(3)), block_on(backend.next()));
+        assert_eq!(Some(packet(4)), block_on(backend.next()));
+        queue.push(packet(5));
+        assert_eq!(3, queue.packets_dropped);
+    }
+
+    #[test]
+    fn backend_drains_before_closing() {
+        let (mut queue, backend) = queue(4, OutgoingQueuePolicy::Backpressure);
+        queue.push(packet(0));
+        queue.push(packet(1));
+        drop(queue);
+
+        assert_eq!(Some(packet(0)), block_on(backend.next()));
+        assert_eq!(Some(packet(1)), block_on(backend.next()));
+        assert_eq!(None, block_on(backend.next()));
+    }
+
+    #[test]
+    fn backend_woken() {
+        let (mut queue, backend) = queue(4, OutgoingQueuePolicy::Backpressure);
+        let wakes = Arc::new(CountWakes::default());
+        let waker = waker(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let mut next = pin!(backend.next());
+        assert_eq!(Poll::Pending, next.as_mut().poll(&mut cx));
+        queue.push(packet(0));
+        assert_eq!(1, wakes.0.load(Ordering::SeqCst));
+        assert_eq!(Poll::Ready(Some(packet(0))), next.as_mut().poll(&mut cx));
+
+        let mut next = pin!(backend.next());
+        assert_eq!(Poll::Pending, next.as_mut().poll(&mut cx));
+        drop(queue);
+        assert_eq!(2, wakes.0.load(Ordering::SeqCst));
+        assert_eq!(Poll::Ready(None), next.as_mut().poll(&mut cx));
+    }
+}
@@ -0,0 +1,811 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use aeronet::{
+    error::pretty_error,
+    lane::{LaneIndex, LaneKind, LaneReliability},
+};
+use aeronet_proto::{session::MessageKey, ty::MessageSeq};
+use bytes::{Bytes, BytesMut};
+use futures::{
+    channel::{mpsc, oneshot},
+    stream::FuturesUnordered,
+    FutureExt, SinkExt, StreamExt,
+};
+use tracing::debug;
+use xwt_core::{prelude::*, stream, utils::maybe};
+
+use crate::{
+    resume::{ResumeToken, TOKEN_LEN},
+    runtime::WebTransportRuntime,
+    shared::StreamError,
+};
+
+use super::{Connection, InternalError, PollEvent, MSG_BUF_CAP};
+
+/// Maximum length of a message buffered into a single read from a stream.
+///
+/// Messages larger than this are read in multiple parts, so that a peer can't
+/// make us allocate a huge buffer up front by only sending a message length.
+const READ_CHUNK_LEN: usize = 64 * 1024;
+
+/// Header of the stream which the server sends a client's [`ResumeToken`] on,
+/// in place of a lane index.
+///
+/// The header is followed by the token's bytes.
+pub const RESUME_TOKEN_HEADER: u64 = u64::MAX;
+
+/// Frontend half of the WebTransport streams which messages on stream lanes
+/// are sent and received over.
+///
+/// Each stream lane is sent over its own unidirectional stream, opened when
+/// the first message on that lane is sent. The stream starts with the lane
+/// index as a little-endian `u64`, followed by messages, each prefixed by its
+/// length as a little-endian `u64`.
+///
+/// A lane's stream is never reopened, since messages queued on a failed
+/// stream can't be sent again without breaking the lane's ordering. Instead,
+/// if any stream fails, the whole connection is closed with a
+/// [`StreamError`].
+///
+/// Messages on stream lanes are never acknowledged, since the peer doesn't
+/// report which messages it has received on its streams.
+#[derive(Debug)]
+pub struct StreamLanes {
+    /// Next message sequence number of each stream lane.
+    pub lanes: HashMap<LaneIndex, MessageSeq>,
+    pub buf: Vec<(LaneIndex, Bytes)>,
+    /// Number of bytes of messages which have been flushed to the backend, but
+    /// not written to their stream yet.
+    pub queued: Arc<AtomicUsize>,
+    pub send: mpsc::UnboundedSender<StreamSend>,
+    pub recv: mpsc::Receiver<PollEvent>,
+}
+
+/// Data which the frontend asks the backend to send over a stream.
+#[derive(Debug)]
+pub enum StreamSend {
+    Msg {
+        lane: LaneIndex,
+        msg: Bytes,
+    },
+    /// Only the server issues resume tokens.
+    #[cfg_attr(
+        not(all(feature = "server", not(target_family = "wasm"))),
+        allow(dead_code)
+    )]
+    ResumeToken(ResumeToken),
+}
+
+/// Backend half of [`StreamLanes`].
+#[derive(Debug)]
+pub struct StreamBackend {
+    pub recv_s: mpsc::UnboundedReceiver<StreamSend>,
+    pub send_r: mpsc::Sender<PollEvent>,
+    pub queued: Arc<AtomicUsize>,
+    pub num_recv_lanes: usize,
+    pub max_msg_len: usize,
+    /// Whether the peer may send us a [`ResumeToken`], which only the server
+    /// does, and only if the client asked for one.
+    pub accept_resume_token: bool,
+}
+
+/// What a stream opened by the peer is used for.
+#[derive(Debug, PartialEq, Eq)]
+enum StreamHeader {
+    Lane(LaneIndex),
+    ResumeToken(ResumeToken),
+}
+
+/// Creates the channels for sending messages on `lanes` over streams.
+///
+/// Only lanes which are reliable in `send_lanes` are sent over streams, since
+/// unreliable lanes should stay on datagrams.
+pub fn stream_lanes(
+    lanes: &[LaneIndex],
+    send_lanes: &[LaneKind],
+    num_recv_lanes: usize,
+    max_msg_len: usize,
+    accept_resume_token: bool,
+) -> (StreamLanes, StreamBackend) {
+    let lanes = lanes
+        .iter()
+        .copied()
+        .filter(|lane| {
+            usize::try_from(lane.into_raw())
+                .ok()
+                .and_then(|index| send_lanes.get(index))
+                .is_some_and(|kind| kind.reliability() == LaneReliability::Reliable)
+        })
+        .map(|lane| (lane, MessageSeq::new(0)))
+        .collect();
+    let (send_s, recv_s) = mpsc::unbounded();
+    let (send_r, recv_r) = mpsc::channel(MSG_BUF_CAP);
+    let queued = Arc::new(AtomicUsize::new(0));
+    (
+        StreamLanes {
+            lanes,
+            buf: Vec::new(),
+            queued: queued.clone(),
+            send: send_s,
+            recv: recv_r,
+        },
+        StreamBackend {
+            recv_s,
+            send_r,
+            queued,
+            num_recv_lanes,
+            max_msg_len,
+            accept_resume_token,
+        },
+    )
+}
+
+impl StreamLanes {
+    /// Buffers up `msg` for sending on `lane`'s stream if `lane` is a stream
+    /// lane, or gives the message back otherwise.
+    pub fn send(&mut self, msg: Bytes, lane: LaneIndex) -> Result<MessageKey, Bytes> {
+        let Some(next_seq) = self.lanes.get_mut(&lane) else {
+            return Err(msg);
+        };
+
+        let msg_key = MessageKey::from_raw(lane, *next_seq);
+        *next_seq += MessageSeq::ONE;
+        self.buf.push((lane, msg));
+        Ok(msg_key)
+    }
+
+    /// Sends all buffered messages to the backend.
+    ///
+    /// Returns the number of bytes sent.
+    pub fn flush(&mut self) -> usize {
+        self.buf
+            .drain(..)
+            .map(|(lane, msg)| {
+                let len = msg.len();
+                self.queued.fetch_add(len, Ordering::Relaxed);
+                // ignore errors here, pick them up in `poll`
+                let _ = self.send.unbounded_send(StreamSend::Msg { lane, msg });
+                len
+            })
+            .sum()
+    }
+
+    /// Sends a [`ResumeToken`] to the peer over a new stream.
+    #[cfg(all(feature = "server", not(target_family = "wasm")))]
+    pub fn send_resume_token(&self, token: ResumeToken) {
+        // ignore errors here, pick them up in `poll`
+        let _ = self.send.unbounded_send(StreamSend::ResumeToken(token));
+    }
+
+    /// Gets how many bytes of messages are buffered on stream lanes, waiting
+    /// to be written to their streams.
+    ///
+    /// These count towards the session's memory usage.
+    pub fn memory_usage(&self) -> usize {
+        self.buf.iter().map(|(_, msg)| msg.len()).sum::<usize>()
+            + self.queued.load(Ordering::Relaxed)
+    }
+}
+
+/// Spawns the tasks which send and receive messages on stream lanes.
+///
+/// Each task stops once its returned sender is dropped. If any stream fails,
+/// its task reports the error to `send_err`, which closes the connection.
+pub fn spawn_stream_loops<E: maybe::Send + 'static>(
+    runtime: &WebTransportRuntime,
+    conn: &Arc<Connection>,
+    streams: StreamBackend,
+    send_err: &mpsc::Sender<InternalError<E>>,
+) -> (oneshot::Sender<()>, oneshot::Sender<()>) {
+    let StreamBackend {
+        recv_s,
+        send_r,
+        queued,
+        num_recv_lanes,
+        max_msg_len,
+        accept_resume_token,
+    } = streams;
+
+    let (send_sending_closed, recv_sending_closed) = oneshot::channel();
+    runtime.spawn({
+        let conn = conn.clone();
+        let mut send_err = send_err.clone();
+        async move {
+            let err = stream_send_loop(conn, recv_sending_closed, recv_s, queued)
+                .await
+                .unwrap_err();
+            let _ = send_err.try_send(err);
+        }
+    });
+
+    let (send_receiving_closed, recv_receiving_closed) = oneshot::channel();
+    runtime.spawn({
+        let conn = conn.clone();
+        let mut send_err = send_err.clone();
+        async move {
+            let err = stream_recv_loop(
+                conn,
+                recv_receiving_closed,
+                send_r,
+                num_recv_lanes,
+                max_msg_len,
+                accept_resume_token,
+            )
+            .await
+            .unwrap_err();
+            let _ = send_err.try_send(err);
+        }
+    });
+
+    (send_sending_closed, send_receiving_closed)
+}
+
+/// Gets the error to close the connection with after a stream failed with
+/// `err`.
+///
+/// A stream usually fails because the whole connection was lost, in which case
+/// we report the reason for that instead.
+fn stream_error<E>(conn: &Connection, err: StreamError) -> InternalError<E> {
+    #[cfg(not(target_family = "wasm"))]
+    if let Some(err) = conn.0.closed().now_or_never() {
+        return InternalError::ConnectionLost(err);
+    }
+    #[cfg(target_family = "wasm")]
+    let _ = conn;
+
+    InternalError::Stream(err)
+}
+
+async fn stream_send_loop<E>(
+    conn: Arc<Connection>,
+    mut recv_closed: oneshot::Receiver<()>,
+    mut recv_s: mpsc::UnboundedReceiver<StreamSend>,
+    queued: Arc<AtomicUsize>,
+) -> Result<(), InternalError<E>> {
+    // all lanes are driven from this task, so that a failed lane fails this
+    // whole loop, rather than silently dropping the messages queued on it
+    let mut lanes = HashMap::<LaneIndex, mpsc::UnboundedSender<Bytes>>::new();
+    let mut lane_loops = FuturesUnordered::new();
+    let mut token_sends = FuturesUnordered::new();
+
+    loop {
+        let send = futures::select! {
+            x = recv_s.next() => x.ok_or(InternalError::FrontendClosed)?,
+            res = lane_loops.select_next_some() => {
+                // a lane loop only finishes once it has failed, or once we
+                // have stopped giving it messages
+                res?;
+                continue;
+            }
+            res = token_sends.select_next_some() => {
+                res?;
+                continue;
+            }
+            _ = recv_closed => return Ok(()),
+        };
+
+        let (lane, msg) = match send {
+            StreamSend::Msg { lane, msg } => (lane, msg),
+            StreamSend::ResumeToken(token) => {
+                token_sends.push(send_resume_token(&conn, token));
+                continue;
+            }
+        };
+        let send_lane = lanes.entry(lane).or_insert_with(|| {
+            let (send_lane, recv_lane) = mpsc::unbounded();
+            lane_loops.push(send_lane_loop(&conn, lane, recv_lane, &queued));
+            send_lane
+        });
+        // if this fails, the lane loop has failed, and we'll pick up its error
+        // on the next iteration
+        let _ = send_lane.unbounded_send(msg);
+    }
+}
+
+async fn send_lane_loop<E>(
+    conn: &Connection,
+    lane: LaneIndex,
+    mut recv_lane: mpsc::UnboundedReceiver<Bytes>,
+    queued: &AtomicUsize,
+) -> Result<(), InternalError<E>> {
+    let mut stream = async {
+        let opening = conn.open_uni().await.map_err(|err| {
+            debug!(
+                "Failed to open stream for {lane:?}: {:#}",
+                pretty_error(&err)
+            );
+        })?;
+        opening.wait_uni().await.map_err(|err| {
+            debug!(
+                "Failed to open stream for {lane:?}: {:#}",
+                pretty_error(&err)
+            );
+        })
+    }
+    .await
+    .map_err(|()| stream_error(conn, StreamError::Open { lane }))?;
+
+    let write_err = |err| {
+        debug!(
+            "Failed to write to stream for {lane:?}: {:#}",
+            pretty_error(&err)
+        );
+        stream_error(conn, StreamError::Write { lane })
+    };
+    write_all(&mut stream, &lane.into_raw().to_le_bytes())
+        .await
+        .map_err(write_err)?;
+
+    while let Some(msg) = recv_lane.next().await {
+        let len = msg.len() as u64;
+        write_all(&mut stream, &len.to_le_bytes())
+            .await
+            .map_err(write_err)?;
+        write_all(&mut stream, &msg).await.map_err(write_err)?;
+        // the message is now buffered by QUIC instead of us
+        queued.fetch_sub(msg.len(), Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+async fn send_resume_token<E>(
+    conn: &Connection,
+    token: ResumeToken,
+) -> Result<(), InternalError<E>> {
+    let mut stream = async {
+        let opening = conn.open_uni().await.map_err(|err| {
+            debug!(
+                "Failed to open stream for resume token: {:#}",
+                pretty_error(&err)
+            );
+        })?;
+        opening.wait_uni().await.map_err(|err| {
+            debug!(
+                "Failed to open stream for resume token: {:#}",
+                pretty_error(&err)
+            );
+        })
+    }
+    .await
+    .map_err(|()| stream_error(conn, StreamError::SendResumeToken))?;
+
+    let mut buf = RESUME_TOKEN_HEADER.to_le_bytes().to_vec();
+    buf.extend_from_slice(&token.to_bytes());
+    write_all(&mut stream, &buf).await.map_err(|err| {
+        debug!(
+            "Failed to write to stream for resume token: {:#}",
+            pretty_error(&err)
+        );
+        stream_error(conn, StreamError::SendResumeToken)
+    })
+}
+
+async fn stream_recv_loop<E>(
+    conn: Arc<Connection>,
+    mut recv_closed: oneshot::Receiver<()>,
+    send_r: mpsc::Sender<PollEvent>,
+    num_recv_lanes: usize,
+    max_msg_len: usize,
+    accept_resume_token: bool,
+) -> Result<(), InternalError<E>> {
+    // like sending, all lanes are driven from this task, so that we can make
+    // sure each lane only ever has a single stream, keeping it ordered
+    let mut headers = FuturesUnordered::new();
+    let mut lane_loops = FuturesUnordered::new();
+    let mut opened_lanes = HashSet::new();
+
+    loop {
+        futures::select! {
+            x = conn.accept_uni().fuse() => {
+                #[allow(clippy::useless_conversion)] // multi-target support
+                let mut stream = x.map_err(|err| InternalError::ConnectionLost(err.into()))?;
+                headers.push(async move {
+                    let header = read_header(&mut stream, num_recv_lanes, accept_resume_token).await;
+                    (stream, header)
+                });
+            }
+            header = headers.select_next_some() => {
+                let (stream, header) = header;
+                let lane = match header.map_err(|err| stream_error(&conn, err))? {
+                    Some(StreamHeader::Lane(lane)) => lane,
+                    Some(StreamHeader::ResumeToken(token)) => {
+                        send_r
+                            .clone()
+                            .send(PollEvent::ResumeToken(token))
+                            .await
+                            .map_err(|_| InternalError::FrontendClosed)?;
+                        continue;
+                    }
+                    None => {
+                        // the peer finished the stream without using it
+                        continue;
+                    }
+                };
+                if !opened_lanes.insert(lane) {
+                    return Err(InternalError::Stream(StreamError::DuplicateLane { lane }));
+                }
+                lane_loops.push(recv_lane_loop(stream, lane, send_r.clone(), max_msg_len));
+            }
+            res = lane_loops.select_next_some() => {
+                res.map_err(|err| stream_error(&conn, err))?;
+            }
+            _ = recv_closed => return Ok(()),
+        }
+    }
+}
+
+/// Reads the header of a stream which the peer opened, along with the
+/// [`ResumeToken`] if the stream is used for one.
+///
+/// Returns [`None`] if the stream finished before the header was read.
+async fn read_header<R: stream::Read>(
+    stream: &mut R,
+    num_recv_lanes: usize,
+    accept_resume_token: bool,
+) -> Result<Option<StreamHeader>, StreamError> {
+    let read_err = |err| {
+        debug!("Failed to read from stream: {:#}", pretty_error(&err));
+        StreamError::Read
+    };
+
+    let mut header = [0; 8];
+    if !read_exact(stream, &mut header).await.map_err(read_err)? {
+        return Ok(None);
+    }
+
+    let header = u64::from_le_bytes(header);
+    if header == RESUME_TOKEN_HEADER && accept_resume_token {
+        let mut token = [0; TOKEN_LEN];
+        if !read_exact(stream, &mut token).await.map_err(read_err)? {
+            return Ok(None);
+        }
+        return Ok(Some(StreamHeader::ResumeToken(ResumeToken::from_bytes(
+            token,
+        ))));
+    }
+
+    let lane = LaneIndex::from_raw(header);
+    if usize::try_from(lane.into_raw()).map_or(true, |index| index >= num_recv_lanes) {
+        return Err(StreamError::InvalidLane { lane });
+    }
+    Ok(Some(StreamHeader::Lane(lane)))
+}
+
+/// Reads messages from the stream for `lane`, until the stream finishes.
+async fn recv_lane_loop<R: stream::Read>(
+    mut stream: R,
+    lane: LaneIndex,
+    mut send_r: mpsc::Sender<PollEvent>,
+    max_msg_len: usize,
+) -> Result<(), StreamError> {
+    while let Some(msg) = read_msg(&mut stream, lane, max_msg_len).await? {
+        if send_r.send(PollEvent::Recv { msg, lane }).await.is_err() {
+            // the frontend is closed, and the other loops will report this
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// Reads the next length-prefixed message from the stream for `lane`.
+///
+/// Returns [`None`] if the stream finished cleanly before the next message.
+async fn read_msg<R: stream::Read>(
+    stream: &mut R,
+    lane: LaneIndex,
+    max_msg_len: usize,
+) -> Result<Option<Bytes>, StreamError> {
+    let read_err = |err| {
+        debug!(
+            "Failed to read from stream for {lane:?}: {:#}",
+            pretty_error(&err)
+        );
+        StreamError::Read
+    };
+
+    let mut header = [0; 8];
+    if !read_exact(stream, &mut header).await.map_err(read_err)? {
+        return Ok(None);
+    }
+    let len = u64::from_le_bytes(header);
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= max_msg_len)
+        .ok_or(StreamError::TooLong { lane, len })?;
+
+    let mut msg = BytesMut::with_capacity(len.min(READ_CHUNK_LEN));
+    while msg.len() < len {
+        let start = msg.len();
+        let chunk_len = (len - start).min(READ_CHUNK_LEN);
+        msg.resize(start + chunk_len, 0);
+        if !read_exact(stream, &mut msg[start..])
+            .await
+            .map_err(read_err)?
+        {
+            return Err(StreamError::Truncated { lane });
+        }
+    }
+    Ok(Some(msg.freeze()))
+}
+
+async fn write_all<W: stream::Write>(stream: &mut W, mut buf: &[u8]) -> Result<(), W::Error> {
+    while !buf.is_empty() {
+        let written = stream.write(buf).await?;
+        buf = &buf[written..];
+    }
+    Ok(())
+}
+
+/// Fills `buf` with data read from `stream`, returning `false` if the stream
+/// finished first.
+async fn read_exact<R: stream::Read>(stream: &mut R, mut buf: &mut [u8]) -> Result<bool, R::Error> {
+    while !buf.is_empty() {
+        match stream.read(buf).await? {
+            None | Some(0) => return Ok(false),
+            Some(read) => buf = &mut buf[read..],
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        future::{self, Future},
+    };
+
+    use assert_matches::assert_matches;
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("read failed")]
+    struct ReadFailed;
+
+    /// Stream which returns at most one chunk of data per read, then either
+    /// finishes or fails.
+    struct ChunkedStream {
+        chunks: VecDeque<Vec<u8>>,
+        fail: bool,
+    }
+
+    impl ChunkedStream {
+        fn new(chunks: impl IntoIterator<Item = Vec<u8>>) -> Self {
+            Self {
+                chunks: chunks.into_iter().collect(),
+                fail: false,
+            }
+        }
+
+        fn failing(chunks: impl IntoIterator<Item = Vec<u8>>) -> Self {
+            Self {
+                fail: true,
+                ..Self::new(chunks)
+            }
+        }
+    }
+
+    impl stream::Read for ChunkedStream {
+        type Error = ReadFailed;
+
+        fn read(
+            &mut self,
+            buf: &mut [u8],
+        ) -> impl Future<Output = Result<Option<usize>, Self::Error>> {
+            let Some(chunk) = self.chunks.front_mut() else {
+                return future::ready(if self.fail { Err(ReadFailed) } else { Ok(None) });
+            };
+            let len = chunk.len().min(buf.len());
+            buf[..len].copy_from_slice(&chunk[..len]);
+            chunk.drain(..len);
+            if chunk.is_empty() {
+                self.chunks.pop_front();
+            }
+            future::ready(Ok(Some(len)))
+        }
+    }
+
+    fn msg(msg: &[u8]) -> Vec<u8> {
+        let mut buf = (msg.len() as u64).to_le_bytes().to_vec();
+        buf.extend_from_slice(msg);
+        buf
+    }
+
+    const LANE: LaneIndex = LaneIndex::from_raw(1);
+
+    #[test]
+    fn send_seq_per_lane() {
+        let (mut streams, _backend) = stream_lanes(
+            &[LaneIndex::from_raw(0), LANE, LaneIndex::from_raw(2)],
+            &[
+                LaneKind::ReliableOrdered,
+                LaneKind::ReliableUnordered,
+                LaneKind::UnreliableUnordered,
+            ],
+            3,
+            16,
+            false,
+        );
+        let key = |lane, seq| MessageKey::from_raw(LaneIndex::from_raw(lane), MessageSeq::new(seq));
+
+        assert_eq!(
+            Ok(key(0, 0)),
+            streams.send(Bytes::new(), LaneIndex::from_raw(0))
+        );
+        assert_eq!(Ok(key(1, 0)), streams.send(Bytes::new(), LANE));
+        assert_eq!(
+            Ok(key(0, 1)),
+            streams.send(Bytes::new(), LaneIndex::from_raw(0))
+        );
+        assert_eq!(Ok(key(1, 1)), streams.send(Bytes::new(), LANE));
+        // unreliable lanes stay on datagrams
+        assert_eq!(
+            Err(Bytes::from_static(b"msg")),
+            streams.send(Bytes::from_static(b"msg"), LaneIndex::from_raw(2))
+        );
+    }
+
+    #[test]
+    fn memory_usage() {
+        let (mut streams, backend) =
+            stream_lanes(&[LANE], &[LaneKind::ReliableOrdered; 2], 2, 16, false);
+        streams.send(Bytes::from_static(b"hello"), LANE).unwrap();
+        assert_eq!(5, streams.memory_usage());
+
+        // messages still count once flushed, until they are written
+        assert_eq!(5, streams.flush());
+        streams.send(Bytes::from_static(b"world!"), LANE).unwrap();
+        assert_eq!(11, streams.memory_usage());
+
+        backend.queued.fetch_sub(5, Ordering::Relaxed);
+        assert_eq!(6, streams.memory_usage());
+    }
+
+    #[test]
+    fn read_exact_across_chunks() {
+        let mut stream = ChunkedStream::new([vec![1, 2], vec![3], vec![4, 5, 6]]);
+        let mut buf = [0; 5];
+        assert!(block_on(read_exact(&mut stream, &mut buf)).unwrap());
+        assert_eq!([1, 2, 3, 4, 5], buf);
+
+        let mut buf = [0; 1];
+        assert!(block_on(read_exact(&mut stream, &mut buf)).unwrap());
+        assert_eq!([6], buf);
+    }
+
+    #[test]
+    fn read_exact_finished() {
+        let mut stream = ChunkedStream::new([vec![1, 2]]);
+        let mut buf = [0; 4];
+        assert!(!block_on(read_exact(&mut stream, &mut buf)).unwrap());
+    }
+
+    #[test]
+    fn read_exact_error() {
+        let mut stream = ChunkedStream::failing([vec![1, 2]]);
+        let mut buf = [0; 4];
+        assert_matches!(block_on(read_exact(&mut stream, &mut buf)), Err(ReadFailed));
+    }
+
+    #[test]
+    fn read_msgs() {
+        let mut stream = ChunkedStream::new([msg(b"hello"), msg(b""), msg(b"world")]);
+        let mut read = || block_on(read_msg(&mut stream, LANE, 16)).unwrap();
+        assert_eq!(Some(Bytes::from_static(b"hello")), read());
+        assert_eq!(Some(Bytes::new()), read());
+        assert_eq!(Some(Bytes::from_static(b"world")), read());
+        assert_eq!(None, read());
+    }
+
+    #[test]
+    fn read_msg_in_chunks() {
+        let data = vec![7; READ_CHUNK_LEN * 2 + 1];
+        let mut stream = ChunkedStream::new(msg(&data).chunks(1000).map(<[u8]>::to_vec));
+        let msg = block_on(read_msg(&mut stream, LANE, data.len()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(data, msg);
+    }
+
+    #[test]
+    fn read_msg_max_len() {
+        let mut stream = ChunkedStream::new([msg(b"hello")]);
+        assert_matches!(block_on(read_msg(&mut stream, LANE, 5)), Ok(Some(_)));
+
+        let mut stream = ChunkedStream::new([msg(b"hello")]);
+        assert_matches!(
+            block_on(read_msg(&mut stream, LANE, 4)),
+            Err(StreamError::TooLong { lane: LANE, len: 5 })
+        );
+    }
+
+    #[test]
+    fn read_msg_huge_len() {
+        // we must not try to allocate anything for this
+        let mut stream = ChunkedStream::new([u64::MAX.to_le_bytes().to_vec()]);
+        assert_matches!(
+            block_on(read_msg(&mut stream, LANE, 16)),
+            Err(StreamError::TooLong {
+                lane: LANE,
+                len: u64::MAX
+            })
+        );
+    }
+
+    #[test]
+    fn read_msg_truncated() {
+        let mut buf = msg(b"hello");
+        buf.pop();
+        let mut stream = ChunkedStream::new([buf]);
+        assert_matches!(
+            block_on(read_msg(&mut stream, LANE, 16)),
+            Err(StreamError::Truncated { lane: LANE })
+        );
+    }
+
+    #[test]
+    fn read_msg_error() {
+        let mut stream = ChunkedStream::failing([msg(b"hello")]);
+        assert_matches!(block_on(read_msg(&mut stream, LANE, 16)), Ok(Some(_)));
+        assert_matches!(
+            block_on(read_msg(&mut stream, LANE, 16)),
+            Err(StreamError::Read)
+        );
+    }
+
+    #[test]
+    fn read_valid_lane() {
+        let mut stream = ChunkedStream::new([1u64.to_le_bytes().to_vec()]);
+        assert_matches!(
+            block_on(read_header(&mut stream, 2, false)),
+            Ok(Some(StreamHeader::Lane(LANE)))
+        );
+    }
+
+    #[test]
+    fn read_invalid_lane() {
+        for lane in [2, u64::MAX] {
+            let mut stream = ChunkedStream::new([lane.to_le_bytes().to_vec()]);
+            assert_matches!(
+                block_on(read_header(&mut stream, 2, false)),
+                Err(StreamError::InvalidLane { lane: l }) if l == LaneIndex::from_raw(lane)
+            );
+        }
+    }
+
+    #[test]
+    fn read_lane_finished() {
+        let mut stream = ChunkedStream::new([]);
+        assert_matches!(block_on(read_header(&mut stream, 2, false)), Ok(None));
+    }
+
+    #[test]
+    fn read_resume_token() {
+        let token = ResumeToken::from_bytes([3; TOKEN_LEN]);
+        let mut stream = ChunkedStream::new([
+            RESUME_TOKEN_HEADER.to_le_bytes().to_vec(),
+            token.to_bytes().to_vec(),
+        ]);
+        assert_matches!(
+            block_on(read_header(&mut stream, 2, true)),
+            Ok(Some(StreamHeader::ResumeToken(t))) if t == token
+        );
+    }
+
+    #[test]
+    fn read_resume_token_truncated() {
+        let mut stream = ChunkedStream::new([
+            RESUME_TOKEN_HEADER.to_le_bytes().to_vec(),
+            vec![3; TOKEN_LEN - 1],
+        ]);
+        assert_matches!(block_on(read_header(&mut stream, 2, true)), Ok(None));
+    }
+}
@@ -13,11 +13,15 @@ pub use wtransport;
 pub use xwt_web_sys;
 
 pub mod cert;
+pub mod resume;
 pub mod runtime;
 pub mod shared;
 
 mod internal;
 
+#[cfg(feature = "token")]
+pub mod token;
+
 #[cfg(feature = "client")]
 pub mod client;
 
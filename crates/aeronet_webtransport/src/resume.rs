@@ -0,0 +1,153 @@
+//! Session resumption, allowing a client which briefly loses its connection to
+//! reattach to its old session on the server.
+//!
+//! When a [`WebTransportClient`] with resumption enabled first connects, it
+//! asks the server for a [`ResumeToken`] by adding the [`ISSUE_QUERY_PARAM`]
+//! query parameter to the URL it connects to. Once the server has accepted the
+//! client, it generates a random token and sends it back over a unidirectional
+//! stream on the new connection, since there is no way to pass data back to a
+//! browser client in the response to its session request.
+//!
+//! If the connection is then lost, both sides keep their [`Session`] - the
+//! server keeps the client around for its resume grace period, and the client
+//! keeps its session until its next connection attempt. When the client
+//! reconnects to the same target, it passes its token in
+//! [`RESUME_QUERY_PARAM`]. If the server knows the token, the request is
+//! handled like any other connecting client - it must be accepted manually or
+//! by the server's admission policy, and if the old client was accepted with a
+//! connect token, the new request must be accepted with a connect token for
+//! the same user. Once connected, the new connection is reattached to the old
+//! client, re-sending any reliable messages which were not acknowledged yet.
+//! Requests with an unknown token are rejected immediately.
+//!
+//! Messages on stream lanes can't be re-sent, so a server which uses stream
+//! lanes never issues tokens, and a client which uses stream lanes never asks
+//! for one.
+//!
+//! The token is only ever sent over the encrypted WebTransport connection.
+//!
+//! [`WebTransportClient`]: crate::client::WebTransportClient
+//! [`Session`]: aeronet_proto::session::Session
+
+use std::fmt;
+
+use base64::Engine;
+
+/// Name of the query string parameter which asks the server to issue a token,
+/// sent when a client connects for the first time.
+pub const ISSUE_QUERY_PARAM: &str = "issue_resume_token";
+
+/// Name of the query string parameter which holds an existing token, sent when
+/// a client attempts to resume its old session.
+pub const RESUME_QUERY_PARAM: &str = "resume_token";
+
+/// Disconnect reason given for the key of a connecting client once it has
+/// resumed the session of another client, which it continues under that
+/// client's key.
+pub const RESUMED_REASON: &str = "resumed an existing session";
+
+/// Length in bytes of a [`ResumeToken`].
+pub const TOKEN_LEN: usize = 16;
+
+const BASE64: base64::engine::GeneralPurpose = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+/// Random secret issued by the server which identifies a client's session,
+/// used to resume it after the client's connection is lost.
+///
+/// See the [module-level documentation](self).
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResumeToken([u8; TOKEN_LEN]);
+
+impl fmt::Debug for ResumeToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResumeToken").finish_non_exhaustive()
+    }
+}
+
+impl ResumeToken {
+    /// Generates a new token using the operating system's secure random
+    /// number generator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the random number generator is not available.
+    #[must_use]
+    pub fn generate() -> Self {
+        let mut bytes = [0; TOKEN_LEN];
+        getrandom::getrandom(&mut bytes).expect("random number generator should be available");
+        Self(bytes)
+    }
+
+    /// Creates a token from its raw bytes.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; TOKEN_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Gets the raw bytes of this token.
+    #[must_use]
+    pub const fn to_bytes(self) -> [u8; TOKEN_LEN] {
+        self.0
+    }
+
+    /// Encodes this token into a string which can be placed in a URL.
+    #[must_use]
+    pub fn encode(&self) -> String {
+        BASE64.encode(self.0)
+    }
+
+    /// Decodes a token string created by [`ResumeToken::encode`].
+    ///
+    /// Returns [`None`] if the string is not a valid token.
+    #[must_use]
+    pub fn decode(token: &str) -> Option<Self> {
+        let bytes = BASE64.decode(token).ok()?;
+        bytes.try_into().ok().map(Self)
+    }
+}
+
+/// What a client asked the server to do in its session request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResumeRequest {
+    /// Client is connecting for the first time, and wants to be issued a
+    /// token so that it can resume its session later.
+    Issue,
+    /// Client wants to resume the session which was issued this token.
+    Resume(ResumeToken),
+}
+
+impl ResumeRequest {
+    /// Adds this request to the query string of `target`, the URL that a
+    /// client connects to.
+    #[must_use]
+    pub fn add_to(&self, target: &str) -> String {
+        let sep = if target.contains('?') { '&' } else { '?' };
+        match self {
+            Self::Issue => format!("{target}{sep}{ISSUE_QUERY_PARAM}=1"),
+            Self::Resume(token) => format!("{target}{sep}{RESUME_QUERY_PARAM}={}", token.encode()),
+        }
+    }
+
+    /// Finds the request in the query string of a session request's `path`.
+    ///
+    /// If a resume token is present but invalid, returns [`None`].
+    ///
+    /// ```
+    /// # use aeronet_webtransport::resume::{ResumeRequest, ResumeToken};
+    /// let req = ResumeRequest::Resume(ResumeToken::from_bytes([1; 16]));
+    /// let target = req.add_to("https://[::1]:1234/game");
+    /// assert_eq!(Some(req), ResumeRequest::find(&target));
+    /// ```
+    #[must_use]
+    pub fn find(path: &str) -> Option<Self> {
+        let (_, query) = path.split_once('?')?;
+        query
+            .split('&')
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .find_map(|(key, value)| match key {
+                ISSUE_QUERY_PARAM => Some(Self::Issue),
+                RESUME_QUERY_PARAM => ResumeToken::decode(value).map(Self::Resume),
+                _ => None,
+            })
+    }
+}
@@ -1,9 +1,22 @@
 //! See [`WebTransportRuntime`].
 
-use std::{future::Future, time::Duration};
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use xwt_core::utils::maybe;
 
+#[cfg(not(target_family = "wasm"))]
+use {
+    std::{io, thread},
+    tracing::{debug, warn},
+};
+
 /// Provides a platform-agnostic way of spawning futures required to drive a
 /// WebTransport endpoint.
 ///
@@ -22,11 +35,23 @@ use xwt_core::utils::maybe;
 ///
 /// On a native target, this holds a handle to a `tokio` runtime, because
 /// `wtransport` currently only supports this async runtime. The [`Default`]
-/// impl will create and leak a new `tokio` runtime, and store a handle to this
-/// leaked runtime.
+/// impl will create a new multi-threaded `tokio` runtime which is owned by
+/// this value. Use [`WebTransportRuntime::builder`] to configure the runtime
+/// instead, e.g. to limit its number of worker threads.
+///
+/// An owned runtime is shared between clones of this value, and is shut down
+/// once the last clone is dropped. Shutting down cancels all of its tasks, and
+/// waits up to a timeout for its threads to finish. If any tasks still haven't
+/// stopped after that, a warning is logged - see
+/// [`WebTransportRuntime::num_running_tasks`]. If the last clone is dropped
+/// from inside an async context, where blocking isn't allowed, the runtime is
+/// instead shut down in the background without waiting or logging. Clients and
+/// servers which are still using the runtime lose their connections once it
+/// shuts down, so close them before dropping the runtime.
 ///
 /// If you already have a runtime handle, you can use
 /// `WebTransportRuntime::from(handle)` to create a runtime from that handle.
+/// This does not take ownership of the runtime.
 ///
 /// ## WASM
 ///
@@ -37,29 +62,26 @@ use xwt_core::utils::maybe;
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
 pub struct WebTransportRuntime {
-    #[cfg(target_family = "wasm")]
-    _priv: (),
     #[cfg(not(target_family = "wasm"))]
     runtime: tokio::runtime::Handle,
+    #[cfg(not(target_family = "wasm"))]
+    _owned: Option<Arc<OwnedRuntime>>,
+    running: Arc<AtomicUsize>,
 }
 
-#[allow(clippy::derivable_impls)] // no it can't because conditional cfg logic
 impl Default for WebTransportRuntime {
     fn default() -> Self {
         #[cfg(target_family = "wasm")]
         {
-            Self { _priv: () }
+            Self {
+                running: Arc::new(AtomicUsize::new(0)),
+            }
         }
         #[cfg(not(target_family = "wasm"))]
         {
-            let runtime = tokio::runtime::Builder::new_multi_thread()
-                .enable_all()
+            RuntimeBuilder::default()
                 .build()
-                .expect("failed to create tokio runtime");
-            let runtime = Box::leak(Box::new(runtime));
-            Self {
-                runtime: runtime.handle().clone(),
-            }
+                .expect("failed to create tokio runtime")
         }
     }
 }
@@ -67,16 +89,33 @@ impl Default for WebTransportRuntime {
 #[cfg(not(target_family = "wasm"))]
 impl From<tokio::runtime::Handle> for WebTransportRuntime {
     fn from(value: tokio::runtime::Handle) -> Self {
-        Self { runtime: value }
+        Self {
+            runtime: value,
+            _owned: None,
+            running: Arc::new(AtomicUsize::new(0)),
+        }
     }
 }
 
 impl WebTransportRuntime {
+    /// Creates a builder for configuring a new owned `tokio` runtime.
+    #[cfg(not(target_family = "wasm"))]
+    #[must_use]
+    pub fn builder() -> RuntimeBuilder {
+        RuntimeBuilder::default()
+    }
+
     /// Spawns a future on the task runtime.
     pub fn spawn<F>(&self, future: F)
     where
         F: Future<Output = ()> + maybe::Send + 'static,
     {
+        let running = RunningTask::new(self.running.clone());
+        let future = async move {
+            let _running = running;
+            future.await;
+        };
+
         #[cfg(target_family = "wasm")]
         {
             wasm_bindgen_futures::spawn_local(future);
@@ -98,4 +137,352 @@ impl WebTransportRuntime {
             tokio::time::sleep(duration).await;
         }
     }
+
+    /// Gets the number of tasks spawned using [`WebTransportRuntime::spawn`],
+    /// on this value or any of its clones, which are still running.
+    #[must_use]
+    pub fn num_running_tasks(&self) -> usize {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Creates a clone of this value which does not keep an owned runtime
+    /// alive, for moving into tasks spawned on that runtime.
+    ///
+    /// If tasks kept the runtime alive, it would never be shut down.
+    pub(crate) fn detached(&self) -> Self {
+        Self {
+            #[cfg(not(target_family = "wasm"))]
+            runtime: self.runtime.clone(),
+            #[cfg(not(target_family = "wasm"))]
+            _owned: None,
+            running: self.running.clone(),
+        }
+    }
+}
+
+/// Keeps track of a task spawned on a [`WebTransportRuntime`] while it is
+/// running, including if it is dropped before completing.
+#[derive(Debug)]
+struct RunningTask(Arc<AtomicUsize>);
+
+impl RunningTask {
+    fn new(running: Arc<AtomicUsize>) -> Self {
+        running.fetch_add(1, Ordering::Relaxed);
+        Self(running)
+    }
+}
+
+impl Drop for RunningTask {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Configures and creates a [`WebTransportRuntime`] which owns its `tokio`
+/// runtime.
+///
+/// By default, this creates a multi-threaded runtime with one worker thread
+/// per CPU core.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use aeronet_webtransport::runtime::WebTransportRuntime;
+///
+/// let runtime = WebTransportRuntime::builder()
+///     .with_worker_threads(2)
+///     .with_thread_name("my-game-net")
+///     .with_shutdown_timeout(Duration::from_millis(500))
+///     .build()
+///     .unwrap();
+/// ```
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug, Clone)]
+pub struct RuntimeBuilder {
+    /// Number of worker threads for a multi-threaded runtime, or [`None`] to
+    /// use one per CPU core.
+    pub worker_threads: Option<usize>,
+    /// Name of the threads created by the runtime.
+    pub thread_name: Option<String>,
+    /// Whether to create a current-thread runtime, which runs all tasks on a
+    /// single dedicated thread, instead of a multi-threaded runtime.
+    pub current_thread: bool,
+    /// How long to wait for the runtime's threads to finish when shutting it
+    /// down.
+    pub shutdown_timeout: Duration,
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl Default for RuntimeBuilder {
+    fn default() -> Self {
+        Self {
+            worker_threads: None,
+            thread_name: None,
+            current_thread: false,
+            shutdown_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl RuntimeBuilder {
+    /// Sets [`RuntimeBuilder::worker_threads`].
+    #[must_use]
+    pub const fn with_worker_threads(mut self, worker_threads: usize) -> Self {
+        self.worker_threads = Some(worker_threads);
+        self
+    }
+
+    /// Sets [`RuntimeBuilder::thread_name`].
+    #[must_use]
+    pub fn with_thread_name(mut self, thread_name: impl Into<String>) -> Self {
+        self.thread_name = Some(thread_name.into());
+        self
+    }
+
+    /// Sets [`RuntimeBuilder::current_thread`].
+    #[must_use]
+    pub const fn with_current_thread(mut self, current_thread: bool) -> Self {
+        self.current_thread = current_thread;
+        self
+    }
+
+    /// Sets [`RuntimeBuilder::shutdown_timeout`].
+    #[must_use]
+    pub const fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    /// Creates the runtime.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the runtime or its threads could not be created.
+    pub fn build(self) -> io::Result<WebTransportRuntime> {
+        let mut builder = if self.current_thread {
+            tokio::runtime::Builder::new_current_thread()
+        } else {
+            tokio::runtime::Builder::new_multi_thread()
+        };
+        builder.enable_all();
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(thread_name) = &self.thread_name {
+            builder.thread_name(thread_name);
+        }
+        let runtime = builder.build()?;
+        let handle = runtime.handle().clone();
+        let running = Arc::new(AtomicUsize::new(0));
+        let shutdown_timeout = self.shutdown_timeout;
+
+        let kind = if self.current_thread {
+            // a current-thread runtime only makes progress while it's being
+            // blocked on, so give it a thread to do that on
+            let (send_stop, recv_stop) = futures::channel::oneshot::channel::<()>();
+            let mut thread = thread::Builder::new();
+            if let Some(thread_name) = self.thread_name {
+                thread = thread.name(thread_name);
+            }
+            let running = running.clone();
+            let thread = thread.spawn(move || {
+                let _ = runtime.block_on(recv_stop);
+                shutdown(runtime, shutdown_timeout, &running);
+            })?;
+            RuntimeKind::CurrentThread {
+                send_stop: Some(send_stop),
+                thread: Some(thread),
+            }
+        } else {
+            RuntimeKind::MultiThread(Some(runtime))
+        };
+
+        Ok(WebTransportRuntime {
+            runtime: handle,
+            _owned: Some(Arc::new(OwnedRuntime {
+                kind,
+                shutdown_timeout,
+                running: running.clone(),
+            })),
+            running,
+        })
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug)]
+struct OwnedRuntime {
+    kind: RuntimeKind,
+    shutdown_timeout: Duration,
+    running: Arc<AtomicUsize>,
+}
+
+#[cfg(not(target_family = "wasm"))]
+#[derive(Debug)]
+enum RuntimeKind {
+    MultiThread(Option<tokio::runtime::Runtime>),
+    CurrentThread {
+        send_stop: Option<futures::channel::oneshot::Sender<()>>,
+        thread: Option<thread::JoinHandle<()>>,
+    },
+}
+
+#[cfg(not(target_family = "wasm"))]
+impl Drop for OwnedRuntime {
+    fn drop(&mut self) {
+        // blocking on shutdown from inside an async context would panic
+        let in_async = tokio::runtime::Handle::try_current().is_ok();
+        match &mut self.kind {
+            RuntimeKind::MultiThread(runtime) => {
+                let Some(runtime) = runtime.take() else {
+                    return;
+                };
+                if in_async {
+                    runtime.shutdown_background();
+                } else {
+                    shutdown(runtime, self.shutdown_timeout, &self.running);
+                }
+            }
+            RuntimeKind::CurrentThread { send_stop, thread } => {
+                if let Some(send_stop) = send_stop.take() {
+                    let _ = send_stop.send(());
+                }
+                if let Some(thread) = thread.take() {
+                    // the thread shuts down the runtime itself, so without
+                    // joining it, that happens in the background
+                    if !in_async {
+                        let _ = thread.join();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn shutdown(runtime: tokio::runtime::Runtime, timeout: Duration, running: &AtomicUsize) {
+    runtime.shutdown_timeout(timeout);
+    // shutting down drops every task which the runtime could cancel in time,
+    // so any which are left are stuck
+    let running = running.load(Ordering::Relaxed);
+    if running > 0 {
+        warn!("{running} tasks were still running after shutting down runtime");
+    }
+    debug!("Shut down runtime");
+}
+
+#[cfg(all(test, not(target_family = "wasm")))]
+mod tests {
+    use std::{sync::mpsc, time::Instant};
+
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Spawns a task which never completes on its own.
+    fn spawn_pending(runtime: &WebTransportRuntime) {
+        runtime.spawn(futures::future::pending());
+    }
+
+    fn wait_until_stopped(running: &WebTransportRuntime) {
+        let start = Instant::now();
+        while running.num_running_tasks() > 0 {
+            assert!(start.elapsed() < TIMEOUT, "tasks still running");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn thread_name(runtime: &WebTransportRuntime) -> Option<String> {
+        let (send, recv) = mpsc::channel();
+        runtime.spawn(async move {
+            let _ = send.send(thread::current().name().map(ToOwned::to_owned));
+        });
+        recv.recv_timeout(TIMEOUT).unwrap()
+    }
+
+    #[test]
+    fn thread_name_multi_thread() {
+        let runtime = WebTransportRuntime::builder()
+            .with_worker_threads(1)
+            .with_thread_name("net")
+            .build()
+            .unwrap();
+        assert_eq!(Some("net"), thread_name(&runtime).as_deref());
+    }
+
+    #[test]
+    fn thread_name_current_thread() {
+        let runtime = WebTransportRuntime::builder()
+            .with_current_thread(true)
+            .with_thread_name("net")
+            .build()
+            .unwrap();
+        assert_eq!(Some("net"), thread_name(&runtime).as_deref());
+    }
+
+    #[test]
+    fn counts_running_tasks() {
+        let runtime = WebTransportRuntime::default();
+        assert_eq!(0, runtime.num_running_tasks());
+        spawn_pending(&runtime);
+        // clones share their count of running tasks
+        let clone = runtime.clone();
+        spawn_pending(&clone);
+        assert_eq!(2, runtime.num_running_tasks());
+        assert_eq!(2, clone.num_running_tasks());
+    }
+
+    #[test]
+    fn drop_cancels_tasks() {
+        for current_thread in [false, true] {
+            let runtime = WebTransportRuntime::builder()
+                .with_current_thread(current_thread)
+                .build()
+                .unwrap();
+            spawn_pending(&runtime);
+            let running = runtime.detached();
+
+            // dropping a clone doesn't shut down the runtime
+            drop(runtime.clone());
+            assert_eq!(1, running.num_running_tasks());
+
+            // shutdown blocks until the tasks are cancelled
+            drop(runtime);
+            assert_eq!(0, running.num_running_tasks());
+        }
+    }
+
+    #[test]
+    fn drop_in_async_context() {
+        let outer = tokio::runtime::Runtime::new().unwrap();
+        for current_thread in [false, true] {
+            let runtime = WebTransportRuntime::builder()
+                .with_current_thread(current_thread)
+                .build()
+                .unwrap();
+            spawn_pending(&runtime);
+            let running = runtime.detached();
+
+            // this must not panic by blocking inside of the outer runtime
+            outer.block_on(async move { drop(runtime) });
+            wait_until_stopped(&running);
+        }
+    }
+
+    #[test]
+    fn from_handle_not_owned() {
+        let outer = tokio::runtime::Runtime::new().unwrap();
+        let runtime = WebTransportRuntime::from(outer.handle().clone());
+        spawn_pending(&runtime);
+        let running = runtime.detached();
+
+        // the runtime isn't ours to shut down
+        drop(runtime);
+        assert_eq!(1, running.num_running_tasks());
+        drop(outer);
+        assert_eq!(0, running.num_running_tasks());
+    }
 }
@@ -0,0 +1,490 @@
+//! Policies for automatically accepting or rejecting connecting clients.
+//!
+//! By default, every client connecting to a [`WebTransportServer`] stays
+//! [`Connecting`] until you respond to its session request using
+//! [`WebTransportServer::respond_to_request`]. Instead, you can set an
+//! [`AdmissionPolicy`] using [`WebTransportServer::set_admission_policy`],
+//! which the server evaluates for every client which it hasn't responded to
+//! yet, each time it is polled.
+//!
+//! Policies can be combined using [`AllOf`], which rejects a client if any of
+//! its policies reject it. For example, to accept clients from a specific
+//! origin, up to a maximum number of clients:
+//!
+//! ```
+//! use aeronet_webtransport::server::admission::{AllOf, MaxClients, OriginAllowlist};
+//!
+//! let policy = AllOf::new()
+//!     .with(OriginAllowlist::new(["https://example.com"]))
+//!     .with(MaxClients(64));
+//! ```
+//!
+//! To keep deciding manually, but stop requests from hanging forever, combine
+//! [`Manual`] with a [`RequestTimeout`].
+//!
+//! [`WebTransportServer`]: super::WebTransportServer
+//! [`WebTransportServer::respond_to_request`]: super::WebTransportServer::respond_to_request
+//! [`WebTransportServer::set_admission_policy`]: super::WebTransportServer::set_admission_policy
+
+use std::{collections::HashSet, fmt::Debug};
+
+use web_time::Duration;
+
+use super::{ClientKey, Connecting, ConnectionResponse, RequestInfo};
+
+/// Decides whether a connecting client is allowed to connect to a
+/// [`WebTransportServer`].
+///
+/// See the [module-level documentation](self).
+///
+/// [`WebTransportServer`]: super::WebTransportServer
+pub trait AdmissionPolicy: Debug + Send + Sync + 'static {
+    /// Decides how to respond to a client's session request.
+    ///
+    /// If this returns [`Admission::Pending`], this is called again for the
+    /// same client the next time the server is polled.
+    fn admit(&mut self, request: &AdmissionRequest<'_>) -> Admission;
+}
+
+/// Decision made by an [`AdmissionPolicy`] for a connecting client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Admission {
+    /// Accept the client.
+    ///
+    /// When combined using [`AllOf`], this means that this policy has no
+    /// objection to the client connecting.
+    Allow,
+    /// Reject the client with the given response.
+    Deny(ConnectionResponse),
+    /// Don't decide yet, and evaluate the policy again on the next poll.
+    Pending,
+}
+
+/// Session request of a connecting client, passed to an [`AdmissionPolicy`].
+#[derive(Debug)]
+pub struct AdmissionRequest<'a> {
+    /// Key of the connecting client.
+    pub client_key: ClientKey,
+    /// State of the connecting client, including its session request.
+    pub connecting: &'a Connecting,
+    /// How long ago the server received this session request, measured in the
+    /// delta time passed to the server's `poll`.
+    pub elapsed: Duration,
+    /// Number of clients which are connected, or which have been accepted and
+    /// are still connecting, not including this client.
+    pub num_clients: usize,
+    /// Number of clients counted in [`AdmissionRequest::num_clients`] which
+    /// have the same IP address as this client.
+    pub num_clients_from_ip: usize,
+}
+
+/// Leaves the decision to you, using
+/// [`WebTransportServer::respond_to_request`].
+///
+/// This is the default policy.
+///
+/// [`WebTransportServer::respond_to_request`]: super::WebTransportServer::respond_to_request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Manual;
+
+impl AdmissionPolicy for Manual {
+    fn admit(&mut self, _: &AdmissionRequest<'_>) -> Admission {
+        Admission::Pending
+    }
+}
+
+/// Accepts every client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct AllowAll;
+
+impl AdmissionPolicy for AllowAll {
+    fn admit(&mut self, _: &AdmissionRequest<'_>) -> Admission {
+        Admission::Allow
+    }
+}
+
+/// Rejects clients whose `origin` is not in a set of allowed origins, with
+/// [`ConnectionResponse::Forbidden`].
+///
+/// Browsers always send the origin of the page which is connecting, but native
+/// clients may not send one at all. Whether clients without an origin are
+/// allowed is determined by [`OriginAllowlist::allow_missing`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OriginAllowlist {
+    /// Origins which are allowed to connect, e.g. `https://example.com`.
+    pub origins: HashSet<String>,
+    /// Whether clients which don't send an origin are allowed to connect.
+    pub allow_missing: bool,
+}
+
+impl OriginAllowlist {
+    /// Creates a policy allowing only the given origins.
+    ///
+    /// Clients without an origin are rejected.
+    #[must_use]
+    pub fn new(origins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            origins: origins.into_iter().map(Into::into).collect(),
+            allow_missing: false,
+        }
+    }
+
+    /// Sets whether clients which don't send an origin are allowed to connect.
+    #[must_use]
+    pub const fn with_allow_missing(mut self, allow_missing: bool) -> Self {
+        self.allow_missing = allow_missing;
+        self
+    }
+}
+
+impl AdmissionPolicy for OriginAllowlist {
+    fn admit(&mut self, request: &AdmissionRequest<'_>) -> Admission {
+        let allowed = request
+            .connecting
+            .origin()
+            .map_or(self.allow_missing, |origin| self.origins.contains(origin));
+        if allowed {
+            Admission::Allow
+        } else {
+            Admission::Deny(ConnectionResponse::Forbidden)
+        }
+    }
+}
+
+/// Rejects clients whose request path does not start with one of a list of
+/// prefixes, with [`ConnectionResponse::NotFound`].
+///
+/// The query string of the path, i.e. anything after `?`, is ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathPrefixes {
+    /// Prefixes which the path of a client's request must start with, e.g.
+    /// `/lobby/`.
+    pub prefixes: Vec<String>,
+}
+
+impl PathPrefixes {
+    /// Creates a policy allowing only paths starting with the given prefixes.
+    #[must_use]
+    pub fn new(prefixes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            prefixes: prefixes.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl AdmissionPolicy for PathPrefixes {
+    fn admit(&mut self, request: &AdmissionRequest<'_>) -> Admission {
+        let path = request.connecting.path();
+        let path = path.split_once('?').map_or(path, |(path, _)| path);
+        if self
+            .prefixes
+            .iter()
+            .any(|prefix| path.starts_with(prefix.as_str()))
+        {
+            Admission::Allow
+        } else {
+            Admission::Deny(ConnectionResponse::NotFound)
+        }
+    }
+}
+
+/// Rejects clients once this many clients are connected, with
+/// [`ConnectionResponse::Forbidden`].
+///
+/// See [`AdmissionRequest::num_clients`] for which clients are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaxClients(pub usize);
+
+impl AdmissionPolicy for MaxClients {
+    fn admit(&mut self, request: &AdmissionRequest<'_>) -> Admission {
+        if request.num_clients < self.0 {
+            Admission::Allow
+        } else {
+            Admission::Deny(ConnectionResponse::Forbidden)
+        }
+    }
+}
+
+/// Rejects clients once this many clients with the same IP address are
+/// connected, with [`ConnectionResponse::Forbidden`].
+///
+/// See [`AdmissionRequest::num_clients_from_ip`] for which clients are
+/// counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MaxClientsPerIp(pub usize);
+
+impl AdmissionPolicy for MaxClientsPerIp {
+    fn admit(&mut self, request: &AdmissionRequest<'_>) -> Admission {
+        if request.num_clients_from_ip < self.0 {
+            Admission::Allow
+        } else {
+            Admission::Deny(ConnectionResponse::Forbidden)
+        }
+    }
+}
+
+/// Rejects clients which haven't been responded to within this duration, with
+/// [`ConnectionResponse::Forbidden`].
+///
+/// Before the timeout, this has no objection to the client connecting, so
+/// combine it with another policy using [`AllOf`] - usually [`Manual`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestTimeout(pub Duration);
+
+impl AdmissionPolicy for RequestTimeout {
+    fn admit(&mut self, request: &AdmissionRequest<'_>) -> Admission {
+        if request.elapsed < self.0 {
+            Admission::Allow
+        } else {
+            Admission::Deny(ConnectionResponse::Forbidden)
+        }
+    }
+}
+
+/// Combines multiple policies, all of which must allow a client for it to be
+/// accepted.
+///
+/// Policies are evaluated in the order that they were added, and:
+/// * if a policy denies the client, that denial is used, and the rest of the
+///   policies are not evaluated
+/// * otherwise, if any policy is pending, the client stays pending
+/// * otherwise, the client is accepted
+///
+/// An empty `AllOf` accepts every client.
+#[derive(Debug, Default)]
+pub struct AllOf {
+    policies: Vec<Box<dyn AdmissionPolicy>>,
+}
+
+impl AllOf {
+    /// Creates an empty combination of policies.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a policy to this combination.
+    #[must_use]
+    pub fn with(mut self, policy: impl AdmissionPolicy) -> Self {
+        self.policies.push(Box::new(policy));
+        self
+    }
+}
+
+impl AdmissionPolicy for AllOf {
+    fn admit(&mut self, request: &AdmissionRequest<'_>) -> Admission {
+        let mut admission = Admission::Allow;
+        for policy in &mut self.policies {
+            match policy.admit(request) {
+                Admission::Allow => {}
+                Admission::Deny(resp) => return Admission::Deny(resp),
+                Admission::Pending => admission = Admission::Pending,
+            }
+        }
+        admission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{Ipv4Addr, SocketAddr},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use super::*;
+
+    fn connecting() -> Connecting {
+        Connecting::new_test(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).0
+    }
+
+    fn request(connecting: &Connecting) -> AdmissionRequest<'_> {
+        AdmissionRequest {
+            client_key: ClientKey::default(),
+            connecting,
+            elapsed: Duration::ZERO,
+            num_clients: 0,
+            num_clients_from_ip: 0,
+        }
+    }
+
+    fn admit_origin(policy: &mut OriginAllowlist, origin: Option<&str>) -> Admission {
+        let mut connecting = connecting();
+        connecting.request.origin = origin.map(ToOwned::to_owned);
+        policy.admit(&request(&connecting))
+    }
+
+    fn admit_path(policy: &mut PathPrefixes, path: &str) -> Admission {
+        let mut connecting = connecting();
+        connecting.request.path = path.to_owned();
+        policy.admit(&request(&connecting))
+    }
+
+    const FORBIDDEN: Admission = Admission::Deny(ConnectionResponse::Forbidden);
+
+    const NOT_FOUND: Admission = Admission::Deny(ConnectionResponse::NotFound);
+
+    /// Policy which counts how many times it was evaluated.
+    #[derive(Debug)]
+    struct Counted(Admission, Arc<AtomicUsize>);
+
+    impl AdmissionPolicy for Counted {
+        fn admit(&mut self, _: &AdmissionRequest<'_>) -> Admission {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0
+        }
+    }
+
+    #[test]
+    fn origin_allowlist() {
+        let mut policy = OriginAllowlist::new(["https://example.com"]);
+        assert_eq!(
+            Admission::Allow,
+            admit_origin(&mut policy, Some("https://example.com"))
+        );
+        assert_eq!(
+            FORBIDDEN,
+            admit_origin(&mut policy, Some("https://example.com.evil"))
+        );
+        assert_eq!(
+            FORBIDDEN,
+            admit_origin(&mut policy, Some("http://example.com"))
+        );
+        assert_eq!(FORBIDDEN, admit_origin(&mut policy, None));
+    }
+
+    #[test]
+    fn origin_allowlist_allow_missing() {
+        let mut policy = OriginAllowlist::new(["https://example.com"]).with_allow_missing(true);
+        assert_eq!(Admission::Allow, admit_origin(&mut policy, None));
+        assert_eq!(
+            FORBIDDEN,
+            admit_origin(&mut policy, Some("https://other.com"))
+        );
+    }
+
+    #[test]
+    fn path_prefixes() {
+        let mut policy = PathPrefixes::new(["/lobby/", "/match"]);
+        assert_eq!(Admission::Allow, admit_path(&mut policy, "/lobby/1"));
+        assert_eq!(Admission::Allow, admit_path(&mut policy, "/match"));
+        assert_eq!(Admission::Allow, admit_path(&mut policy, "/match/2"));
+        assert_eq!(NOT_FOUND, admit_path(&mut policy, "/"));
+        assert_eq!(NOT_FOUND, admit_path(&mut policy, "/lobby"));
+        assert_eq!(NOT_FOUND, admit_path(&mut policy, "/other/lobby/"));
+    }
+
+    #[test]
+    fn path_prefixes_ignore_query() {
+        let mut policy = PathPrefixes::new(["/lobby/"]);
+        assert_eq!(Admission::Allow, admit_path(&mut policy, "/lobby/?token=a"));
+        // the prefix must match the path itself, not the query
+        assert_eq!(NOT_FOUND, admit_path(&mut policy, "/?path=/lobby/"));
+        assert_eq!(NOT_FOUND, admit_path(&mut policy, "/lob?by/"));
+    }
+
+    #[test]
+    fn path_prefixes_empty() {
+        let mut policy = PathPrefixes::new([] as [&str; 0]);
+        assert_eq!(NOT_FOUND, admit_path(&mut policy, "/"));
+    }
+
+    #[test]
+    fn max_clients() {
+        let connecting = connecting();
+        let admit = |max, num_clients| {
+            MaxClients(max).admit(&AdmissionRequest {
+                num_clients,
+                ..request(&connecting)
+            })
+        };
+        assert_eq!(Admission::Allow, admit(2, 0));
+        assert_eq!(Admission::Allow, admit(2, 1));
+        assert_eq!(FORBIDDEN, admit(2, 2));
+        assert_eq!(FORBIDDEN, admit(2, 3));
+        assert_eq!(FORBIDDEN, admit(0, 0));
+    }
+
+    #[test]
+    fn max_clients_per_ip() {
+        let connecting = connecting();
+        let admit = |max, num_clients, num_clients_from_ip| {
+            MaxClientsPerIp(max).admit(&AdmissionRequest {
+                num_clients,
+                num_clients_from_ip,
+                ..request(&connecting)
+            })
+        };
+        // clients from other IPs don't count
+        assert_eq!(Admission::Allow, admit(1, 10, 0));
+        assert_eq!(FORBIDDEN, admit(1, 1, 1));
+        assert_eq!(Admission::Allow, admit(2, 1, 1));
+    }
+
+    #[test]
+    fn request_timeout() {
+        let connecting = connecting();
+        let admit = |elapsed| {
+            RequestTimeout(Duration::from_secs(1)).admit(&AdmissionRequest {
+                elapsed,
+                ..request(&connecting)
+            })
+        };
+        assert_eq!(Admission::Allow, admit(Duration::ZERO));
+        assert_eq!(Admission::Allow, admit(Duration::from_millis(999)));
+        assert_eq!(FORBIDDEN, admit(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn all_of_empty() {
+        let connecting = connecting();
+        assert_eq!(Admission::Allow, AllOf::new().admit(&request(&connecting)));
+    }
+
+    #[test]
+    fn all_of_pending() {
+        let connecting = connecting();
+        let mut policy = AllOf::new().with(Manual).with(AllowAll);
+        assert_eq!(Admission::Pending, policy.admit(&request(&connecting)));
+        let mut policy = AllOf::new().with(AllowAll).with(Manual);
+        assert_eq!(Admission::Pending, policy.admit(&request(&connecting)));
+    }
+
+    #[test]
+    fn all_of_deny_first() {
+        let connecting = connecting();
+        let evaluated = Arc::new(AtomicUsize::new(0));
+        let mut policy = AllOf::new()
+            .with(Manual)
+            .with(MaxClients(0))
+            .with(Counted(Admission::Allow, evaluated.clone()));
+        // a denial wins over an earlier pending policy
+        assert_eq!(FORBIDDEN, policy.admit(&request(&connecting)));
+        // and the policies after it aren't evaluated
+        assert_eq!(0, evaluated.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn all_of_first_denial_used() {
+        let connecting = connecting();
+        let mut policy = AllOf::new()
+            .with(PathPrefixes::new(["/lobby/"]))
+            .with(MaxClients(0));
+        assert_eq!(NOT_FOUND, policy.admit(&request(&connecting)));
+    }
+
+    #[test]
+    fn all_of_evaluates_all_when_allowed() {
+        let connecting = connecting();
+        let evaluated = Arc::new(AtomicUsize::new(0));
+        let mut policy = AllOf::new()
+            .with(Counted(Admission::Allow, evaluated.clone()))
+            .with(Counted(Admission::Pending, evaluated.clone()))
+            .with(Counted(Admission::Allow, evaluated.clone()));
+        assert_eq!(Admission::Pending, policy.admit(&request(&connecting)));
+        assert_eq!(3, evaluated.load(Ordering::SeqCst));
+    }
+}
@@ -1,10 +1,10 @@
-use aeronet::{client::DisconnectReason, error::pretty_error};
+use aeronet::{client::DisconnectReason, error::pretty_error, lane::LaneIndex};
 use aeronet_proto::session::{Session, SessionConfig};
 use bytes::Bytes;
 use futures::{
     channel::{mpsc, oneshot},
     never::Never,
-    FutureExt, SinkExt,
+    FutureExt, SinkExt, StreamExt,
 };
 use tracing::{debug, debug_span, field, Instrument};
 use web_time::Instant;
@@ -14,6 +14,7 @@ use xwt_core::prelude::*;
 use crate::{
     internal::{self, ConnectionMeta, MIN_MTU},
     runtime::WebTransportRuntime,
+    shared::OutgoingQueueConfig,
 };
 
 use super::{
@@ -24,6 +25,8 @@ pub async fn start(
     runtime: WebTransportRuntime,
     net_config: ServerConfig,
     session_config: SessionConfig,
+    stream_lanes: Vec<LaneIndex>,
+    outgoing_queue: OutgoingQueueConfig,
     send_open: oneshot::Sender<ToOpen>,
 ) -> Result<Never, ServerError> {
     let endpoint = wtransport::Endpoint::server(net_config).map_err(ServerError::CreateEndpoint)?;
@@ -31,10 +34,14 @@ pub async fn start(
 
     let (send_closed, mut recv_closed) = oneshot::channel::<()>();
     let (send_connecting, recv_connecting) = mpsc::channel::<ToConnecting>(4);
+    let (send_reload, mut recv_reload) = mpsc::unbounded::<ServerConfig>();
+    let (send_reloaded, recv_reloaded) = mpsc::unbounded::<Result<(), ServerError>>();
     send_open
         .send(ToOpen {
             local_addr,
             recv_connecting,
+            send_reload,
+            recv_reloaded,
             send_closed,
         })
         .map_err(|_| ServerError::FrontendClosed)?;
@@ -43,13 +50,35 @@ pub async fn start(
         let session = futures::select! {
             _ = recv_closed => return Err(ServerError::FrontendClosed),
             x = endpoint.accept().fuse() => x,
+            net_config = recv_reload.next() => {
+                let net_config = net_config.ok_or(ServerError::FrontendClosed)?;
+                // only new connections use the new config,
+                // so existing sessions continue as normal
+                let result = endpoint
+                    .reload_config(net_config, false)
+                    .map_err(ServerError::ReloadConfig);
+                match &result {
+                    Ok(()) => debug!("Reloaded server config"),
+                    Err(err) => debug!("Failed to reload server config: {:#}", pretty_error(err)),
+                }
+                let _ = send_reloaded.unbounded_send(result);
+                continue;
+            }
         };
         let runtime_clone = runtime.clone();
         let send_connecting = send_connecting.clone();
         let session_config = session_config.clone();
+        let stream_lanes = stream_lanes.clone();
         runtime.spawn(async move {
-            if let Err(err) =
-                start_handle_session(runtime_clone, session_config, send_connecting, session).await
+            if let Err(err) = start_handle_session(
+                runtime_clone,
+                session_config,
+                stream_lanes,
+                outgoing_queue,
+                send_connecting,
+                session,
+            )
+            .await
             {
                 debug!("Failed to start handling session: {:#}", pretty_error(&err));
             }
@@ -60,6 +89,8 @@ pub async fn start(
 async fn start_handle_session(
     runtime: WebTransportRuntime,
     session_config: SessionConfig,
+    stream_lanes: Vec<LaneIndex>,
+    outgoing_queue: OutgoingQueueConfig,
     mut send_connecting: mpsc::Sender<ToConnecting>,
     session: IncomingSession,
 ) -> Result<(), ServerError> {
@@ -71,6 +102,7 @@ async fn start_handle_session(
     let (send_connected, recv_connected) = oneshot::channel::<ToConnected>();
     send_connecting
         .send(ToConnecting {
+            remote_addr: req.remote_address(),
             authority: req.authority().to_string(),
             path: req.path().to_string(),
             origin: req.origin().map(ToOwned::to_owned),
@@ -86,8 +118,16 @@ async fn start_handle_session(
     let client_key = recv_key.await.map_err(|_| ServerError::FrontendClosed)?;
 
     let err = async move {
-        let Err(err) =
-            handle_session(runtime, session_config, req, recv_conn_resp, send_connected).await
+        let Err(err) = handle_session(
+            runtime,
+            session_config,
+            &stream_lanes,
+            outgoing_queue,
+            req,
+            recv_conn_resp,
+            send_connected,
+        )
+        .await
         else {
             unreachable!()
         };
@@ -113,6 +153,8 @@ async fn start_handle_session(
 async fn handle_session(
     runtime: WebTransportRuntime,
     session_config: SessionConfig,
+    stream_lanes: &[LaneIndex],
+    outgoing_queue: OutgoingQueueConfig,
     req: SessionRequest,
     recv_conn_resp: oneshot::Receiver<ConnectionResponse>,
     send_connected: oneshot::Sender<ToConnected>,
@@ -143,6 +185,14 @@ async fn handle_session(
         return Err(ServerError::DatagramsNotSupported.into());
     };
     let conn = conn.0;
+    let (streams, stream_backend) = internal::stream_lanes(
+        stream_lanes,
+        &session_config.server_lanes,
+        session_config.client_lanes.len(),
+        session_config.max_memory_usage,
+        // we issue resume tokens, so we never accept them from the client
+        false,
+    );
     let session = Session::server(Instant::now(), session_config, MIN_MTU, mtu)
         .map_err(ServerError::MtuTooSmall)?;
 
@@ -150,7 +200,7 @@ async fn handle_session(
 
     let (send_meta, recv_meta) = mpsc::channel::<ConnectionMeta>(1);
     let (send_c2s, recv_c2s) = mpsc::channel::<Bytes>(internal::MSG_BUF_CAP);
-    let (send_s2c, recv_s2c) = mpsc::unbounded::<Bytes>();
+    let (send_s2c, recv_s2c) = internal::outgoing_queue(outgoing_queue);
     let (send_local_dc, recv_local_dc) = oneshot::channel::<String>();
     send_connected
         .send(ToConnected {
@@ -159,6 +209,7 @@ async fn handle_session(
             recv_meta,
             recv_c2s,
             send_s2c,
+            streams,
             send_local_dc,
             session,
         })
@@ -166,7 +217,15 @@ async fn handle_session(
     let conn = xwt_wtransport::Connection(conn);
 
     debug!("Starting connection loop");
-    internal::handle_connection(runtime, conn, recv_s2c, send_c2s, send_meta, recv_local_dc)
-        .await
-        .map_err(|err| err.map_err(From::from))
+    internal::handle_connection(
+        runtime,
+        conn,
+        recv_s2c,
+        send_c2s,
+        send_meta,
+        stream_backend,
+        recv_local_dc,
+    )
+    .await
+    .map_err(|err| err.map_err(From::from))
 }
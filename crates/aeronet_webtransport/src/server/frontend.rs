@@ -1,7 +1,7 @@
-use std::mem;
+use std::{collections::HashMap, mem, net::IpAddr};
 
 use aeronet::{
-    client::ClientState,
+    client::{ClientState, DisconnectReason},
     error::pretty_error,
     lane::LaneIndex,
     server::{CloseReason, ServerEvent, ServerState, ServerTransport},
@@ -12,18 +12,32 @@ use bytes::Bytes;
 use futures::channel::oneshot;
 use slotmap::SlotMap;
 use tracing::{debug, field, trace_span};
-use web_time::Duration;
+use web_time::{Duration, Instant};
 
 use crate::{
     internal::{ConnectionInner, PollEvent},
+    resume::{ResumeRequest, ResumeToken, RESUMED_REASON},
     runtime::WebTransportRuntime,
+    shared::OutgoingQueueConfig,
+};
+
+#[cfg(feature = "token")]
+use {
+    crate::token,
+    aeronet_proto::token::{ReplayCache, TokenKey},
+    web_time::SystemTime,
 };
 
 use super::{
-    backend, Client, ClientKey, Connected, Connecting, ConnectionResponse, Open, Opening,
-    ServerConfig, ServerError, State, ToOpen, WebTransportServer,
+    admission::{Admission, AdmissionPolicy, AdmissionRequest},
+    backend, Client, ClientKey, Connected, Connecting, ConnectionResponse, Lost, Open, Opening,
+    Request, ServerConfig, ServerError, State, ToConnected, ToOpen, WebTransportServer,
 };
 
+/// Disconnect reason sent to a client's old connection when it resumes its
+/// session on a new connection.
+const RESUMED_DISCONNECT_REASON: &str = "resumed on another connection";
+
 impl Default for WebTransportServer {
     fn default() -> Self {
         Self::new()
@@ -38,9 +52,102 @@ impl WebTransportServer {
     pub const fn new() -> Self {
         Self {
             state: State::Closed,
+            resume_grace_period: Duration::ZERO,
+            stream_lanes: Vec::new(),
+            outgoing_queue: OutgoingQueueConfig::new(),
+            admission: None,
         }
     }
 
+    /// Gets how long a client which lost its connection is kept around for,
+    /// waiting for it to resume its session.
+    ///
+    /// See [`WebTransportServer::set_resume_grace_period`].
+    #[must_use]
+    pub const fn resume_grace_period(&self) -> Duration {
+        self.resume_grace_period
+    }
+
+    /// Sets how long a client which lost its connection is kept around for,
+    /// waiting for it to resume its session.
+    ///
+    /// During this time, the client stays [`ClientState::Connected`], but
+    /// [`Connected::is_suspended`] returns `true`. If the client reconnects
+    /// with its [`ResumeToken`] within this time, and the new connection is
+    /// accepted, it keeps its old [`ClientKey`] and session, and no event is
+    /// emitted for the old key, since it never stopped being connected.
+    /// Otherwise, [`ServerEvent::Disconnected`] is emitted with the reason that
+    /// the connection was originally lost.
+    ///
+    /// Only clients which lose their connection due to an error are kept, and
+    /// only if they connected with resumption enabled. Resume tokens are only
+    /// issued while this is not zero, and if this server has no
+    /// [stream lanes](WebTransportServer::set_stream_lanes). By default, this
+    /// is zero, which disables resumption.
+    ///
+    /// See [`resume`].
+    ///
+    /// [`ResumeToken`]: crate::resume::ResumeToken
+    /// [`resume`]: crate::resume
+    pub fn set_resume_grace_period(&mut self, grace_period: Duration) {
+        self.resume_grace_period = grace_period;
+    }
+
+    /// Gets which lanes this server sends messages on over WebTransport
+    /// streams, rather than datagrams.
+    ///
+    /// See [`WebTransportServer::set_stream_lanes`].
+    #[must_use]
+    pub fn stream_lanes(&self) -> &[LaneIndex] {
+        &self.stream_lanes
+    }
+
+    /// Sets which lanes this server sends messages on over WebTransport
+    /// streams, rather than datagrams.
+    ///
+    /// See [`stream lanes`] for how these lanes are sent. This takes effect on
+    /// the next call to [`WebTransportServer::open`].
+    ///
+    /// [`stream lanes`]: crate#stream-lanes
+    pub fn set_stream_lanes(&mut self, lanes: impl IntoIterator<Item = impl Into<LaneIndex>>) {
+        self.stream_lanes = lanes.into_iter().map(Into::into).collect();
+    }
+
+    /// Gets the configuration of the queues of packets waiting to be sent to
+    /// each client.
+    ///
+    /// See [`WebTransportServer::set_outgoing_queue`].
+    #[must_use]
+    pub const fn outgoing_queue(&self) -> OutgoingQueueConfig {
+        self.outgoing_queue
+    }
+
+    /// Sets the configuration of the queues of packets waiting to be sent to
+    /// each client.
+    ///
+    /// See [`outgoing queue`] for how these queues are used. This takes effect
+    /// on the next call to [`WebTransportServer::open`].
+    ///
+    /// [`outgoing queue`]: crate#outgoing-queue
+    pub fn set_outgoing_queue(&mut self, config: OutgoingQueueConfig) {
+        self.outgoing_queue = config;
+    }
+
+    /// Sets the policy used to automatically accept or reject clients which
+    /// are connecting to this server.
+    ///
+    /// The policy is evaluated for every connecting client which hasn't been
+    /// responded to yet, each time this server is polled. By default, every
+    /// client must be responded to manually using
+    /// [`WebTransportServer::respond_to_request`].
+    ///
+    /// See [`admission`].
+    ///
+    /// [`admission`]: super::admission
+    pub fn set_admission_policy(&mut self, policy: impl AdmissionPolicy) {
+        self.admission = Some(Box::new(policy));
+    }
+
     /// Starts opening this server for client connections.
     ///
     /// This automatically spawns the backend task on the runtime provided.
@@ -61,10 +168,22 @@ impl WebTransportServer {
         let (send_open, recv_open) = oneshot::channel::<ToOpen>();
         let (send_err, recv_err) = oneshot::channel::<ServerError>();
 
-        let runtime_clone = runtime.clone();
+        let stream_lanes = self.stream_lanes.clone();
+        let uses_stream_lanes = !stream_lanes.is_empty();
+        let outgoing_queue = self.outgoing_queue;
+        let runtime_clone = runtime.detached();
         runtime.spawn(async move {
             debug!("Started server backend");
-            match backend::start(runtime_clone, net_config, session_config, send_open).await {
+            match backend::start(
+                runtime_clone,
+                net_config,
+                session_config,
+                stream_lanes,
+                outgoing_queue,
+                send_open,
+            )
+            .await
+            {
                 Err(ServerError::FrontendClosed) => {
                     debug!("Server closed by frontend");
                 }
@@ -79,11 +198,36 @@ impl WebTransportServer {
         self.state = State::Opening(Opening {
             recv_open,
             recv_err,
+            uses_stream_lanes,
         });
 
         debug!("Opened server");
         Ok(())
     }
+
+    /// Replaces the network configuration of this server while it is open,
+    /// e.g. to swap its TLS identity for a new certificate.
+    ///
+    /// Clients which connect after the reload use the new configuration, and
+    /// clients which are already connected continue uninterrupted. The server's
+    /// socket is not rebound, so the bind address of `net_config` is ignored.
+    ///
+    /// The new configuration is applied by the backend task, which emits
+    /// [`ServerEvent::Reloaded`] with the result once it is done.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the server is not open.
+    pub fn reload_config(&mut self, net_config: ServerConfig) -> Result<(), ServerError> {
+        let State::Open(server) = &mut self.state else {
+            return Err(ServerError::NotOpen);
+        };
+
+        server
+            .send_reload
+            .unbounded_send(net_config)
+            .map_err(|_| ServerError::BackendClosed)
+    }
 }
 
 impl ServerTransport for WebTransportServer {
@@ -133,10 +277,18 @@ impl ServerTransport for WebTransportServer {
 
     fn poll(&mut self, delta_time: Duration) -> impl Iterator<Item = ServerEvent<Self>> {
         let mut events = Vec::new();
+        let grace_period = self.resume_grace_period;
+        let admission = &mut self.admission;
         replace_with::replace_with_or_abort(&mut self.state, |state| match state {
             State::Closed => State::Closed,
             State::Opening(server) => Self::poll_opening(server, &mut events),
-            State::Open(server) => Self::poll_open(server, &mut events, delta_time),
+            State::Open(server) => Self::poll_open(
+                server,
+                &mut events,
+                delta_time,
+                grace_period,
+                admission.as_deref_mut(),
+            ),
             State::Closing { reason } => {
                 events.push(ServerEvent::Closed {
                     reason: CloseReason::Local(reason),
@@ -180,6 +332,10 @@ impl ServerTransport for WebTransportServer {
             let Client::Connected(client) = client else {
                 continue;
             };
+            if client.lost.is_some() {
+                // keep the messages buffered until the client resumes
+                continue;
+            }
             client.inner.flush();
         }
         Ok(())
@@ -198,6 +354,7 @@ impl ServerTransport for WebTransportServer {
             .clients
             .remove(client_key)
             .ok_or(ServerError::ClientNotConnected)?;
+        server.resume_tokens.retain(|_, key| *key != client_key);
         if let Client::Connected(client) = client {
             let reason = reason.into();
             let _ = client.inner.send_local_dc.send(reason);
@@ -247,13 +404,78 @@ impl WebTransportServer {
             return Err(ServerError::ClientNotConnecting);
         };
 
-        // ignore errors here because we'll pick up errors in `poll`
-        let _ = client
-            .send_conn_resp
-            .take()
-            .ok_or(ServerError::AlreadyResponded)?
-            .send(resp);
-        Ok(())
+        client.respond(resp)
+    }
+
+    /// Verifies the connect token in a connecting client's session request,
+    /// accepting the client if it is valid and rejecting it otherwise.
+    ///
+    /// If accepted, the verified token is available through
+    /// [`Connected::identity`] once the client connects.
+    ///
+    /// Each token can only be used once, so a token which has already been
+    /// used to connect to this server is rejected - unless the client is
+    /// [resuming] the session which the token was used for.
+    ///
+    /// See [`Connecting::verify_token`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the server is not open, the client is not connecting, if we
+    /// have already responded to this client's connection request, or if the
+    /// client's token is not valid. In the last case, the client is rejected.
+    ///
+    /// [resuming]: Connecting::resumes
+    #[cfg(feature = "token")]
+    pub fn respond_with_token(
+        &mut self,
+        client_key: ClientKey,
+        key: &TokenKey,
+    ) -> Result<(), ServerError> {
+        let State::Open(server) = &mut self.state else {
+            return Err(ServerError::NotOpen);
+        };
+        let resumed_nonce =
+            match server.clients.get(client_key) {
+                Some(Client::Connecting(client)) => client.resumes.and_then(|old_key| match server
+                    .clients
+                    .get(old_key)
+                {
+                    Some(Client::Connected(old)) => old.identity.as_ref().map(|token| token.nonce),
+                    _ => None,
+                }),
+                _ => return Err(ServerError::ClientNotConnecting),
+            };
+        let Some(Client::Connecting(client)) = server.clients.get_mut(client_key) else {
+            unreachable!("client should be connecting");
+        };
+
+        if client.send_conn_resp.is_none() {
+            return Err(ServerError::AlreadyResponded);
+        }
+        let now = SystemTime::now();
+        let result = client.verify_token(key, now).and_then(|token| {
+            if resumed_nonce == Some(token.nonce) {
+                // the session which this client resumes was started with this
+                // same token, so it has already been checked
+                return Ok(token);
+            }
+            server
+                .used_tokens
+                .check(&token, now)
+                .map(|()| token)
+                .map_err(token::ConnectTokenError::Verify)
+        });
+        match result {
+            Ok(token) => {
+                client.identity = Some(token);
+                client.respond(ConnectionResponse::Accepted)
+            }
+            Err(err) => {
+                client.respond(ConnectionResponse::Forbidden)?;
+                Err(ServerError::ConnectToken(err))
+            }
+        }
     }
 
     fn poll_opening(mut server: Opening, events: &mut Vec<ServerEvent<Self>>) -> State {
@@ -269,7 +491,13 @@ impl WebTransportServer {
                 State::Open(Open {
                     local_addr: next.local_addr,
                     recv_connecting: next.recv_connecting,
+                    send_reload: next.send_reload,
+                    recv_reloaded: next.recv_reloaded,
                     clients: SlotMap::default(),
+                    resume_tokens: HashMap::new(),
+                    uses_stream_lanes: server.uses_stream_lanes,
+                    #[cfg(feature = "token")]
+                    used_tokens: ReplayCache::new(),
                     _send_closed: next.send_closed,
                 })
             }
@@ -286,24 +514,58 @@ impl WebTransportServer {
         mut server: Open,
         events: &mut Vec<ServerEvent<Self>>,
         delta_time: Duration,
+        grace_period: Duration,
+        admission: Option<&mut dyn AdmissionPolicy>,
     ) -> State {
+        while let Ok(Some(result)) = server.recv_reloaded.try_next() {
+            events.push(ServerEvent::Reloaded { result });
+        }
+
+        let issue_resume_tokens = !grace_period.is_zero() && !server.uses_stream_lanes;
         let res = (|| {
             while let Ok(client) = server.recv_connecting.try_next() {
                 let client = client.ok_or(ServerError::BackendClosed)?;
+                let (issue_resume_token, resumes) = match ResumeRequest::find(&client.path) {
+                    Some(ResumeRequest::Resume(token)) => {
+                        let Some(old_key) = Self::find_resumed(&server, token, grace_period) else {
+                            // the session to resume doesn't exist (anymore), so
+                            // the client must reconnect as a new client
+                            let _ = client.send_conn_resp.send(ConnectionResponse::Forbidden);
+                            // this request never gets a real key
+                            let _ = client.send_key.send(ClientKey::default());
+                            continue;
+                        };
+                        (false, Some(old_key))
+                    }
+                    Some(ResumeRequest::Issue) => (issue_resume_tokens, None),
+                    None => (false, None),
+                };
+
                 let client_key = server.clients.insert(Client::Connecting(Connecting {
-                    authority: client.authority,
-                    path: client.path,
-                    origin: client.origin,
-                    user_agent: client.user_agent,
-                    headers: client.headers,
+                    request: Request::new(&client),
+                    #[cfg(feature = "token")]
+                    connect_token: token::find(&client.path, &client.headers)
+                        .map(ToOwned::to_owned),
+                    remote_addr: client.remote_addr,
+                    elapsed: Duration::ZERO,
+                    accepted: false,
                     recv_dc: client.recv_dc,
                     send_conn_resp: Some(client.send_conn_resp),
                     recv_connected: client.recv_connected,
+                    issue_resume_token,
+                    resumes,
+                    resumed: None,
+                    #[cfg(feature = "token")]
+                    identity: None,
                 }));
                 let _ = client.send_key.send(client_key);
                 events.push(ServerEvent::Connecting { client_key });
             }
 
+            if let Some(admission) = admission {
+                Self::admit_clients(&mut server, admission, delta_time);
+            }
+
             for (client_key, client) in &mut server.clients {
                 let span = trace_span!(
                     "client",
@@ -313,16 +575,23 @@ impl WebTransportServer {
 
                 replace_with::replace_with_or_abort(client, |client_state| match client_state {
                     Client::Disconnected => ClientState::Disconnected,
-                    Client::Connecting(client) => Self::poll_connecting(events, client_key, client),
+                    Client::Connecting(client) => {
+                        Self::poll_connecting(events, client_key, client, &mut server.resume_tokens)
+                    }
                     Client::Connected(client) => {
-                        Self::poll_connected(events, client_key, client, delta_time)
+                        Self::poll_connected(events, client_key, client, delta_time, grace_period)
                     }
                 });
             }
 
+            Self::resume_clients(&mut server, events);
+
             server
                 .clients
                 .retain(|_, client| !matches!(client, Client::Disconnected));
+            server
+                .resume_tokens
+                .retain(|_, client_key| server.clients.contains_key(*client_key));
 
             Ok::<_, ServerError>(())
         })();
@@ -336,10 +605,89 @@ impl WebTransportServer {
         }
     }
 
+    fn admit_clients(server: &mut Open, admission: &mut dyn AdmissionPolicy, delta_time: Duration) {
+        let mut pending = Vec::new();
+        for (client_key, client) in &mut server.clients {
+            if let Client::Connecting(client) = client {
+                if client.send_conn_resp.is_some() {
+                    client.elapsed += delta_time;
+                    pending.push(client_key);
+                }
+            }
+        }
+        if pending.is_empty() {
+            return;
+        }
+
+        let mut num_clients = 0usize;
+        let mut clients_per_ip = HashMap::<IpAddr, usize>::new();
+        for client in server.clients.values() {
+            let remote_addr = match client {
+                Client::Connecting(client) if client.accepted && client.resumes.is_none() => {
+                    client.remote_addr
+                }
+                Client::Connected(client) => client.inner.remote_addr,
+                Client::Connecting(_) | Client::Disconnected => continue,
+            };
+            num_clients += 1;
+            *clients_per_ip.entry(remote_addr.ip()).or_default() += 1;
+        }
+
+        for client_key in pending {
+            // a client resuming a session replaces the client which it resumes,
+            // so that client is not counted against it
+            let resumed_ip = match server.clients.get(client_key) {
+                Some(Client::Connecting(Connecting {
+                    resumes: Some(old_key),
+                    ..
+                })) => match server.clients.get(*old_key) {
+                    Some(Client::Connected(old)) => Some(old.inner.remote_addr.ip()),
+                    _ => None,
+                },
+                _ => None,
+            };
+            let Some(Client::Connecting(client)) = server.clients.get_mut(client_key) else {
+                continue;
+            };
+            let ip = client.remote_addr.ip();
+            let replaced = usize::from(resumed_ip.is_some());
+            let replaced_from_ip = usize::from(resumed_ip == Some(ip));
+            let request = AdmissionRequest {
+                client_key,
+                connecting: client,
+                elapsed: client.elapsed,
+                num_clients: num_clients.saturating_sub(replaced),
+                num_clients_from_ip: clients_per_ip
+                    .get(&ip)
+                    .copied()
+                    .unwrap_or_default()
+                    .saturating_sub(replaced_from_ip),
+            };
+            let resp = match admission.admit(&request) {
+                Admission::Pending => continue,
+                Admission::Allow => {
+                    if resumed_ip.is_none() {
+                        num_clients += 1;
+                        *clients_per_ip.entry(ip).or_default() += 1;
+                    }
+                    ConnectionResponse::Accepted
+                }
+                Admission::Deny(resp) => resp,
+            };
+            debug!(
+                client = field::debug(slotmap::Key::data(&client_key)),
+                "Admission policy responded with {resp:?}"
+            );
+            // we just checked that we haven't responded yet
+            let _ = client.respond(resp);
+        }
+    }
+
     fn poll_connecting(
         events: &mut Vec<ServerEvent<Self>>,
         client_key: ClientKey,
         mut client: Connecting,
+        resume_tokens: &mut HashMap<ResumeToken, ClientKey>,
     ) -> Client {
         let res = (|| {
             if let Some(err) = client
@@ -350,9 +698,26 @@ impl WebTransportServer {
                 return Err(err);
             }
 
+            if client.resumed.is_some() {
+                return Ok(Client::Connecting(client));
+            }
+
             if let Ok(Some(next)) = client.recv_connected.try_recv() {
+                if client.resumes.is_some() {
+                    // handed over to the resumed client in `resume_clients`
+                    client.resumed = Some(next);
+                    return Ok(Client::Connecting(client));
+                }
+
+                let resume_token = client.issue_resume_token.then(|| {
+                    let token = ResumeToken::generate();
+                    resume_tokens.insert(token, client_key);
+                    next.streams.send_resume_token(token);
+                    token
+                });
                 events.push(ServerEvent::Connected { client_key });
                 Ok(Client::Connected(Connected {
+                    request: client.request,
                     inner: ConnectionInner {
                         remote_addr: next.remote_addr,
                         raw_rtt: next.initial_rtt,
@@ -361,9 +726,14 @@ impl WebTransportServer {
                         recv_meta: next.recv_meta,
                         recv_msgs: next.recv_c2s,
                         send_msgs: next.send_s2c,
+                        streams: next.streams,
                         send_local_dc: next.send_local_dc,
                         fatal_error: None,
                     },
+                    resume_token,
+                    lost: None,
+                    #[cfg(feature = "token")]
+                    identity: client.identity,
                 }))
             } else {
                 Ok(Client::Connecting(client))
@@ -379,41 +749,324 @@ impl WebTransportServer {
         }
     }
 
+    fn find_resumed(
+        server: &Open,
+        token: ResumeToken,
+        grace_period: Duration,
+    ) -> Option<ClientKey> {
+        if grace_period.is_zero() {
+            return None;
+        }
+        let client_key = *server.resume_tokens.get(&token)?;
+        matches!(server.clients.get(client_key), Some(Client::Connected(_))).then_some(client_key)
+    }
+
+    fn resume_clients(server: &mut Open, events: &mut Vec<ServerEvent<Self>>) {
+        let resumed = server
+            .clients
+            .iter()
+            .filter(|(_, client)| {
+                matches!(
+                    client,
+                    Client::Connecting(Connecting {
+                        resumed: Some(_),
+                        ..
+                    })
+                )
+            })
+            .map(|(client_key, _)| client_key)
+            .collect::<Vec<_>>();
+
+        for client_key in resumed {
+            let Some(Client::Connecting(mut client)) = server.clients.remove(client_key) else {
+                unreachable!("should be a connecting client");
+            };
+            let (Some(next), Some(old_key)) = (client.resumed.take(), client.resumes) else {
+                unreachable!("should be a resumed client");
+            };
+
+            let reason = match server.clients.get_mut(old_key) {
+                Some(Client::Connected(old)) if Self::may_resume(old, &client) => {
+                    debug!(
+                        client = field::debug(slotmap::Key::data(&old_key)),
+                        "Client resumed its session"
+                    );
+                    // the resumed client was already `Connected`, so we don't
+                    // emit any event for it
+                    old.resume(client.recv_dc, next);
+                    DisconnectReason::Local(RESUMED_REASON.to_owned())
+                }
+                _ => {
+                    // the session expired while this client was connecting, or
+                    // belongs to another user
+                    let err = ServerError::ResumeRejected;
+                    let _ = next.send_local_dc.send(err.to_string());
+                    DisconnectReason::Error(err)
+                }
+            };
+            events.push(ServerEvent::Disconnected { client_key, reason });
+        }
+    }
+
+    #[cfg(feature = "token")]
+    fn may_resume(old: &Connected, new: &Connecting) -> bool {
+        old.identity.as_ref().map_or(true, |old| {
+            new.identity
+                .as_ref()
+                .is_some_and(|new| new.user_id == old.user_id)
+        })
+    }
+
+    #[cfg(not(feature = "token"))]
+    const fn may_resume(_: &Connected, _: &Connecting) -> bool {
+        true
+    }
+
     fn poll_connected(
         events: &mut Vec<ServerEvent<Self>>,
         client_key: ClientKey,
         mut client: Connected,
         delta_time: Duration,
+        grace_period: Duration,
     ) -> Client {
-        let res = client.inner.poll(delta_time, |event| {
-            events.push(match event {
-                PollEvent::Ack { msg_key } => ServerEvent::Ack {
-                    client_key,
-                    msg_key,
-                },
-                PollEvent::Recv { msg, lane } => ServerEvent::Recv {
-                    client_key,
-                    msg,
-                    lane,
-                },
-            });
+        if let Some(lost) = &mut client.lost {
+            lost.elapsed += delta_time;
+            if lost.elapsed >= grace_period {
+                let Lost { reason, .. } = client.lost.take().expect("should be lost");
+                events.push(ServerEvent::Disconnected { client_key, reason });
+                return Client::Disconnected;
+            }
+            return match client.inner.session.update(delta_time) {
+                Ok(()) => Client::Connected(client),
+                Err(err) => {
+                    events.push(ServerEvent::Disconnected {
+                        client_key,
+                        reason: ServerError::OutOfMemory(err).into(),
+                    });
+                    Client::Disconnected
+                }
+            };
+        }
+
+        let res = client.inner.poll(delta_time, |event| match event {
+            PollEvent::Ack { msg_key } => events.push(ServerEvent::Ack {
+                client_key,
+                msg_key,
+            }),
+            PollEvent::Recv { msg, lane } => events.push(ServerEvent::Recv {
+                client_key,
+                msg,
+                lane,
+            }),
+            // we issue tokens, and never accept them on a stream
+            PollEvent::ResumeToken(_) => {}
         });
 
-        match res {
+        match res.map_err(|reason| reason.map_err(ServerError::from)) {
             Ok(()) => Client::Connected(client),
-            Err(reason) => {
-                events.push(ServerEvent::Disconnected {
-                    client_key,
-                    reason: reason.map_err(From::from),
+            Err(reason @ DisconnectReason::Error(ServerError::ConnectionLost(_)))
+                if !grace_period.is_zero() && client.resume_token.is_some() =>
+            {
+                debug!(
+                    "Connection lost, waiting for client to resume: {:#}",
+                    pretty_error(&reason)
+                );
+                client.lost = Some(Lost {
+                    elapsed: Duration::ZERO,
+                    reason,
                 });
+                Client::Connected(client)
+            }
+            Err(reason) => {
+                events.push(ServerEvent::Disconnected { client_key, reason });
                 Client::Disconnected
             }
         }
     }
 }
 
+impl Connected {
+    fn resume(
+        &mut self,
+        recv_dc: oneshot::Receiver<DisconnectReason<ServerError>>,
+        next: ToConnected,
+    ) {
+        // if we haven't noticed that the old connection was lost yet,
+        // make sure that it's closed now
+        let old_send_local_dc = mem::replace(&mut self.inner.send_local_dc, next.send_local_dc);
+        let _ = old_send_local_dc.send(RESUMED_DISCONNECT_REASON.to_owned());
+
+        self.inner.recv_dc = recv_dc;
+        self.inner.remote_addr = next.remote_addr;
+        self.inner.raw_rtt = next.initial_rtt;
+        self.inner.recv_meta = next.recv_meta;
+        self.inner.recv_msgs = next.recv_c2s;
+        // keep counting dropped packets across connections, like the session
+        // keeps counting bytes sent
+        let packets_dropped = self.inner.send_msgs.packets_dropped;
+        self.inner.send_msgs = next.send_s2c;
+        self.inner.send_msgs.packets_dropped = packets_dropped;
+        // resume tokens are never issued if either side uses stream lanes, so
+        // there are no stream lane messages to carry over
+        self.inner.streams = next.streams;
+        // both sessions were created with the same min MTU
+        let _ = self.inner.session.set_mtu(next.session.mtu());
+        self.inner.session.resume(Instant::now());
+        self.lost = None;
+    }
+}
+
 impl Drop for WebTransportServer {
     fn drop(&mut self) {
         let _ = self.close(DROP_DISCONNECT_REASON);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use futures::channel::mpsc;
+
+    use crate::server::admission::{
+        AllOf, Manual, MaxClients, MaxClientsPerIp, PathPrefixes, RequestTimeout,
+    };
+
+    use super::*;
+
+    const DT: Duration = Duration::from_millis(600);
+
+    const IP_A: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+
+    const IP_B: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+
+    fn open() -> Open {
+        Open {
+            local_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            recv_connecting: mpsc::channel(1).1,
+            send_reload: mpsc::unbounded().0,
+            recv_reloaded: mpsc::unbounded().1,
+            clients: SlotMap::default(),
+            resume_tokens: HashMap::new(),
+            uses_stream_lanes: false,
+            #[cfg(feature = "token")]
+            used_tokens: ReplayCache::new(),
+            _send_closed: oneshot::channel().0,
+        }
+    }
+
+    /// Adds a client requesting `path` from `ip`, returning the receiver of
+    /// the response to its request.
+    fn connect(
+        server: &mut Open,
+        ip: Ipv4Addr,
+        path: &str,
+    ) -> oneshot::Receiver<ConnectionResponse> {
+        let (mut connecting, recv_resp) = Connecting::new_test(SocketAddr::from((ip, 1234)));
+        connecting.request.path = path.to_owned();
+        server.clients.insert(Client::Connecting(connecting));
+        recv_resp
+    }
+
+    fn responses(
+        recv_resps: &mut [oneshot::Receiver<ConnectionResponse>],
+    ) -> Vec<Option<ConnectionResponse>> {
+        recv_resps
+            .iter_mut()
+            .map(|recv_resp| recv_resp.try_recv().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn max_clients_in_one_poll() {
+        let mut server = open();
+        let mut recv_resps = [
+            connect(&mut server, IP_A, "/"),
+            connect(&mut server, IP_B, "/"),
+        ];
+
+        // both requests are evaluated in the same poll, but only one may pass
+        WebTransportServer::admit_clients(&mut server, &mut MaxClients(1), DT);
+        assert_eq!(
+            vec![
+                Some(ConnectionResponse::Accepted),
+                Some(ConnectionResponse::Forbidden)
+            ],
+            responses(&mut recv_resps)
+        );
+    }
+
+    #[test]
+    fn accepted_clients_counted() {
+        let mut server = open();
+        let mut policy = MaxClients(1);
+        let mut first = connect(&mut server, IP_A, "/");
+        WebTransportServer::admit_clients(&mut server, &mut policy, DT);
+        assert_eq!(Ok(Some(ConnectionResponse::Accepted)), first.try_recv());
+
+        // the first client hasn't finished connecting, but still counts
+        let mut second = connect(&mut server, IP_B, "/");
+        WebTransportServer::admit_clients(&mut server, &mut policy, DT);
+        assert_eq!(Ok(Some(ConnectionResponse::Forbidden)), second.try_recv());
+    }
+
+    #[test]
+    fn denied_clients_not_counted() {
+        let mut server = open();
+        let mut policy = AllOf::new()
+            .with(PathPrefixes::new(["/lobby/"]))
+            .with(MaxClients(1));
+        let mut recv_resps = [
+            connect(&mut server, IP_A, "/other"),
+            connect(&mut server, IP_A, "/lobby/"),
+        ];
+        server.clients.insert(Client::Disconnected);
+
+        WebTransportServer::admit_clients(&mut server, &mut policy, DT);
+        assert_eq!(
+            vec![
+                Some(ConnectionResponse::NotFound),
+                Some(ConnectionResponse::Accepted)
+            ],
+            responses(&mut recv_resps)
+        );
+    }
+
+    #[test]
+    fn max_clients_per_ip_in_one_poll() {
+        let mut server = open();
+        let mut recv_resps = [
+            connect(&mut server, IP_A, "/"),
+            connect(&mut server, IP_A, "/"),
+            connect(&mut server, IP_B, "/"),
+        ];
+
+        WebTransportServer::admit_clients(&mut server, &mut MaxClientsPerIp(1), DT);
+        assert_eq!(
+            vec![
+                Some(ConnectionResponse::Accepted),
+                Some(ConnectionResponse::Forbidden),
+                Some(ConnectionResponse::Accepted)
+            ],
+            responses(&mut recv_resps)
+        );
+    }
+
+    #[test]
+    fn pending_clients_time_out() {
+        let mut server = open();
+        let mut policy = AllOf::new()
+            .with(Manual)
+            .with(RequestTimeout(Duration::from_secs(1)));
+        let mut recv_resp = connect(&mut server, IP_A, "/");
+
+        // elapsed time accumulates across polls
+        WebTransportServer::admit_clients(&mut server, &mut policy, DT);
+        assert_eq!(Ok(None), recv_resp.try_recv());
+        WebTransportServer::admit_clients(&mut server, &mut policy, DT);
+        assert_eq!(
+            Ok(Some(ConnectionResponse::Forbidden)),
+            recv_resp.try_recv()
+        );
+    }
+}
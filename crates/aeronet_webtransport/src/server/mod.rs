@@ -1,5 +1,7 @@
 //! Server-side transport implementation.
 
+pub mod admission;
+
 mod backend;
 mod frontend;
 
@@ -7,6 +9,7 @@ use std::{collections::HashMap, io, net::SocketAddr};
 
 use aeronet::{
     client::{ClientState, DisconnectReason},
+    lane::LaneIndex,
     stats::{ConnectedAt, MessageStats, RemoteAddr, Rtt},
 };
 use aeronet_proto::session::{FatalSendError, MtuTooSmall, OutOfMemory, SendError, Session};
@@ -17,8 +20,18 @@ use web_time::{Duration, Instant};
 use wtransport::error::ConnectionError;
 
 use crate::{
-    internal::{self, ConnectionInner, ConnectionMeta, InternalError},
-    shared::RawRtt,
+    internal::{self, ConnectionInner, ConnectionMeta, InternalError, OutgoingQueue, StreamLanes},
+    resume::{ResumeToken, ISSUE_QUERY_PARAM, RESUME_QUERY_PARAM},
+    shared::{OutgoingQueueConfig, OutgoingQueueStats, RawRtt, StreamError},
+};
+
+use admission::AdmissionPolicy;
+
+#[cfg(feature = "token")]
+use {
+    crate::token::{self, ConnectTokenError},
+    aeronet_proto::token::{ConnectToken, ReplayCache, TokenKey},
+    web_time::SystemTime,
 };
 
 /// Server network configuration.
@@ -33,6 +46,10 @@ pub type ServerConfig = wtransport::ServerConfig;
 #[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
 pub struct WebTransportServer {
     state: State,
+    resume_grace_period: Duration,
+    stream_lanes: Vec<LaneIndex>,
+    outgoing_queue: OutgoingQueueConfig,
+    admission: Option<Box<dyn AdmissionPolicy>>,
 }
 
 #[derive(Debug)]
@@ -100,6 +117,9 @@ pub enum ServerError {
     /// Failed to get our endpoint's local socket address.
     #[error("failed to get endpoint local address")]
     GetLocalAddr(#[source] io::Error),
+    /// Failed to apply a new configuration to our endpoint.
+    #[error("failed to reload endpoint config")]
+    ReloadConfig(#[source] io::Error),
     /// Failed to await the client's session request.
     #[error("failed to await session request")]
     AwaitSessionRequest(#[source] ConnectionError),
@@ -117,6 +137,20 @@ pub enum ServerError {
     /// Frontend did not allow this client to complete the connection.
     #[error("rejected by server")]
     Rejected,
+    /// Client connected to resume a session which no longer exists, or which
+    /// was started by a different user.
+    ///
+    /// See [`resume`](crate::resume).
+    #[error("not allowed to resume session")]
+    ResumeRejected,
+    /// Client did not provide a valid connect token.
+    #[cfg(feature = "token")]
+    #[error("invalid connect token")]
+    ConnectToken(#[source] crate::token::ConnectTokenError),
+
+    /// See [`StreamError`].
+    #[error(transparent)]
+    Stream(StreamError),
 
     // connection
     /// Lost connection.
@@ -135,6 +169,7 @@ impl From<InternalError<Self>> for ServerError {
             InternalError::FatalSend(err) => Self::FatalSend(err),
             InternalError::FrontendClosed => Self::FrontendClosed,
             InternalError::DatagramsNotSupported => Self::DatagramsNotSupported,
+            InternalError::Stream(err) => Self::Stream(err),
             InternalError::ConnectionLost(err) => Self::ConnectionLost(err),
         }
     }
@@ -144,7 +179,11 @@ slotmap::new_key_type! {
     /// Key uniquely identifying a client in a [`WebTransportServer`].
     ///
     /// If the same physical client disconnects and reconnects (i.e. the same
-    /// process), this counts as a new client.
+    /// process), this counts as a new client - unless the client resumes its
+    /// old session, in which case it continues under its old key once it has
+    /// connected. See [`resume`].
+    ///
+    /// [`resume`]: crate::resume
     pub struct ClientKey;
 }
 
@@ -155,12 +194,15 @@ slotmap::new_key_type! {
 pub struct Opening {
     recv_open: oneshot::Receiver<ToOpen>,
     recv_err: oneshot::Receiver<ServerError>,
+    uses_stream_lanes: bool,
 }
 
 #[derive(Debug)]
 struct ToOpen {
     local_addr: SocketAddr,
     recv_connecting: mpsc::Receiver<ToConnecting>,
+    send_reload: mpsc::UnboundedSender<ServerConfig>,
+    recv_reloaded: mpsc::UnboundedReceiver<Result<(), ServerError>>,
     send_closed: oneshot::Sender<()>,
 }
 
@@ -172,7 +214,13 @@ pub struct Open {
     /// Address of the local socket that this server's endpoint is bound to.
     pub local_addr: SocketAddr,
     recv_connecting: mpsc::Receiver<ToConnecting>,
+    send_reload: mpsc::UnboundedSender<ServerConfig>,
+    recv_reloaded: mpsc::UnboundedReceiver<Result<(), ServerError>>,
     clients: SlotMap<ClientKey, Client>,
+    resume_tokens: HashMap<ResumeToken, ClientKey>,
+    uses_stream_lanes: bool,
+    #[cfg(feature = "token")]
+    used_tokens: ReplayCache,
     _send_closed: oneshot::Sender<()>,
 }
 
@@ -180,6 +228,7 @@ type Client = ClientState<Connecting, Connected>;
 
 #[derive(Debug)]
 struct ToConnecting {
+    remote_addr: SocketAddr,
     authority: String,
     path: String,
     origin: Option<String>,
@@ -195,24 +244,249 @@ struct ToConnecting {
 /// [`ClientState::Connecting`].
 ///
 /// After receiving a [`ServerEvent::Connecting`], use the information in this
-/// to determine whether to accept or to reject this client.
+/// to determine whether to accept or to reject this client. The metadata of
+/// the session request can be read using [`RequestInfo`].
+///
+/// If this client is [resuming] the session of another client, it is only
+/// given its own key until it connects. Once connected, it continues under the
+/// key of the client it resumed, and [`ServerEvent::Disconnected`] is emitted
+/// for its own key with the reason [`RESUMED_REASON`] instead of
+/// [`ServerEvent::Connected`].
 ///
 /// [`ServerEvent::Connecting`]: aeronet::server::ServerEvent::Connecting
+/// [resuming]: Connecting::resumes
+/// [`ServerEvent::Disconnected`]: aeronet::server::ServerEvent::Disconnected
+/// [`RESUMED_REASON`]: crate::resume::RESUMED_REASON
+/// [`ServerEvent::Connected`]: aeronet::server::ServerEvent::Connected
 #[derive(Debug)]
 pub struct Connecting {
-    /// `:authority` field of the request.
-    pub authority: String,
-    /// `:path` field of the request.
-    pub path: String,
-    /// `origin` field of the request.
-    pub origin: Option<String>,
-    /// `user-agent` field of the request.
-    pub user_agent: Option<String>,
-    /// All headers present in the request.
-    pub headers: HashMap<String, String>,
+    request: Request,
+    remote_addr: SocketAddr,
+    elapsed: Duration,
+    accepted: bool,
     recv_dc: oneshot::Receiver<DisconnectReason<ServerError>>,
     send_conn_resp: Option<oneshot::Sender<ConnectionResponse>>,
     recv_connected: oneshot::Receiver<ToConnected>,
+    issue_resume_token: bool,
+    resumes: Option<ClientKey>,
+    resumed: Option<ToConnected>,
+    #[cfg(feature = "token")]
+    connect_token: Option<String>,
+    #[cfg(feature = "token")]
+    identity: Option<ConnectToken>,
+}
+
+/// Allows access to the metadata of the WebTransport session request which a
+/// client connected with.
+///
+/// This is available both while the client is [`Connecting`], and after it
+/// has [`Connected`]. If the client resumed its session on a new connection,
+/// this is the metadata of the request which originally started the session.
+///
+/// Query parameters and headers which carry secrets, like [resume tokens] and
+/// connect tokens, are removed from the request before it is exposed here.
+///
+/// Use [`ServerTransport::client_state`] to get access to a client's state.
+///
+/// [resume tokens]: crate::resume
+/// [`ServerTransport::client_state`]: aeronet::server::ServerTransport::client_state
+pub trait RequestInfo {
+    /// Gets the `:authority` field of the request.
+    fn authority(&self) -> &str;
+
+    /// Gets the `:path` field of the request.
+    fn path(&self) -> &str;
+
+    /// Gets the `origin` field of the request.
+    fn origin(&self) -> Option<&str>;
+
+    /// Gets the `user-agent` field of the request.
+    fn user_agent(&self) -> Option<&str>;
+
+    /// Gets all headers present in the request.
+    fn headers(&self) -> &HashMap<String, String>;
+}
+
+/// Metadata of a session request, kept across the [`Connecting`] and
+/// [`Connected`] states.
+#[derive(Debug)]
+struct Request {
+    authority: String,
+    path: String,
+    origin: Option<String>,
+    user_agent: Option<String>,
+    headers: HashMap<String, String>,
+}
+
+/// Query string parameters which are removed from a request's path before it
+/// is exposed through [`RequestInfo`].
+const SECRET_QUERY_PARAMS: &[&str] = &[
+    ISSUE_QUERY_PARAM,
+    RESUME_QUERY_PARAM,
+    #[cfg(feature = "token")]
+    token::QUERY_PARAM,
+];
+
+/// Headers which are removed from a request before it is exposed through
+/// [`RequestInfo`].
+const SECRET_HEADERS: &[&str] = &[
+    #[cfg(feature = "token")]
+    token::HEADER,
+];
+
+impl Request {
+    fn new(client: &ToConnecting) -> Self {
+        Self {
+            authority: client.authority.clone(),
+            path: strip_secret_query_params(&client.path),
+            origin: client.origin.clone(),
+            user_agent: client.user_agent.clone(),
+            headers: client
+                .headers
+                .iter()
+                .filter(|(name, _)| !SECRET_HEADERS.contains(&name.as_str()))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+fn strip_secret_query_params(path: &str) -> String {
+    let Some((path, query)) = path.split_once('?') else {
+        return path.to_owned();
+    };
+    let query = query
+        .split('&')
+        .filter(|pair| {
+            let key = pair.split_once('=').map_or(*pair, |(key, _)| key);
+            !SECRET_QUERY_PARAMS.contains(&key)
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    if query.is_empty() {
+        path.to_owned()
+    } else {
+        format!("{path}?{query}")
+    }
+}
+
+impl RequestInfo for Connecting {
+    fn authority(&self) -> &str {
+        &self.request.authority
+    }
+
+    fn path(&self) -> &str {
+        &self.request.path
+    }
+
+    fn origin(&self) -> Option<&str> {
+        self.request.origin.as_deref()
+    }
+
+    fn user_agent(&self) -> Option<&str> {
+        self.request.user_agent.as_deref()
+    }
+
+    fn headers(&self) -> &HashMap<String, String> {
+        &self.request.headers
+    }
+}
+
+impl RemoteAddr for Connecting {
+    fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+}
+
+impl Connecting {
+    /// Gets the key of the client whose session this client is attempting to
+    /// resume, if any.
+    ///
+    /// The request carried a valid [`ResumeToken`], but it must still be
+    /// accepted like any other client. If the resumed client was accepted with
+    /// a connect token, this client must also be accepted with a connect token
+    /// for the same user, or it is disconnected once it connects.
+    ///
+    /// See [`resume`](crate::resume).
+    #[must_use]
+    pub const fn resumes(&self) -> Option<ClientKey> {
+        self.resumes
+    }
+
+    /// Sends `resp` as the response to this client's session request.
+    fn respond(&mut self, resp: ConnectionResponse) -> Result<(), ServerError> {
+        let send_conn_resp = self
+            .send_conn_resp
+            .take()
+            .ok_or(ServerError::AlreadyResponded)?;
+        self.accepted = resp == ConnectionResponse::Accepted;
+        // ignore errors here because we'll pick up errors in `poll`
+        let _ = send_conn_resp.send(resp);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl Connecting {
+    /// Creates a client which has requested to connect from `remote_addr`,
+    /// without any connection behind it.
+    ///
+    /// Returns the receiver of the response to its request alongside it.
+    pub(crate) fn new_test(
+        remote_addr: SocketAddr,
+    ) -> (Self, oneshot::Receiver<ConnectionResponse>) {
+        let (send_conn_resp, recv_conn_resp) = oneshot::channel();
+        let connecting = Self {
+            request: Request {
+                authority: "localhost".into(),
+                path: "/".into(),
+                origin: None,
+                user_agent: None,
+                headers: HashMap::new(),
+            },
+            remote_addr,
+            elapsed: Duration::ZERO,
+            accepted: false,
+            recv_dc: oneshot::channel().1,
+            send_conn_resp: Some(send_conn_resp),
+            recv_connected: oneshot::channel().1,
+            issue_resume_token: false,
+            resumes: None,
+            resumed: None,
+            #[cfg(feature = "token")]
+            connect_token: None,
+            #[cfg(feature = "token")]
+            identity: None,
+        };
+        (connecting, recv_conn_resp)
+    }
+}
+
+#[cfg(feature = "token")]
+impl Connecting {
+    /// Finds and verifies the connect token in this client's session request.
+    ///
+    /// See [`token::find`] for where the token is read from. This doesn't check
+    /// if the token has already been used, but
+    /// [`WebTransportServer::respond_with_token`] does.
+    ///
+    /// # Errors
+    ///
+    /// Errors if there is no token, or the token is not valid.
+    pub fn verify_token(
+        &self,
+        key: &TokenKey,
+        now: SystemTime,
+    ) -> Result<ConnectToken, ConnectTokenError> {
+        let token = self
+            .connect_token
+            .as_deref()
+            .ok_or(ConnectTokenError::Missing)?;
+        token::decode(token)
+            .map_err(ConnectTokenError::Base64)?
+            .verify(key, now)
+            .map_err(ConnectTokenError::Verify)
+    }
 }
 
 #[derive(Debug)]
@@ -221,19 +495,54 @@ struct ToConnected {
     initial_rtt: Duration,
     recv_meta: mpsc::Receiver<ConnectionMeta>,
     recv_c2s: mpsc::Receiver<Bytes>,
-    send_s2c: mpsc::UnboundedSender<Bytes>,
+    send_s2c: OutgoingQueue,
+    streams: StreamLanes,
     send_local_dc: oneshot::Sender<String>,
     session: Session,
 }
 
 /// State of a client connected to a [`WebTransportServer`] when it is
 /// [`ClientState::Connected`].
+///
+/// The metadata of the session request which this client connected with is
+/// kept, and can be read using [`RequestInfo`].
 #[derive(Debug)]
 pub struct Connected {
+    request: Request,
     inner: ConnectionInner<ServerError>,
+    resume_token: Option<ResumeToken>,
+    lost: Option<Lost>,
+    #[cfg(feature = "token")]
+    identity: Option<ConnectToken>,
+}
+
+#[derive(Debug)]
+struct Lost {
+    elapsed: Duration,
+    reason: DisconnectReason<ServerError>,
 }
 
 impl Connected {
+    /// Gets if this client's connection was lost, and the server is waiting
+    /// for it to resume its session.
+    ///
+    /// Messages sent to a client in this state are buffered, and sent once it
+    /// resumes. See [`resume`].
+    ///
+    /// [`resume`]: crate::resume
+    #[must_use]
+    pub const fn is_suspended(&self) -> bool {
+        self.lost.is_some()
+    }
+
+    /// Gets the verified connect token of this client, if it was accepted
+    /// using [`WebTransportServer::respond_with_token`].
+    #[cfg(feature = "token")]
+    #[must_use]
+    pub const fn identity(&self) -> Option<&ConnectToken> {
+        self.identity.as_ref()
+    }
+
     /// Provides access to the underlying [`Session`] for reading more detailed
     /// network statistics.
     #[must_use]
@@ -242,6 +551,28 @@ impl Connected {
     }
 }
 
+impl RequestInfo for Connected {
+    fn authority(&self) -> &str {
+        &self.request.authority
+    }
+
+    fn path(&self) -> &str {
+        &self.request.path
+    }
+
+    fn origin(&self) -> Option<&str> {
+        self.request.origin.as_deref()
+    }
+
+    fn user_agent(&self) -> Option<&str> {
+        self.request.user_agent.as_deref()
+    }
+
+    fn headers(&self) -> &HashMap<String, String> {
+        &self.request.headers
+    }
+}
+
 impl ConnectedAt for Connected {
     fn connected_at(&self) -> Instant {
         self.session().connected_at()
@@ -277,3 +608,52 @@ impl RawRtt for Connected {
         self.inner.raw_rtt
     }
 }
+
+impl OutgoingQueueStats for Connected {
+    fn outgoing_queue_len(&self) -> usize {
+        self.inner.send_msgs.len()
+    }
+
+    fn outgoing_packets_dropped(&self) -> usize {
+        self.inner.send_msgs.packets_dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_no_query() {
+        assert_eq!("/lobby", strip_secret_query_params("/lobby"));
+    }
+
+    #[test]
+    fn strip_only_secret_params() {
+        assert_eq!(
+            "/lobby",
+            strip_secret_query_params("/lobby?issue_resume_token=1")
+        );
+        assert_eq!(
+            "/lobby",
+            strip_secret_query_params("/lobby?resume_token=AAAA")
+        );
+    }
+
+    #[test]
+    fn strip_keeps_other_params() {
+        assert_eq!(
+            "/lobby?room=1&mode",
+            strip_secret_query_params("/lobby?resume_token=AAAA&room=1&mode")
+        );
+    }
+
+    #[cfg(feature = "token")]
+    #[test]
+    fn strip_connect_token() {
+        assert_eq!(
+            "/lobby?room=1",
+            strip_secret_query_params("/lobby?room=1&connect_token=AQID")
+        );
+    }
+}
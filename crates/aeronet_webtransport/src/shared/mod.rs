@@ -7,6 +7,7 @@ pub use js_error::*;
 
 pub use aeronet_proto::session::MessageKey;
 
+use aeronet::lane::LaneIndex;
 use web_time::Duration;
 
 /// Low-level [`Rtt`] value provided by the underlying WebTransport connection.
@@ -21,3 +22,154 @@ pub trait RawRtt {
     /// Gets the low-level RTT value.
     fn raw_rtt(&self) -> Duration;
 }
+
+/// Configures the queue of outgoing packets which a connection's frontend
+/// flushes out, and which its backend task sends along the connection.
+///
+/// If the connection stalls, packets build up in this queue until it reaches
+/// its `capacity`, at which point `policy` decides what happens to any more
+/// packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutgoingQueueConfig {
+    /// Maximum number of packets which can be queued up for sending.
+    ///
+    /// A capacity of 0 is treated as 1.
+    ///
+    /// By default, this is 256.
+    pub capacity: usize,
+    /// What to do with packets flushed while the queue is full.
+    ///
+    /// By default, this is [`OutgoingQueuePolicy::Backpressure`].
+    pub policy: OutgoingQueuePolicy,
+}
+
+impl Default for OutgoingQueueConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutgoingQueueConfig {
+    /// Creates the default configuration.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            capacity: 256,
+            policy: OutgoingQueuePolicy::Backpressure,
+        }
+    }
+
+    /// Sets [`OutgoingQueueConfig::capacity`].
+    #[must_use]
+    pub const fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets [`OutgoingQueueConfig::policy`].
+    #[must_use]
+    pub const fn with_policy(mut self, policy: OutgoingQueuePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+/// What a connection does when flushing while its outgoing packet queue is
+/// full.
+///
+/// See [`OutgoingQueueConfig`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OutgoingQueuePolicy {
+    /// Stop building packets until the backend has sent some of the queued
+    /// packets.
+    ///
+    /// Messages which haven't been built into packets yet stay in the
+    /// [`Session`], and are sent on a later flush. They still count towards
+    /// the session's memory usage, so if the connection stays stalled, the
+    /// session eventually runs out of memory and the connection is closed.
+    ///
+    /// [`Session`]: aeronet_proto::session::Session
+    #[default]
+    Backpressure,
+    /// Drop the oldest queued packet to make room for the new one, whatever
+    /// it contains.
+    ///
+    /// Packets are opaque to the queue, so this may drop a packet carrying
+    /// reliable fragments just as well as one carrying only unreliable
+    /// messages. Reliable fragments in a dropped packet are never
+    /// acknowledged, so the [`Session`] sends them again later, at the cost of
+    /// the bandwidth spent on them - but unreliable messages in it are lost.
+    /// This favours sending the most recent packets over older ones.
+    ///
+    /// [`Session`]: aeronet_proto::session::Session
+    DropOldestPacket,
+}
+
+/// Statistics on a connection's outgoing packet queue.
+///
+/// See [`OutgoingQueueConfig`].
+pub trait OutgoingQueueStats {
+    /// Gets the number of packets currently queued up for sending.
+    fn outgoing_queue_len(&self) -> usize;
+
+    /// Gets the number of packets dropped because the queue was full, using
+    /// [`OutgoingQueuePolicy::DropOldestPacket`].
+    fn outgoing_packets_dropped(&self) -> usize;
+}
+
+/// Error on one of the WebTransport streams which stream lanes are sent over.
+///
+/// Messages on a stream lane can't be sent again on a new stream without
+/// breaking the lane's ordering guarantees, so any of these errors closes the
+/// whole connection.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum StreamError {
+    /// Failed to open a stream for sending messages on a lane.
+    #[error("failed to open stream for {lane:?}")]
+    Open {
+        /// Lane which the stream was opened for.
+        lane: LaneIndex,
+    },
+    /// Failed to write to a lane's stream.
+    #[error("failed to write to stream for {lane:?}")]
+    Write {
+        /// Lane which the stream was opened for.
+        lane: LaneIndex,
+    },
+    /// Failed to read from a stream which the peer opened.
+    #[error("failed to read from stream")]
+    Read,
+    /// Peer opened a stream for a lane which we can't receive messages on.
+    #[error("peer opened stream for invalid {lane:?}")]
+    InvalidLane {
+        /// Lane which the stream was opened for.
+        lane: LaneIndex,
+    },
+    /// Peer opened a stream for a lane which it has already opened a stream
+    /// for.
+    #[error("peer opened another stream for {lane:?}")]
+    DuplicateLane {
+        /// Lane which the stream was opened for.
+        lane: LaneIndex,
+    },
+    /// Peer sent a message which is longer than the maximum length of a
+    /// message which we accept.
+    #[error("peer sent message of length {len} on {lane:?}, which is too long")]
+    TooLong {
+        /// Lane which the message was sent on.
+        lane: LaneIndex,
+        /// Length of the message as given by the peer.
+        len: u64,
+    },
+    /// Peer finished a stream in the middle of a message.
+    #[error("stream for {lane:?} finished in the middle of a message")]
+    Truncated {
+        /// Lane which the stream was opened for.
+        lane: LaneIndex,
+    },
+    /// Failed to send a client its resume token over a stream.
+    ///
+    /// See [`resume`](crate::resume).
+    #[error("failed to send resume token")]
+    SendResumeToken,
+}
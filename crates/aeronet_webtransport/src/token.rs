@@ -0,0 +1,17 @@
+//! Helpers for passing a [`SignedToken`] from a client to a server in the
+//! WebTransport session request.
+//!
+//! Browsers don't allow setting custom headers on a WebTransport request, so
+//! clients should use [`with_token`] to add the token to the query string of
+//! the URL they connect to. Native clients and proxies may instead set the
+//! [`HEADER`] header. The server checks both in [`find`].
+//!
+//! These are the same helpers as in [`aeronet_proto::token::request`], so a
+//! token can be passed to any transport with an HTTP request step in the same
+//! way.
+//!
+//! [`SignedToken`]: aeronet_proto::token::SignedToken
+
+pub use aeronet_proto::token::request::{
+    decode, encode, find, with_token, ConnectTokenError, HEADER, QUERY_PARAM,
+};
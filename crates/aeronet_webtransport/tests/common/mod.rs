@@ -0,0 +1,250 @@
+//! Helpers for running a native WebTransport server and clients over the
+//! loopback interface.
+#![allow(dead_code)] // not every test uses every helper
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport},
+    lane::LaneKind,
+    server::{ServerEvent, ServerState, ServerTransport},
+};
+use aeronet_proto::session::SessionConfig;
+use aeronet_webtransport::{
+    cert,
+    client::{ClientConfig, WebTransportClient},
+    runtime::WebTransportRuntime,
+    server::{ClientKey, ConnectionResponse, ServerConfig, WebTransportServer},
+    wtransport::{self, Identity},
+};
+use assert_matches::assert_matches;
+
+pub const DT: Duration = Duration::from_millis(10);
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Idle timeout of connections made with [`lossy_server_config`] and
+/// [`lossy_client_config`], after which a cut connection is considered lost.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub fn session_config() -> SessionConfig {
+    SessionConfig::default().with_lanes([LaneKind::ReliableOrdered])
+}
+
+pub fn identity() -> Identity {
+    cert::self_signed(
+        ["localhost", "127.0.0.1"],
+        SystemTime::now(),
+        Duration::from_secs(60 * 60),
+    )
+    .unwrap()
+}
+
+pub fn server_config(identity: &Identity) -> ServerConfig {
+    wtransport::ServerConfig::builder()
+        .with_bind_address((Ipv4Addr::LOCALHOST, 0).into())
+        .with_identity(identity)
+        .build()
+}
+
+/// Creates a server config which notices quickly when a connection is lost.
+pub fn lossy_server_config(identity: &Identity) -> ServerConfig {
+    wtransport::ServerConfig::builder()
+        .with_bind_address((Ipv4Addr::LOCALHOST, 0).into())
+        .with_identity(identity)
+        .keep_alive_interval(Some(IDLE_TIMEOUT / 4))
+        .max_idle_timeout(Some(IDLE_TIMEOUT))
+        .unwrap()
+        .build()
+}
+
+/// Creates a client config which trusts the certificate of `identity`, and
+/// notices quickly when a connection is lost.
+pub fn lossy_client_config(identity: &Identity) -> ClientConfig {
+    let cert = &identity.certificate_chain().as_slice()[0];
+    wtransport::ClientConfig::builder()
+        .with_bind_default()
+        .with_server_certificate_hashes([cert.hash()])
+        .keep_alive_interval(Some(IDLE_TIMEOUT / 4))
+        .max_idle_timeout(Some(IDLE_TIMEOUT))
+        .unwrap()
+        .build()
+}
+
+/// Creates a client config which trusts the certificate of `identity`.
+pub fn client_config(identity: &Identity) -> ClientConfig {
+    let cert = &identity.certificate_chain().as_slice()[0];
+    wtransport::ClientConfig::builder()
+        .with_bind_default()
+        .with_server_certificate_hashes([cert.hash()])
+        .build()
+}
+
+/// Opens `server`, and waits until it is open.
+///
+/// Returns the URL which clients can connect to.
+pub fn open_server(
+    runtime: &WebTransportRuntime,
+    server: &mut WebTransportServer,
+    identity: &Identity,
+) -> String {
+    open_server_with(runtime, server, server_config(identity))
+}
+
+/// Opens `server` with `net_config`, and waits until it is open.
+///
+/// Returns the URL which clients can connect to.
+pub fn open_server_with(
+    runtime: &WebTransportRuntime,
+    server: &mut WebTransportServer,
+    net_config: ServerConfig,
+) -> String {
+    server.open(runtime, net_config, session_config()).unwrap();
+    poll_until(|| {
+        server
+            .poll(DT)
+            .next()
+            .map(|event| assert_matches!(event, ServerEvent::Opened))
+    });
+    let ServerState::Open(open) = server.state() else {
+        panic!("server should be open");
+    };
+    url(open.local_addr)
+}
+
+pub fn url(addr: SocketAddr) -> String {
+    format!("https://{addr}/")
+}
+
+/// Calls `f` repeatedly until it returns [`Some`], or panics if this takes too
+/// long.
+pub fn poll_until<T>(mut f: impl FnMut() -> Option<T>) -> T {
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < TIMEOUT, "timed out");
+        if let Some(value) = f() {
+            return value;
+        }
+        thread::sleep(DT);
+    }
+}
+
+/// Polls `client`, passing its events to `f`, and flushes it.
+pub fn poll_client<T>(
+    client: &mut WebTransportClient,
+    f: impl FnMut(ClientEvent<WebTransportClient>) -> Option<T>,
+) -> Option<T> {
+    let result = client.poll(DT).find_map(f);
+    let _ = client.flush();
+    result
+}
+
+/// Polls `server`, collecting all of its events, and flushes it.
+pub fn poll_server(server: &mut WebTransportServer) -> Vec<ServerEvent<WebTransportServer>> {
+    let events = server.poll(DT).collect();
+    let _ = server.flush();
+    events
+}
+
+/// Accepts `client` on `server`, and waits until both sides are connected.
+pub fn accept(server: &mut WebTransportServer, client: &mut WebTransportClient) -> ClientKey {
+    let mut client_key = None;
+    let mut server_connected = false;
+    let mut client_connected = false;
+    poll_until(|| {
+        for event in poll_server(server) {
+            match event {
+                ServerEvent::Connecting { client_key: key } => {
+                    server
+                        .respond_to_request(key, ConnectionResponse::Accepted)
+                        .unwrap();
+                    client_key = Some(key);
+                }
+                ServerEvent::Connected { client_key: key } => {
+                    assert_eq!(client_key, Some(key));
+                    server_connected = true;
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        client_connected |= poll_client(client, |event| match event {
+            ClientEvent::Connected => Some(()),
+            event => panic!("unexpected event {event:?}"),
+        })
+        .is_some();
+        (server_connected && client_connected).then_some(())
+    });
+    client_key.unwrap()
+}
+
+/// Forwards UDP datagrams between a client and a server, and can cut the
+/// connection between them to simulate a network outage.
+///
+/// Only the most recent client address is forwarded to, so this only supports
+/// one client at a time.
+pub struct Proxy {
+    pub addr: SocketAddr,
+    cut: Arc<AtomicBool>,
+    closed: Arc<AtomicBool>,
+}
+
+impl Proxy {
+    pub fn new(server_addr: SocketAddr) -> Self {
+        let downstream = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let upstream = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        upstream.connect(server_addr).unwrap();
+        downstream.set_nonblocking(true).unwrap();
+        upstream.set_nonblocking(true).unwrap();
+
+        let addr = downstream.local_addr().unwrap();
+        let cut = Arc::new(AtomicBool::new(false));
+        let closed = Arc::new(AtomicBool::new(false));
+        let (cut_clone, closed_clone) = (cut.clone(), closed.clone());
+        thread::spawn(move || {
+            let mut buf = vec![0; 65536];
+            let mut client_addr = None;
+            while !closed_clone.load(Ordering::Relaxed) {
+                let mut idle = true;
+                // errors are either `WouldBlock`, or ICMP errors from closed endpoints
+                if let Ok((len, from)) = downstream.recv_from(&mut buf) {
+                    idle = false;
+                    client_addr = Some(from);
+                    if !cut_clone.load(Ordering::Relaxed) {
+                        let _ = upstream.send(&buf[..len]);
+                    }
+                }
+                if let Ok(len) = upstream.recv(&mut buf) {
+                    idle = false;
+                    if let (Some(client_addr), false) =
+                        (client_addr, cut_clone.load(Ordering::Relaxed))
+                    {
+                        let _ = downstream.send_to(&buf[..len], client_addr);
+                    }
+                }
+                if idle {
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+        });
+
+        Self { addr, cut, closed }
+    }
+
+    /// Sets if datagrams are dropped instead of being forwarded.
+    pub fn set_cut(&self, cut: bool) {
+        self.cut.store(cut, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+}
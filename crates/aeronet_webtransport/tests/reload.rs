@@ -0,0 +1,113 @@
+//! Tests for reloading the configuration of an open server.
+#![cfg(all(feature = "client", feature = "server", not(target_family = "wasm")))]
+
+mod common;
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport, DisconnectReason},
+    lane::LaneIndex,
+    server::{ServerEvent, ServerTransport},
+};
+use aeronet_webtransport::{
+    client::{ClientError, WebTransportClient},
+    runtime::WebTransportRuntime,
+    server::{ServerError, WebTransportServer},
+    wtransport::Identity,
+};
+use assert_matches::assert_matches;
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+fn connect(runtime: &WebTransportRuntime, identity: &Identity, url: &str) -> WebTransportClient {
+    let mut client = WebTransportClient::new();
+    client
+        .connect(
+            runtime,
+            common::client_config(identity),
+            common::session_config(),
+            url,
+        )
+        .unwrap();
+    client
+}
+
+#[test]
+fn reload_swaps_identity() {
+    let runtime = WebTransportRuntime::default();
+    let old_identity = common::identity();
+    let new_identity = common::identity();
+    let mut server = WebTransportServer::new();
+    let url = common::open_server(&runtime, &mut server, &old_identity);
+
+    let mut old_client = connect(&runtime, &old_identity, &url);
+    let client_key = common::accept(&mut server, &mut old_client);
+
+    server
+        .reload_config(common::server_config(&new_identity))
+        .unwrap();
+    let result = common::poll_until(|| {
+        common::poll_server(&mut server)
+            .into_iter()
+            .next()
+            .map(|event| match event {
+                ServerEvent::Reloaded { result } => result,
+                event => panic!("unexpected event {event:?}"),
+            })
+    });
+    assert_matches!(result, Ok(()));
+
+    // new clients must now trust the new certificate, and are rejected during
+    // the TLS handshake otherwise
+    let mut new_client = connect(&runtime, &new_identity, &url);
+    common::accept(&mut server, &mut new_client);
+
+    let mut stale_client = connect(&runtime, &old_identity, &url);
+    let reason = common::poll_until(|| {
+        assert!(common::poll_server(&mut server).is_empty());
+        common::poll_client(&mut stale_client, |event| match event {
+            ClientEvent::Disconnected { reason } => Some(reason),
+            event => panic!("unexpected event {event:?}"),
+        })
+    });
+    assert_matches!(reason, DisconnectReason::Error(ClientError::Connect(_)));
+
+    // clients connected before the reload are unaffected
+    old_client.send(b"hello".as_slice(), LANE).unwrap();
+    old_client.flush().unwrap();
+    let msg = common::poll_until(|| {
+        common::poll_server(&mut server)
+            .into_iter()
+            .find_map(|event| match event {
+                ServerEvent::Recv {
+                    client_key: key,
+                    msg,
+                    ..
+                } if key == client_key => Some(msg),
+                _ => None,
+            })
+    });
+    assert_eq!(b"hello"[..], msg);
+    assert!(old_client.state().is_connected());
+}
+
+#[test]
+fn reload_not_open() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    assert_matches!(
+        server.reload_config(common::server_config(&identity)),
+        Err(ServerError::NotOpen)
+    );
+
+    common::open_server(&runtime, &mut server, &identity);
+    server.close("closing").unwrap();
+    assert_matches!(
+        server.reload_config(common::server_config(&identity)),
+        Err(ServerError::NotOpen)
+    );
+    // a failed reload never emits an event
+    assert!(common::poll_server(&mut server)
+        .iter()
+        .all(|event| !matches!(event, ServerEvent::Reloaded { .. })));
+}
@@ -0,0 +1,127 @@
+//! Tests for reading the session request of a connecting and connected client.
+#![cfg(all(feature = "client", feature = "server", not(target_family = "wasm")))]
+
+mod common;
+
+use std::time::Duration;
+
+use aeronet::{
+    client::{ClientEvent, ClientState},
+    server::{ServerEvent, ServerTransport},
+};
+use aeronet_webtransport::{
+    client::WebTransportClient,
+    runtime::WebTransportRuntime,
+    server::{ClientKey, ConnectionResponse, RequestInfo, WebTransportServer},
+};
+
+/// Asserts that the request info of a client is what it connected with,
+/// without any secrets.
+fn assert_request(info: &impl RequestInfo) {
+    assert_eq!("/lobby?room=1", info.path());
+    assert!(info.authority().starts_with("127.0.0.1:"));
+    assert!(info.headers().keys().all(|name| !name.contains("token")));
+}
+
+/// Connects `client` to `server`, responding to its request with `respond`
+/// after checking the request info of the connecting client.
+fn connect(
+    server: &mut WebTransportServer,
+    client: &mut WebTransportClient,
+    mut respond: impl FnMut(&mut WebTransportServer, ClientKey),
+) -> ClientKey {
+    let mut client_key = None;
+    let mut server_connected = false;
+    let mut client_connected = false;
+    common::poll_until(|| {
+        for event in common::poll_server(server) {
+            match event {
+                ServerEvent::Connecting { client_key: key } => {
+                    let ClientState::Connecting(connecting) = server.client_state(key) else {
+                        panic!("client should be connecting");
+                    };
+                    assert_request(connecting);
+                    respond(server, key);
+                    client_key = Some(key);
+                }
+                ServerEvent::Connected { .. } => server_connected = true,
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        client_connected |= common::poll_client(client, |event| match event {
+            ClientEvent::Connected => Some(()),
+            event => panic!("unexpected event {event:?}"),
+        })
+        .is_some();
+        (server_connected && client_connected).then_some(())
+    });
+    client_key.unwrap()
+}
+
+#[test]
+fn request_info_without_resume_params() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    server.set_resume_grace_period(Duration::from_secs(10));
+    let url = common::open_server(&runtime, &mut server, &identity);
+
+    // the client adds a query param asking for a resume token
+    let mut client = WebTransportClient::new();
+    client.set_resumable(true);
+    client
+        .connect(
+            &runtime,
+            common::client_config(&identity),
+            common::session_config(),
+            format!("{url}lobby?room=1"),
+        )
+        .unwrap();
+    let client_key = connect(&mut server, &mut client, |server, key| {
+        server
+            .respond_to_request(key, ConnectionResponse::Accepted)
+            .unwrap();
+    });
+
+    let ClientState::Connected(connected) = server.client_state(client_key) else {
+        panic!("client should be connected");
+    };
+    assert_request(connected);
+}
+
+#[cfg(feature = "token")]
+#[test]
+fn request_info_without_connect_token() {
+    use aeronet_webtransport::{
+        proto::token::{ConnectToken, TokenKey},
+        token,
+    };
+    use web_time::SystemTime;
+
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    let url = common::open_server(&runtime, &mut server, &identity);
+
+    let key = TokenKey::from_bytes([1; 32]);
+    let signed = ConnectToken::new(7, "", SystemTime::now(), Duration::from_secs(60)).sign(&key);
+    let mut client = WebTransportClient::new();
+    client
+        .connect(
+            &runtime,
+            common::client_config(&identity),
+            common::session_config(),
+            token::with_token(&format!("{url}lobby?room=1"), &signed),
+        )
+        .unwrap();
+    // the token is no longer in the path, but can still be verified
+    let client_key = connect(&mut server, &mut client, |server, client_key| {
+        server.respond_with_token(client_key, &key).unwrap();
+    });
+
+    let ClientState::Connected(connected) = server.client_state(client_key) else {
+        panic!("client should be connected");
+    };
+    assert_request(connected);
+    assert_eq!(7, connected.identity().unwrap().user_id);
+}
@@ -0,0 +1,334 @@
+//! Tests for resuming a client's session on a new connection.
+#![cfg(all(feature = "client", feature = "server", not(target_family = "wasm")))]
+
+mod common;
+
+use std::time::Duration;
+
+use aeronet::{
+    client::{ClientEvent, ClientState, ClientTransport, DisconnectReason},
+    lane::LaneIndex,
+    server::{ServerEvent, ServerState, ServerTransport},
+};
+use aeronet_webtransport::{
+    client::{ClientError, WebTransportClient},
+    resume::{ResumeRequest, ResumeToken, RESUMED_REASON},
+    runtime::WebTransportRuntime,
+    server::{
+        admission::MaxClients, ClientKey, ConnectionResponse, ServerError, WebTransportServer,
+    },
+    wtransport::Identity,
+};
+use assert_matches::assert_matches;
+use common::Proxy;
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+/// Opens `server` behind a [`Proxy`], and connects a resumable `client` to it
+/// through the proxy.
+///
+/// Returns the URL of the proxy, and the key of the client once the server has
+/// issued it a resume token.
+fn connect_through_proxy(
+    runtime: &WebTransportRuntime,
+    identity: &Identity,
+    server: &mut WebTransportServer,
+    client: &mut WebTransportClient,
+) -> (Proxy, String, ClientKey) {
+    common::open_server_with(runtime, server, common::lossy_server_config(identity));
+    let ServerState::Open(open) = server.state() else {
+        panic!("server should be open");
+    };
+    let proxy = Proxy::new(open.local_addr);
+    let url = common::url(proxy.addr);
+
+    client.set_resumable(true);
+    client
+        .connect(
+            runtime,
+            common::lossy_client_config(identity),
+            common::session_config(),
+            url.clone(),
+        )
+        .unwrap();
+    let client_key = common::accept(server, client);
+    common::poll_until(|| {
+        assert!(common::poll_server(server).is_empty());
+        common::poll_client(client, |event| -> Option<()> {
+            panic!("unexpected event {event:?}")
+        });
+        let ClientState::Connected(client) = client.state() else {
+            panic!("client should be connected");
+        };
+        client.is_resumable().then_some(())
+    });
+    (proxy, url, client_key)
+}
+
+/// Cuts the connection of `client`, and waits until both sides notice.
+fn lose_connection(
+    proxy: &Proxy,
+    server: &mut WebTransportServer,
+    client_key: ClientKey,
+    client: &mut WebTransportClient,
+) {
+    proxy.set_cut(true);
+    let mut client_lost = false;
+    common::poll_until(|| {
+        // the client stays connected on the server while it is suspended
+        assert!(common::poll_server(server).is_empty());
+        client_lost |= common::poll_client(client, |event| match event {
+            ClientEvent::Disconnected {
+                reason: DisconnectReason::Error(ClientError::ConnectionLost(_)),
+            } => Some(()),
+            event => panic!("unexpected event {event:?}"),
+        })
+        .is_some();
+        let ClientState::Connected(server_client) = server.client_state(client_key) else {
+            panic!("client should be connected on the server");
+        };
+        (client_lost && server_client.is_suspended()).then_some(())
+    });
+    proxy.set_cut(false);
+}
+
+#[test]
+fn resume_after_connection_lost() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    server.set_resume_grace_period(Duration::from_secs(10));
+    let mut client = WebTransportClient::new();
+    let (proxy, url, client_key) =
+        connect_through_proxy(&runtime, &identity, &mut server, &mut client);
+    lose_connection(&proxy, &mut server, client_key, &mut client);
+
+    // messages sent while the client is suspended are kept until it resumes
+    server.send(client_key, b"hello".as_slice(), LANE).unwrap();
+
+    client
+        .connect(
+            &runtime,
+            common::lossy_client_config(&identity),
+            common::session_config(),
+            url,
+        )
+        .unwrap();
+
+    // the new connection must be accepted like any other client, under a
+    // temporary key which is disconnected once it has resumed the session
+    let mut resumed = false;
+    let mut msg = None;
+    let msg = common::poll_until(|| {
+        for event in common::poll_server(&mut server) {
+            match event {
+                ServerEvent::Connecting { client_key: key } => {
+                    assert_ne!(client_key, key);
+                    let ClientState::Connecting(connecting) = server.client_state(key) else {
+                        panic!("client should be connecting");
+                    };
+                    assert_eq!(Some(client_key), connecting.resumes());
+                    server
+                        .respond_to_request(key, ConnectionResponse::Accepted)
+                        .unwrap();
+                }
+                ServerEvent::Disconnected {
+                    client_key: key,
+                    reason: DisconnectReason::Local(reason),
+                } => {
+                    assert_ne!(client_key, key);
+                    assert_eq!(RESUMED_REASON, reason);
+                    resumed = true;
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        msg = msg.take().or_else(|| {
+            common::poll_client(&mut client, |event| match event {
+                ClientEvent::Connected => None,
+                ClientEvent::Recv { msg, .. } => Some(msg),
+                event => panic!("unexpected event {event:?}"),
+            })
+        });
+        resumed.then(|| msg.clone()).flatten()
+    });
+    assert_eq!(b"hello"[..], msg);
+
+    let ClientState::Connected(server_client) = server.client_state(client_key) else {
+        panic!("client should be connected on the server");
+    };
+    assert!(!server_client.is_suspended());
+}
+
+#[test]
+fn resuming_client_replaces_resumed_client_in_admission() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    server.set_resume_grace_period(Duration::from_secs(10));
+    let mut client = WebTransportClient::new();
+    let (proxy, url, client_key) =
+        connect_through_proxy(&runtime, &identity, &mut server, &mut client);
+    lose_connection(&proxy, &mut server, client_key, &mut client);
+
+    // the suspended client doesn't count against the client resuming it
+    server.set_admission_policy(MaxClients(1));
+    client
+        .connect(
+            &runtime,
+            common::lossy_client_config(&identity),
+            common::session_config(),
+            url,
+        )
+        .unwrap();
+    let mut resumed = false;
+    let mut client_connected = false;
+    common::poll_until(|| {
+        for event in common::poll_server(&mut server) {
+            match event {
+                ServerEvent::Connecting { .. } => {}
+                ServerEvent::Disconnected {
+                    reason: DisconnectReason::Local(reason),
+                    ..
+                } if reason == RESUMED_REASON => resumed = true,
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        client_connected |= common::poll_client(&mut client, |event| match event {
+            ClientEvent::Connected => Some(()),
+            event => panic!("unexpected event {event:?}"),
+        })
+        .is_some();
+        (resumed && client_connected).then_some(())
+    });
+    assert_eq!(vec![client_key], server.client_keys().collect::<Vec<_>>());
+}
+
+#[test]
+fn grace_period_expires() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    server.set_resume_grace_period(Duration::from_millis(500));
+    let mut client = WebTransportClient::new();
+    let (proxy, url, client_key) =
+        connect_through_proxy(&runtime, &identity, &mut server, &mut client);
+    lose_connection(&proxy, &mut server, client_key, &mut client);
+
+    // the client is disconnected with the reason its connection was lost
+    common::poll_until(|| {
+        common::poll_server(&mut server)
+            .into_iter()
+            .next()
+            .map(|event| match event {
+                ServerEvent::Disconnected {
+                    client_key: key,
+                    reason: DisconnectReason::Error(ServerError::ConnectionLost(_)),
+                } if key == client_key => {}
+                event => panic!("unexpected event {event:?}"),
+            })
+    });
+
+    // so its token is no longer valid
+    let connect = |client: &mut WebTransportClient| {
+        client
+            .connect(
+                &runtime,
+                common::lossy_client_config(&identity),
+                common::session_config(),
+                url.clone(),
+            )
+            .unwrap();
+    };
+    connect(&mut client);
+    common::poll_until(|| {
+        assert!(common::poll_server(&mut server).is_empty());
+        common::poll_client(&mut client, |event| match event {
+            ClientEvent::Disconnected { .. } => Some(()),
+            event => panic!("unexpected event {event:?}"),
+        })
+    });
+
+    // and the client starts a new session next time
+    connect(&mut client);
+    let key = common::poll_until(|| {
+        common::poll_server(&mut server)
+            .into_iter()
+            .next()
+            .map(|event| match event {
+                ServerEvent::Connecting { client_key } => client_key,
+                event => panic!("unexpected event {event:?}"),
+            })
+    });
+    let ClientState::Connecting(connecting) = server.client_state(key) else {
+        panic!("client should be connecting");
+    };
+    assert_eq!(None, connecting.resumes());
+}
+
+#[test]
+fn resume_unknown_token_rejected() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    server.set_resume_grace_period(Duration::from_secs(10));
+    let url = common::open_server(&runtime, &mut server, &identity);
+
+    let mut client = WebTransportClient::new();
+    client
+        .connect(
+            &runtime,
+            common::client_config(&identity),
+            common::session_config(),
+            ResumeRequest::Resume(ResumeToken::generate()).add_to(&url),
+        )
+        .unwrap();
+    common::poll_until(|| {
+        // the request never shows up as a new client
+        assert!(common::poll_server(&mut server).is_empty());
+        common::poll_client(&mut client, |event| match event {
+            ClientEvent::Disconnected { .. } => Some(()),
+            event => panic!("unexpected event {event:?}"),
+        })
+    });
+}
+
+#[test]
+fn no_token_issued_with_stream_lanes() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    server.set_resume_grace_period(Duration::from_secs(10));
+    server.set_stream_lanes([LANE]);
+    let url = common::open_server(&runtime, &mut server, &identity);
+
+    let mut client = WebTransportClient::new();
+    client.set_resumable(true);
+    client
+        .connect(
+            &runtime,
+            common::client_config(&identity),
+            common::session_config(),
+            url,
+        )
+        .unwrap();
+    let client_key = common::accept(&mut server, &mut client);
+
+    // give the server a chance to send a token, if it were going to
+    server.send(client_key, b"hello".as_slice(), LANE).unwrap();
+    common::poll_until(|| {
+        assert!(common::poll_server(&mut server).is_empty());
+        common::poll_client(&mut client, |event| match event {
+            ClientEvent::Recv { .. } => Some(()),
+            event => panic!("unexpected event {event:?}"),
+        })
+    });
+    let ClientState::Connected(client) = client.state() else {
+        panic!("client should be connected");
+    };
+    assert!(!client.is_resumable());
+    assert_matches!(
+        server.client_state(client_key),
+        ClientState::Connected(client) if !client.is_suspended()
+    );
+}
@@ -0,0 +1,86 @@
+//! Tests for sending messages on stream lanes between a client and server.
+#![cfg(all(feature = "client", feature = "server", not(target_family = "wasm")))]
+
+mod common;
+
+use aeronet::{
+    client::{ClientEvent, ClientTransport},
+    lane::LaneIndex,
+    server::{ServerEvent, ServerTransport},
+};
+use aeronet_proto::{session::MessageKey, ty::MessageSeq};
+use aeronet_webtransport::{
+    client::WebTransportClient, runtime::WebTransportRuntime, server::WebTransportServer,
+};
+use bytes::Bytes;
+
+const LANE: LaneIndex = LaneIndex::from_raw(0);
+
+/// Messages to send, including one which is much larger than a datagram.
+fn msgs() -> Vec<Bytes> {
+    vec![
+        Bytes::from_static(b"first"),
+        Bytes::from(vec![7; 256 * 1024]),
+        Bytes::new(),
+        Bytes::from_static(b"last"),
+    ]
+}
+
+#[test]
+fn send_and_recv_on_stream_lane() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    server.set_stream_lanes([LANE]);
+    let url = common::open_server(&runtime, &mut server, &identity);
+
+    let mut client = WebTransportClient::new();
+    client.set_stream_lanes([LANE]);
+    client
+        .connect(
+            &runtime,
+            common::client_config(&identity),
+            common::session_config(),
+            url,
+        )
+        .unwrap();
+    let client_key = common::accept(&mut server, &mut client);
+
+    // messages are keyed like session messages
+    for (seq, msg) in (0..).zip(msgs()) {
+        let msg_key = MessageKey::from_raw(LANE, MessageSeq::new(seq));
+        assert_eq!(msg_key, client.send(msg, LANE).unwrap());
+        assert_eq!(
+            msg_key,
+            server.send(client_key, b"reply".as_slice(), LANE).unwrap()
+        );
+    }
+
+    // messages arrive in order, and stream lanes never emit acks
+    let mut server_recv = Vec::new();
+    let mut client_recv = Vec::new();
+    common::poll_until(|| {
+        for event in common::poll_server(&mut server) {
+            match event {
+                ServerEvent::Recv { msg, lane, .. } => {
+                    assert_eq!(LANE, lane);
+                    server_recv.push(msg);
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        common::poll_client(&mut client, |event| -> Option<()> {
+            match event {
+                ClientEvent::Recv { msg, lane } => {
+                    assert_eq!(LANE, lane);
+                    client_recv.push(msg);
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+            None
+        });
+        (server_recv.len() == msgs().len() && client_recv.len() == msgs().len()).then_some(())
+    });
+    assert_eq!(msgs(), server_recv);
+    assert!(client_recv.iter().all(|msg| msg[..] == b"reply"[..]));
+}
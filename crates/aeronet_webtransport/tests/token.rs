@@ -0,0 +1,79 @@
+//! Tests for verifying connect tokens in session requests.
+#![cfg(all(
+    feature = "client",
+    feature = "server",
+    feature = "token",
+    not(target_family = "wasm")
+))]
+
+mod common;
+
+use std::time::Duration;
+
+use aeronet::server::ServerEvent;
+use aeronet_proto::token::{ConnectToken, TokenError, TokenKey};
+use aeronet_webtransport::{
+    client::WebTransportClient,
+    runtime::WebTransportRuntime,
+    server::{ServerError, WebTransportServer},
+    token::{self, ConnectTokenError},
+    wtransport::Identity,
+};
+use assert_matches::assert_matches;
+use web_time::SystemTime;
+
+fn connect(runtime: &WebTransportRuntime, url: &str, identity: &Identity) -> WebTransportClient {
+    let mut client = WebTransportClient::new();
+    client
+        .connect(
+            runtime,
+            common::client_config(identity),
+            common::session_config(),
+            url,
+        )
+        .unwrap();
+    client
+}
+
+/// Waits until a client's session request reaches `server`, and responds to it
+/// with [`WebTransportServer::respond_with_token`].
+fn respond(
+    server: &mut WebTransportServer,
+    client: &mut WebTransportClient,
+    key: &TokenKey,
+) -> Result<(), ServerError> {
+    common::poll_until(|| {
+        let _ = common::poll_client(client, |_| None::<()>);
+        common::poll_server(server)
+            .into_iter()
+            .find_map(|event| match event {
+                ServerEvent::Connecting { client_key } => {
+                    Some(server.respond_with_token(client_key, key))
+                }
+                _ => None,
+            })
+    })
+}
+
+#[test]
+fn replayed_token_rejected() {
+    let runtime = WebTransportRuntime::default();
+    let identity = common::identity();
+    let mut server = WebTransportServer::new();
+    let url = common::open_server(&runtime, &mut server, &identity);
+
+    let key = TokenKey::from_bytes([2; 32]);
+    let signed = ConnectToken::new(7, "", SystemTime::now(), Duration::from_secs(60)).sign(&key);
+    let url = token::with_token(&url, &signed);
+
+    let mut first = connect(&runtime, &url, &identity);
+    respond(&mut server, &mut first, &key).unwrap();
+
+    let mut second = connect(&runtime, &url, &identity);
+    assert_matches!(
+        respond(&mut server, &mut second, &key),
+        Err(ServerError::ConnectToken(ConnectTokenError::Verify(
+            TokenError::Replayed
+        )))
+    );
+}